@@ -17,10 +17,10 @@
 use std::{
     env,
     ffi::OsStr,
-    fs::File,
+    fs::{self, File},
     io::{prelude::*, Write},
     iter::Iterator,
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 use anyhow::Result;
@@ -29,6 +29,9 @@ use zip::{write::FileOptions, CompressionMethod, ZipWriter};
 
 const DEFAULT_UNIX_PERMISSIONS: u32 = 0o755;
 
+/// The crate which bundles the `wasm-opt` optimizer with `cargo-contract`.
+const BINARYEN_CRATE: &str = "binaryen";
+
 fn main() {
     let manifest_dir: PathBuf = env::var("CARGO_MANIFEST_DIR")
         .expect("CARGO_MANIFEST_DIR should be set by cargo")
@@ -37,6 +40,12 @@ fn main() {
         .expect("OUT_DIR should be set by cargo")
         .into();
 
+    let binaryen_crate_version = binaryen_crate_version(&manifest_dir);
+    println!(
+        "cargo:rustc-env=BINARYEN_CRATE_VERSION={}",
+        binaryen_crate_version
+    );
+
     let template_dir = manifest_dir.join("templates").join("new");
     let dst_file = out_dir.join("template.zip");
 
@@ -64,6 +73,57 @@ fn main() {
     );
 }
 
+/// Returns the version of the `binaryen` crate as locked in the `Cargo.lock` of the package.
+///
+/// This is the version of the wrapper crate, not the release of binaryen it bundles. Without a
+/// `Cargo.lock` next to the manifest, e.g. if `cargo-contract` is installed from a registry
+/// without `--locked`, the version required by the manifest is returned.
+fn binaryen_crate_version(manifest_dir: &Path) -> String {
+    let locked = fs::read_to_string(manifest_dir.join("Cargo.lock"))
+        .ok()
+        .and_then(|lock| locked_version(&lock, BINARYEN_CRATE));
+    locked
+        .or_else(|| {
+            let manifest = fs::read_to_string(manifest_dir.join("Cargo.toml")).ok()?;
+            required_version(&manifest, BINARYEN_CRATE)
+        })
+        .unwrap_or_else(|| panic!("The version of the `{}` crate is not found", BINARYEN_CRATE))
+}
+
+/// Returns the version of the `package` in the lock file `lock`.
+fn locked_version(lock: &str, package: &str) -> Option<String> {
+    let name = format!("name = \"{}\"", package);
+    let mut lines = lock.lines().map(str::trim);
+    lines.find(|line| *line == name)?;
+    quoted_value(lines.next()?, "version")
+}
+
+/// Returns the version of the dependency on `package` the manifest `manifest` requires, as
+/// `package = "<version>"` or in a `[dependencies.<package>]` table.
+fn required_version(manifest: &str, package: &str) -> Option<String> {
+    let table = format!("[dependencies.{}]", package);
+    let mut lines = manifest.lines().map(str::trim);
+    let version =
+        match lines.find(|line| quoted_value(line, package).is_some() || *line == table)? {
+            line if line == table => lines
+                .take_while(|line| !line.starts_with('['))
+                .find_map(|line| quoted_value(line, "version"))?,
+            line => quoted_value(line, package)?,
+        };
+    Some(version.trim_start_matches(&['=', '^', ' '][..]).to_string())
+}
+
+/// Returns the string value of `key` if the `line` is `key = "<value>"`.
+fn quoted_value(line: &str, key: &str) -> Option<String> {
+    let value = line
+        .strip_prefix(key)?
+        .trim_start()
+        .strip_prefix('=')?
+        .trim();
+    let value = value.strip_prefix('"')?.strip_suffix('"')?;
+    Some(value.to_string())
+}
+
 fn zip_dir(src_dir: &PathBuf, dst_file: &PathBuf, method: CompressionMethod) -> Result<()> {
     if !src_dir.exists() {
         anyhow::bail!("src_dir '{}' does not exist", src_dir.display());
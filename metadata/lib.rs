@@ -50,18 +50,42 @@
 //!
 //! // serialize to json
 //! let json = serde_json::to_value(&metadata).unwrap();
+//!
+//! // deserialize from json, e.g. of a `metadata.json` or `.contract` file
+//! let deserialized: ContractMetadata = serde_json::from_value(json).unwrap();
+//! assert_eq!(deserialized, metadata);
 //! ```
 
 use core::fmt::{Display, Formatter, Result as DisplayResult, Write};
 use semver::Version;
-use serde::{Serialize, Serializer};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::{Map, Value};
 use url::Url;
 
+mod schema;
+
+pub use schema::{validate, ValidationError};
+
 const METADATA_VERSION: &str = "0.1.0";
 
+/// The metadata version of a bundle with a compressed `source.wasm`.
+///
+/// Readers of version `0.1.0` would mistake the compressed bytes for the Wasm code.
+const COMPRESSED_METADATA_VERSION: &str = "0.2.0";
+
+/// The first metadata version which stores the code under the language agnostic
+/// `source.contract_binary` key instead of `source.wasm`.
+const CONTRACT_BINARY_METADATA_VERSION: &str = "0.3.0";
+
+/// The metadata versions which can be emitted.
+const SUPPORTED_METADATA_VERSIONS: [&str; 3] = [
+    METADATA_VERSION,
+    COMPRESSED_METADATA_VERSION,
+    CONTRACT_BINARY_METADATA_VERSION,
+];
+
 /// Smart contract metadata.
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Deserialize)]
 pub struct ContractMetadata {
     #[serde(rename = "metadataVersion")]
     metadata_version: semver::Version,
@@ -72,6 +96,9 @@ pub struct ContractMetadata {
     /// Raw JSON of the contract abi metadata, generated during contract compilation.
     #[serde(flatten)]
     abi: Map<String, Value>,
+    /// The metadata version requested with `with_version`, kept when the source changes.
+    #[serde(skip)]
+    requested_version: Option<Version>,
 }
 
 impl ContractMetadata {
@@ -82,8 +109,7 @@ impl ContractMetadata {
         user: Option<User>,
         abi: Map<String, Value>,
     ) -> Self {
-        let metadata_version = semver::Version::parse(METADATA_VERSION)
-            .expect("METADATA_VERSION is a valid semver string");
+        let metadata_version = metadata_version(&source, None);
 
         Self {
             metadata_version,
@@ -91,14 +117,200 @@ impl ContractMetadata {
             contract,
             user,
             abi,
+            requested_version: None,
         }
     }
 
+    /// Construct new contract metadata of the metadata version `version`.
+    ///
+    /// Fails if `version` is not a supported metadata version, or if it cannot represent the
+    /// `source`, e.g. `0.1.0` with a compressed Wasm.
+    pub fn with_version(
+        version: Version,
+        source: Source,
+        contract: Contract,
+        user: Option<User>,
+        abi: Map<String, Value>,
+    ) -> Result<Self, String> {
+        if !SUPPORTED_METADATA_VERSIONS
+            .iter()
+            .any(|supported| Version::parse(supported).as_ref() == Ok(&version))
+        {
+            return Err(format!(
+                "Unsupported metadata version {}, supported are {}",
+                version,
+                SUPPORTED_METADATA_VERSIONS.join(", ")
+            ));
+        }
+        let required = metadata_version(&source, None);
+        if version < required {
+            return Err(format!(
+                "The metadata version {} cannot represent the source, it requires {}",
+                version, required
+            ));
+        }
+
+        Ok(Self {
+            metadata_version: version.clone(),
+            source,
+            contract,
+            user,
+            abi,
+            requested_version: Some(version),
+        })
+    }
+
+    /// Returns the hash of the Wasm code.
+    pub fn code_hash(&self) -> &CodeHash {
+        &self.source.hash
+    }
+
+    /// Returns the Wasm code in the `source` section, `None` if it was removed.
+    pub fn source_wasm(&self) -> Option<&[u8]> {
+        self.source.wasm.as_ref().map(|wasm| &wasm.wasm[..])
+    }
+
+    /// Embeds the Wasm code in the `source` section, e.g. to bundle it with the metadata.
+    pub fn set_source_wasm(&mut self, wasm: SourceWasm) {
+        self.source.wasm = Some(wasm);
+        self.metadata_version = metadata_version(&self.source, self.requested_version.as_ref());
+    }
+
     pub fn remove_source_wasm_attribute(&mut self) {
         self.source.wasm = None;
+        self.source.wasm_compression = None;
+        self.metadata_version = metadata_version(&self.source, self.requested_version.as_ref());
+    }
+
+    /// Compresses the Wasm code in the `source` section with `compression`.
+    ///
+    /// The metadata version is raised to `0.2.0`, so that older readers do not mistake the
+    /// compressed bytes for the Wasm code.
+    pub fn set_wasm_compression(&mut self, compression: WasmCompression) {
+        if self.source.wasm.is_some() {
+            self.source.wasm_compression = Some(compression);
+            self.metadata_version = metadata_version(&self.source, self.requested_version.as_ref());
+        }
+    }
+
+    /// Returns the key under which the Wasm code is serialized in the `source` section, it
+    /// follows from the metadata version.
+    pub fn source_code_key(&self) -> SourceCodeKey {
+        SourceCodeKey::of_version(&self.metadata_version)
+    }
+
+    /// Returns a view of the metadata which serializes to the legacy flat layout.
+    pub fn legacy(&self) -> LegacyContractMetadata<'_> {
+        LegacyContractMetadata {
+            metadata_version: &self.metadata_version,
+            source: &self.source,
+            contract: &self.contract,
+            user: self.user.as_ref(),
+            abi: &self.abi,
+        }
+    }
+}
+
+/// Metadata is equal if it serializes equally, the requested metadata version only constrains
+/// later changes.
+impl PartialEq for ContractMetadata {
+    fn eq(&self, other: &Self) -> bool {
+        self.metadata_version == other.metadata_version
+            && self.source == other.source
+            && self.contract == other.contract
+            && self.user == other.user
+            && self.abi == other.abi
+    }
+}
+
+impl Eq for ContractMetadata {}
+
+impl Serialize for ContractMetadata {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        #[derive(Serialize)]
+        struct Fields<'a> {
+            #[serde(rename = "metadataVersion")]
+            metadata_version: &'a Version,
+            source: SourceWithCodeKey<'a>,
+            contract: &'a Contract,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            user: Option<&'a User>,
+            #[serde(flatten)]
+            abi: &'a Map<String, Value>,
+        }
+
+        Fields {
+            metadata_version: &self.metadata_version,
+            source: SourceWithCodeKey {
+                source: &self.source,
+                code_key: self.source_code_key(),
+            },
+            contract: &self.contract,
+            user: self.user.as_ref(),
+            abi: &self.abi,
+        }
+        .serialize(serializer)
     }
 }
 
+/// Returns the metadata version required to read `source`, at least the `requested` one.
+fn metadata_version(source: &Source, requested: Option<&Version>) -> Version {
+    let version = if source.wasm_compression.is_some() {
+        COMPRESSED_METADATA_VERSION
+    } else {
+        METADATA_VERSION
+    };
+    let required = Version::parse(version).expect("metadata versions are valid semver strings");
+    match requested {
+        Some(requested) if *requested > required => requested.clone(),
+        _ => required,
+    }
+}
+
+/// Smart contract metadata in the legacy flat layout, as expected by some older explorers.
+///
+/// The fields of the `source` and `contract` sections are placed at the top level next to the
+/// contract abi instead of being nested, the code hash is named `codeHash`.
+#[derive(Debug, Serialize)]
+pub struct LegacyContractMetadata<'a> {
+    #[serde(rename = "metadataVersion")]
+    metadata_version: &'a Version,
+    #[serde(flatten, serialize_with = "serialize_legacy_source")]
+    source: &'a Source,
+    #[serde(flatten)]
+    contract: &'a Contract,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<&'a User>,
+    #[serde(flatten)]
+    abi: &'a Map<String, Value>,
+}
+
+/// Serializes the `source` section with the key names of the legacy layout.
+fn serialize_legacy_source<S>(source: &&Source, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    use serde::ser::SerializeMap;
+
+    let mut map = serializer.serialize_map(None)?;
+    map.serialize_entry("codeHash", &source.hash)?;
+    map.serialize_entry("language", &source.language)?;
+    map.serialize_entry("compiler", &source.compiler)?;
+    if let Some(wasm) = source.serialized_wasm() {
+        map.serialize_entry("wasm", &wasm)?;
+    }
+    if let Some(compression) = &source.wasm_compression {
+        map.serialize_entry("wasmCompression", compression)?;
+    }
+    if let Some(build_info) = &source.build_info {
+        map.serialize_entry("buildInfo", build_info)?;
+    }
+    map.end()
+}
+
 /// Representation of the Wasm code hash.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct CodeHash(pub [u8; 32]);
@@ -112,13 +324,168 @@ impl Serialize for CodeHash {
     }
 }
 
-#[derive(Clone, Debug, Serialize)]
+impl<'de> Deserialize<'de> for CodeHash {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let bytes = deserialize_from_byte_str(deserializer)?;
+        let mut hash = [0u8; 32];
+        if bytes.len() != hash.len() {
+            return Err(de::Error::invalid_length(bytes.len(), &"32 bytes"));
+        }
+        hash.copy_from_slice(&bytes);
+        Ok(CodeHash(hash))
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Source {
     hash: CodeHash,
     language: SourceLanguage,
     compiler: SourceCompiler,
-    #[serde(skip_serializing_if = "Option::is_none")]
     wasm: Option<SourceWasm>,
+    /// How `wasm` is compressed when serialized, it is kept uncompressed in memory.
+    wasm_compression: Option<WasmCompression>,
+    /// Extra information about the environment in which the contract was built.
+    ///
+    /// Useful for reproducible builds.
+    build_info: Option<Map<String, Value>>,
+    /// The exact toolchain the contract was compiled with, e.g. the `rustc -V` output.
+    toolchain: Option<String>,
+    /// When the contract was built, in ISO-8601.
+    build_timestamp: Option<String>,
+}
+
+/// The key under which the Wasm code is stored in the `source` section.
+///
+/// Both keys are accepted when reading metadata, the key which is written follows from the
+/// metadata version.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SourceCodeKey {
+    /// `source.wasm`, the key of the metadata versions before `0.3.0`.
+    Wasm,
+    /// `source.contract_binary`, the language agnostic key of the metadata versions from `0.3.0`.
+    ContractBinary,
+}
+
+impl SourceCodeKey {
+    /// Returns the key of the metadata version `version`.
+    pub fn of_version(version: &Version) -> Self {
+        let contract_binary = Version::parse(CONTRACT_BINARY_METADATA_VERSION)
+            .expect("metadata versions are valid semver strings");
+        if *version >= contract_binary {
+            Self::ContractBinary
+        } else {
+            Self::Wasm
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Wasm => "wasm",
+            Self::ContractBinary => "contract_binary",
+        }
+    }
+}
+
+/// Serializes a `Source` on its own as of the current metadata version, i.e. under `wasm`.
+impl Serialize for Source {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        SourceWithCodeKey {
+            source: self,
+            code_key: SourceCodeKey::Wasm,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// A `source` section which is serialized with the code key of its metadata version.
+struct SourceWithCodeKey<'a> {
+    source: &'a Source,
+    code_key: SourceCodeKey,
+}
+
+impl Serialize for SourceWithCodeKey<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        use serde::ser::SerializeMap;
+
+        let source = self.source;
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("hash", &source.hash)?;
+        map.serialize_entry("language", &source.language)?;
+        map.serialize_entry("compiler", &source.compiler)?;
+        if let Some(wasm) = source.serialized_wasm() {
+            map.serialize_entry(self.code_key.as_str(), &wasm)?;
+        }
+        if let Some(compression) = &source.wasm_compression {
+            map.serialize_entry("wasm_compression", compression)?;
+        }
+        if let Some(build_info) = &source.build_info {
+            map.serialize_entry("build_info", build_info)?;
+        }
+        if let Some(toolchain) = &source.toolchain {
+            map.serialize_entry("toolchain", toolchain)?;
+        }
+        if let Some(build_timestamp) = &source.build_timestamp {
+            map.serialize_entry("build_timestamp", build_timestamp)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Source {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Fields {
+            hash: CodeHash,
+            language: SourceLanguage,
+            compiler: SourceCompiler,
+            wasm: Option<SourceWasm>,
+            contract_binary: Option<SourceWasm>,
+            wasm_compression: Option<WasmCompression>,
+            build_info: Option<Map<String, Value>>,
+            toolchain: Option<String>,
+            build_timestamp: Option<String>,
+        }
+
+        let fields = Fields::deserialize(deserializer)?;
+        let wasm = match (fields.wasm, fields.contract_binary) {
+            (Some(_), Some(_)) => {
+                return Err(de::Error::custom(
+                    "source contains both `wasm` and `contract_binary`",
+                ))
+            }
+            (wasm, contract_binary) => wasm.or(contract_binary),
+        };
+        let wasm = match (wasm, fields.wasm_compression) {
+            (Some(wasm), Some(compression)) => Some(SourceWasm::new(
+                compression
+                    .decompress(&wasm.wasm)
+                    .map_err(de::Error::custom)?,
+            )),
+            (wasm, _) => wasm,
+        };
+        Ok(Source {
+            hash: fields.hash,
+            language: fields.language,
+            compiler: fields.compiler,
+            wasm,
+            wasm_compression: fields.wasm_compression,
+            build_info: fields.build_info,
+            toolchain: fields.toolchain,
+            build_timestamp: fields.build_timestamp,
+        })
+    }
 }
 
 impl Source {
@@ -134,12 +501,75 @@ impl Source {
             language,
             compiler,
             wasm,
+            wasm_compression: None,
+            build_info: None,
+            toolchain: None,
+            build_timestamp: None,
+        }
+    }
+
+    /// Constructs a new InkProjectSource with additional information about the build, e.g. the
+    /// versions of the tools it used.
+    pub fn with_build_info(
+        wasm: Option<SourceWasm>,
+        hash: CodeHash,
+        language: SourceLanguage,
+        compiler: SourceCompiler,
+        build_info: Map<String, Value>,
+    ) -> Self {
+        Source {
+            build_info: Some(build_info),
+            ..Self::new(wasm, hash, language, compiler)
+        }
+    }
+
+    /// Records the exact toolchain the contract was compiled with, e.g. the `rustc -V` output.
+    pub fn set_toolchain(&mut self, toolchain: String) {
+        self.toolchain = Some(toolchain);
+    }
+
+    /// Records when the contract was built, in ISO-8601.
+    pub fn set_build_timestamp(&mut self, build_timestamp: String) {
+        self.build_timestamp = Some(build_timestamp);
+    }
+
+    /// Returns the Wasm code as it is serialized, i.e. compressed if requested.
+    fn serialized_wasm(&self) -> Option<SourceWasm> {
+        let wasm = self.wasm.as_ref()?;
+        Some(match self.wasm_compression {
+            Some(compression) => SourceWasm::new(compression.compress(&wasm.wasm)),
+            None => wasm.clone(),
+        })
+    }
+}
+
+/// The algorithm with which the Wasm code in the `source` section is compressed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WasmCompression {
+    /// A raw DEFLATE stream, as specified by RFC 1951.
+    Deflate,
+}
+
+impl WasmCompression {
+    /// Compresses the Wasm code.
+    pub fn compress(&self, wasm: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Deflate => miniz_oxide::deflate::compress_to_vec(wasm, 10),
+        }
+    }
+
+    /// Decompresses the Wasm code.
+    pub fn decompress(&self, compressed: &[u8]) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Deflate => miniz_oxide::inflate::decompress_to_vec(compressed)
+                .map_err(|status| format!("invalid deflate stream: {:?}", status)),
         }
     }
 }
 
 /// The bytes of the compiled Wasm smart contract.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct SourceWasm {
     wasm: Vec<u8>,
 }
@@ -160,6 +590,25 @@ impl Serialize for SourceWasm {
     }
 }
 
+impl<'de> Deserialize<'de> for SourceWasm {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_from_byte_str(deserializer).map(SourceWasm::new)
+    }
+}
+
+impl Display for CodeHash {
+    fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult {
+        write!(f, "0x").expect("failed writing to string");
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte).expect("failed writing to string");
+        }
+        write!(f, "")
+    }
+}
+
 impl Display for SourceWasm {
     fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult {
         write!(f, "0x").expect("failed writing to string");
@@ -171,7 +620,7 @@ impl Display for SourceWasm {
 }
 
 /// The language and version in which a smart contract is written.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct SourceLanguage {
     language: Language,
     version: Version,
@@ -193,6 +642,22 @@ impl Serialize for SourceLanguage {
     }
 }
 
+impl<'de> Deserialize<'de> for SourceLanguage {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let (language, version) = deserialize_name_and_version(deserializer)?;
+        let language = match language.as_str() {
+            "ink!" => Language::Ink,
+            "Solidity" => Language::Solidity,
+            "AssemblyScript" => Language::AssemblyScript,
+            _ => return Err(de::Error::custom(format!("unknown language {}", language))),
+        };
+        Ok(SourceLanguage::new(language, version))
+    }
+}
+
 impl Display for SourceLanguage {
     fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult {
         write!(f, "{} {}", self.language, self.version)
@@ -200,7 +665,7 @@ impl Display for SourceLanguage {
 }
 
 /// The language in which the smart contract is written.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Language {
     Ink,
     Solidity,
@@ -218,7 +683,7 @@ impl Display for Language {
 }
 
 /// A compiler used to compile a smart contract.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct SourceCompiler {
     compiler: Compiler,
     version: Version,
@@ -239,6 +704,21 @@ impl Serialize for SourceCompiler {
     }
 }
 
+impl<'de> Deserialize<'de> for SourceCompiler {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let (compiler, version) = deserialize_name_and_version(deserializer)?;
+        let compiler = match compiler.as_str() {
+            "rustc" => Compiler::RustC,
+            "solang" => Compiler::Solang,
+            _ => return Err(de::Error::custom(format!("unknown compiler {}", compiler))),
+        };
+        Ok(SourceCompiler::new(compiler, version))
+    }
+}
+
 impl SourceCompiler {
     pub fn new(compiler: Compiler, version: Version) -> Self {
         SourceCompiler { compiler, version }
@@ -246,7 +726,7 @@ impl SourceCompiler {
 }
 
 /// Compilers used to compile a smart contract.
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
 pub enum Compiler {
     RustC,
     Solang,
@@ -262,7 +742,7 @@ impl Display for Compiler {
 }
 
 /// Metadata about a smart contract.
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Contract {
     name: String,
     version: Version,
@@ -276,7 +756,12 @@ pub struct Contract {
     #[serde(skip_serializing_if = "Option::is_none")]
     homepage: Option<Url>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    release_notes: Option<Url>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     license: Option<String>,
+    /// Runtime features the target chain must support, e.g. `pallet:Contracts`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    required_features: Option<Vec<String>>,
 }
 
 impl Contract {
@@ -286,7 +771,7 @@ impl Contract {
 }
 
 /// Additional user defined metadata, can be any valid json.
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct User {
     #[serde(flatten)]
     json: Map<String, Value>,
@@ -309,7 +794,25 @@ pub struct ContractBuilder {
     documentation: Option<Url>,
     repository: Option<Url>,
     homepage: Option<Url>,
+    release_notes: Option<Url>,
     license: Option<String>,
+    required_features: Option<Vec<String>>,
+    /// The first misuse of a setter, returned by `build`.
+    error: Option<MetadataError>,
+}
+
+/// Sets the builder `field` to `value`, recording an error if it was already set.
+fn set_once<T>(
+    field: &mut Option<T>,
+    value: T,
+    name: &'static str,
+    error: &mut Option<MetadataError>,
+) {
+    if field.is_some() {
+        error.get_or_insert(MetadataError::SetMoreThanOnce(name));
+    } else {
+        *field = Some(value);
+    }
 }
 
 impl ContractBuilder {
@@ -318,42 +821,36 @@ impl ContractBuilder {
     where
         S: AsRef<str>,
     {
-        if self.name.is_some() {
-            panic!("name has already been set")
-        }
-        self.name = Some(name.as_ref().to_string());
+        set_once(
+            &mut self.name,
+            name.as_ref().to_string(),
+            "name",
+            &mut self.error,
+        );
         self
     }
 
     /// Set the contract version (required)
     pub fn version(&mut self, version: Version) -> &mut Self {
-        if self.version.is_some() {
-            panic!("version has already been set")
-        }
-        self.version = Some(version);
+        set_once(&mut self.version, version, "version", &mut self.error);
         self
     }
 
-    /// Set the contract version (required)
+    /// Set the contract authors (required)
     pub fn authors<I, S>(&mut self, authors: I) -> &mut Self
     where
         I: IntoIterator<Item = S>,
         S: AsRef<str>,
     {
-        if self.authors.is_some() {
-            panic!("authors has already been set")
-        }
-
         let authors = authors
             .into_iter()
             .map(|s| s.as_ref().to_string())
             .collect::<Vec<_>>();
 
-        if authors.len() == 0 {
-            panic!("must have at least one author")
+        if authors.is_empty() {
+            self.error.get_or_insert(MetadataError::NoAuthors);
         }
-
-        self.authors = Some(authors);
+        set_once(&mut self.authors, authors, "authors", &mut self.error);
         self
     }
 
@@ -362,37 +859,51 @@ impl ContractBuilder {
     where
         S: AsRef<str>,
     {
-        if self.description.is_some() {
-            panic!("description has already been set")
-        }
-        self.description = Some(description.as_ref().to_string());
+        set_once(
+            &mut self.description,
+            description.as_ref().to_string(),
+            "description",
+            &mut self.error,
+        );
         self
     }
 
     /// Set the contract documentation url (optional)
     pub fn documentation(&mut self, documentation: Url) -> &mut Self {
-        if self.documentation.is_some() {
-            panic!("documentation is already set")
-        }
-        self.documentation = Some(documentation);
+        set_once(
+            &mut self.documentation,
+            documentation,
+            "documentation",
+            &mut self.error,
+        );
         self
     }
 
     /// Set the contract repository url (optional)
     pub fn repository(&mut self, repository: Url) -> &mut Self {
-        if self.repository.is_some() {
-            panic!("repository is already set")
-        }
-        self.repository = Some(repository);
+        set_once(
+            &mut self.repository,
+            repository,
+            "repository",
+            &mut self.error,
+        );
         self
     }
 
     /// Set the contract homepage url (optional)
     pub fn homepage(&mut self, homepage: Url) -> &mut Self {
-        if self.homepage.is_some() {
-            panic!("homepage is already set")
-        }
-        self.homepage = Some(homepage);
+        set_once(&mut self.homepage, homepage, "homepage", &mut self.error);
+        self
+    }
+
+    /// Set the url of the release notes of this contract version (optional)
+    pub fn release_notes(&mut self, release_notes: Url) -> &mut Self {
+        set_once(
+            &mut self.release_notes,
+            release_notes,
+            "release_notes",
+            &mut self.error,
+        );
         self
     }
 
@@ -401,17 +912,42 @@ impl ContractBuilder {
     where
         S: AsRef<str>,
     {
-        if self.license.is_some() {
-            panic!("license has already been set")
-        }
-        self.license = Some(license.as_ref().to_string());
+        set_once(
+            &mut self.license,
+            license.as_ref().to_string(),
+            "license",
+            &mut self.error,
+        );
+        self
+    }
+
+    /// Set the runtime features the target chain must support (optional)
+    pub fn required_features<I, S>(&mut self, required_features: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let required_features = required_features
+            .into_iter()
+            .map(|s| s.as_ref().to_string())
+            .collect();
+        set_once(
+            &mut self.required_features,
+            required_features,
+            "required_features",
+            &mut self.error,
+        );
         self
     }
 
     /// Finalize construction of the [`ContractMetadata`].
     ///
-    /// Returns an `Err` if any required fields missing.
-    pub fn build(&self) -> Result<Contract, String> {
+    /// Returns an `Err` if any required fields missing, or if a setter was misused, e.g. a field
+    /// was set more than once.
+    pub fn build(&self) -> Result<Contract, MetadataError> {
+        if let Some(error) = &self.error {
+            return Err(error.clone());
+        }
         let mut required = Vec::new();
 
         if let (Some(name), Some(version), Some(authors)) =
@@ -425,7 +961,9 @@ impl ContractBuilder {
                 documentation: self.documentation.clone(),
                 repository: self.repository.clone(),
                 homepage: self.homepage.clone(),
+                release_notes: self.release_notes.clone(),
                 license: self.license.clone(),
+                required_features: self.required_features.clone(),
             })
         } else {
             if self.name.is_none() {
@@ -437,14 +975,38 @@ impl ContractBuilder {
             if self.authors.is_none() {
                 required.push("authors")
             }
-            Err(format!(
+            Err(MetadataError::MissingFields(required))
+        }
+    }
+}
+
+/// An error building the metadata.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MetadataError {
+    /// The required fields which were not set, e.g. `name`.
+    MissingFields(Vec<&'static str>),
+    /// The field was set more than once.
+    SetMoreThanOnce(&'static str),
+    /// The authors were set to an empty list.
+    NoAuthors,
+}
+
+impl Display for MetadataError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult {
+        match self {
+            Self::MissingFields(fields) => write!(
+                f,
                 "Missing required non-default fields: {}",
-                required.join(", ")
-            ))
+                fields.join(", ")
+            ),
+            Self::SetMoreThanOnce(field) => write!(f, "{} was set more than once", field),
+            Self::NoAuthors => write!(f, "must have at least one author"),
         }
     }
 }
 
+impl std::error::Error for MetadataError {}
+
 /// Serializes the given bytes as byte string.
 fn serialize_as_byte_str<S>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error>
 where
@@ -462,6 +1024,41 @@ where
     serializer.serialize_str(&hex)
 }
 
+/// Deserializes a hex string with an optional `0x` prefix, as written by `serialize_as_byte_str`.
+fn deserialize_from_byte_str<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let hex = String::deserialize(deserializer)?;
+    let hex = hex.trim_start_matches("0x");
+    if hex.len() % 2 != 0 {
+        return Err(de::Error::custom("hex string should have an even length"));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| de::Error::custom("invalid hex string"))
+        })
+        .collect()
+}
+
+/// Deserializes a string of a name and a version separated by a space, e.g. `rustc 1.46.0`.
+fn deserialize_name_and_version<'de, D>(deserializer: D) -> Result<(String, Version), D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    let mut parts = value.splitn(2, ' ');
+    let name = parts.next().unwrap_or_default().to_string();
+    let version = parts
+        .next()
+        .ok_or_else(|| de::Error::custom(format!("{} should contain a version", value)))?;
+    let version = Version::parse(version).map_err(de::Error::custom)?;
+    Ok((name, version))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -478,7 +1075,7 @@ mod tests {
 
         assert_eq!(
             missing_name.unwrap_err(),
-            "Missing required non-default fields: name"
+            MetadataError::MissingFields(vec!["name"])
         );
 
         let missing_version = Contract::builder()
@@ -489,7 +1086,7 @@ mod tests {
 
         assert_eq!(
             missing_version.unwrap_err(),
-            "Missing required non-default fields: version"
+            MetadataError::MissingFields(vec!["version"])
         );
 
         let missing_authors = Contract::builder()
@@ -500,7 +1097,7 @@ mod tests {
 
         assert_eq!(
             missing_authors.unwrap_err(),
-            "Missing required non-default fields: authors"
+            MetadataError::MissingFields(vec!["authors"])
         );
 
         let missing_all = Contract::builder()
@@ -509,19 +1106,63 @@ mod tests {
             // .authors(vec!["Parity Technologies <admin@parity.io>".to_string()])
             .build();
 
+        let missing_all = missing_all.unwrap_err();
+        assert_eq!(
+            missing_all,
+            MetadataError::MissingFields(vec!["name", "version", "authors"])
+        );
         assert_eq!(
-            missing_all.unwrap_err(),
+            missing_all.to_string(),
             "Missing required non-default fields: name, version, authors"
         );
     }
 
+    #[test]
+    fn builder_misuse_is_returned_from_build() {
+        let set_twice = Contract::builder()
+            .name("incrementer")
+            .version(Version::new(2, 1, 0))
+            .authors(vec!["Parity Technologies <admin@parity.io>"])
+            .license("Apache-2.0")
+            .license("MIT")
+            .build()
+            .unwrap_err();
+        assert_eq!(set_twice, MetadataError::SetMoreThanOnce("license"));
+        assert_eq!(set_twice.to_string(), "license was set more than once");
+
+        let no_authors = Contract::builder()
+            .name("incrementer")
+            .version(Version::new(2, 1, 0))
+            .authors(Vec::<String>::new())
+            .build()
+            .unwrap_err();
+        assert_eq!(no_authors, MetadataError::NoAuthors);
+        assert_eq!(no_authors.to_string(), "must have at least one author");
+    }
+
     #[test]
     fn json_with_optional_fields() {
         let language = SourceLanguage::new(Language::Ink, Version::new(2, 1, 0));
         let compiler =
             SourceCompiler::new(Compiler::RustC, Version::parse("1.46.0-nightly").unwrap());
         let wasm = SourceWasm::new(vec![0u8, 1u8, 2u8]);
-        let source = Source::new(Some(wasm), CodeHash([0u8; 32]), language, compiler);
+        let build_info = json! {
+            {
+                "binaryen_crate_version": "0.12.0"
+            }
+        }
+        .as_object()
+        .unwrap()
+        .clone();
+        let mut source = Source::with_build_info(
+            Some(wasm),
+            CodeHash([0u8; 32]),
+            language,
+            compiler,
+            build_info,
+        );
+        source.set_toolchain("rustc 1.46.0-nightly (f455e46ea 2020-06-20)".to_string());
+        source.set_build_timestamp("2020-06-21T12:00:00Z".to_string());
         let contract = Contract::builder()
             .name("incrementer".to_string())
             .version(Version::new(2, 1, 0))
@@ -530,6 +1171,7 @@ mod tests {
             .documentation(Url::parse("http://docs.rs/").unwrap())
             .repository(Url::parse("http://github.com/paritytech/ink/").unwrap())
             .homepage(Url::parse("http://example.com/").unwrap())
+            .release_notes(Url::parse("http://example.com/releases/2.1.0").unwrap())
             .license("Apache-2.0".to_string())
             .build()
             .unwrap();
@@ -566,7 +1208,12 @@ mod tests {
                     "hash": "0x0000000000000000000000000000000000000000000000000000000000000000",
                     "language": "ink! 2.1.0",
                     "compiler": "rustc 1.46.0-nightly",
-                    "wasm": "0x000102"
+                    "wasm": "0x000102",
+                    "build_info": {
+                        "binaryen_crate_version": "0.12.0"
+                    },
+                    "toolchain": "rustc 1.46.0-nightly (f455e46ea 2020-06-20)",
+                    "build_timestamp": "2020-06-21T12:00:00Z"
                 },
                 "contract": {
                     "name": "incrementer",
@@ -578,6 +1225,7 @@ mod tests {
                     "documentation": "http://docs.rs/",
                     "repository": "http://github.com/paritytech/ink/",
                     "homepage": "http://example.com/",
+                    "release_notes": "http://example.com/releases/2.1.0",
                     "license": "Apache-2.0",
                 },
                 "user": {
@@ -596,6 +1244,9 @@ mod tests {
         };
 
         assert_eq!(json, expected);
+
+        let deserialized: ContractMetadata = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized, metadata);
     }
 
     #[test]
@@ -648,4 +1299,338 @@ mod tests {
 
         assert_eq!(json, expected);
     }
+
+    #[test]
+    fn legacy_json_is_flat() {
+        let language = SourceLanguage::new(Language::Ink, Version::new(2, 1, 0));
+        let compiler =
+            SourceCompiler::new(Compiler::RustC, Version::parse("1.46.0-nightly").unwrap());
+        let wasm = SourceWasm::new(vec![0u8, 1u8, 2u8]);
+        let source = Source::new(Some(wasm), CodeHash([0u8; 32]), language, compiler);
+        let contract = Contract::builder()
+            .name("incrementer".to_string())
+            .version(Version::new(2, 1, 0))
+            .authors(vec!["Parity Technologies <admin@parity.io>".to_string()])
+            .description("increment a value".to_string())
+            .build()
+            .unwrap();
+        let abi_json = json! {
+            {
+                "spec": {},
+                "storage": {},
+                "types": []
+            }
+        }
+        .as_object()
+        .unwrap()
+        .clone();
+
+        let metadata = ContractMetadata::new(source, contract, None, abi_json);
+        let json = serde_json::to_value(metadata.legacy()).unwrap();
+
+        let expected = json! {
+            {
+                "metadataVersion": "0.1.0",
+                "codeHash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                "language": "ink! 2.1.0",
+                "compiler": "rustc 1.46.0-nightly",
+                "wasm": "0x000102",
+                "name": "incrementer",
+                "version": "2.1.0",
+                "authors": [
+                  "Parity Technologies <admin@parity.io>"
+                ],
+                "description": "increment a value",
+                "spec": {},
+                "storage": {},
+                "types": []
+            }
+        };
+
+        assert_eq!(json, expected);
+    }
+
+    fn source_json(code_key: &str) -> Value {
+        json!({
+            "hash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+            "language": "ink! 2.1.0",
+            "compiler": "rustc 1.46.0-nightly",
+            code_key: "0x00010203",
+        })
+    }
+
+    #[test]
+    fn source_wasm_key_round_trips() {
+        let json = source_json("wasm");
+
+        let source: Source = serde_json::from_value(json.clone()).unwrap();
+
+        assert_eq!(source.wasm.as_ref().unwrap().wasm, vec![0, 1, 2, 3]);
+        assert_eq!(serde_json::to_value(&source).unwrap(), json);
+    }
+
+    #[test]
+    fn source_contract_binary_key_is_read() {
+        let source: Source = serde_json::from_value(source_json("contract_binary")).unwrap();
+
+        assert_eq!(source.wasm.as_ref().unwrap().wasm, vec![0, 1, 2, 3]);
+        assert_eq!(serde_json::to_value(&source).unwrap(), source_json("wasm"));
+    }
+
+    #[test]
+    fn source_code_key_follows_the_metadata_version() {
+        let source: Source = serde_json::from_value(source_json("wasm")).unwrap();
+        let contract = Contract::builder()
+            .name("incrementer".to_string())
+            .version(Version::new(2, 1, 0))
+            .authors(vec!["Parity Technologies <admin@parity.io>".to_string()])
+            .build()
+            .unwrap();
+        let metadata = ContractMetadata::with_version(
+            Version::new(0, 3, 0),
+            source,
+            contract,
+            None,
+            Map::new(),
+        )
+        .unwrap();
+
+        let json = serde_json::to_value(&metadata).unwrap();
+        assert_eq!(metadata.source_code_key(), SourceCodeKey::ContractBinary);
+        assert_eq!(json["source"], source_json("contract_binary"));
+        let deserialized: ContractMetadata = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&deserialized).unwrap(), json);
+
+        // an older version is written with its own key, whichever key was read
+        let mut json = json;
+        json["metadataVersion"] = json!("0.1.0");
+        let deserialized: ContractMetadata = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized.source_code_key(), SourceCodeKey::Wasm);
+        assert_eq!(
+            serde_json::to_value(&deserialized).unwrap()["source"],
+            source_json("wasm")
+        );
+    }
+
+    #[test]
+    fn compressed_source_wasm_round_trips() {
+        let source: Source = serde_json::from_value(source_json("wasm")).unwrap();
+        let mut metadata = ContractMetadata::new(
+            source,
+            Contract::builder()
+                .name("incrementer".to_string())
+                .version(Version::new(2, 1, 0))
+                .authors(vec!["Parity Technologies <admin@parity.io>".to_string()])
+                .build()
+                .unwrap(),
+            None,
+            Map::new(),
+        );
+
+        metadata.set_wasm_compression(WasmCompression::Deflate);
+        let json = serde_json::to_value(&metadata).unwrap();
+
+        assert_eq!(json["metadataVersion"], "0.2.0");
+        assert_eq!(json["source"]["wasm_compression"], "deflate");
+        assert_ne!(json["source"]["wasm"], source_json("wasm")["wasm"]);
+        let source: Source = serde_json::from_value(json["source"].clone()).unwrap();
+        assert_eq!(source.wasm_compression, Some(WasmCompression::Deflate));
+        assert_eq!(source.wasm.as_ref().unwrap().wasm, vec![0, 1, 2, 3]);
+        assert_eq!(serde_json::to_value(&source).unwrap(), json["source"]);
+
+        metadata.remove_source_wasm_attribute();
+        let json = serde_json::to_value(&metadata).unwrap();
+        assert_eq!(json["metadataVersion"], "0.1.0");
+        assert!(json["source"].get("wasm_compression").is_none());
+    }
+
+    #[test]
+    fn explicit_metadata_version_is_validated() {
+        let contract = Contract::builder()
+            .name("incrementer".to_string())
+            .version(Version::new(2, 1, 0))
+            .authors(vec!["Parity Technologies <admin@parity.io>".to_string()])
+            .build()
+            .unwrap();
+        let source: Source = serde_json::from_value(source_json("wasm")).unwrap();
+        let with_version = |version: &str| {
+            ContractMetadata::with_version(
+                Version::parse(version).unwrap(),
+                source.clone(),
+                contract.clone(),
+                None,
+                Map::new(),
+            )
+        };
+
+        let mut metadata = with_version("0.2.0").unwrap();
+        assert_eq!(
+            serde_json::to_value(&metadata).unwrap()["metadataVersion"],
+            "0.2.0"
+        );
+        metadata.remove_source_wasm_attribute();
+        assert_eq!(
+            serde_json::to_value(&metadata).unwrap()["metadataVersion"],
+            "0.2.0"
+        );
+        assert_eq!(
+            with_version("0.4.0").unwrap_err(),
+            "Unsupported metadata version 0.4.0, supported are 0.1.0, 0.2.0, 0.3.0"
+        );
+
+        let mut compressed = source.clone();
+        compressed.wasm_compression = Some(WasmCompression::Deflate);
+        assert_eq!(
+            ContractMetadata::with_version(
+                Version::new(0, 1, 0),
+                compressed,
+                contract,
+                None,
+                Map::new()
+            )
+            .unwrap_err(),
+            "The metadata version 0.1.0 cannot represent the source, it requires 0.2.0"
+        );
+    }
+
+    #[test]
+    fn uncompressed_source_wasm_is_the_default() {
+        let source: Source = serde_json::from_value(source_json("wasm")).unwrap();
+
+        assert_eq!(source.wasm_compression, None);
+        assert!(serde_json::to_value(&source)
+            .unwrap()
+            .get("wasm_compression")
+            .is_none());
+    }
+
+    #[test]
+    fn yaml_round_trips_with_hex_byte_strings() {
+        let source: Source = serde_json::from_value(source_json("wasm")).unwrap();
+        let metadata = ContractMetadata::new(
+            source,
+            Contract::builder()
+                .name("incrementer".to_string())
+                .version(Version::new(2, 1, 0))
+                .authors(vec!["Parity Technologies <admin@parity.io>".to_string()])
+                .build()
+                .unwrap(),
+            None,
+            Map::new(),
+        );
+
+        let yaml = serde_yaml::to_string(&metadata).unwrap();
+        let from_yaml: Value = serde_yaml::from_str(&yaml).unwrap();
+
+        assert!(yaml.contains("0x00010203"));
+        assert_eq!(from_yaml, serde_json::to_value(&metadata).unwrap());
+        let source: Source =
+            serde_yaml::from_str(&serde_yaml::to_string(&from_yaml["source"]).unwrap()).unwrap();
+        assert_eq!(source.wasm.as_ref().unwrap().wasm, vec![0, 1, 2, 3]);
+        assert_eq!(source.hash, CodeHash([0u8; 32]));
+    }
+
+    #[test]
+    fn metadata_deserializes_and_wasm_can_be_removed_and_restored() {
+        let json = json!({
+            "metadataVersion": "0.1.0",
+            "source": source_json("wasm"),
+            "contract": {
+                "name": "incrementer",
+                "version": "2.1.0",
+                "authors": ["Parity Technologies <admin@parity.io>"]
+            },
+            "spec": {}
+        });
+
+        let mut metadata: ContractMetadata = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(metadata.source_wasm(), Some(&[0u8, 1, 2, 3][..]));
+        assert_eq!(metadata.code_hash(), &CodeHash([0u8; 32]));
+
+        metadata.remove_source_wasm_attribute();
+        assert_eq!(metadata.source_wasm(), None);
+        metadata.set_source_wasm(SourceWasm::new(vec![0, 1, 2, 3]));
+        assert_eq!(serde_json::to_value(&metadata).unwrap(), json);
+    }
+
+    #[test]
+    fn metadata_round_trips_to_an_equal_value() {
+        let language: SourceLanguage = serde_json::from_value(json!("ink! 2.1.0")).unwrap();
+        let compiler: SourceCompiler =
+            serde_json::from_value(json!("rustc 1.46.0-nightly")).unwrap();
+        assert_eq!(
+            language,
+            SourceLanguage::new(Language::Ink, Version::new(2, 1, 0))
+        );
+        assert_eq!(
+            compiler,
+            SourceCompiler::new(Compiler::RustC, Version::parse("1.46.0-nightly").unwrap())
+        );
+        let source = Source::new(
+            Some(SourceWasm::new(vec![0, 1, 2, 3])),
+            CodeHash([1u8; 32]),
+            language,
+            compiler,
+        );
+        let contract = Contract::builder()
+            .name("incrementer".to_string())
+            .version(Version::new(2, 1, 0))
+            .authors(vec!["Parity Technologies <admin@parity.io>".to_string()])
+            .build()
+            .unwrap();
+        let mut metadata = ContractMetadata::new(source, contract, None, Map::new());
+        metadata.set_wasm_compression(WasmCompression::Deflate);
+
+        let json = serde_json::to_string(&metadata).unwrap();
+        let deserialized: ContractMetadata = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(deserialized, metadata);
+        assert_eq!(deserialized.code_hash(), &CodeHash([1u8; 32]));
+        assert_eq!(deserialized.source_wasm(), Some(&[0u8, 1, 2, 3][..]));
+    }
+
+    #[test]
+    fn required_features_round_trip() {
+        let contract = Contract::builder()
+            .name("incrementer".to_string())
+            .version(Version::new(2, 1, 0))
+            .authors(vec!["Parity Technologies <admin@parity.io>".to_string()])
+            .required_features(vec!["pallet:Contracts", "rpc:contracts_call"])
+            .build()
+            .unwrap();
+
+        let json = serde_json::to_value(&contract).unwrap();
+        assert_eq!(
+            json["required_features"],
+            json!(["pallet:Contracts", "rpc:contracts_call"])
+        );
+        let deserialized: Contract = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&deserialized).unwrap(), json);
+
+        // metadata written before the field was introduced still deserializes
+        let mut legacy = json;
+        legacy.as_object_mut().unwrap().remove("required_features");
+        let deserialized: Contract = serde_json::from_value(legacy.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&deserialized).unwrap(), legacy);
+    }
+
+    #[test]
+    fn release_notes_must_be_a_url() {
+        let contract = json!({
+            "name": "incrementer",
+            "version": "2.1.0",
+            "authors": ["Parity Technologies <admin@parity.io>"],
+            "release_notes": "not a url"
+        });
+
+        assert!(serde_json::from_value::<Contract>(contract).is_err());
+    }
+
+    #[test]
+    fn source_with_both_code_keys_is_rejected() {
+        let mut json = source_json("wasm");
+        json["contract_binary"] = json["wasm"].clone();
+
+        assert!(serde_json::from_value::<Source>(json).is_err());
+    }
 }
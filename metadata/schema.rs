@@ -0,0 +1,387 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Validation of metadata documents against the JSON Schema of their `metadataVersion`.
+//!
+//! The schemas are bundled with this crate, see `schemas/`. Only the subset of JSON Schema they
+//! use is supported: `type`, `enum`, `format`, `properties`, `required`, `additionalProperties`,
+//! `items` and `$ref`s to the `definitions` of the schema. The formats are `hash`, `hex`,
+//! `semver`, `uri`, `language` and `compiler`.
+
+use super::{SourceCompiler, SourceLanguage};
+use core::fmt::{Display, Formatter, Result as DisplayResult};
+use semver::Version;
+use serde_json::{Map, Value};
+use url::Url;
+
+/// The schemas of the metadata versions.
+const SCHEMAS: [(&str, &str); 3] = [
+    ("0.1.0", include_str!("schemas/metadata-0.1.0.json")),
+    ("0.2.0", include_str!("schemas/metadata-0.2.0.json")),
+    ("0.3.0", include_str!("schemas/metadata-0.3.0.json")),
+];
+
+/// A violation of the metadata schema.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ValidationError {
+    /// The JSON pointer of the offending value, e.g. `/contract/version`.
+    pub pointer: String,
+    /// What is wrong with the value.
+    pub message: String,
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult {
+        let pointer = if self.pointer.is_empty() {
+            "/"
+        } else {
+            &self.pointer
+        };
+        write!(f, "{}: {}", pointer, self.message)
+    }
+}
+
+/// Validates a metadata document against the schema of its `metadataVersion`.
+///
+/// Returns every violation, not only the first one.
+pub fn validate(metadata: &Value) -> Result<(), Vec<ValidationError>> {
+    let version = match metadata.get("metadataVersion") {
+        Some(Value::String(version)) => version,
+        Some(version) => {
+            return Err(vec![error(
+                "/metadataVersion",
+                format!("expected a string, found {}", kind(version)),
+            )])
+        }
+        None => return Err(vec![error("/metadataVersion", "missing key".into())]),
+    };
+    let schema = match SCHEMAS
+        .iter()
+        .find(|(schema_version, _)| schema_version == version)
+    {
+        Some((_, schema)) => schema,
+        None => {
+            let supported = SCHEMAS
+                .iter()
+                .map(|(version, _)| *version)
+                .collect::<Vec<_>>();
+            return Err(vec![error(
+                "/metadataVersion",
+                format!(
+                    "unsupported version {}, supported are {}",
+                    version,
+                    supported.join(", ")
+                ),
+            )]);
+        }
+    };
+    let schema: Value = serde_json::from_str(schema).expect("the bundled schemas are valid JSON");
+
+    let mut errors = Vec::new();
+    Validator { root: &schema }.validate(&schema, metadata, "", &mut errors);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+struct Validator<'a> {
+    /// The schema containing the `definitions` referred to by `$ref`.
+    root: &'a Value,
+}
+
+impl<'a> Validator<'a> {
+    fn validate(
+        &self,
+        schema: &'a Value,
+        value: &Value,
+        pointer: &str,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        let schema = self.resolve(schema);
+        if let Some(expected) = schema.get("type") {
+            let types = match expected {
+                Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+                expected => expected.as_str().into_iter().collect::<Vec<_>>(),
+            };
+            if !types.iter().any(|expected| has_type(value, expected)) {
+                let expected = types.iter().map(|expected| article(expected));
+                errors.push(error(
+                    pointer,
+                    format!(
+                        "expected {}, found {}",
+                        expected.collect::<Vec<_>>().join(" or "),
+                        kind(value)
+                    ),
+                ));
+                return;
+            }
+        }
+        if let Some(Value::Array(allowed)) = schema.get("enum") {
+            if !allowed.contains(value) {
+                let allowed = allowed.iter().map(Value::to_string).collect::<Vec<_>>();
+                errors.push(error(
+                    pointer,
+                    format!("expected one of {}, found {}", allowed.join(", "), value),
+                ));
+            }
+        }
+        if let (Some(Value::String(format)), Value::String(string)) = (schema.get("format"), value)
+        {
+            if let Err(message) = check_format(format, string) {
+                errors.push(error(pointer, message));
+            }
+        }
+        match value {
+            Value::Object(object) => self.validate_object(schema, object, pointer, errors),
+            Value::Array(array) => {
+                if let Some(items) = schema.get("items") {
+                    for (index, item) in array.iter().enumerate() {
+                        self.validate(items, item, &format!("{}/{}", pointer, index), errors);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn validate_object(
+        &self,
+        schema: &'a Value,
+        object: &Map<String, Value>,
+        pointer: &str,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for key in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(key) {
+                    errors.push(error(&child(pointer, key), "missing key".into()));
+                }
+            }
+        }
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (key, value) in object {
+            let pointer = child(pointer, key);
+            let property = properties.and_then(|properties| properties.get(key));
+            match (property, schema.get("additionalProperties")) {
+                (Some(property), _) => self.validate(property, value, &pointer, errors),
+                (None, Some(Value::Bool(false))) => {
+                    errors.push(error(&pointer, "unknown key".into()))
+                }
+                (None, Some(additional)) if additional.is_object() => {
+                    self.validate(additional, value, &pointer, errors)
+                }
+                (None, _) => (),
+            }
+        }
+    }
+
+    /// Follows a `$ref` to the `definitions` of the root schema.
+    fn resolve(&self, schema: &'a Value) -> &'a Value {
+        match schema.get("$ref").and_then(Value::as_str) {
+            Some(reference) => {
+                let definition = reference
+                    .strip_prefix('#')
+                    .and_then(|pointer| self.root.pointer(pointer))
+                    .expect("the bundled schemas only refer to their own definitions");
+                self.resolve(definition)
+            }
+            None => schema,
+        }
+    }
+}
+
+fn error(pointer: &str, message: String) -> ValidationError {
+    ValidationError {
+        pointer: pointer.into(),
+        message,
+    }
+}
+
+/// Returns the JSON pointer of the `key` of the object at `pointer`.
+fn child(pointer: &str, key: &str) -> String {
+    format!("{}/{}", pointer, key.replace('~', "~0").replace('/', "~1"))
+}
+
+/// Checks a string against a `format` of the bundled schemas.
+fn check_format(format: &str, string: &str) -> Result<(), String> {
+    let from_str = |string: &str| Value::String(string.into());
+    match format {
+        "hash" if !is_hex(string) || string.len() != 66 => {
+            Err("expected a 32 byte hash, hex encoded with a 0x prefix".into())
+        }
+        "hex" if !is_hex(string) => Err("expected bytes, hex encoded with a 0x prefix".into()),
+        "semver" => Version::parse(string)
+            .map(|_| ())
+            .map_err(|err| format!("`{}` is not a valid version, {}", string, err)),
+        "uri" => Url::parse(string)
+            .map(|_| ())
+            .map_err(|err| format!("`{}` is not a valid URL, {}", string, err)),
+        "language" => serde_json::from_value::<SourceLanguage>(from_str(string))
+            .map(|_| ())
+            .map_err(|err| format!("`{}` is not a language and version, {}", string, err)),
+        "compiler" => serde_json::from_value::<SourceCompiler>(from_str(string))
+            .map(|_| ())
+            .map_err(|err| format!("`{}` is not a compiler and version, {}", string, err)),
+        _ => Ok(()),
+    }
+}
+
+fn is_hex(string: &str) -> bool {
+    match string.strip_prefix("0x") {
+        Some(digits) => digits.len() % 2 == 0 && digits.chars().all(|c| c.is_ascii_hexdigit()),
+        None => false,
+    }
+}
+
+fn has_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => false,
+    }
+}
+
+/// Describes a JSON Schema type for diagnostics.
+fn article(expected: &str) -> String {
+    match expected {
+        "null" => "null".into(),
+        "array" | "integer" | "object" => format!("an {}", expected),
+        _ => format!("a {}", expected),
+    }
+}
+
+/// Describes the type of a JSON value for diagnostics.
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::validate;
+    use serde_json::{json, Value};
+
+    fn metadata() -> Value {
+        json!({
+            "metadataVersion": "0.1.0",
+            "source": {
+                "hash": format!("0x{}", "00".repeat(32)),
+                "language": "ink! 3.0.0-rc2",
+                "compiler": "rustc 1.49.0-nightly",
+                "wasm": "0x0061736d"
+            },
+            "contract": {
+                "name": "flipper",
+                "version": "0.1.0",
+                "authors": ["Parity Technologies <admin@parity.io>"],
+                "repository": "https://github.com/paritytech/ink"
+            },
+            "spec": {},
+            "storage": {},
+            "types": []
+        })
+    }
+
+    fn errors(metadata: Value) -> Vec<String> {
+        validate(&metadata)
+            .unwrap_err()
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn valid_metadata_passes() {
+        assert_eq!(validate(&metadata()), Ok(()));
+    }
+
+    #[test]
+    fn violations_are_reported_with_their_pointer() {
+        let mut metadata = metadata();
+        metadata["source"]["hash"] = json!("0x00");
+        metadata["contract"]["version"] = json!("one");
+        metadata["contract"]["authors"] = json!(["alice", 7]);
+        metadata["contract"]["repository"] = json!("github");
+        metadata["contract"]["licence"] = json!("MIT");
+        metadata.as_object_mut().unwrap().remove("types");
+
+        assert_eq!(
+            errors(metadata),
+            vec![
+                "/types: missing key",
+                "/contract/authors/1: expected a string, found a number",
+                "/contract/licence: unknown key",
+                "/contract/repository: `github` is not a valid URL, relative URL without a base",
+                "/contract/version: `one` is not a valid version, encountered unexpected token: \
+                 AlphaNumeric(\"one\")",
+                "/source/hash: expected a 32 byte hash, hex encoded with a 0x prefix",
+            ]
+        );
+    }
+
+    #[test]
+    fn compression_requires_version_0_2_0() {
+        let mut metadata = metadata();
+        metadata["source"]["wasm_compression"] = json!("deflate");
+        assert_eq!(
+            errors(metadata.clone()),
+            vec!["/source/wasm_compression: unknown key"]
+        );
+
+        metadata["metadataVersion"] = json!("0.2.0");
+        assert_eq!(validate(&metadata), Ok(()));
+        metadata["source"]["wasm_compression"] = json!("lz4");
+        assert_eq!(
+            errors(metadata),
+            vec!["/source/wasm_compression: expected one of \"deflate\", found \"lz4\""]
+        );
+    }
+
+    #[test]
+    fn version_0_3_0_stores_the_code_under_contract_binary() {
+        let mut metadata = metadata();
+        metadata["metadataVersion"] = json!("0.3.0");
+        metadata["source"]["wasm"] = json!("0x0061736d");
+        assert_eq!(errors(metadata.clone()), vec!["/source/wasm: unknown key"]);
+
+        let wasm = metadata["source"].as_object_mut().unwrap().remove("wasm");
+        metadata["source"]["contract_binary"] = wasm.unwrap();
+        assert_eq!(validate(&metadata), Ok(()));
+    }
+
+    #[test]
+    fn unsupported_versions_are_rejected() {
+        let mut metadata = metadata();
+        metadata["metadataVersion"] = json!("9.0.0");
+        assert_eq!(
+            errors(metadata),
+            vec!["/metadataVersion: unsupported version 9.0.0, supported are 0.1.0, 0.2.0, 0.3.0"]
+        );
+    }
+}
@@ -0,0 +1,205 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use anyhow::Result;
+use colored::Colorize;
+use serde_json::{json, Value};
+use sp_core::crypto::AccountId32;
+use structopt::StructOpt;
+use subxt::{system::AccountStoreExt, ClientBuilder, DefaultNodeRuntime};
+
+use super::storage::{ContractInfo, ContractInfoOfStore};
+
+/// Prints the balance of a deployed contract and the info the contracts pallet keeps about it.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "account")]
+pub struct AccountCommand {
+    /// Websockets url of a substrate node
+    #[structopt(
+        name = "url",
+        long,
+        parse(try_from_str),
+        default_value = "ws://localhost:9944"
+    )]
+    url: url::Url,
+    /// The address of the contract
+    #[structopt(long)]
+    contract: AccountId32,
+    /// Print the account info as JSON, the balances as decimal strings
+    #[structopt(long)]
+    output_json: bool,
+}
+
+impl AccountCommand {
+    pub fn exec(&self) -> Result<String> {
+        let account = self.fetch_account()?;
+        if self.output_json {
+            return Ok(serde_json::to_string_pretty(&account.to_json())?);
+        }
+        Ok(account.display())
+    }
+
+    fn fetch_account(&self) -> Result<ContractAccount> {
+        async_std::task::block_on(async move {
+            let rpc = jsonrpsee::ws_client(self.url.as_str()).await?;
+            let cli = ClientBuilder::<DefaultNodeRuntime>::new()
+                .set_client(rpc)
+                .build()
+                .await?;
+            let info = cli
+                .fetch(
+                    &ContractInfoOfStore {
+                        account: &self.contract,
+                    },
+                    None,
+                )
+                .await?
+                .ok_or(anyhow::anyhow!(
+                    "No contract found at {}, the address is no contract account on {}",
+                    self.contract,
+                    self.url
+                ))?;
+            let balance = cli.account(&self.contract, None).await?.data;
+            Ok(ContractAccount {
+                contract: self.contract.clone(),
+                free: balance.free,
+                reserved: balance.reserved,
+                info,
+            })
+        })
+    }
+}
+
+/// The balance of a contract and its info.
+struct ContractAccount {
+    contract: AccountId32,
+    free: u128,
+    reserved: u128,
+    info: ContractInfo,
+}
+
+impl ContractAccount {
+    fn display(&self) -> String {
+        let mut lines = vec![
+            format!("{} {}", "Contract:".bold(), self.contract),
+            format!("{} {}", "Free balance:".bold(), self.free),
+            format!("{} {}", "Reserved balance:".bold(), self.reserved),
+        ];
+        match &self.info {
+            ContractInfo::Alive(info) => {
+                lines.push(format!("{} {:?}", "Code hash:".bold(), info.code_hash));
+                lines.push(format!(
+                    "{} {} bytes in {} entries",
+                    "Storage:".bold(),
+                    info.storage_size,
+                    info.total_pair_count
+                ));
+                lines.push(format!(
+                    "{} {}",
+                    "Rent allowance:".bold(),
+                    info.rent_allowance
+                ));
+                lines.push(format!(
+                    "{} {}",
+                    "Rent paid up to block:".bold(),
+                    info.deduct_block
+                ));
+            }
+            ContractInfo::Tombstone(hash) => lines.push(format!(
+                "{} evicted, only its tombstone {:?} remains",
+                "Status:".bold(),
+                hash
+            )),
+        }
+        lines.join("\n\t")
+    }
+
+    fn to_json(&self) -> Value {
+        let mut json = json!({
+            "contract": self.contract.to_string(),
+            "free": self.free.to_string(),
+            "reserved": self.reserved.to_string(),
+        });
+        match &self.info {
+            ContractInfo::Alive(info) => {
+                json["status"] = "alive".into();
+                json["code_hash"] = format!("{:?}", info.code_hash).into();
+                json["storage_size"] = info.storage_size.into();
+                json["storage_entries"] = info.total_pair_count.into();
+                json["rent_allowance"] = info.rent_allowance.to_string().into();
+                json["deduct_block"] = info.deduct_block.into();
+            }
+            ContractInfo::Tombstone(hash) => {
+                json["status"] = "tombstone".into();
+                json["tombstone"] = format!("{:?}", hash).into();
+            }
+        }
+        json
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::storage::AliveContractInfo;
+    use sp_core::{crypto::Ss58Codec, H256};
+
+    fn account(info: ContractInfo) -> ContractAccount {
+        ContractAccount {
+            contract: AccountId32::from_ss58check(
+                "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY",
+            )
+            .unwrap(),
+            free: u128::max_value(),
+            reserved: 0,
+            info,
+        }
+    }
+
+    #[test]
+    fn alive_contract_is_reported_as_json() {
+        let account = account(ContractInfo::Alive(AliveContractInfo {
+            trie_id: vec![1],
+            storage_size: 64,
+            empty_pair_count: 0,
+            total_pair_count: 2,
+            code_hash: H256::repeat_byte(0x11),
+            rent_allowance: 1000,
+            deduct_block: 42,
+            last_write: None,
+        }));
+
+        let json = account.to_json();
+
+        assert_eq!(json["free"], "340282366920938463463374607431768211455");
+        assert_eq!(json["status"], "alive");
+        assert_eq!(json["code_hash"], format!("0x{}", "11".repeat(32)));
+        assert_eq!(json["storage_entries"], 2);
+        assert_eq!(json["rent_allowance"], "1000");
+        assert!(account.display().contains("64 bytes in 2 entries"));
+    }
+
+    #[test]
+    fn evicted_contract_has_no_code_hash() {
+        let account = account(ContractInfo::Tombstone(H256::zero()));
+
+        let json = account.to_json();
+
+        assert_eq!(json["status"], "tombstone");
+        assert!(json.get("code_hash").is_none());
+        assert!(account.display().contains("evicted"));
+    }
+}
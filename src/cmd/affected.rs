@@ -0,0 +1,164 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Selects the contracts of a workspace which are affected by the changes since a git ref.
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{Context, Result};
+use cargo_metadata::{Metadata as CargoMetadata, Package, PackageId};
+
+/// Returns the contracts of the workspace, i.e. the members which depend on `ink_lang`.
+pub(crate) fn workspace_contracts(metadata: &CargoMetadata) -> Vec<&Package> {
+    metadata
+        .packages
+        .iter()
+        .filter(|package| metadata.workspace_members.contains(&package.id))
+        .filter(|package| {
+            package
+                .dependencies
+                .iter()
+                .any(|dep| dep.name == "ink_lang")
+        })
+        .collect()
+}
+
+/// Returns the files changed since the git ref `since`, including untracked files.
+pub(crate) fn changed_files(workspace_root: &Path, since: &str) -> Result<Vec<PathBuf>> {
+    let git = |args: &[&str]| -> Result<Vec<PathBuf>> {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(workspace_root)
+            .output()
+            .context("Error invoking `git`")?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "`git {}` failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8(output.stdout)?
+            .lines()
+            .map(|file| workspace_root.join(file))
+            .collect())
+    };
+    let mut changed = git(&["diff", "--name-only", "--relative", since])?;
+    changed.extend(git(&["ls-files", "--others", "--exclude-standard"])?);
+    Ok(changed)
+}
+
+/// Splits the contracts into a tuple of `(affected, unchanged)`.
+///
+/// A contract is affected by a changed file in its own directory, in the directory of a workspace
+/// member it depends on, or outside of all members, e.g. the workspace `Cargo.lock`.
+pub(crate) fn partition_affected<'a>(
+    metadata: &CargoMetadata,
+    contracts: Vec<&'a Package>,
+    changed: &[PathBuf],
+) -> (Vec<&'a Package>, Vec<&'a Package>) {
+    let member_dir = |id: &PackageId| {
+        metadata
+            .packages
+            .iter()
+            .find(|package| package.id == *id)
+            .and_then(|package| package.manifest_path.parent())
+    };
+    let member_dirs = metadata
+        .workspace_members
+        .iter()
+        .filter_map(member_dir)
+        .collect::<Vec<_>>();
+    contracts.into_iter().partition(|contract| {
+        let dirs = workspace_dependencies(metadata, &contract.id)
+            .iter()
+            .filter_map(member_dir)
+            .collect::<Vec<_>>();
+        is_affected(&dirs, &member_dirs, changed)
+    })
+}
+
+/// Returns the package and the workspace members it transitively depends on.
+fn workspace_dependencies(metadata: &CargoMetadata, package: &PackageId) -> HashSet<PackageId> {
+    let nodes = metadata
+        .resolve
+        .as_ref()
+        .map(|resolve| resolve.nodes.as_slice())
+        .unwrap_or_default();
+    let mut found = HashSet::new();
+    let mut pending = vec![package.clone()];
+    while let Some(id) = pending.pop() {
+        if !metadata.workspace_members.contains(&id) || !found.insert(id.clone()) {
+            continue;
+        }
+        if let Some(node) = nodes.iter().find(|node| node.id == id) {
+            pending.extend(node.dependencies.iter().cloned());
+        }
+    }
+    found
+}
+
+/// Returns `true` if any changed file is in one of the `package_dirs`, or outside of all
+/// `member_dirs`.
+fn is_affected(package_dirs: &[&Path], member_dirs: &[&Path], changed: &[PathBuf]) -> bool {
+    changed.iter().any(|file| {
+        package_dirs.iter().any(|dir| file.starts_with(dir))
+            || !member_dirs.iter().any(|dir| file.starts_with(dir))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_affected;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn changes_are_mapped_to_packages() {
+        let (flipper, erc20, shared) = (
+            Path::new("/ws/flipper"),
+            Path::new("/ws/erc20"),
+            Path::new("/ws/shared"),
+        );
+        let members = [flipper, erc20, shared];
+        let changed = |file: &str| vec![PathBuf::from(file)];
+
+        assert!(is_affected(
+            &[flipper],
+            &members,
+            &changed("/ws/flipper/lib.rs")
+        ));
+        assert!(!is_affected(
+            &[flipper],
+            &members,
+            &changed("/ws/erc20/lib.rs")
+        ));
+        assert!(is_affected(
+            &[erc20, shared],
+            &members,
+            &changed("/ws/shared/lib.rs")
+        ));
+        assert!(is_affected(
+            &[flipper],
+            &members,
+            &changed("/ws/Cargo.lock")
+        ));
+        assert!(!is_affected(&[flipper], &members, &[]));
+    }
+}
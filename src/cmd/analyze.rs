@@ -0,0 +1,406 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Reports which host functions each constructor and message of a contract can reach.
+//!
+//! A call graph is built from the `call` instructions of the Wasm. An indirect call may reach
+//! every function of the table, so the graph over-approximates what is actually called.
+//!
+//! Messages are dispatched by selector inside the `call` export, so they cannot be told apart by
+//! the structure of the module alone. Instead the function names of the `name` section are
+//! matched against the constructors and messages of the metadata. This is why the original Wasm
+//! emitted by `cargo` is analyzed, the optimized one has its names stripped. A constructor or
+//! message without a function of its own has been inlined into the dispatcher, it is then
+//! attributed everything reachable from the `deploy` respectively `call` export.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    convert::TryFrom,
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use parity_wasm::elements::{External, Instruction, Internal, Module};
+use serde_json::Value;
+use structopt::StructOpt;
+
+use super::{bindings::spec_name, metadata::read_metadata};
+use crate::{crate_metadata::CrateMetadata, workspace::ManifestPath};
+
+/// Analyzes which host functions the constructors and messages of a contract reach.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "analyze")]
+pub struct AnalyzeCommand {
+    /// Path to the Cargo.toml of the contract to analyze
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    /// Path to the Wasm to analyze, defaults to the unoptimized Wasm built by `cargo`. It needs a
+    /// `name` section to map functions to messages
+    #[structopt(long, parse(from_os_str))]
+    wasm: Option<PathBuf>,
+    /// Path to the contract metadata, defaults to the `metadata.json` built for the contract
+    #[structopt(long, parse(from_os_str))]
+    metadata: Option<PathBuf>,
+}
+
+impl AnalyzeCommand {
+    pub fn exec(&self) -> Result<String> {
+        let (wasm, metadata) = self.paths()?;
+        let module = parity_wasm::deserialize_file(&wasm)
+            .context(format!("Loading wasm file '{}'", wasm.display()))?;
+        // a module without a readable name section is still analyzed, without names
+        let module = module.parse_names().unwrap_or_else(|(_, module)| module);
+        let metadata = read_metadata(&metadata)?;
+
+        let graph = CallGraph::new(&module);
+        let spec = metadata
+            .get("spec")
+            .ok_or(anyhow::anyhow!("spec not found in the metadata"))?;
+        let mut out = String::new();
+        for (kind, export) in &[("constructor", "deploy"), ("message", "call")] {
+            let specs = spec
+                .get(format!("{}s", kind))
+                .and_then(Value::as_array)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            for item in specs {
+                let summary = graph.summarize(item, export)?;
+                out.push_str(&summary.display(kind));
+            }
+        }
+        Ok(out)
+    }
+
+    /// Returns the paths of the Wasm and the metadata, defaulting to those of the contract.
+    fn paths(&self) -> Result<(PathBuf, PathBuf)> {
+        if let (Some(wasm), Some(metadata)) = (self.wasm.as_ref(), self.metadata.as_ref()) {
+            return Ok((wasm.clone(), metadata.clone()));
+        }
+        let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+        let crate_metadata = CrateMetadata::collect(&manifest_path)?;
+        let wasm = self
+            .wasm
+            .clone()
+            .unwrap_or_else(|| crate_metadata.original_wasm.clone());
+        let metadata = self
+            .metadata
+            .clone()
+            .unwrap_or_else(|| crate_metadata.dest_metadata.clone());
+        Ok((wasm, metadata))
+    }
+}
+
+/// The functions of a module and the functions each of them calls.
+///
+/// Functions are identified by their index in the function index space, imports first.
+struct CallGraph {
+    /// The `module.field` of each imported function.
+    imports: Vec<String>,
+    /// The name of each function, from the `name` section.
+    names: HashMap<u32, String>,
+    /// The callees of each defined function.
+    callees: HashMap<u32, BTreeSet<u32>>,
+    /// The function exports by name.
+    exports: HashMap<String, u32>,
+}
+
+impl CallGraph {
+    fn new(module: &Module) -> Self {
+        let imports = module
+            .import_section()
+            .map(|section| section.entries())
+            .unwrap_or_default()
+            .iter()
+            .filter(|entry| matches!(entry.external(), External::Function(_)))
+            .map(|entry| format!("{}.{}", entry.module(), entry.field()))
+            .collect::<Vec<_>>();
+
+        let names = module
+            .names_section()
+            .and_then(|section| section.functions())
+            .map(|functions| {
+                functions
+                    .names()
+                    .iter()
+                    .map(|(index, name)| (index, name.clone()))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // any function of the table may be the target of an indirect call
+        let table = module
+            .elements_section()
+            .map(|section| section.entries())
+            .unwrap_or_default()
+            .iter()
+            .flat_map(|segment| segment.members().iter().copied())
+            .collect::<BTreeSet<_>>();
+
+        let bodies = module
+            .code_section()
+            .map(|section| section.bodies())
+            .unwrap_or_default();
+        let callees = bodies
+            .iter()
+            .enumerate()
+            .map(|(index, body)| {
+                let mut callees = BTreeSet::new();
+                for instruction in body.code().elements() {
+                    match instruction {
+                        Instruction::Call(callee) => {
+                            callees.insert(*callee);
+                        }
+                        Instruction::CallIndirect(_, _) => callees.extend(&table),
+                        _ => (),
+                    }
+                }
+                ((imports.len() + index) as u32, callees)
+            })
+            .collect();
+
+        let exports = module
+            .export_section()
+            .map(|section| section.entries())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|entry| match entry.internal() {
+                Internal::Function(index) => Some((entry.field().to_string(), *index)),
+                _ => None,
+            })
+            .collect();
+
+        CallGraph {
+            imports,
+            names,
+            callees,
+            exports,
+        }
+    }
+
+    /// Returns the host functions and the number of internal functions reachable from `entry`.
+    fn reachable(&self, entry: u32) -> (BTreeSet<String>, usize) {
+        let mut visited = BTreeSet::new();
+        let mut stack = vec![entry];
+        while let Some(function) = stack.pop() {
+            if !visited.insert(function) {
+                continue;
+            }
+            if let Some(callees) = self.callees.get(&function) {
+                stack.extend(callees.iter().filter(|callee| !visited.contains(callee)));
+            }
+        }
+        let host_functions = visited
+            .iter()
+            .filter_map(|function| self.imports.get(*function as usize).cloned())
+            .collect::<BTreeSet<_>>();
+        let internal = visited.len() - host_functions.len();
+        (host_functions, internal)
+    }
+
+    /// Returns the defined function implementing the constructor or message `label`, if it was
+    /// not inlined.
+    ///
+    /// Names are demangled by the linker and end in a hash segment, e.g.
+    /// `flipper::flipper::Flipper::flip::h6e0f3a3fb2b4fe7a`.
+    fn function_of(&self, label: &str) -> Option<u32> {
+        let suffix = format!("::{}", label);
+        let mut candidates = self.names.iter().filter(|(index, name)| {
+            **index as usize >= self.imports.len() && strip_hash(name).ends_with(&suffix)
+        });
+        match (candidates.next(), candidates.next()) {
+            (Some((index, _)), None) => Some(*index),
+            // an ambiguous label, e.g. a generic function instantiated several times
+            _ => None,
+        }
+    }
+
+    /// Summarizes what a constructor or message of the metadata reaches.
+    fn summarize(&self, spec: &Value, export: &str) -> Result<Summary> {
+        let label = spec
+            .get("name")
+            .and_then(Value::as_array)
+            .and_then(|segments| segments.last())
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let (entry, inlined) = match self.function_of(label) {
+            Some(function) => (function, false),
+            None => {
+                let function = *self
+                    .exports
+                    .get(export)
+                    .ok_or(anyhow::anyhow!("The wasm has no `{}` export", export))?;
+                (function, true)
+            }
+        };
+        let (host_functions, internal_functions) = self.reachable(entry);
+        Ok(Summary {
+            name: spec_name(spec),
+            selector: spec
+                .get("selector")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            entry: self
+                .names
+                .get(&entry)
+                .map(|name| strip_hash(name).to_string())
+                .unwrap_or_else(|| export.to_string()),
+            inlined,
+            host_functions,
+            internal_functions,
+        })
+    }
+}
+
+/// Strips the trailing `::h<hash>` segment of a demangled function name.
+fn strip_hash(name: &str) -> &str {
+    match name.rfind("::h") {
+        Some(at)
+            if name.len() - at == 19 && name[at + 3..].chars().all(|c| c.is_ascii_hexdigit()) =>
+        {
+            &name[..at]
+        }
+        _ => name,
+    }
+}
+
+/// What a single constructor or message reaches.
+struct Summary {
+    name: String,
+    selector: String,
+    /// The name of the function the analysis started from.
+    entry: String,
+    /// Whether the constructor or message has no function of its own, so that `entry` is the
+    /// dispatcher and the result includes all other constructors or messages.
+    inlined: bool,
+    host_functions: BTreeSet<String>,
+    internal_functions: usize,
+}
+
+impl Summary {
+    fn display(&self, kind: &str) -> String {
+        let mut out = format!("{} {} ({})\n", kind, self.name.bold(), self.selector);
+        out.push_str(&format!("  entry: {}\n", self.entry));
+        if self.inlined {
+            out.push_str(&format!(
+                "  {}\n",
+                "inlined into the dispatcher, includes all other entry points".yellow()
+            ));
+        }
+        out.push_str(&format!(
+            "  internal functions: {}\n",
+            self.internal_functions
+        ));
+        if self.host_functions.is_empty() {
+            out.push_str("  host functions: none\n");
+        } else {
+            out.push_str("  host functions:\n");
+            for host_function in &self.host_functions {
+                out.push_str(&format!("    - {}\n", host_function));
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const CONTRACT: &str = r#"
+(module
+    (type (;0;) (func (param i32 i32)))
+    (type (;1;) (func))
+    (import "seal0" "seal_transfer" (func $transfer (type 0)))
+    (import "seal0" "seal_balance" (func $balance (type 0)))
+    (import "seal0" "seal_set_storage" (func $set_storage (type 0)))
+    (func $new (type 1) i32.const 0 i32.const 0 call $set_storage)
+    (func $get_balance (type 1) i32.const 0 i32.const 0 call $balance)
+    (func $pay (type 1) call $get_balance i32.const 0 i32.const 0 call $transfer)
+    (func $deploy (type 1) call $new)
+    (func $call (type 1) call $get_balance call $pay)
+    (export "deploy" (func $deploy))
+    (export "call" (func $call))
+)
+"#;
+
+    fn graph() -> CallGraph {
+        let wasm = wabt::wat2wasm(CONTRACT).expect("invalid wabt");
+        let module = parity_wasm::deserialize_buffer::<Module>(&wasm).unwrap();
+        let mut graph = CallGraph::new(&module);
+        // the `name` section is written by the linker, `wabt` does not emit it
+        graph.names = vec![
+            (3, "flipper::Flipper::new::h0123456789abcdef"),
+            (4, "flipper::Flipper::get_balance::h0123456789abcdef"),
+            (5, "flipper::Flipper::pay::h0123456789abcdef"),
+        ]
+        .into_iter()
+        .map(|(index, name)| (index, name.to_string()))
+        .collect();
+        graph
+    }
+
+    #[test]
+    fn reports_host_functions_per_message() {
+        let graph = graph();
+        let message = |name: &str| json!({ "name": [name], "selector": "0x00000000" });
+
+        let get_balance = graph.summarize(&message("get_balance"), "call").unwrap();
+        assert_eq!(get_balance.entry, "flipper::Flipper::get_balance");
+        assert!(!get_balance.inlined);
+        assert_eq!(
+            get_balance.host_functions.into_iter().collect::<Vec<_>>(),
+            vec!["seal0.seal_balance"]
+        );
+
+        let pay = graph.summarize(&message("pay"), "call").unwrap();
+        assert_eq!(
+            pay.host_functions.into_iter().collect::<Vec<_>>(),
+            vec!["seal0.seal_balance", "seal0.seal_transfer"]
+        );
+        assert_eq!(pay.internal_functions, 2);
+    }
+
+    #[test]
+    fn inlined_messages_are_attributed_the_dispatcher() {
+        let graph = graph();
+
+        let flip = graph
+            .summarize(&json!({ "name": ["flip"] }), "call")
+            .unwrap();
+
+        assert!(flip.inlined);
+        assert_eq!(flip.entry, "call");
+        assert_eq!(
+            flip.host_functions.into_iter().collect::<Vec<_>>(),
+            vec!["seal0.seal_balance", "seal0.seal_transfer"]
+        );
+    }
+
+    #[test]
+    fn strips_hash_segment() {
+        assert_eq!(
+            strip_hash("flipper::Flipper::flip::h6e0f3a3fb2b4fe7a"),
+            "flipper::Flipper::flip"
+        );
+        assert_eq!(
+            strip_hash("flipper::Flipper::hello"),
+            "flipper::Flipper::hello"
+        );
+    }
+}
@@ -0,0 +1,165 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Estimates the fee of an extrinsic, and checks that the signing account can pay for it before
+//! submitting it.
+
+use anyhow::Result;
+use codec::Encode;
+use jsonrpsee::common::{to_value, Params};
+use serde_json::Value;
+use subxt::{system::AccountStoreExt, Call, Client, DefaultNodeRuntime, Signer};
+
+use super::signer::ExtrinsicSigner;
+
+/// The fee of an extrinsic and the weight of its call, estimated by the node via the
+/// `payment_queryInfo` RPC.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct FeeEstimate {
+    pub weight: u64,
+    pub fee: u128,
+}
+
+/// Signs the `call` and estimates its fee, nothing is submitted.
+pub(crate) async fn estimate_fee<C>(
+    cli: &Client<DefaultNodeRuntime>,
+    rpc: &jsonrpsee::Client,
+    signer: &ExtrinsicSigner,
+    call: C,
+) -> Result<FeeEstimate>
+where
+    C: Call<DefaultNodeRuntime> + Send + Sync,
+{
+    let extrinsic = cli.create_signed(call, signer).await?;
+    let info: Value = rpc
+        .request(
+            "payment_queryInfo",
+            Params::Array(vec![
+                to_value(format!("0x{}", hex::encode(extrinsic.encode())))?,
+                Value::Null,
+            ]),
+        )
+        .await?;
+    fee_estimate(&info)
+}
+
+/// Refuses to continue if the free balance of the signer does not cover the estimated fee of the
+/// `call` plus the balance it `transfer`s, e.g. the endowment of a contract.
+///
+/// The fee is estimated by the node via the `payment_queryInfo` RPC.
+pub(crate) async fn check_balance<C>(
+    cli: &Client<DefaultNodeRuntime>,
+    rpc: &jsonrpsee::Client,
+    signer: &ExtrinsicSigner,
+    call: C,
+    transfer: u128,
+) -> Result<()>
+where
+    C: Call<DefaultNodeRuntime> + Send + Sync,
+{
+    let fee = estimate_fee(cli, rpc, signer, call).await?.fee;
+    let free = cli.account(signer.account_id(), None).await?.data.free;
+    log::info!("Free balance: {}, estimated fee: {}", free, fee);
+
+    ensure_sufficient_balance(free, fee, transfer)
+}
+
+/// Reads the weight and the fee of the dispatch info returned by `payment_queryInfo`.
+fn fee_estimate(info: &Value) -> Result<FeeEstimate> {
+    let weight = info
+        .get("weight")
+        .and_then(Value::as_u64)
+        .ok_or(anyhow::anyhow!("weight not found in the fee estimate"))?;
+    Ok(FeeEstimate {
+        weight,
+        fee: partial_fee(info)?,
+    })
+}
+
+/// Reads the `partialFee` of the dispatch info returned by `payment_queryInfo`.
+///
+/// Depending on the node version the fee is a number, a decimal string or a hex string.
+fn partial_fee(info: &Value) -> Result<u128> {
+    let fee = info
+        .get("partialFee")
+        .ok_or(anyhow::anyhow!("partialFee not found in the fee estimate"))?;
+    let parsed = match fee {
+        Value::Number(fee) => fee.as_u64().map(Into::into),
+        Value::String(fee) if fee.starts_with("0x") => {
+            u128::from_str_radix(fee.trim_start_matches("0x"), 16).ok()
+        }
+        Value::String(fee) => fee.parse().ok(),
+        _ => None,
+    };
+    parsed.ok_or(anyhow::anyhow!(
+        "Invalid partialFee {} in the fee estimate",
+        fee
+    ))
+}
+
+fn ensure_sufficient_balance(free: u128, fee: u128, transfer: u128) -> Result<()> {
+    let required = fee.saturating_add(transfer);
+    if free < required {
+        anyhow::bail!(
+            "Insufficient funds: the free balance of {} does not cover the estimated fee of {} \
+             plus the transferred {}, short by {}",
+            free,
+            fee,
+            transfer,
+            required - free
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn partial_fee_is_parsed() {
+        let fee = |fee: Value| partial_fee(&json!({ "partialFee": fee })).ok();
+
+        assert_eq!(fee(json!(125)), Some(125));
+        assert_eq!(fee(json!("125")), Some(125));
+        assert_eq!(fee(json!("0x7d")), Some(125));
+        assert_eq!(fee(json!(null)), None);
+    }
+
+    #[test]
+    fn fee_estimate_is_parsed() {
+        let info = json!({ "weight": 195000000, "class": "normal", "partialFee": "0x7d" });
+
+        assert_eq!(
+            fee_estimate(&info).unwrap(),
+            FeeEstimate {
+                weight: 195_000_000,
+                fee: 125
+            }
+        );
+        assert!(fee_estimate(&json!({ "partialFee": 125 })).is_err());
+    }
+
+    #[test]
+    fn shortfall_is_reported() {
+        assert!(ensure_sufficient_balance(150, 100, 50).is_ok());
+
+        let err = ensure_sufficient_balance(100, 100, 50).unwrap_err();
+
+        assert!(err.to_string().ends_with("short by 50"));
+    }
+}
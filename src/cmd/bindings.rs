@@ -0,0 +1,462 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Generates bindings for other ecosystems from the contract metadata.
+//!
+//! # Solidity ABI
+//!
+//! Messages are mapped to `function` entries and the constructor to a `constructor` entry. A
+//! message that does not mutate the storage is `view`, a payable one `payable`, all others are
+//! `nonpayable`.
+//!
+//! Types are mapped on a best-effort basis:
+//!
+//! - `bool` → `bool`, `str` → `string`
+//! - `u8` … `u128` → `uint8` … `uint128`, `i8` … `i128` → `int8` … `int128`
+//! - `Vec<u8>` → `bytes`, `[u8; N]` with `N <= 32` → `bytesN`
+//! - `Vec<T>` → `T[]`, `[T; N]` → `T[N]`
+//! - a struct with a single field, e.g. `AccountId`, → the type of that field
+//! - `()` as return type → no outputs
+//!
+//! All other types, e.g. enums, tuples and structs with several fields, cannot be mapped. The
+//! messages using them are left out of the ABI and reported.
+
+use std::{convert::TryFrom, fs, path::PathBuf};
+
+use anyhow::Result;
+use colored::Colorize;
+use serde_json::{json, Value};
+use structopt::StructOpt;
+
+use crate::{crate_metadata::CrateMetadata, workspace::ManifestPath, BindingsFormat};
+
+/// Generates bindings from the metadata of a contract.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "generate-bindings")]
+pub struct GenerateBindingsCommand {
+    /// The format of the bindings.
+    ///
+    /// - `solidity-abi`: A Solidity style ABI JSON of the contract constructor and messages.
+    #[structopt(
+        long,
+        default_value = "solidity-abi",
+        value_name = "solidity-abi",
+        verbatim_doc_comment
+    )]
+    format: BindingsFormat,
+    /// Path to the contract metadata, JSON or YAML, defaults to the `metadata.json` built for the
+    /// contract in the current directory
+    #[structopt(long, parse(from_os_str))]
+    metadata: Option<PathBuf>,
+    /// Write the bindings to this file instead of printing them
+    #[structopt(long, parse(from_os_str))]
+    output: Option<PathBuf>,
+}
+
+impl GenerateBindingsCommand {
+    pub fn exec(&self) -> Result<String> {
+        let metadata = self.load_metadata()?;
+        let (abi, unmapped) = match self.format {
+            BindingsFormat::SolidityAbi => solidity_abi(&metadata)?,
+        };
+        for item in &unmapped {
+            println!(
+                "{} {}",
+                "warning:".yellow().bold(),
+                format!("left out of the bindings: {}", item).bold()
+            );
+        }
+
+        let contents = serde_json::to_string_pretty(&abi)?;
+        match self.output.as_ref() {
+            Some(output) => {
+                fs::write(output, contents)?;
+                Ok(format!("Bindings written to {}", output.display()))
+            }
+            None => Ok(contents),
+        }
+    }
+
+    /// Reads the contract metadata, the target `metadata.json` of the current project by default.
+    fn load_metadata(&self) -> Result<Value> {
+        let path = match self.metadata.as_ref() {
+            Some(path) => path.clone(),
+            None => {
+                let manifest_path = ManifestPath::try_from(None::<PathBuf>)?;
+                CrateMetadata::collect(&manifest_path)?.dest_metadata
+            }
+        };
+        super::metadata::read_metadata(&path)
+    }
+}
+
+/// Maps the constructors and messages of the metadata to a Solidity style ABI.
+///
+/// Returns a tuple of `(abi, unmapped)`, where `unmapped` describes every constructor or message
+/// which was left out.
+fn solidity_abi(metadata: &Value) -> Result<(Value, Vec<String>)> {
+    let spec = metadata
+        .get("spec")
+        .ok_or(anyhow::anyhow!("spec not found in the metadata"))?;
+    let types = metadata
+        .get("types")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let specs = |kind: &str| {
+        spec.get(kind)
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    };
+
+    let mut abi = Vec::new();
+    let mut unmapped = Vec::new();
+    for (index, constructor) in specs("constructors").iter().enumerate() {
+        let name = spec_name(constructor);
+        if index > 0 {
+            unmapped.push(format!(
+                "constructor `{}`, only a single constructor is supported",
+                name
+            ));
+            continue;
+        }
+        match inputs(types, constructor) {
+            Ok(inputs) => abi.push(json!({
+                "type": "constructor",
+                "inputs": inputs,
+                "stateMutability": "payable",
+            })),
+            Err(err) => unmapped.push(format!("constructor `{}`, {}", name, err)),
+        }
+    }
+    for message in specs("messages") {
+        let name = spec_name(message);
+        match function(types, message) {
+            Ok(function) => abi.push(function),
+            Err(err) => unmapped.push(format!("message `{}`, {}", name, err)),
+        }
+    }
+    Ok((Value::Array(abi), unmapped))
+}
+
+/// Reduces the metadata to the names and selectors of the constructors, messages and events.
+///
+/// The types are left out, so the slim metadata is marked with `"slim": true` to keep clients
+/// from decoding with it.
+pub(crate) fn slim_metadata(metadata: &Value) -> Result<Value> {
+    let spec = metadata
+        .get("spec")
+        .ok_or(anyhow::anyhow!("spec not found in the metadata"))?;
+    let specs = |kind: &str| {
+        spec.get(kind)
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    };
+    let selectors = |kind: &str| {
+        specs(kind)
+            .iter()
+            .map(|spec| json!({ "name": spec_name(spec), "selector": spec["selector"] }))
+            .collect::<Vec<_>>()
+    };
+    let events = specs("events")
+        .iter()
+        .map(|event| json!({ "name": spec_name(event) }))
+        .collect::<Vec<_>>();
+    let hash = metadata
+        .pointer("/source/hash")
+        .or_else(|| metadata.get("codeHash"));
+
+    Ok(json!({
+        "slim": true,
+        "metadataVersion": metadata["metadataVersion"],
+        "contract": {
+            "name": metadata["contract"]["name"],
+            "version": metadata["contract"]["version"],
+        },
+        "hash": hash,
+        "spec": {
+            "constructors": selectors("constructors"),
+            "messages": selectors("messages"),
+            "events": events,
+        },
+    }))
+}
+
+/// Returns the name of a constructor, message or event, the segments of its path joined by `_`.
+pub(crate) fn spec_name(spec: &Value) -> String {
+    match spec.get("name") {
+        Some(Value::String(name)) => name.clone(),
+        Some(Value::Array(segments)) => segments
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join("_"),
+        _ => String::new(),
+    }
+}
+
+fn function(types: &[Value], message: &Value) -> Result<Value> {
+    let inputs = inputs(types, message)?;
+    let outputs = match message.get("returnType").filter(|ty| !ty.is_null()) {
+        Some(return_type) => {
+            let ty = type_id(return_type)?;
+            if is_unit(types, ty) {
+                Vec::new()
+            } else {
+                let ty = solidity_type(types, ty)
+                    .ok_or(anyhow::anyhow!("the return type cannot be mapped"))?;
+                vec![json!({ "name": "", "type": ty })]
+            }
+        }
+        None => Vec::new(),
+    };
+    let mutates = message.get("mutates").and_then(Value::as_bool) == Some(true);
+    let payable = message.get("payable").and_then(Value::as_bool) == Some(true);
+    let state_mutability = if payable {
+        "payable"
+    } else if mutates {
+        "nonpayable"
+    } else {
+        "view"
+    };
+    Ok(json!({
+        "type": "function",
+        "name": spec_name(message),
+        "inputs": inputs,
+        "outputs": outputs,
+        "stateMutability": state_mutability,
+    }))
+}
+
+fn inputs(types: &[Value], spec: &Value) -> Result<Vec<Value>> {
+    let args = spec
+        .get("args")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    args.iter()
+        .map(|arg| {
+            let name = arg.get("name").and_then(Value::as_str).unwrap_or_default();
+            let ty = arg
+                .get("type")
+                .ok_or(anyhow::anyhow!("argument `{}` has no type", name))
+                .and_then(type_id)?;
+            let ty = solidity_type(types, ty).ok_or(anyhow::anyhow!(
+                "the type of argument `{}` cannot be mapped",
+                name
+            ))?;
+            Ok(json!({ "name": name, "type": ty }))
+        })
+        .collect()
+}
+
+/// Returns the id in the type registry of a type spec of an argument or return type.
+fn type_id(type_spec: &Value) -> Result<u64> {
+    type_spec
+        .get("type")
+        .and_then(Value::as_u64)
+        .ok_or(anyhow::anyhow!("type spec should contain a type id"))
+}
+
+/// Returns the definition of a type in the type registry.
+fn type_def(types: &[Value], ty: u64) -> Option<&Value> {
+    // type ids in the registry are 1-based
+    types.get((ty as usize).checked_sub(1)?)?.get("def")
+}
+
+fn is_unit(types: &[Value], ty: u64) -> bool {
+    type_def(types, ty)
+        .and_then(|def| def.get("tuple"))
+        .and_then(Value::as_array)
+        .map(Vec::is_empty)
+        == Some(true)
+}
+
+/// Maps a type of the registry to a Solidity type, according to the rules of the module docs.
+fn solidity_type(types: &[Value], ty: u64) -> Option<String> {
+    let def = type_def(types, ty)?;
+    let is_u8 = |ty: u64| {
+        type_def(types, ty)
+            .and_then(|def| def.get("primitive"))
+            .and_then(Value::as_str)
+            == Some("u8")
+    };
+    if let Some(primitive) = def.get("primitive").and_then(Value::as_str) {
+        return match primitive {
+            "bool" => Some("bool".into()),
+            "str" => Some("string".into()),
+            "u8" | "u16" | "u32" | "u64" | "u128" => Some(format!("uint{}", &primitive[1..])),
+            "i8" | "i16" | "i32" | "i64" | "i128" => Some(format!("int{}", &primitive[1..])),
+            _ => None,
+        };
+    }
+    if let Some(sequence) = def.get("sequence") {
+        let elem = sequence.get("type")?.as_u64()?;
+        if is_u8(elem) {
+            return Some("bytes".into());
+        }
+        return Some(format!("{}[]", solidity_type(types, elem)?));
+    }
+    if let Some(array) = def.get("array") {
+        let elem = array.get("type")?.as_u64()?;
+        let len = array.get("len")?.as_u64()?;
+        if is_u8(elem) && len > 0 && len <= 32 {
+            return Some(format!("bytes{}", len));
+        }
+        return Some(format!("{}[{}]", solidity_type(types, elem)?, len));
+    }
+    if let Some(fields) = def
+        .get("composite")
+        .and_then(|composite| composite.get("fields"))
+        .and_then(Value::as_array)
+    {
+        if let [field] = fields.as_slice() {
+            return solidity_type(types, field.get("type")?.as_u64()?);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn types() -> Vec<Value> {
+        vec![
+            json!({ "def": { "primitive": "bool" } }),
+            json!({ "def": { "primitive": "u8" } }),
+            json!({ "def": { "array": { "len": 32, "type": 2 } } }),
+            json!({
+                "def": { "composite": { "fields": [ { "type": 3, "typeName": "[u8; 32]" } ] } },
+                "path": ["ink_env", "types", "AccountId"]
+            }),
+            json!({ "def": { "sequence": { "type": 2 } } }),
+            json!({ "def": { "primitive": "u128" } }),
+            json!({ "def": { "sequence": { "type": 6 } } }),
+            json!({ "def": { "tuple": [] } }),
+            json!({ "def": { "variant": { "variants": [] } } }),
+        ]
+    }
+
+    #[test]
+    fn slim_metadata_keeps_names_and_selectors() {
+        let metadata = json!({
+            "metadataVersion": "0.1.0",
+            "source": { "hash": "0x01", "language": "ink! 3.0.0-rc2" },
+            "contract": { "name": "flipper", "version": "0.1.0", "authors": ["Parity"] },
+            "spec": {
+                "constructors": [{ "name": ["new"], "selector": "0xd183512b", "args": [] }],
+                "messages": [{ "name": ["flip"], "selector": "0x633aa551", "mutates": true }],
+                "events": [{ "name": "Flipped", "args": [] }]
+            },
+            "types": types()
+        });
+
+        let slim = slim_metadata(&metadata).unwrap();
+
+        assert_eq!(
+            slim,
+            json!({
+                "slim": true,
+                "metadataVersion": "0.1.0",
+                "contract": { "name": "flipper", "version": "0.1.0" },
+                "hash": "0x01",
+                "spec": {
+                    "constructors": [{ "name": "new", "selector": "0xd183512b" }],
+                    "messages": [{ "name": "flip", "selector": "0x633aa551" }],
+                    "events": [{ "name": "Flipped" }]
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn maps_types() {
+        let types = types();
+        let mapped = (1..=9)
+            .map(|ty| solidity_type(&types, ty))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            mapped,
+            vec![
+                Some("bool".into()),
+                Some("uint8".into()),
+                Some("bytes32".into()),
+                Some("bytes32".into()),
+                Some("bytes".into()),
+                Some("uint128".into()),
+                Some("uint128[]".into()),
+                None,
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn unmappable_messages_are_reported() {
+        let metadata = json!({
+            "spec": {
+                "constructors": [
+                    { "args": [ { "name": "init_value", "type": { "displayName": ["bool"], "type": 1 } } ], "name": ["new"] },
+                    { "args": [], "name": ["default"] }
+                ],
+                "messages": [
+                    { "args": [], "mutates": true, "name": ["flip"], "payable": false, "returnType": null },
+                    { "args": [], "mutates": false, "name": ["get"], "payable": false, "returnType": { "displayName": ["bool"], "type": 1 } },
+                    { "args": [ { "name": "choice", "type": { "displayName": ["Choice"], "type": 9 } } ], "mutates": true, "name": ["choose"], "payable": false, "returnType": null }
+                ]
+            },
+            "types": types()
+        });
+
+        let (abi, unmapped) = solidity_abi(&metadata).unwrap();
+
+        assert_eq!(
+            abi,
+            json!([
+                {
+                    "type": "constructor",
+                    "inputs": [ { "name": "init_value", "type": "bool" } ],
+                    "stateMutability": "payable"
+                },
+                {
+                    "type": "function",
+                    "name": "flip",
+                    "inputs": [],
+                    "outputs": [],
+                    "stateMutability": "nonpayable"
+                },
+                {
+                    "type": "function",
+                    "name": "get",
+                    "inputs": [],
+                    "outputs": [ { "name": "", "type": "bool" } ],
+                    "stateMutability": "view"
+                }
+            ])
+        );
+        assert_eq!(
+            unmapped,
+            vec![
+                "constructor `default`, only a single constructor is supported".to_string(),
+                "message `choose`, the type of argument `choice` cannot be mapped".to_string(),
+            ]
+        );
+    }
+}
@@ -16,26 +16,52 @@
 
 use std::{
     convert::TryFrom,
-    fs::{metadata, File},
-    io::{Read, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    process::Command,
+    time::{Duration, Instant},
 };
 
+use super::{
+    bindings::{slim_metadata, spec_name},
+    deterministic::{require_deterministic, BinaryenAttestation},
+    lock_summary::write_lock_summary,
+    metadata::read_metadata,
+    optimizer::{OptimizationPasses, OptimizerConfig},
+    progress::BuildStep,
+    size_report::SizeReport,
+};
 use crate::{
-    crate_metadata::CrateMetadata,
+    crate_metadata::{ContractLanguage, CrateMetadata},
     util,
-    workspace::{ManifestPath, Profile, Workspace},
-    BuildArtifacts, BuildResult, UnstableFlags, UnstableOptions, VerbosityFlags,
+    workspace::{Manifest, ManifestPath, Profile, Workspace},
+    BuildArtifacts, BuildMode, BuildOptions, BuildResult, BuildTimings, MetadataCompat,
+    MetadataFormat, UnstableOptions, VerbosityFlags,
 };
 use crate::{OptimizationResult, Verbosity};
 use anyhow::{Context, Result};
+use cargo_metadata::{MetadataCommand, Package};
 use colored::Colorize;
-use parity_wasm::elements::{External, MemoryType, Module, Section};
+use parity_wasm::elements::{External, Internal, MemoryType, Module, Section};
+use rayon::prelude::*;
+use semver::{Version, VersionReq};
 use structopt::StructOpt;
 
 /// This is the maximum number of pages available for a contract to allocate.
 const MAX_MEMORY_PAGES: u32 = 16;
 
+/// The functions the contracts pallet calls, which a contract has to export.
+const ENTRY_POINTS: [&str; 2] = ["call", "deploy"];
+
+/// The target the contract is compiled for.
+pub(crate) const TARGET: &str = "wasm32-unknown-unknown";
+
+/// The version of the `binaryen` crate which bundles the `wasm-opt` optimizer with
+/// `cargo-contract`.
+///
+/// This is the version of the wrapper crate, e.g. `0.12.0`, not the release of binaryen it
+/// bundles. Read from the `Cargo.lock` by the build script.
+pub(crate) const BINARYEN_CRATE_VERSION: &str = env!("BINARYEN_CRATE_VERSION");
+
 /// Executes build of the smart-contract which produces a wasm binary that is ready for deploying.
 ///
 /// It does so by invoking `cargo build` and then post processing the final binary.
@@ -43,11 +69,19 @@ const MAX_MEMORY_PAGES: u32 = 16;
 #[structopt(name = "build")]
 pub struct BuildCommand {
     /// Path to the Cargo.toml of the contract to build
+    ///
+    /// Every contract of the workspace, i.e. every member depending on `ink_lang`, is built if
+    /// it is a virtual manifest.
     #[structopt(long, parse(from_os_str))]
     manifest_path: Option<PathBuf>,
+    /// Only build this contract of the workspace
+    #[structopt(long, value_name = "name")]
+    package: Option<String>,
     /// Which build artifacts to generate.
     ///
     /// - `all`: Generate the Wasm, the metadata and a bundled `<name>.contract` file.
+    ///   The Wasm is only embedded in the bundle, `metadata.json` never contains it, so it
+    ///   can be uploaded separately.
     ///
     /// - `code-only`: Only the Wasm is created, generation of metadata and a bundled
     ///   `<name>.contract` file is skipped.
@@ -58,6 +92,260 @@ pub struct BuildCommand {
         verbatim_doc_comment
     )]
     build_artifact: BuildArtifacts,
+    /// The layout of the generated metadata.
+    ///
+    /// - `current`: The metadata contains nested `source` and `contract` sections.
+    ///
+    /// - `legacy`: The flat layout expected by some older explorers, the fields of `source`
+    ///   and `contract` are placed at the top level next to the contract abi.
+    #[structopt(
+        long = "metadata-compat",
+        default_value = "current",
+        value_name = "current | legacy",
+        verbatim_doc_comment
+    )]
+    metadata_compat: MetadataCompat,
+    /// The format of the metadata file, the bundle is always JSON.
+    ///
+    /// - `json` or `pretty`: The canonical form, written to `metadata.json` pretty printed for
+    ///   diffing.
+    ///
+    /// - `compact`: Written to `metadata.json` without whitespace, smaller for uploading.
+    ///
+    /// - `yaml`: For reading and editing, written to `metadata.yaml`. The byte strings are hex
+    ///   encoded as in the JSON, but only the JSON is meant for hashing.
+    #[structopt(
+        long = "metadata-format",
+        default_value = "json",
+        value_name = "json | pretty | compact | yaml",
+        verbatim_doc_comment
+    )]
+    metadata_format: MetadataFormat,
+    /// Refuse to build if the version of the `binaryen` crate which bundles the `wasm-opt`
+    /// optimizer does not satisfy this semver requirement, e.g. `=0.12.0`.
+    ///
+    /// Guards against a changed optimizer silently producing a different code hash. The
+    /// requirement is on the version of the crate, not on the release of binaryen it bundles.
+    #[structopt(long, value_name = "semver")]
+    require_binaryen_crate_version: Option<VersionReq>,
+    /// Refuse to build if the toolchain, `binaryen` crate version or target differ from the ones
+    /// attested in this metadata or bundle of an earlier build
+    ///
+    /// Fails before building, reporting each mismatched component, instead of building a code
+    /// with a different hash.
+    #[structopt(long, parse(from_os_str), value_name = "attestation.json")]
+    verify_env: Option<PathBuf>,
+    /// Refuse to build unless the build is reproducible.
+    ///
+    /// Requires the toolchain to be pinned by a `rust-toolchain` file, a clean git working tree,
+    /// a `Cargo.lock` and an offline build with `--offline`, and the `binaryen` crate
+    /// version to be attested with `--require-binaryen-crate-version` or `--verify-env`. Each input
+    /// breaking the reproducibility is reported.
+    #[structopt(long)]
+    deterministic_only: bool,
+    /// Never access the network, fail if a dependency would have to be fetched
+    ///
+    /// The dependencies must already be vendored or in the cargo cache, e.g. by running
+    /// `cargo fetch` beforehand. Every invocation of cargo runs offline, as with
+    /// `CARGO_NET_OFFLINE=true`.
+    #[structopt(long)]
+    offline: bool,
+    /// The target directory of cargo, instead of `CARGO_TARGET_DIR` or the `target` directory of
+    /// the workspace. The artifacts are written to its `ink` subdirectory
+    ///
+    /// The contracts of a workspace name their metadata after their package. Other contracts
+    /// sharing a target directory also share its `metadata.json`, use `--dest-dir` to keep their
+    /// artifacts apart.
+    #[structopt(long, parse(from_os_str), value_name = "path")]
+    target_dir: Option<PathBuf>,
+    /// Still emit the Wasm if generating the metadata fails.
+    ///
+    /// The failure is reported as a warning and neither the metadata nor the bundle are written.
+    #[structopt(long)]
+    ignore_metadata_errors: bool,
+    /// Build for a shared (threaded) memory, allowing the `atomics` and `bulk-memory` features.
+    ///
+    /// Only some experimental runtimes support shared memory, most production runtimes do not
+    /// yet. By default the contract uses a single non-shared memory. With a shared memory the
+    /// optimizer is run with the `threads` and `bulk-memory` features.
+    #[structopt(long)]
+    shared_memory: bool,
+    /// Keep the `name` section and the DWARF debug info in the wasm, for profiling and debugging
+    ///
+    /// The wasm is still optimized, but it is larger, so such a build is not meant to be
+    /// deployed. By default all custom sections are stripped.
+    #[structopt(long)]
+    keep_debug_symbols: bool,
+    /// Keep the unoptimized wasm if optimizing grows it by more than this percentage
+    ///
+    /// In rare cases `wasm-opt` produces a larger module than its input.
+    #[structopt(long, default_value = "0", value_name = "percent")]
+    max_relative_size_increase: f64,
+    /// Read the settings of the `wasm-opt` optimizer from this TOML file, e.g. `wasm-opt.toml`
+    ///
+    /// The file may set `optimization-level`, `shrink-level`, `debug-info`, `passes` and
+    /// `features`. The optimizer flags of the command line take precedence.
+    #[structopt(long, parse(from_os_str), value_name = "wasm-opt.toml")]
+    opt_config: Option<PathBuf>,
+    /// The optimization passes of `wasm-opt`, like its `-O` flags: 0 to 4, `s` for size or `z`
+    /// for aggressively optimizing for size [default: level 3 with shrink level 1]
+    #[structopt(
+        long,
+        value_name = "0|1|2|3|4|s|z",
+        conflicts_with_all = &["opt-level", "shrink-level"]
+    )]
+    optimization_passes: Option<OptimizationPasses>,
+    /// The optimization level of `wasm-opt`, 0 to 4 [default: 3]
+    #[structopt(long, value_name = "level")]
+    opt_level: Option<u32>,
+    /// The shrink level of `wasm-opt`, 0 to 2 [default: 1]
+    #[structopt(long, value_name = "level")]
+    shrink_level: Option<u32>,
+    /// An optimization pass to run instead of the default passes of the optimization level,
+    /// can be passed several times
+    #[structopt(long, value_name = "pass", number_of_values = 1)]
+    opt_pass: Vec<String>,
+    /// A wasm feature `wasm-opt` may use in addition to the ones of the module, e.g. `sign-ext`,
+    /// can be passed several times
+    #[structopt(long, value_name = "feature", number_of_values = 1)]
+    opt_feature: Vec<String>,
+    /// Merge the measured weights of messages from this JSON file into the metadata
+    ///
+    /// The file maps message names or selectors to weights, e.g. `{ "flip": 1250000 }`. Every
+    /// entry must map to a message of the contract.
+    #[structopt(long, parse(from_os_str), value_name = "weights.json")]
+    weights: Option<PathBuf>,
+    /// Compress the Wasm embedded in the bundle, the standalone `.wasm` stays uncompressed
+    ///
+    /// The bundle records the compression in `source.wasm_compression` and has the metadata
+    /// version `0.2.0`, so that older readers do not mistake the compressed bytes for the code.
+    #[structopt(long)]
+    compress_wasm: bool,
+    /// Explain common failures of `cargo build` and how to fix them
+    ///
+    /// The output of cargo is shown once it finished. If the failure is explained its raw errors
+    /// are only shown with `--verbose`.
+    #[structopt(long)]
+    explain_error: bool,
+    /// Only regenerate the metadata if the code is unchanged since the last build, e.g. if only
+    /// doc comments changed
+    ///
+    /// The contract is built in full if the code cannot be proven unchanged, e.g. if it uses
+    /// `line!()` or has path dependencies.
+    #[structopt(long)]
+    reuse_wasm: bool,
+    /// Compile with the dev profile and skip optimizing the Wasm, for fast iteration.
+    ///
+    /// The code of a debug build is larger and slower, it is not meant to be deployed.
+    #[structopt(long, conflicts_with = "release")]
+    debug: bool,
+    /// Compile with the release profile and optimize the Wasm, the default
+    #[structopt(long)]
+    release: bool,
+    /// The name of the contract in the metadata, instead of the package name from Cargo.toml
+    #[structopt(long, value_name = "name")]
+    contract_name: Option<String>,
+    /// The version of the contract in the metadata, instead of the package version from
+    /// Cargo.toml
+    #[structopt(long, value_name = "semver", parse(try_from_str = Version::parse))]
+    contract_version: Option<Version>,
+    /// A runtime feature the target chain must support, in addition to the
+    /// `[package.metadata.contract] required-features` of Cargo.toml
+    ///
+    /// `pallet:<Name>` and `rpc:<method>` can be checked by `deploy --check-required-features`,
+    /// other features are recorded for the reader of the metadata. Can be passed several times.
+    #[structopt(long, value_name = "feature", number_of_values = 1)]
+    required_feature: Vec<String>,
+    /// Print how long each step of the build took
+    #[structopt(long)]
+    timings: bool,
+    /// Write a JSON report of how long each step of the build took to this file
+    #[structopt(long, parse(from_os_str), value_name = "path")]
+    timings_json: Option<PathBuf>,
+    /// Warn about constructors and messages without documentation in the metadata
+    ///
+    /// Explorers show the documentation to the users of a contract.
+    #[structopt(long)]
+    warn_missing_docs: bool,
+    /// Fail if a constructor or message has no documentation in the metadata
+    ///
+    /// The artifacts are still written.
+    #[structopt(long)]
+    deny_missing_docs: bool,
+    /// Fail if the contract defines no messages, instead of only warning
+    ///
+    /// A contract without messages is usually a mistake, e.g. the wrong crate was built or the
+    /// `#[ink(message)]` attributes are missing.
+    #[structopt(long)]
+    deny_empty_contract: bool,
+    /// The maximum code size in bytes the chain accepts, the `MaxCodeLen` of the contracts
+    /// pallet, warn if the Wasm is larger
+    ///
+    /// Uploading larger code fails on chain.
+    #[structopt(long, value_name = "bytes")]
+    max_code_size: Option<u64>,
+    /// Fail if the Wasm is larger than `--max-code-size`, instead of only warning
+    #[structopt(long, requires = "max-code-size")]
+    fail_on_oversize: bool,
+    /// The file extension of the bundle, some artifact stores key on it
+    #[structopt(
+        long,
+        default_value = "contract",
+        value_name = "ext",
+        parse(try_from_str = parse_bundle_extension)
+    )]
+    bundle_extension: String,
+    /// Also write `metadata.slim.json`, the metadata reduced to the names and selectors
+    ///
+    /// It has no types, so it is marked with `"slim": true` and cannot be used for decoding.
+    #[structopt(long)]
+    slim_metadata: bool,
+    /// Write a `<bundle>.mime` file next to the bundle, containing its MIME type
+    #[structopt(long)]
+    emit_mime: bool,
+    /// Write `lock-summary.json` next to the Wasm, recording the toolchain, the ink! version and
+    /// the code hash
+    ///
+    /// A lightweight provenance record, the metadata and its hash are not affected.
+    #[structopt(long)]
+    emit_lock_summary: bool,
+    /// Write `<name>.wat` next to the Wasm, the text format of the final, optimized Wasm
+    ///
+    /// For reviewing the generated code, e.g. when debugging codegen regressions.
+    #[structopt(long)]
+    emit_wat: bool,
+    /// Print the size of each section of the final Wasm and its largest functions
+    ///
+    /// Functions are named only if the Wasm keeps its `name` section, see
+    /// `--keep-debug-symbols`. With `--output-json` the report is part of the JSON.
+    #[structopt(long)]
+    size_report: bool,
+    /// Build a Wasm whose code hash does not depend on the machine, for verifying the source of
+    /// deployed code
+    ///
+    /// The local paths of the contract, the cargo home and the toolchain are remapped, the
+    /// `SOURCE_DATE_EPOCH` is pinned to 0 unless set, and no build time is recorded in the
+    /// metadata. The toolchain must still be the same, see `--deterministic-only`.
+    #[structopt(long)]
+    reproducible: bool,
+    /// Comma separated features of the contract to enable, e.g. `--features=debug,erc20`
+    ///
+    /// The default features are never enabled, as they usually include `std`.
+    #[structopt(long, value_name = "features", use_delimiter = true)]
+    features: Vec<String>,
+    /// Copy the artifacts to this directory, which is created if missing
+    ///
+    /// The printed paths are those of the copies. Each contract of a workspace is copied to a
+    /// subdirectory named after it.
+    #[structopt(long, parse(from_os_str), value_name = "path")]
+    dest_dir: Option<PathBuf>,
+    /// Print the paths of the artifacts and the optimization result as JSON, instead of the
+    /// progress and the summary
+    ///
+    /// The paths are absolute, artifacts which are not generated are `null`. Cargo still reports
+    /// on stderr. With `--timings` the timings are included.
+    #[structopt(long)]
+    output_json: bool,
     #[structopt(flatten)]
     verbosity: VerbosityFlags,
     #[structopt(flatten)]
@@ -67,25 +355,497 @@ pub struct BuildCommand {
 impl BuildCommand {
     pub fn exec(&self) -> Result<BuildResult> {
         let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
-        let unstable_flags: UnstableFlags =
-            TryFrom::<&UnstableOptions>::try_from(&self.unstable_options)?;
-        let verbosity: Option<Verbosity> = TryFrom::<&VerbosityFlags>::try_from(&self.verbosity)?;
-        execute(
-            &manifest_path,
+        self.configure_cargo()?;
+        self.exec_manifest(&manifest_path, self.verbosity()?, self.dest_dir.as_deref())
+    }
+
+    /// Configures every following invocation of cargo, including `cargo metadata`, to run
+    /// offline with `--offline` and to use the `--target-dir`.
+    fn configure_cargo(&self) -> Result<()> {
+        if self.offline {
+            std::env::set_var("CARGO_NET_OFFLINE", "true");
+        }
+        if let Some(target_dir) = self.target_dir.as_ref() {
+            // cargo runs in temporary directories, a relative path would be resolved against them
+            let target_dir = std::env::current_dir()?.join(target_dir);
+            std::env::set_var("CARGO_TARGET_DIR", target_dir);
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if the contracts of a workspace should be built, i.e. the manifest is a
+    /// virtual manifest or a `--package` is selected.
+    pub fn workspace(&self) -> Result<bool> {
+        if self.package.is_some() {
+            return Ok(true);
+        }
+        let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+        Ok(Manifest::new(&PathBuf::from(manifest_path))?.is_virtual())
+    }
+
+    /// Builds the contracts of the workspace, or only the one selected by `--package`. Returns
+    /// the results of all contracts followed by a summary.
+    ///
+    /// The contracts are built concurrently, bounded by the number of cores. Cargo serializes the
+    /// compilation of the contracts sharing a target directory, so mostly the optimization of
+    /// their Wasm runs in parallel.
+    pub fn exec_workspace(&self) -> Result<String> {
+        let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+        self.configure_cargo()?;
+        let metadata = MetadataCommand::new()
+            .manifest_path(manifest_path.as_ref())
+            .exec()
+            .context("Error invoking `cargo metadata`")?;
+
+        let mut contracts = super::affected::workspace_contracts(&metadata);
+        if let Some(package) = self.package.as_ref() {
+            contracts = select_package(contracts, package)?;
+        }
+        if contracts.is_empty() {
+            anyhow::bail!("The workspace has no contracts, i.e. members depending on `ink_lang`");
+        }
+        let verbosity = self.verbosity()?;
+        // the steps of contracts built concurrently would interleave, so unless verbose only the
+        // start and the end of each contract is reported
+        let contract_verbosity = match verbosity {
+            _ if Verbosity::at_least(verbosity, Verbosity::Verbose) => verbosity,
+            _ if contracts.len() > 1 => Some(Verbosity::Quiet),
+            _ => verbosity,
+        };
+        let results = contracts
+            .par_iter()
+            .map(|contract| {
+                util::maybe_println(
+                    verbosity,
+                    format!(
+                        "{} {}",
+                        "Building contract".bright_green().bold(),
+                        contract.name
+                    ),
+                );
+                let dest_dir = self.dest_dir.as_ref().map(|dir| dir.join(&contract.name));
+                let result = self
+                    .exec_manifest(
+                        &ManifestPath::new(&contract.manifest_path)?,
+                        contract_verbosity,
+                        dest_dir.as_deref(),
+                    )
+                    .context(format!("Failed to build the contract `{}`", contract.name))?;
+                util::maybe_println(
+                    verbosity,
+                    format!(
+                        "{} {}",
+                        "Built contract".bright_green().bold(),
+                        contract.name
+                    ),
+                );
+                Ok((contract.name.clone(), result))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if self.output_json {
+            let json = results
+                .iter()
+                .map(|(name, result)| {
+                    Ok(serde_json::json!({ "contract": name, "result": result.to_json(self.timings)? }))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            return Ok(serde_json::to_string_pretty(&json)?);
+        }
+        let mut out = String::new();
+        for (name, result) in &results {
+            out.push_str(&format!("\n{}{}\n", name.bold(), result.display()));
+        }
+        out.push_str(&format!("\nBuilt {} contracts.", results.len()));
+        Ok(out)
+    }
+
+    /// The verbosity of the build, quiet if the result is printed as JSON.
+    fn verbosity(&self) -> Result<Option<Verbosity>> {
+        if self.output_json {
+            return Ok(Some(Verbosity::Quiet));
+        }
+        TryFrom::<&VerbosityFlags>::try_from(&self.verbosity)
+    }
+
+    /// Builds the contract of `manifest_path`, copying its artifacts to `dest_dir` if given.
+    fn exec_manifest(
+        &self,
+        manifest_path: &ManifestPath,
+        verbosity: Option<Verbosity>,
+        dest_dir: Option<&Path>,
+    ) -> Result<BuildResult> {
+        let build_options = BuildOptions {
+            shared_memory: self.shared_memory,
+            keep_debug_symbols: self.keep_debug_symbols,
+            max_relative_size_increase: self.max_relative_size_increase,
+            weights: self
+                .weights
+                .as_ref()
+                .map(|weights| super::weights::load_weights(weights))
+                .transpose()?,
+            compress_wasm: self.compress_wasm,
+            explain_error: self.explain_error,
+            reuse_wasm: self.reuse_wasm,
+            contract_name: self.contract_name.clone(),
+            contract_version: self.contract_version.clone(),
+            metadata_format: self.metadata_format,
+            required_features: self.required_feature.clone(),
+            optimizer: self.optimizer_config()?,
+            build_mode: match (self.debug, self.release) {
+                (true, false) => BuildMode::Debug,
+                _ => BuildMode::Release,
+            },
+            reproducible: self.reproducible,
+            features: self.features.clone(),
+            unstable_flags: TryFrom::<&UnstableOptions>::try_from(&self.unstable_options)?,
+        };
+        if self.deterministic_only {
+            let crate_metadata = CrateMetadata::collect(manifest_path)?;
+            let package_dir = crate_metadata
+                .root_package
+                .manifest_path
+                .parent()
+                .expect("the manifest is in the package directory");
+            let binaryen = BinaryenAttestation {
+                required: self.require_binaryen_crate_version.as_ref(),
+                attestation: self.verify_env.as_deref(),
+            };
+            require_deterministic(
+                package_dir,
+                &crate_metadata.cargo_meta.workspace_root,
+                binaryen,
+            )?;
+        } else {
+            if let Some(required) = self.require_binaryen_crate_version.as_ref() {
+                check_binaryen_crate_version(required)?;
+            }
+            if let Some(attestation) = self.verify_env.as_ref() {
+                super::verify_env::verify_env(attestation)?;
+            }
+        }
+        let mut result = execute(
+            manifest_path,
             verbosity,
             true,
             self.build_artifact,
-            unstable_flags,
-        )
+            self.metadata_compat,
+            self.ignore_metadata_errors,
+            build_options,
+        )?;
+        if let Some(generated) = result.dest_bundle.take() {
+            let dest_bundle = generated.with_extension(&self.bundle_extension);
+            if generated != dest_bundle {
+                let mut artifacts = result.dest_wasm.iter().chain(&result.dest_metadata);
+                if let Some(artifact) = artifacts.find(|path| **path == dest_bundle) {
+                    anyhow::bail!(
+                        "The bundle would overwrite the artifact {}",
+                        artifact.display()
+                    );
+                }
+                std::fs::rename(&generated, &dest_bundle)?;
+            }
+            if self.emit_mime {
+                let dest_mime = PathBuf::from(format!("{}.mime", dest_bundle.display()));
+                std::fs::write(&dest_mime, format!("{}\n", BUNDLE_MIME_TYPE))?;
+                result.dest_mime = Some(dest_mime);
+            }
+            result.dest_bundle = Some(dest_bundle);
+        }
+        if self.slim_metadata {
+            if let Some(dest_metadata) = result.dest_metadata.as_ref() {
+                let metadata = read_metadata(dest_metadata)?;
+                let slim = slim_metadata(&metadata)?;
+                let dest_slim_metadata = dest_metadata.with_extension("slim.json");
+                std::fs::write(&dest_slim_metadata, serde_json::to_string_pretty(&slim)?)?;
+                result.dest_slim_metadata = Some(dest_slim_metadata);
+            }
+        }
+        if self.emit_lock_summary {
+            if let Some(dest_wasm) = result.dest_wasm.as_ref() {
+                let crate_metadata = CrateMetadata::collect(manifest_path)?;
+                result.dest_lock_summary = Some(write_lock_summary(&crate_metadata, dest_wasm)?);
+            }
+        }
+        if self.emit_wat {
+            if let Some(dest_wasm) = result.dest_wasm.as_ref() {
+                result.dest_wat = Some(write_wat(dest_wasm)?);
+            }
+        }
+        if self.size_report {
+            if let Some(dest_wasm) = result.dest_wasm.as_ref() {
+                let wasm = std::fs::read(dest_wasm)
+                    .context(format!("Failed to read {}", dest_wasm.display()))?;
+                result.size_report = Some(
+                    SizeReport::new(&wasm)
+                        .context(format!("Failed to analyze {}", dest_wasm.display()))?,
+                );
+            }
+        }
+        if self.timings && !self.output_json {
+            println!("\n{}", result.timings.display());
+        }
+        if let Some(timings_json) = self.timings_json.as_ref() {
+            let report = serde_json::to_string_pretty(&result.timings.to_json())?;
+            std::fs::write(timings_json, report)?;
+        }
+        if self.warn_missing_docs || self.deny_missing_docs {
+            if let Some(dest_metadata) = result.dest_metadata.as_ref() {
+                let metadata = read_metadata(dest_metadata)?;
+                let undocumented = undocumented_items(&metadata);
+                for item in &undocumented {
+                    util::maybe_println(
+                        verbosity,
+                        format!(
+                            "{} {}",
+                            "warning:".yellow().bold(),
+                            format!("{} has no documentation", item).bold()
+                        ),
+                    );
+                }
+                if self.deny_missing_docs && !undocumented.is_empty() {
+                    anyhow::bail!(
+                        "{} constructors or messages have no documentation",
+                        undocumented.len()
+                    );
+                }
+            }
+        }
+        if let Some(dest_metadata) = result.dest_metadata.as_ref() {
+            let metadata = read_metadata(dest_metadata)?;
+            if !has_messages(&metadata) {
+                if self.deny_empty_contract {
+                    anyhow::bail!(
+                        "The contract defines no messages, was the right crate built and are \
+                         its messages annotated with #[ink(message)]?"
+                    );
+                }
+                util::maybe_println(
+                    verbosity,
+                    format!(
+                        "{} {}",
+                        "warning:".yellow().bold(),
+                        "The contract defines no messages".bold()
+                    ),
+                );
+            }
+        }
+        if let (Some(max_code_size), Some(dest_wasm)) =
+            (self.max_code_size, result.dest_wasm.as_ref())
+        {
+            let code_size = std::fs::metadata(dest_wasm)?.len();
+            if let Some(oversize) = oversize(code_size, max_code_size) {
+                if self.fail_on_oversize {
+                    anyhow::bail!("{}", oversize);
+                }
+                util::maybe_println(
+                    verbosity,
+                    format!("{} {}", "warning:".yellow().bold(), oversize.bold()),
+                );
+            }
+        }
+        if let Some(dest_dir) = dest_dir {
+            copy_artifacts(&mut result, dest_dir)?;
+        }
+        Ok(result)
+    }
+
+    /// Returns `true` if the result should be printed as JSON.
+    pub fn output_json(&self) -> bool {
+        self.output_json
+    }
+
+    /// Returns `true` if the timings of the build should be reported.
+    pub fn timings(&self) -> bool {
+        self.timings
+    }
+
+    /// Returns the optimizer settings of the `--opt-config` file, overridden by the flags.
+    fn optimizer_config(&self) -> Result<OptimizerConfig> {
+        let file = match self.opt_config.as_ref() {
+            Some(path) => OptimizerConfig::load(path)?,
+            None => OptimizerConfig::default(),
+        };
+        let non_empty = |values: &Vec<String>| Some(values.clone()).filter(|v| !v.is_empty());
+        let levels = self.optimization_passes.map(OptimizationPasses::levels);
+        let optimizer = file.merge(OptimizerConfig {
+            optimization_level: levels.map(|(level, _)| level).or(self.opt_level),
+            shrink_level: levels.map(|(_, level)| level).or(self.shrink_level),
+            debug_info: Some(true).filter(|_| self.keep_debug_symbols),
+            passes: non_empty(&self.opt_pass),
+            features: non_empty(&self.opt_feature),
+        });
+        optimizer.validate()?;
+        Ok(optimizer)
+    }
+}
+
+/// Writes the text format of the Wasm at `dest_wasm` to `<name>.wat` next to it, returning its
+/// path.
+fn write_wat(dest_wasm: &Path) -> Result<PathBuf> {
+    let wasm =
+        std::fs::read(dest_wasm).context(format!("Failed to read {}", dest_wasm.display()))?;
+    let wat = wabt::wasm2wat(&wasm).context(format!(
+        "Failed to convert {} to the text format",
+        dest_wasm.display()
+    ))?;
+    let dest_wat = dest_wasm.with_extension("wat");
+    std::fs::write(&dest_wat, wat)?;
+    Ok(dest_wat)
+}
+
+/// Copies the artifacts of `result` to `dest_dir`, creating it if missing, and points `result` at
+/// the copies.
+fn copy_artifacts(result: &mut BuildResult, dest_dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(dest_dir)
+        .context(format!("Failed to create {}", dest_dir.display()))?;
+    let artifacts = vec![
+        &mut result.dest_metadata,
+        &mut result.dest_wasm,
+        &mut result.dest_wat,
+        &mut result.dest_bundle,
+        &mut result.dest_mime,
+        &mut result.dest_slim_metadata,
+        &mut result.dest_lock_summary,
+    ];
+    for artifact in artifacts.into_iter().filter_map(Option::as_mut) {
+        let dest = dest_dir.join(artifact.file_name().expect("artifacts are files"));
+        std::fs::copy(&artifact, &dest).context(format!(
+            "Failed to copy {} to {}",
+            artifact.display(),
+            dest_dir.display()
+        ))?;
+        *artifact = dest;
+    }
+    Ok(())
+}
+
+/// The MIME type of the bundle, which is a JSON file.
+const BUNDLE_MIME_TYPE: &str = "application/json";
+
+/// The extensions of the other artifacts written next to the bundle, which the bundle must not
+/// replace.
+const ARTIFACT_EXTENSIONS: [&str; 6] = [
+    "wasm",
+    "wat",
+    "json",
+    "yaml",
+    "slim.json",
+    "lock-summary.json",
+];
+
+/// Parses the `--bundle-extension`, which must not contain a path separator nor be the extension
+/// of another artifact.
+fn parse_bundle_extension(extension: &str) -> Result<String> {
+    let extension = extension.trim_start_matches('.');
+    if extension.is_empty() {
+        anyhow::bail!("The bundle extension must not be empty");
+    }
+    if extension.contains(|c: char| c == '/' || c == '\\' || c == std::path::MAIN_SEPARATOR) {
+        anyhow::bail!(
+            "The bundle extension '{}' must not contain a path separator",
+            extension
+        );
+    }
+    if ARTIFACT_EXTENSIONS
+        .iter()
+        .any(|artifact| artifact.eq_ignore_ascii_case(extension))
+    {
+        anyhow::bail!(
+            "The bundle extension '{}' is the extension of another artifact",
+            extension
+        );
+    }
+    Ok(extension.to_string())
+}
+
+/// Returns the constructors and messages of the metadata which have no documentation.
+fn undocumented_items(metadata: &serde_json::Value) -> Vec<String> {
+    let mut undocumented = Vec::new();
+    for (kind, key) in &[("constructor", "constructors"), ("message", "messages")] {
+        let specs = metadata
+            .get("spec")
+            .and_then(|spec| spec.get(key))
+            .and_then(serde_json::Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        for spec in specs {
+            let documented = spec
+                .get("docs")
+                .and_then(serde_json::Value::as_array)
+                .map(|docs| {
+                    docs.iter()
+                        .filter_map(serde_json::Value::as_str)
+                        .any(|line| !line.trim().is_empty())
+                })
+                == Some(true);
+            if !documented {
+                undocumented.push(format!("{} `{}`", kind, spec_name(spec)));
+            }
+        }
+    }
+    undocumented
+}
+
+/// Describes by how much the Wasm exceeds the maximum code size of the chain, if it does.
+fn oversize(code_size: u64, max_code_size: u64) -> Option<String> {
+    if code_size <= max_code_size {
+        return None;
+    }
+    let kb = |bytes: u64| bytes as f64 / 1000.0;
+    Some(format!(
+        "The Wasm ({:.1}K) is {:.1}K larger than the maximum code size of {:.1}K, uploading it \
+         would fail",
+        kb(code_size),
+        kb(code_size - max_code_size),
+        kb(max_code_size)
+    ))
+}
+
+/// Returns the contract named `package`, or an error listing the contracts.
+fn select_package<'a>(contracts: Vec<&'a Package>, package: &str) -> Result<Vec<&'a Package>> {
+    match contracts.iter().find(|contract| contract.name == package) {
+        Some(contract) => Ok(vec![*contract]),
+        None => {
+            let names = contracts
+                .iter()
+                .map(|contract| contract.name.as_str())
+                .collect::<Vec<_>>();
+            anyhow::bail!(
+                "No contract `{}` in the workspace, the contracts are: {}",
+                package,
+                names.join(", ")
+            )
+        }
     }
 }
 
+/// Returns whether the abi spec of the metadata contains any message.
+fn has_messages(metadata: &serde_json::Value) -> bool {
+    metadata
+        .get("spec")
+        .and_then(|spec| spec.get("messages"))
+        .and_then(serde_json::Value::as_array)
+        .map_or(false, |messages| !messages.is_empty())
+}
+
 #[derive(Debug, StructOpt)]
 #[structopt(name = "check")]
 pub struct CheckCommand {
     /// Path to the Cargo.toml of the contract to build
     #[structopt(long, parse(from_os_str))]
     manifest_path: Option<PathBuf>,
+    /// Check every contract of the workspace, i.e. every member depending on `ink_lang`
+    #[structopt(long)]
+    workspace: bool,
+    /// Only check the contracts of the workspace affected by the changes since this git ref
+    #[structopt(long, value_name = "ref", requires = "workspace")]
+    since: Option<String>,
+    /// Comma separated features of the contract to check, e.g. `--features=debug,erc20`
+    ///
+    /// The default features are never enabled, as they usually include `std`.
+    #[structopt(long, value_name = "features", use_delimiter = true)]
+    features: Vec<String>,
     #[structopt(flatten)]
     verbosity: VerbosityFlags,
     #[structopt(flatten)]
@@ -95,17 +855,157 @@ pub struct CheckCommand {
 impl CheckCommand {
     pub fn exec(&self) -> Result<BuildResult> {
         let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
-        let unstable_flags: UnstableFlags =
-            TryFrom::<&UnstableOptions>::try_from(&self.unstable_options)?;
+        let build_options = BuildOptions {
+            features: self.features.clone(),
+            unstable_flags: TryFrom::<&UnstableOptions>::try_from(&self.unstable_options)?,
+            ..Default::default()
+        };
         let verbosity: Option<Verbosity> = TryFrom::<&VerbosityFlags>::try_from(&self.verbosity)?;
         execute(
             &manifest_path,
             verbosity,
             false,
             BuildArtifacts::CheckOnly,
-            unstable_flags,
+            MetadataCompat::Current,
+            false,
+            build_options,
         )
     }
+
+    /// Returns `true` if all contracts of the workspace should be checked.
+    pub fn workspace(&self) -> bool {
+        self.workspace
+    }
+
+    /// Checks the contracts of the workspace, or with `--since` only those affected by the
+    /// changes since the git ref. Returns a summary of the checked and skipped contracts.
+    pub fn exec_workspace(&self) -> Result<String> {
+        let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+        let build_options = BuildOptions {
+            features: self.features.clone(),
+            unstable_flags: TryFrom::<&UnstableOptions>::try_from(&self.unstable_options)?,
+            ..Default::default()
+        };
+        let verbosity: Option<Verbosity> = TryFrom::<&VerbosityFlags>::try_from(&self.verbosity)?;
+        let metadata = MetadataCommand::new()
+            .manifest_path(manifest_path.as_ref())
+            .exec()
+            .context("Error invoking `cargo metadata`")?;
+
+        let contracts = super::affected::workspace_contracts(&metadata);
+        let (affected, unchanged) = match self.since.as_ref() {
+            Some(since) => {
+                let changed = super::affected::changed_files(&metadata.workspace_root, since)?;
+                super::affected::partition_affected(&metadata, contracts, &changed)
+            }
+            None => (contracts, Vec::new()),
+        };
+
+        for contract in &unchanged {
+            println!("{} {}", "Skipping unchanged contract".bold(), contract.name);
+        }
+        for contract in &affected {
+            println!(
+                "{} {}",
+                "Checking contract".bright_green().bold(),
+                contract.name
+            );
+            execute(
+                &ManifestPath::new(&contract.manifest_path)?,
+                verbosity,
+                false,
+                BuildArtifacts::CheckOnly,
+                MetadataCompat::Current,
+                false,
+                build_options.clone(),
+            )?;
+        }
+        Ok(format!(
+            "\nChecked {} contracts, skipped {} unchanged.",
+            affected.len(),
+            unchanged.len()
+        ))
+    }
+}
+
+/// Returns the `--features` arg of cargo enabling the `features` of the contract, if any.
+///
+/// The `prefix` selects the package declaring the features, e.g. `contract/` for the contract
+/// dependency of the metadata generator.
+pub(crate) fn features_arg(features: &[String], prefix: &str) -> Option<String> {
+    if features.is_empty() {
+        return None;
+    }
+    let features = features
+        .iter()
+        .map(|feature| format!("{}{}", prefix, feature))
+        .collect::<Vec<_>>();
+    Some(format!("--features={}", features.join(",")))
+}
+
+/// Returns the `RUSTFLAGS` with the linker args for building the contract.
+fn rustflags(shared_memory: bool, keep_debug_symbols: bool) -> String {
+    let mut rustflags =
+        "-C link-arg=-z -C link-arg=stack-size=65536 -C link-arg=--import-memory".to_string();
+    if shared_memory {
+        // a shared memory must declare its maximum size, in bytes
+        rustflags.push_str(&format!(
+            " -C target-feature=+atomics,+bulk-memory -C link-arg=--shared-memory \
+             -C link-arg=--max-memory={}",
+            MAX_MEMORY_PAGES * 64 * 1024
+        ));
+    }
+    if keep_debug_symbols {
+        rustflags.push_str(" -C debuginfo=2");
+    }
+    rustflags
+}
+
+/// Returns the `--remap-path-prefix` flags replacing each path by the stable prefix it is paired
+/// with, so that no machine specific path ends up in the Wasm.
+fn remap_path_prefixes<'a>(
+    remapped: impl IntoIterator<Item = &'a (PathBuf, &'static str)>,
+) -> Result<String> {
+    remapped
+        .into_iter()
+        .map(|(path, prefix)| {
+            let path = path.display().to_string();
+            // RUSTFLAGS are separated by whitespace
+            if path.contains(char::is_whitespace) {
+                anyhow::bail!(
+                    "--reproducible cannot remap the path '{}', it contains whitespace",
+                    path
+                );
+            }
+            Ok(format!(" --remap-path-prefix={}={}", path, prefix))
+        })
+        .collect()
+}
+
+/// Returns the cargo home, which contains the sources of the dependencies.
+fn cargo_home() -> Option<PathBuf> {
+    std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cargo")))
+}
+
+/// Returns the sysroot of the toolchain used in `dir`, which contains the sources of the standard
+/// library built by `build-std`.
+fn rustc_sysroot(dir: &Path) -> Result<PathBuf> {
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let output = Command::new(&rustc)
+        .args(&["--print", "sysroot"])
+        .current_dir(dir)
+        .output()
+        .context(format!("Error invoking `{} --print sysroot`", rustc))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "`{} --print sysroot` failed: {}",
+            rustc,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(PathBuf::from(String::from_utf8(output.stdout)?.trim()))
 }
 
 /// Builds the project in the specified directory, defaults to the current directory.
@@ -126,41 +1026,84 @@ impl CheckCommand {
 fn build_cargo_project(
     crate_metadata: &CrateMetadata,
     verbosity: Option<Verbosity>,
-    unstable_flags: UnstableFlags,
+    build_options: BuildOptions,
+    timings: &mut BuildTimings,
 ) -> Result<()> {
     util::assert_channel()?;
+    let start = Instant::now();
+    let mut compile = Duration::default();
 
-    // set linker args via RUSTFLAGS.
-    // Currently will override user defined RUSTFLAGS from .cargo/config. See https://github.com/paritytech/cargo-contract/issues/98.
-    std::env::set_var(
-        "RUSTFLAGS",
-        "-C link-arg=-z -C link-arg=stack-size=65536 -C link-arg=--import-memory",
-    );
+    let workspace_root = &crate_metadata.cargo_meta.workspace_root;
+    let mut remapped = Vec::new();
+    let mut pin_source_date_epoch = false;
+    if build_options.reproducible {
+        remapped.push((workspace_root.clone(), "/contract"));
+        if let Some(cargo_home) = cargo_home() {
+            remapped.push((cargo_home, "/cargo"));
+        }
+        remapped.push((rustc_sysroot(workspace_root)?, "/rustc"));
+        pin_source_date_epoch = std::env::var_os("SOURCE_DATE_EPOCH").is_none();
+    }
+
+    let build_mode = build_options.build_mode;
+    let mut cargo_build = |manifest_path: &ManifestPath| {
+        // set linker args via RUSTFLAGS.
+        // Currently will override user defined RUSTFLAGS from .cargo/config. See https://github.com/paritytech/cargo-contract/issues/98.
+        let mut rustflags = rustflags(
+            build_options.shared_memory,
+            build_options.keep_debug_symbols,
+        );
+        if build_options.reproducible {
+            // the manifests are rewritten to a temporary directory, which is the package root
+            let temp_dir = manifest_path
+                .directory()
+                .map(|dir| (dir.to_path_buf(), "/build"));
+            rustflags.push_str(&remap_path_prefixes(remapped.iter().chain(&temp_dir))?);
+        }
+        let mut env = vec![("RUSTFLAGS", rustflags.as_str())];
+        if pin_source_date_epoch {
+            env.push(("SOURCE_DATE_EPOCH", "0"));
+        }
 
-    let cargo_build = |manifest_path: &ManifestPath| {
         let target_dir = &crate_metadata.target_directory;
-        util::invoke_cargo(
-            "build",
-            &[
-                "--target=wasm32-unknown-unknown",
-                "-Zbuild-std",
-                "-Zbuild-std-features=panic_immediate_abort",
-                "--no-default-features",
-                "--release",
-                &format!("--target-dir={}", target_dir.to_string_lossy()),
-            ],
-            manifest_path.directory(),
-            verbosity,
-        )?;
+        let compile_start = Instant::now();
+        let mut args = vec![
+            format!("--target={}", TARGET),
+            "-Zbuild-std".to_string(),
+            "-Zbuild-std-features=panic_immediate_abort".to_string(),
+            "--no-default-features".to_string(),
+            format!("--target-dir={}", target_dir.to_string_lossy()),
+        ];
+        if build_mode == BuildMode::Release {
+            args.push("--release".to_string());
+        }
+        args.extend(features_arg(&build_options.features, ""));
+        if build_options.explain_error {
+            let output = util::invoke_cargo_capturing_stderr(
+                "build",
+                &args,
+                manifest_path.directory(),
+                verbosity,
+                &env,
+            )?;
+            super::explain::check_build_output(&output, verbosity)?;
+        } else {
+            util::invoke_cargo("build", &args, manifest_path.directory(), verbosity, &env)?;
+        }
+        compile = compile_start.elapsed();
         Ok(())
     };
 
-    if unstable_flags.original_manifest {
-        println!(
-            "{} {}",
-            "warning:".yellow().bold(),
-            "with 'original-manifest' enabled, the contract binary may not be of optimal size."
-                .bold()
+    if build_options.unstable_flags.original_manifest {
+        util::maybe_println(
+            verbosity,
+            format!(
+                "{} {}",
+                "warning:".yellow().bold(),
+                "with 'original-manifest' enabled, the contract binary may not be of optimal \
+                 size."
+                    .bold()
+            ),
         );
         cargo_build(&crate_metadata.manifest_path)?;
     } else {
@@ -174,9 +1117,8 @@ fn build_cargo_project(
             .using_temp(cargo_build)?;
     }
 
-    // clear RUSTFLAGS
-    std::env::remove_var("RUSTFLAGS");
-
+    timings.record("Rewriting manifest", start.elapsed() - compile);
+    timings.record("Compiling", compile);
     Ok(())
 }
 
@@ -215,10 +1157,38 @@ fn ensure_maximum_memory_pages(module: &mut Module, maximum_allowed_pages: u32)
     Ok(())
 }
 
+/// Ensures the wasm module exports all functions required by the contracts pallet.
+///
+/// Without them the deployment fails with an obscure error, e.g. if the crate is no `cdylib` or
+/// the ink! attributes are missing.
+fn ensure_entry_points(module: &Module) -> Result<()> {
+    let exported = module
+        .export_section()
+        .map(|section| section.entries())
+        .unwrap_or_default()
+        .iter()
+        .filter(|entry| matches!(entry.internal(), Internal::Function(_)))
+        .map(|entry| entry.field())
+        .collect::<Vec<_>>();
+    let missing = ENTRY_POINTS
+        .iter()
+        .filter(|entry_point| !exported.contains(entry_point))
+        .map(|entry_point| format!("`{}`", entry_point))
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        anyhow::bail!(
+            "The wasm module does not export the required functions {}. Is the crate type \
+             `cdylib` and the contract annotated with `#[ink::contract]`?",
+            missing.join(", ")
+        )
+    }
+    Ok(())
+}
+
 /// Strips all custom sections.
 ///
 /// Presently all custom sections are not required so they can be stripped safely.
-fn strip_custom_sections(module: &mut Module) {
+pub(crate) fn strip_custom_sections(module: &mut Module) {
     module.sections_mut().retain(|section| match section {
         Section::Custom(_) => false,
         Section::Name(_) => false,
@@ -227,100 +1197,203 @@ fn strip_custom_sections(module: &mut Module) {
     });
 }
 
+/// Strips all custom sections except for the `name` section and the DWARF `.debug_*` sections.
+fn strip_non_debug_sections(module: &mut Module) {
+    module.sections_mut().retain(|section| match section {
+        Section::Custom(custom) => custom.name() == "name" || custom.name().starts_with(".debug_"),
+        Section::Reloc(_) => false,
+        _ => true,
+    });
+}
+
 /// Performs required post-processing steps on the wasm artifact.
-fn post_process_wasm(crate_metadata: &CrateMetadata) -> Result<()> {
+fn post_process_wasm(
+    crate_metadata: &CrateMetadata,
+    build_mode: BuildMode,
+    keep_debug_symbols: bool,
+) -> Result<()> {
+    let original_wasm = original_wasm(crate_metadata, build_mode);
     // Deserialize wasm module from a file.
-    let mut module =
-        parity_wasm::deserialize_file(&crate_metadata.original_wasm).context(format!(
-            "Loading original wasm file '{}'",
-            crate_metadata.original_wasm.display()
-        ))?;
+    let mut module = parity_wasm::deserialize_file(&original_wasm).context(format!(
+        "Loading original wasm file '{}'",
+        original_wasm.display()
+    ))?;
 
-    // Perform optimization.
-    //
-    // In practice only tree-shaking is performed, i.e transitively removing all symbols that are
-    // NOT used by the specified entrypoints.
-    if pwasm_utils::optimize(&mut module, ["call", "deploy"].to_vec()).is_err() {
-        anyhow::bail!("Optimizer failed");
-    }
-    ensure_maximum_memory_pages(&mut module, MAX_MEMORY_PAGES)?;
-    strip_custom_sections(&mut module);
+    post_process_module(&mut module, keep_debug_symbols)?;
 
     parity_wasm::serialize_to_file(&crate_metadata.dest_wasm, module)?;
     Ok(())
 }
 
-/// Attempts to perform optional wasm optimization using `binaryen`.
+/// Checks the entry points and the memory of the module, and strips everything else the contracts
+/// pallet does not need.
 ///
-/// The intention is to reduce the size of bloated wasm binaries as a result of missing
-/// optimizations (or bugs?) between Rust and Wasm.
-fn optimize_wasm(crate_metadata: &CrateMetadata) -> Result<OptimizationResult> {
-    let mut optimized = crate_metadata.dest_wasm.clone();
-    optimized.set_file_name(format!("{}-opt.wasm", crate_metadata.package_name));
-
-    let codegen_config = binaryen::CodegenConfig {
-        // execute -O3 optimization passes (spends potentially a lot of time optimizing)
-        optimization_level: 3,
-        // the default
-        shrink_level: 1,
-        // the default
-        debug_info: false,
-    };
+/// Only the entry points stay exported, the functions which are no longer used are then removed by
+/// `wasm-opt`. The custom sections are stripped, except for the debug symbols if they are kept.
+fn post_process_module(module: &mut Module, keep_debug_symbols: bool) -> Result<()> {
+    ensure_entry_points(module)?;
+    if let Some(section) = module.export_section_mut() {
+        section
+            .entries_mut()
+            .retain(|entry| ENTRY_POINTS.contains(&entry.field()));
+    }
+    ensure_maximum_memory_pages(module, MAX_MEMORY_PAGES)?;
+    if keep_debug_symbols {
+        strip_non_debug_sections(module);
+    } else {
+        strip_custom_sections(module);
+    }
+    Ok(())
+}
+
+/// Returns the path of the Wasm compiled by cargo with the profile of the `build_mode`.
+fn original_wasm(crate_metadata: &CrateMetadata, build_mode: BuildMode) -> PathBuf {
+    match build_mode {
+        BuildMode::Release => crate_metadata.original_wasm.clone(),
+        // {target_dir}/wasm32-unknown-unknown/debug/{package_name}.wasm
+        BuildMode::Debug => crate_metadata
+            .target_directory
+            .join(TARGET)
+            .join(build_mode.profile())
+            .join(format!("{}.wasm", crate_metadata.package_name)),
+    }
+}
 
-    let mut dest_wasm_file = File::open(crate_metadata.dest_wasm.as_os_str())?;
-    let mut dest_wasm_file_content = Vec::new();
-    dest_wasm_file.read_to_end(&mut dest_wasm_file_content)?;
+/// Returns the version of the `binaryen` crate which bundles the `wasm-opt` optimizer.
+pub(crate) fn binaryen_crate_version() -> Version {
+    Version::parse(BINARYEN_CRATE_VERSION).expect("BINARYEN_CRATE_VERSION is a valid semver string")
+}
 
-    let mut module = binaryen::Module::read(&dest_wasm_file_content)
-        .map_err(|_| anyhow::anyhow!("binaryen failed to read file content"))?;
-    module.optimize(&codegen_config);
-    let optimized_wasm = module.write();
+/// Checks that the version of the `binaryen` crate which bundles the `wasm-opt` optimizer
+/// satisfies `required`.
+pub(crate) fn check_binaryen_crate_version(required: &VersionReq) -> Result<()> {
+    let found = binaryen_crate_version();
+    if !required.matches(&found) {
+        anyhow::bail!(
+            "The required `binaryen` crate version `{}` does not match the version found: `{}`",
+            required,
+            found
+        );
+    }
+    Ok(())
+}
 
-    let mut optimized_wasm_file = File::create(optimized.as_os_str())?;
-    optimized_wasm_file.write_all(&optimized_wasm)?;
+/// Attempts to perform optional wasm optimization using `binaryen`.
+///
+/// The intention is to reduce the size of bloated wasm binaries as a result of missing
+/// optimizations (or bugs?) between Rust and Wasm.
+fn optimize_wasm(
+    crate_metadata: &CrateMetadata,
+    max_relative_size_increase: f64,
+    optimizer: &OptimizerConfig,
+    verbosity: Option<Verbosity>,
+) -> Result<OptimizationResult> {
+    optimize_wasm_with(
+        &crate_metadata.dest_wasm,
+        max_relative_size_increase,
+        optimizer.describe(),
+        |wasm| optimizer.optimize(wasm),
+        verbosity,
+    )
+}
 
-    let original_size = metadata(&crate_metadata.dest_wasm)?.len() as f64 / 1000.0;
-    let optimized_size = metadata(&optimized)?.len() as f64 / 1000.0;
+/// Optimizes the wasm file at `dest_wasm` in place with `optimizer`, which is described by
+/// `optimization_level`.
+///
+/// In rare cases the optimizer produces a larger module. If the optimized module exceeds the
+/// original by more than `max_relative_size_increase` percent, the original is kept.
+fn optimize_wasm_with<F>(
+    dest_wasm: &Path,
+    max_relative_size_increase: f64,
+    optimization_level: String,
+    optimizer: F,
+    verbosity: Option<Verbosity>,
+) -> Result<OptimizationResult>
+where
+    F: FnOnce(&[u8]) -> Result<Vec<u8>>,
+{
+    let original_wasm = std::fs::read(dest_wasm)?;
+    let optimized_wasm = optimizer(&original_wasm)?;
 
-    // overwrite existing destination wasm file with the optimised version
-    std::fs::rename(&optimized, &crate_metadata.dest_wasm)?;
+    let original_size = original_wasm.len() as f64 / 1000.0;
+    let optimized_size = optimized_wasm.len() as f64 / 1000.0;
+    let kept_original = optimized_size > original_size * (1.0 + max_relative_size_increase / 100.0);
+    if kept_original {
+        util::maybe_println(
+            verbosity,
+            format!(
+                "{} {}",
+                "warning:".yellow().bold(),
+                format!(
+                    "the optimized wasm ({:.1}K) is larger than the original ({:.1}K), \
+                     keeping the original",
+                    optimized_size, original_size
+                )
+                .bold()
+            ),
+        );
+    } else {
+        // overwrite existing destination wasm file with the optimised version
+        std::fs::write(dest_wasm, &optimized_wasm)?;
+    }
     Ok(OptimizationResult {
         original_size,
         optimized_size,
+        kept_original,
+        optimization_level,
     })
 }
 
 /// Executes build of the smart-contract which produces a wasm binary that is ready for deploying.
 ///
 /// It does so by invoking `cargo build` and then post processing the final binary.
-fn execute(
+pub(crate) fn execute(
     manifest_path: &ManifestPath,
     verbosity: Option<Verbosity>,
     optimize_contract: bool,
     build_artifact: BuildArtifacts,
-    unstable_flags: UnstableFlags,
+    metadata_compat: MetadataCompat,
+    ignore_metadata_errors: bool,
+    build_options: BuildOptions,
 ) -> Result<BuildResult> {
     let crate_metadata = CrateMetadata::collect(manifest_path)?;
     if build_artifact == BuildArtifacts::CodeOnly || build_artifact == BuildArtifacts::CheckOnly {
+        let mut timings = BuildTimings::default();
+        let build_mode = build_options.build_mode;
         let (maybe_dest_wasm, maybe_optimization_result) = execute_with_crate_metadata(
             &crate_metadata,
             verbosity,
             optimize_contract,
             build_artifact,
-            unstable_flags,
+            build_options,
+            &mut timings,
         )?;
         let res = BuildResult {
             dest_wasm: maybe_dest_wasm,
+            dest_wat: None,
             dest_metadata: None,
             dest_bundle: None,
+            dest_mime: None,
+            dest_slim_metadata: None,
+            dest_lock_summary: None,
             target_directory: crate_metadata.target_directory,
             optimization_result: maybe_optimization_result,
+            size_report: None,
             build_artifact,
+            build_mode,
+            timings,
         };
         return Ok(res);
     }
 
-    let res = super::metadata::execute(&manifest_path, verbosity, build_artifact, unstable_flags)?;
+    let res = super::metadata::execute(
+        &manifest_path,
+        verbosity,
+        build_artifact,
+        metadata_compat,
+        ignore_metadata_errors,
+        build_options,
+    )?;
     Ok(res)
 }
 
@@ -338,44 +1411,406 @@ pub(crate) fn execute_with_crate_metadata(
     verbosity: Option<Verbosity>,
     optimize_contract: bool,
     build_artifact: BuildArtifacts,
-    unstable_flags: UnstableFlags,
+    build_options: BuildOptions,
+    timings: &mut BuildTimings,
 ) -> Result<(Option<PathBuf>, Option<OptimizationResult>)> {
-    println!(
-        " {} {}",
-        format!("[1/{}]", build_artifact.steps()).bold(),
-        "Building cargo project".bright_green().bold()
-    );
-    build_cargo_project(&crate_metadata, verbosity, unstable_flags)?;
-    println!(
-        " {} {}",
-        format!("[2/{}]", build_artifact.steps()).bold(),
-        "Post processing wasm file".bright_green().bold()
-    );
-    post_process_wasm(&crate_metadata)?;
+    let build_mode = build_options.build_mode;
+    let step = match crate_metadata.language {
+        ContractLanguage::Ink => "Building cargo project",
+        ContractLanguage::Solidity { .. } => "Compiling Solidity with solang",
+    };
+    let steps = build_artifact.steps(build_mode);
+    let step = BuildStep::start(verbosity, 1, steps, step);
+    let shared_memory = build_options.shared_memory;
+    let keep_debug_symbols = build_options.keep_debug_symbols;
+    let max_relative_size_increase = build_options.max_relative_size_increase;
+    let optimizer = if shared_memory {
+        build_options
+            .optimizer
+            .clone()
+            .with_shared_memory_features()
+    } else {
+        build_options.optimizer.clone()
+    };
+    match &crate_metadata.language {
+        ContractLanguage::Ink => {
+            build_cargo_project(&crate_metadata, verbosity, build_options, timings)?
+        }
+        ContractLanguage::Solidity { source } => {
+            let original_wasm = original_wasm(crate_metadata, build_mode);
+            if let Some(dir) = original_wasm.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            timings.time("Compiling", || {
+                super::solang::compile(crate_metadata, source, &original_wasm, verbosity)
+            })?
+        }
+    }
+    drop(step);
+    let step = BuildStep::start(verbosity, 2, steps, "Post processing wasm file");
+    timings.time("Post processing", || {
+        post_process_wasm(&crate_metadata, build_mode, keep_debug_symbols)
+    })?;
     if !optimize_contract {
         return Ok((None, None));
     }
-    println!(
-        " {} {}",
-        format!("[3/{}]", build_artifact.steps()).bold(),
-        "Optimizing wasm file".bright_green().bold()
-    );
-    let optimization_result = optimize_wasm(&crate_metadata)?;
+    if build_mode == BuildMode::Debug {
+        return Ok((Some(crate_metadata.dest_wasm.clone()), None));
+    }
+    drop(step);
+    let _step = BuildStep::start(verbosity, 3, steps, "Optimizing wasm file");
+    let optimization_result = timings.time("Optimizing", || {
+        optimize_wasm(
+            &crate_metadata,
+            max_relative_size_increase,
+            &optimizer,
+            verbosity,
+        )
+    })?;
     Ok((
         Some(crate_metadata.dest_wasm.clone()),
         Some(optimization_result),
     ))
 }
 
-#[cfg(feature = "test-ci-only")]
 #[cfg(test)]
 mod tests {
-    use crate::{cmd, util::tests::with_tmp_dir, BuildArtifacts, ManifestPath, UnstableFlags};
+    use super::{
+        check_binaryen_crate_version, copy_artifacts, ensure_entry_points, features_arg,
+        has_messages, optimize_wasm_with, oversize, parse_bundle_extension, post_process_module,
+        remap_path_prefixes, rustflags, strip_custom_sections, strip_non_debug_sections,
+        undocumented_items, write_wat, OptimizerConfig, BINARYEN_CRATE_VERSION,
+    };
+    use crate::{util::tests::with_tmp_dir, BuildArtifacts, BuildMode, BuildResult};
+    use parity_wasm::elements::{CustomSection, Instruction, Module, Section};
+    use semver::VersionReq;
+    use serde_json::json;
+    use std::path::PathBuf;
+
+    #[test]
+    fn shared_memory_rustflags() {
+        assert!(!rustflags(false, false).contains("shared-memory"));
+        assert!(rustflags(true, false).ends_with(
+            "-C target-feature=+atomics,+bulk-memory -C link-arg=--shared-memory \
+             -C link-arg=--max-memory=1048576"
+        ));
+    }
+
+    #[test]
+    fn debug_symbols_are_kept_on_request() {
+        assert!(!rustflags(false, false).contains("debuginfo"));
+        assert!(rustflags(false, true).ends_with(" -C debuginfo=2"));
+
+        let section = |name: &str| Section::Custom(CustomSection::new(name.to_string(), vec![0]));
+        let module = Module::new(vec![
+            section("name"),
+            section(".debug_info"),
+            section("producers"),
+        ]);
+        let names = |module: &Module| {
+            module
+                .custom_sections()
+                .map(|custom| custom.name().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let mut kept = module.clone();
+        strip_non_debug_sections(&mut kept);
+        assert_eq!(names(&kept), vec!["name", ".debug_info"]);
+        let mut stripped = module;
+        strip_custom_sections(&mut stripped);
+        assert!(names(&stripped).is_empty());
+    }
+
+    #[test]
+    fn missing_entry_points_are_reported() {
+        const CONTRACT: &str = r#"
+(module
+    (func (export "call"))
+    (func (export "deploy"))
+)
+"#;
+        const MISSING_DEPLOY: &str = r#"
+(module
+    (func (export "call"))
+    (global (export "deploy") i32 (i32.const 0))
+)
+"#;
+        let module = |wat| {
+            let wasm = wabt::wat2wasm(wat).expect("invalid wabt");
+            parity_wasm::deserialize_buffer::<Module>(&wasm).unwrap()
+        };
+
+        assert!(ensure_entry_points(&module(CONTRACT)).is_ok());
+        let err = ensure_entry_points(&module(MISSING_DEPLOY))
+            .expect_err("missing entry point must fail")
+            .to_string();
+        assert!(err.contains("required functions `deploy`."));
+        assert!(ensure_entry_points(&module("(module)"))
+            .unwrap_err()
+            .to_string()
+            .contains("`call`, `deploy`"));
+    }
 
+    #[test]
+    fn shared_memory_module_is_optimized() {
+        const SHARED_MEMORY: &str = r#"
+(module
+    (import "env" "memory" (memory 2 16 shared))
+    (func (export "call") (i32.atomic.store (i32.const 0) (i32.const 1)))
+    (func (export "deploy"))
+    (func (export "helper"))
+)
+"#;
+        let mut features = wabt::Features::new();
+        features.enable_threads();
+        let wasm = wabt::wat2wasm_with_features(SHARED_MEMORY, features).expect("invalid wabt");
+        let mut module = parity_wasm::deserialize_buffer::<Module>(&wasm).unwrap();
+
+        post_process_module(&mut module, false).unwrap();
+        let exports = module
+            .export_section()
+            .unwrap()
+            .entries()
+            .iter()
+            .map(|entry| entry.field())
+            .collect::<Vec<_>>();
+        assert_eq!(exports, vec!["call", "deploy"]);
+        let optimized = OptimizerConfig::default()
+            .with_shared_memory_features()
+            .optimize(&parity_wasm::serialize(module).unwrap())
+            .unwrap();
+
+        let optimized = parity_wasm::deserialize_buffer::<Module>(&optimized).unwrap();
+        assert_eq!(optimized.functions_space(), 2);
+        assert!(optimized
+            .code_section()
+            .unwrap()
+            .bodies()
+            .iter()
+            .flat_map(|body| body.code().elements())
+            .any(|instruction| matches!(instruction, Instruction::Atomics(_))));
+    }
+
+    #[test]
+    fn oversized_code_is_reported() {
+        assert_eq!(oversize(100_000, 128_000), None);
+        assert_eq!(oversize(128_000, 128_000), None);
+        assert_eq!(
+            oversize(130_500, 128_000).unwrap(),
+            "The Wasm (130.5K) is 2.5K larger than the maximum code size of 128.0K, uploading \
+             it would fail"
+        );
+    }
+
+    #[test]
+    fn wat_is_written_next_to_the_wasm() {
+        with_tmp_dir(|path| {
+            let dest_wasm = path.join("flipper.wasm");
+            std::fs::write(
+                &dest_wasm,
+                wabt::wat2wasm(r#"(module (func (export "call")))"#)?,
+            )?;
+
+            let dest_wat = write_wat(&dest_wasm)?;
+
+            assert_eq!(dest_wat, path.join("flipper.wat"));
+            let wat = std::fs::read_to_string(&dest_wat)?;
+            assert!(wat.starts_with("(module"));
+            assert!(wat.contains(r#"(export "call""#));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn artifacts_are_copied_to_the_dest_dir() {
+        with_tmp_dir(|path| {
+            let target = path.join("target").join("ink");
+            std::fs::create_dir_all(&target)?;
+            std::fs::write(target.join("flipper.wasm"), [0u8, 97, 115, 109])?;
+            std::fs::write(target.join("metadata.json"), "{}")?;
+            let mut result = BuildResult {
+                dest_metadata: Some(target.join("metadata.json")),
+                dest_wasm: Some(target.join("flipper.wasm")),
+                dest_wat: None,
+                dest_bundle: None,
+                dest_mime: None,
+                dest_slim_metadata: None,
+                dest_lock_summary: None,
+                target_directory: target.clone(),
+                optimization_result: None,
+                size_report: None,
+                build_artifact: BuildArtifacts::All,
+                build_mode: BuildMode::Release,
+                timings: Default::default(),
+            };
+            let dest_dir = path.join("out").join("flipper");
+
+            copy_artifacts(&mut result, &dest_dir)?;
+
+            assert_eq!(result.dest_wasm, Some(dest_dir.join("flipper.wasm")));
+            assert_eq!(result.dest_metadata, Some(dest_dir.join("metadata.json")));
+            assert_eq!(result.dest_bundle, None);
+            assert_eq!(
+                std::fs::read_to_string(dest_dir.join("metadata.json"))?,
+                "{}"
+            );
+            assert!(target.join("flipper.wasm").exists());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn features_are_forwarded_to_cargo() {
+        let features = vec!["debug".to_string(), "erc20".to_string()];
+        assert_eq!(features_arg(&[], ""), None);
+        assert_eq!(
+            features_arg(&features, ""),
+            Some("--features=debug,erc20".to_string())
+        );
+        assert_eq!(
+            features_arg(&features, "contract/"),
+            Some("--features=contract/debug,contract/erc20".to_string())
+        );
+    }
+
+    #[test]
+    fn local_paths_are_remapped() {
+        let remapped = vec![
+            (PathBuf::from("/home/alice/flipper"), "/contract"),
+            (PathBuf::from("/home/alice/.cargo"), "/cargo"),
+        ];
+
+        assert_eq!(
+            remap_path_prefixes(&remapped).unwrap(),
+            " --remap-path-prefix=/home/alice/flipper=/contract \
+             --remap-path-prefix=/home/alice/.cargo=/cargo"
+        );
+        assert!(
+            remap_path_prefixes(&[(PathBuf::from("/home/alice/my contracts"), "/contract")])
+                .unwrap_err()
+                .to_string()
+                .contains("it contains whitespace")
+        );
+    }
+
+    #[test]
+    fn binaryen_crate_version_requirement_is_checked() {
+        let matching = VersionReq::parse(&format!("={}", BINARYEN_CRATE_VERSION)).unwrap();
+        assert!(check_binaryen_crate_version(&matching).is_ok());
+
+        let mismatching = VersionReq::parse("=0.0.1").unwrap();
+        let err = check_binaryen_crate_version(&mismatching)
+            .expect_err("mismatching version must fail")
+            .to_string();
+        assert!(err.contains("=0.0.1"));
+        assert!(err.contains(BINARYEN_CRATE_VERSION));
+    }
+
+    #[test]
+    fn inflated_optimizer_output_is_discarded() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest_wasm = dir.path().join("contract.wasm");
+        std::fs::write(&dest_wasm, vec![0u8; 1000]).unwrap();
+
+        let inflated = optimize_wasm_with(
+            &dest_wasm,
+            10.0,
+            "-O3".into(),
+            |wasm| Ok([wasm, wasm].concat()),
+            None,
+        );
+        let result = inflated.unwrap();
+        assert!(result.kept_original);
+        assert_eq!(std::fs::read(&dest_wasm).unwrap().len(), 1000);
+
+        let within_threshold = optimize_wasm_with(
+            &dest_wasm,
+            10.0,
+            "-O3".into(),
+            |wasm| Ok(wasm.iter().chain(&wasm[..50]).copied().collect()),
+            None,
+        );
+        let result = within_threshold.unwrap();
+        assert!(!result.kept_original);
+        assert_eq!(result.optimization_level, "-O3");
+        assert_eq!(std::fs::read(&dest_wasm).unwrap().len(), 1050);
+    }
+
+    #[test]
+    fn bundle_extension_is_validated() {
+        assert_eq!(parse_bundle_extension("ink").unwrap(), "ink");
+        assert_eq!(
+            parse_bundle_extension(".contract.json").unwrap(),
+            "contract.json"
+        );
+        assert!(parse_bundle_extension("").is_err());
+        assert!(parse_bundle_extension("../contract").is_err());
+        assert!(parse_bundle_extension("a\\b").is_err());
+        assert!(parse_bundle_extension("wasm").is_err());
+        assert!(parse_bundle_extension(".WASM").is_err());
+        assert!(parse_bundle_extension("slim.json").is_err());
+    }
+
+    #[test]
+    fn undocumented_items_are_reported() {
+        let metadata = json!({
+            "spec": {
+                "constructors": [
+                    { "args": [], "docs": [" Creates a new flipper."], "name": ["new"] },
+                    { "args": [], "docs": [], "name": ["default"] }
+                ],
+                "messages": [
+                    { "args": [], "docs": [" Flips the value."], "name": ["flip"] },
+                    { "args": [], "docs": [""], "name": ["get"] },
+                    { "args": [], "name": ["Trait", "set"] }
+                ]
+            }
+        });
+
+        assert_eq!(
+            undocumented_items(&metadata),
+            vec![
+                "constructor `default`",
+                "message `get`",
+                "message `Trait_set`"
+            ]
+        );
+    }
+
+    #[test]
+    fn contracts_without_messages_are_detected() {
+        let message_less = json!({
+            "spec": {
+                "constructors": [{ "args": [], "docs": [], "name": ["new"], "selector": "0x9bae9d5e" }],
+                "docs": [],
+                "events": [],
+                "messages": []
+            }
+        });
+        let flipper = json!({
+            "spec": {
+                "constructors": [],
+                "messages": [{ "args": [], "docs": [], "name": ["flip"], "selector": "0x633aa551" }]
+            }
+        });
+
+        assert!(!has_messages(&message_less));
+        assert!(!has_messages(&json!({})));
+        assert!(has_messages(&flipper));
+    }
+
+    #[cfg(feature = "test-ci-only")]
     #[test]
     fn build_template() {
+        use crate::{
+            cmd, util::tests::with_tmp_dir, BuildArtifacts, BuildOptions, ManifestPath,
+            MetadataCompat,
+        };
+
         with_tmp_dir(|path| {
-            cmd::new::execute("new_project", Some(path)).expect("new project creation failed");
+            cmd::new::execute("new_project", Some(path), Default::default())
+                .expect("new project creation failed");
             let manifest_path =
                 ManifestPath::new(&path.join("new_project").join("Cargo.toml")).unwrap();
             let res = super::execute(
@@ -383,7 +1818,9 @@ mod tests {
                 None,
                 true,
                 BuildArtifacts::All,
-                UnstableFlags::default(),
+                MetadataCompat::Current,
+                false,
+                BuildOptions::default(),
             )
             .expect("build failed");
 
@@ -396,4 +1833,85 @@ mod tests {
             Ok(())
         })
     }
+
+    #[cfg(feature = "test-ci-only")]
+    #[test]
+    fn workspace_members_keep_their_own_artifacts() {
+        use super::BuildCommand;
+        use crate::{cmd, util::tests::with_tmp_dir};
+        use serde_json::Value;
+        use structopt::StructOpt;
+
+        with_tmp_dir(|path| {
+            cmd::new::execute("alice", Some(path), Default::default())?;
+            cmd::new::execute("bob", Some(path), Default::default())?;
+            let manifest_path = path.join("Cargo.toml");
+            std::fs::write(
+                &manifest_path,
+                "[workspace]\nmembers = [\"alice\", \"bob\"]\n",
+            )?;
+            let build = BuildCommand::from_iter_safe(&[
+                "build",
+                "--manifest-path",
+                &manifest_path.to_string_lossy(),
+                "--output-json",
+            ])?;
+
+            let results: Vec<Value> = serde_json::from_str(&build.exec_workspace()?)?;
+            let contract_name = |contract: &str| -> anyhow::Result<Value> {
+                let result = results
+                    .iter()
+                    .find(|result| result["contract"] == contract)
+                    .expect("the contract is built");
+                let dest_metadata = result["result"]["dest_metadata"]
+                    .as_str()
+                    .expect("the metadata is generated");
+                assert!(dest_metadata.ends_with(&format!("{}.json", contract)));
+                let metadata: Value = serde_json::from_slice(&std::fs::read(dest_metadata)?)?;
+                Ok(metadata["contract"]["name"].clone())
+            };
+
+            assert_eq!(contract_name("alice")?, "alice");
+            assert_eq!(contract_name("bob")?, "bob");
+            Ok(())
+        })
+    }
+
+    #[cfg(feature = "test-ci-only")]
+    #[test]
+    fn reproducible_builds_in_different_directories_have_the_same_code_hash() {
+        use crate::{
+            cmd::{self, metadata::blake2_hash},
+            BuildOptions, ManifestPath, MetadataCompat,
+        };
+        use std::path::Path;
+
+        with_tmp_dir(|path| {
+            let build = |dir: &Path| -> anyhow::Result<_> {
+                std::fs::create_dir_all(dir)?;
+                cmd::new::execute("flipper", Some(dir), Default::default())?;
+                let manifest_path = ManifestPath::new(&dir.join("flipper").join("Cargo.toml"))?;
+                let res = super::execute(
+                    &manifest_path,
+                    None,
+                    true,
+                    BuildArtifacts::CodeOnly,
+                    MetadataCompat::Current,
+                    false,
+                    BuildOptions {
+                        reproducible: true,
+                        ..Default::default()
+                    },
+                )?;
+                let wasm = std::fs::read(res.dest_wasm.expect("the Wasm is built"))?;
+                Ok(blake2_hash(&wasm))
+            };
+
+            let first = build(&path.join("alice"))?;
+            let second = build(&path.join("bob").join("contracts"))?;
+
+            assert_eq!(first, second);
+            Ok(())
+        })
+    }
 }
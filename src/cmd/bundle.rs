@@ -0,0 +1,212 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Reads the code of a `<name>.contract` bundle, reporting precisely what is wrong with a
+//! malformed one.
+//!
+//! Every problem is reported with the JSON pointer of the offending location, e.g.
+//! `/source/wasm: expected a string, found a number`. Both the current layout with a nested
+//! `source` section and the flat legacy layout are understood, as is code compressed with the
+//! algorithm given by `source.wasm_compression`.
+
+use anyhow::Result;
+use contract_metadata::WasmCompression;
+use semver::{Version, VersionReq};
+use serde_json::Value;
+
+/// The `metadataVersion`s of the bundles which can be read, `0.2` adds a compressed code.
+const SUPPORTED_METADATA_VERSIONS: &str = ">=0.1, <0.4";
+
+/// Returns the Wasm code of a bundle, after checking its structure.
+pub(crate) fn bundle_code(bundle: &Value) -> Result<Vec<u8>> {
+    if !bundle.is_object() {
+        anyhow::bail!("/: expected an object, found {}", kind(bundle))
+    }
+    check_metadata_version(bundle)?;
+
+    // the current layout nests the code in `source`, the legacy layout has it at the top level
+    let (prefix, compression_pointer) = if bundle.get("source").is_some() {
+        let source = pointer(bundle, "/source")?;
+        if !source.is_object() {
+            anyhow::bail!("/source: expected an object, found {}", kind(source))
+        }
+        string(bundle, "/source/hash")?;
+        ("/source", "/source/wasm_compression")
+    } else {
+        string(bundle, "/codeHash")?;
+        ("", "/wasmCompression")
+    };
+    // newer bundles store the code under the language agnostic `contract_binary` key
+    let code_pointer = [
+        format!("{}/wasm", prefix),
+        format!("{}/contract_binary", prefix),
+    ]
+    .iter()
+    .find(|code_pointer| bundle.pointer(code_pointer).is_some())
+    .cloned()
+    .unwrap_or_else(|| format!("{}/wasm", prefix));
+    let code = string(bundle, &code_pointer)?;
+    let code = hex::decode(code.trim_start_matches("0x"))
+        .map_err(|err| anyhow::anyhow!("{}: expected hex encoded code, {}", code_pointer, err))?;
+
+    match bundle.pointer(compression_pointer) {
+        Some(compression) => {
+            let compression: WasmCompression = serde_json::from_value(compression.clone())
+                .map_err(|_| {
+                    anyhow::anyhow!(
+                        "{}: unsupported compression {}",
+                        compression_pointer,
+                        compression
+                    )
+                })?;
+            compression
+                .decompress(&code)
+                .map_err(|err| anyhow::anyhow!("{}: {}", code_pointer, err))
+        }
+        None => Ok(code),
+    }
+}
+
+/// Checks that the bundle has a `metadataVersion` this version of `cargo-contract` can read.
+fn check_metadata_version(bundle: &Value) -> Result<()> {
+    let version = string(bundle, "/metadataVersion")?;
+    let version = Version::parse(version).map_err(|err| {
+        anyhow::anyhow!(
+            "/metadataVersion: `{}` is not a valid version, {}",
+            version,
+            err
+        )
+    })?;
+    let supported = VersionReq::parse(SUPPORTED_METADATA_VERSIONS)
+        .expect("SUPPORTED_METADATA_VERSIONS is a valid semver requirement");
+    if !supported.matches(&version) {
+        anyhow::bail!(
+            "/metadataVersion: unsupported version {}, supported are {}",
+            version,
+            SUPPORTED_METADATA_VERSIONS
+        )
+    }
+    Ok(())
+}
+
+fn pointer<'a>(bundle: &'a Value, pointer: &str) -> Result<&'a Value> {
+    bundle
+        .pointer(pointer)
+        .ok_or(anyhow::anyhow!("{}: missing key", pointer))
+}
+
+fn string<'a>(bundle: &'a Value, pointer: &str) -> Result<&'a str> {
+    let value = self::pointer(bundle, pointer)?;
+    value.as_str().ok_or(anyhow::anyhow!(
+        "{}: expected a string, found {}",
+        pointer,
+        kind(value)
+    ))
+}
+
+/// Describes the type of a JSON value for diagnostics.
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::bundle_code;
+    use contract_metadata::WasmCompression;
+    use serde_json::json;
+
+    fn error(bundle: serde_json::Value) -> String {
+        bundle_code(&bundle).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn reads_code_of_current_and_legacy_layout() {
+        let current = json!({
+            "metadataVersion": "0.1.0",
+            "source": { "hash": "0x00", "wasm": "0x0061736d" }
+        });
+        let legacy =
+            json!({ "metadataVersion": "0.1.0", "codeHash": "0x00", "wasm": "0x0061736d" });
+
+        assert_eq!(bundle_code(&current).unwrap(), vec![0x00, 0x61, 0x73, 0x6d]);
+        assert_eq!(bundle_code(&legacy).unwrap(), vec![0x00, 0x61, 0x73, 0x6d]);
+    }
+
+    #[test]
+    fn decompresses_code() {
+        let code = vec![0x00, 0x61, 0x73, 0x6d];
+        let compressed = WasmCompression::Deflate.compress(&code);
+        let bundle = json!({
+            "metadataVersion": "0.2.0",
+            "source": {
+                "hash": "0x00",
+                "wasm": format!("0x{}", hex::encode(&compressed)),
+                "wasm_compression": "deflate"
+            }
+        });
+
+        assert_eq!(bundle_code(&bundle).unwrap(), code);
+    }
+
+    #[test]
+    fn malformed_bundles_are_diagnosed() {
+        assert_eq!(error(json!([])), "/: expected an object, found an array");
+        assert_eq!(
+            error(json!({ "source": {} })),
+            "/metadataVersion: missing key"
+        );
+        assert_eq!(
+            error(json!({ "metadataVersion": 1 })),
+            "/metadataVersion: expected a string, found a number"
+        );
+        assert_eq!(
+            error(json!({ "metadataVersion": "2.0.0", "source": {} })),
+            "/metadataVersion: unsupported version 2.0.0, supported are >=0.1, <0.4"
+        );
+        assert_eq!(
+            error(json!({ "metadataVersion": "0.1.0", "source": "0x00" })),
+            "/source: expected an object, found a string"
+        );
+        assert_eq!(
+            error(json!({ "metadataVersion": "0.1.0", "source": { "hash": "0x00" } })),
+            "/source/wasm: missing key"
+        );
+        assert_eq!(
+            error(
+                json!({ "metadataVersion": "0.1.0", "source": { "hash": "0x00", "wasm": null } })
+            ),
+            "/source/wasm: expected a string, found null"
+        );
+        assert_eq!(
+            error(json!({ "metadataVersion": "0.1.0", "codeHash": "0x00", "wasm": "0xzz" })),
+            "/wasm: expected hex encoded code, Invalid character 'z' at position 0"
+        );
+        assert_eq!(
+            error(json!({
+                "metadataVersion": "0.2.0",
+                "source": { "hash": "0x00", "wasm": "0x00", "wasm_compression": "lz4" }
+            })),
+            "/source/wasm_compression: unsupported compression \"lz4\""
+        );
+    }
+}
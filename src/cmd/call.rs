@@ -0,0 +1,602 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Calls a message of a deployed contract, optionally many times for basic load testing.
+//!
+//! The repeated calls are signed with consecutive nonces assigned locally, starting at the
+//! current nonce of the signing account, so that calls in flight at the same time do not
+//! collide. A call rejected by the transaction pool leaves a gap in the nonces which the later
+//! calls wait on, pass `--stop-on-error` to not submit further calls after a failure.
+//!
+//! With `--then-query` a message is dry run with the `contracts_call` RPC once the calls are
+//! included, to read back their effect.
+
+use std::{
+    convert::TryFrom,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use futures::stream::{FuturesUnordered, StreamExt};
+use jsonrpsee::common::{to_value, Params};
+use serde_json::{json, Value};
+use sp_core::crypto::AccountId32;
+use structopt::StructOpt;
+use subxt::{
+    contracts::*,
+    system::{AccountStoreExt, System},
+    ClientBuilder, DefaultNodeRuntime, Signer,
+};
+
+use super::{
+    dry_run::call_gas_limit,
+    events::{contract_event_json, display_contract_event, emitted_by},
+    metadata::read_metadata,
+    signer::ExtrinsicSigner,
+    token::Balance,
+    transcode::{decode_return, encode_call, message},
+};
+use crate::{
+    crate_metadata::CrateMetadata, workspace::ManifestPath, ExtrinsicOpts, GasLimit, HexData,
+};
+
+/// How many of the errors of a load test are listed in the summary.
+const MAX_REPORTED_ERRORS: usize = 5;
+
+/// Call a message of a deployed contract
+#[derive(Debug, StructOpt)]
+#[structopt(name = "call")]
+pub struct CallCommand {
+    #[structopt(flatten)]
+    extrinsic_opts: ExtrinsicOpts,
+    /// The SS58 address of the contract to call
+    #[structopt(long)]
+    contract: AccountId32,
+    /// Hex encoded data to call a contract message with
+    #[structopt(long, required_unless = "message", conflicts_with = "message")]
+    data: Option<HexData>,
+    /// The name of the message to call, encoded with its selector and `--args` from the
+    /// `--metadata`
+    #[structopt(long)]
+    message: Option<String>,
+    /// The arguments of `--message`, e.g. `--message transfer --args <account> 100`
+    ///
+    /// Only arguments of primitive types and 32 byte arrays are supported.
+    #[structopt(long, requires = "message", min_values = 1)]
+    args: Vec<String>,
+    /// Transfers a value to the contract with each call, in the base unit or with a unit of the
+    /// chain's token, e.g. `1.5UNIT` or `2000mUNIT`
+    #[structopt(long, default_value = "0")]
+    value: Balance,
+    /// The decimals of the chain's token for a `--value` with a unit, instead of the
+    /// `tokenDecimals` reported by the node
+    #[structopt(long, value_name = "decimals")]
+    token_decimals: Option<u32>,
+    /// Maximum amount of gas to be used by each call, or `auto` to estimate it with a dry run of
+    /// the call
+    #[structopt(name = "gas", long, default_value = "500000000")]
+    gas_limit: GasLimit,
+    /// The safety margin added to the gas estimated by `--gas auto`
+    #[structopt(long, default_value = "20", value_name = "percent")]
+    gas_margin: u64,
+    /// Submit the call this many times and summarize the success counts and latencies, for
+    /// basic throughput testing
+    #[structopt(long, default_value = "1")]
+    repeat: usize,
+    /// How many of the repeated calls are in flight at the same time
+    #[structopt(long, default_value = "1")]
+    concurrency: usize,
+    /// Do not submit further calls after the first failed one, the calls in flight are awaited
+    #[structopt(long)]
+    stop_on_error: bool,
+    /// Print the signer and the summary of the calls as JSON
+    #[structopt(long)]
+    output_json: bool,
+    /// After the calls are included, dry run this message with the given arguments and print
+    /// its decoded result, e.g. `--then-query balance_of <account>`
+    ///
+    /// Nothing of the query is submitted. Only arguments and results of primitive types and 32
+    /// byte arrays are supported, other results are printed as hex.
+    #[structopt(long, value_name = "message [args]", min_values = 1)]
+    then_query: Vec<String>,
+    /// Path to the contract metadata used by `--message`, `--then-query` and to decode the events emitted by
+    /// the call, defaults to the `metadata.json` built for the contract in the current directory
+    ///
+    /// Without metadata the events are printed as hex.
+    #[structopt(long, parse(from_os_str))]
+    metadata: Option<PathBuf>,
+}
+
+/// A message dry run after the calls, set by `--then-query`.
+struct Query {
+    name: String,
+    metadata: Value,
+    message: Value,
+    data: Vec<u8>,
+}
+
+impl CallCommand {
+    pub fn exec(&self) -> Result<String> {
+        if self.repeat == 0 || self.concurrency == 0 {
+            anyhow::bail!("--repeat and --concurrency must be at least 1")
+        }
+        // a call or query which cannot be encoded fails before anything is submitted
+        let data = self.call_data()?;
+        let query = self.prepare_query()?;
+        let value = self
+            .value
+            .resolve(&self.extrinsic_opts, self.token_decimals)?;
+        let signer = self.extrinsic_opts.signer()?;
+        if !self.output_json {
+            self.extrinsic_opts.print_signer(&signer)?;
+        }
+        // as does a call which reverts in the dry run of `--gas auto`
+        let gas_limit = call_gas_limit(
+            &self.extrinsic_opts,
+            self.gas_limit,
+            self.gas_margin,
+            signer.account_id(),
+            &self.contract,
+            value,
+            &data,
+        )?;
+        self.extrinsic_opts.confirm_chain()?;
+        let report = self.execute_calls(&signer, value, gas_limit, &data)?;
+        if report.failed() > 0 && self.repeat == 1 && !self.output_json {
+            anyhow::bail!("{}", report.errors[0])
+        }
+        let query_result = match query.as_ref() {
+            Some(query) => Some(self.execute_query(query, signer.account_id(), gas_limit)?),
+            None => None,
+        };
+        // the events of a load test would drown the summary
+        let event_metadata = match self.repeat {
+            1 if !report.events.is_empty() => Some(self.event_metadata(query.as_ref())?),
+            _ => None,
+        };
+
+        if self.output_json {
+            let mut json = report.to_json();
+            json["signer"] = signer.account_id().to_string().into();
+            if let Some(metadata) = event_metadata.as_ref() {
+                json["events"] = report
+                    .events
+                    .iter()
+                    .map(|data| contract_event_json(metadata.as_ref(), data).into())
+                    .collect::<Vec<Value>>()
+                    .into();
+            }
+            if let (Some(query), Some(result)) = (query, query_result) {
+                json["query"] = json!({ "message": query.name, "result": result });
+            }
+            return Ok(serde_json::to_string_pretty(&json)?);
+        }
+        let mut out = report.display();
+        if let Some(metadata) = event_metadata.as_ref() {
+            out.push_str(&display_events(metadata.as_ref(), &report.events));
+        }
+        if let (Some(query), Some(result)) = (query, query_result) {
+            out.push_str(&format!(
+                "\n\t{} {}",
+                format!("Query `{}`:", query.name).bold(),
+                result
+            ));
+        }
+        Ok(out)
+    }
+
+    /// Returns the path of `--metadata`, the `metadata.json` of the current project by default.
+    fn metadata_path(&self) -> Result<PathBuf> {
+        match self.metadata.as_ref() {
+            Some(path) => Ok(path.clone()),
+            None => {
+                let manifest_path = ManifestPath::try_from(None::<PathBuf>)?;
+                Ok(CrateMetadata::collect(&manifest_path)?.dest_metadata)
+            }
+        }
+    }
+
+    /// Returns the metadata the emitted events are decoded with, `None` if no `--metadata` is
+    /// given and the current directory has no built contract.
+    fn event_metadata(&self, query: Option<&Query>) -> Result<Option<Value>> {
+        if let Some(query) = query {
+            return Ok(Some(query.metadata.clone()));
+        }
+        match self.metadata.as_ref() {
+            Some(path) => read_metadata(path).map(Some),
+            None => Ok(self
+                .metadata_path()
+                .and_then(|path| read_metadata(&path))
+                .ok()),
+        }
+    }
+
+    /// Returns the `--data`, or the call of `--message` with `--args` encoded.
+    fn call_data(&self) -> Result<Vec<u8>> {
+        let name = match (self.data.as_ref(), self.message.as_ref()) {
+            (Some(data), _) => return Ok(data.0.clone()),
+            (None, Some(name)) => name,
+            (None, None) => anyhow::bail!("Pass either --data or --message"),
+        };
+        let metadata = read_metadata(&self.metadata_path()?)?;
+        let message = message(&metadata, name)?;
+        encode_call(&metadata, message, &self.args)
+            .context(format!("Failed to encode the call of `{}`", name))
+    }
+
+    /// Looks up and encodes the message of `--then-query`.
+    fn prepare_query(&self) -> Result<Option<Query>> {
+        let (name, args) = match self.then_query.split_first() {
+            Some(query) => query,
+            None => return Ok(None),
+        };
+        let metadata = read_metadata(&self.metadata_path()?)?;
+        let message = message(&metadata, name)?.clone();
+        let data = encode_call(&metadata, &message, args)
+            .context(format!("Failed to encode the query `{}`", name))?;
+        Ok(Some(Query {
+            name: name.clone(),
+            metadata,
+            message,
+            data,
+        }))
+    }
+
+    /// Dry runs the query with the `contracts_call` RPC, returning its decoded result.
+    fn execute_query(&self, query: &Query, origin: &AccountId32, gas_limit: u64) -> Result<String> {
+        let response = async_std::task::block_on(async move {
+            let rpc = jsonrpsee::ws_client(self.extrinsic_opts.url()?.as_str()).await?;
+            let call = json!({
+                "origin": origin.to_string(),
+                "dest": self.contract.to_string(),
+                "value": 0,
+                "gasLimit": gas_limit,
+                "inputData": format!("0x{}", hex::encode(&query.data)),
+            });
+            let response: Value = rpc
+                .request("contracts_call", Params::Array(vec![to_value(call)?]))
+                .await?;
+            Ok::<_, anyhow::Error>(response)
+        })?;
+        let value =
+            query_result(&response).context(format!("The query `{}` failed", query.name))?;
+        decode_return(&query.metadata, &query.message, &value)
+    }
+
+    /// Submits the calls, keeping up to `concurrency` of them in flight.
+    fn execute_calls(
+        &self,
+        signer: &ExtrinsicSigner,
+        value: u128,
+        gas_limit: u64,
+        data: &[u8],
+    ) -> Result<LoadTestReport> {
+        async_std::task::block_on(async move {
+            let rpc = self.extrinsic_opts.rpc_client().await?;
+            let cli = ClientBuilder::<DefaultNodeRuntime>::new()
+                .set_client(rpc)
+                .build()
+                .await?;
+            let first_nonce = cli.account(signer.account_id(), None).await?.nonce;
+            let dest = <DefaultNodeRuntime as System>::Address::from(self.contract.clone());
+
+            let call = |nonce| {
+                let mut signer = signer.clone();
+                signer.set_nonce(nonce);
+                let (cli, dest) = (&cli, &dest);
+                async move {
+                    let start = Instant::now();
+                    let result = cli
+                        .call_and_watch(&signer, dest, value, gas_limit, data)
+                        .await;
+                    let events = result.map(|success| {
+                        success
+                            .events
+                            .iter()
+                            .filter_map(|event| {
+                                emitted_by(
+                                    &event.module,
+                                    &event.variant,
+                                    &event.data,
+                                    &self.contract,
+                                )
+                            })
+                            .collect::<Vec<_>>()
+                    });
+                    (events, start.elapsed())
+                }
+            };
+
+            let mut report = LoadTestReport::default();
+            let start = Instant::now();
+            let mut in_flight = FuturesUnordered::new();
+            let mut submitted = 0;
+            loop {
+                while submitted < self.repeat
+                    && in_flight.len() < self.concurrency
+                    && !(self.stop_on_error && report.failed() > 0)
+                {
+                    in_flight.push(call(first_nonce + submitted as u32));
+                    submitted += 1;
+                }
+                match in_flight.next().await {
+                    Some((Ok(events), latency)) => {
+                        report.latencies.push(latency);
+                        report.events.extend(events);
+                    }
+                    Some((Err(err), _)) => report.errors.push(err.to_string()),
+                    None => break,
+                }
+            }
+            report.elapsed = start.elapsed();
+            report.skipped = self.repeat - submitted;
+            Ok(report)
+        })
+    }
+}
+
+/// Returns the data returned by a successful `contracts_call` RPC.
+fn query_result(response: &Value) -> Result<Vec<u8>> {
+    /// The flag of the return value set by `ink_env::return_value` on revert.
+    const REVERT_FLAG: u64 = 1;
+
+    if let Some(error) = response.get("error") {
+        match error.as_str() {
+            Some(error) => anyhow::bail!("{}", error),
+            None => anyhow::bail!("{}", error),
+        }
+    }
+    let success = response
+        .get("success")
+        .ok_or(anyhow::anyhow!("unexpected response {}", response))?;
+    if success
+        .get("flags")
+        .and_then(Value::as_u64)
+        .unwrap_or_default()
+        & REVERT_FLAG
+        != 0
+    {
+        anyhow::bail!("the contract reverted")
+    }
+    let data = success
+        .get("data")
+        .and_then(Value::as_str)
+        .ok_or(anyhow::anyhow!("unexpected response {}", response))?;
+    Ok(hex::decode(data.trim_start_matches("0x"))?)
+}
+
+/// The outcome of a series of calls.
+#[derive(Debug, Default)]
+struct LoadTestReport {
+    /// The time from submission until inclusion in a block of each successful call.
+    latencies: Vec<Duration>,
+    /// The error of each failed call.
+    errors: Vec<String>,
+    /// The calls not submitted because of `--stop-on-error`.
+    skipped: usize,
+    /// The data of the events the contract emitted in the successful calls.
+    events: Vec<Vec<u8>>,
+    /// The time taken by all calls.
+    elapsed: Duration,
+}
+
+impl LoadTestReport {
+    fn succeeded(&self) -> usize {
+        self.latencies.len()
+    }
+
+    fn failed(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// Calls included per second.
+    fn throughput(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.succeeded() as f64 / secs
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns the latency percentiles `(p50, p90, p99, max)`, if any call succeeded.
+    fn percentiles(&self) -> Option<[Duration; 4]> {
+        let mut sorted = self.latencies.clone();
+        sorted.sort();
+        let max = *sorted.last()?;
+        Some([
+            percentile(&sorted, 50.0),
+            percentile(&sorted, 90.0),
+            percentile(&sorted, 99.0),
+            max,
+        ])
+    }
+
+    /// Displays the counts, the latency percentiles and the first errors.
+    fn display(&self) -> String {
+        let mut out = format!(
+            "{} succeeded: {}, failed: {}, skipped: {}",
+            "Calls".bold(),
+            self.succeeded(),
+            self.failed(),
+            self.skipped
+        );
+        if let Some([p50, p90, p99, max]) = self.percentiles() {
+            out.push_str(&format!(
+                "\n\t{} p50 {:.2}s, p90 {:.2}s, p99 {:.2}s, max {:.2}s",
+                "Latency".bold(),
+                p50.as_secs_f64(),
+                p90.as_secs_f64(),
+                p99.as_secs_f64(),
+                max.as_secs_f64()
+            ));
+        }
+        out.push_str(&format!(
+            "\n\t{} {:.2} calls/s over {:.2}s",
+            "Throughput".bold(),
+            self.throughput(),
+            self.elapsed.as_secs_f64()
+        ));
+        for error in self.errors.iter().take(MAX_REPORTED_ERRORS) {
+            out.push_str(&format!("\n\t{} {}", "error:".bright_red().bold(), error));
+        }
+        if self.failed() > MAX_REPORTED_ERRORS {
+            out.push_str(&format!(
+                "\n\t... and {} more errors",
+                self.failed() - MAX_REPORTED_ERRORS
+            ));
+        }
+        out
+    }
+
+    /// Returns the summary as JSON, latencies in seconds.
+    fn to_json(&self) -> Value {
+        let latency = self.percentiles().map(|[p50, p90, p99, max]| {
+            json!({
+                "p50": p50.as_secs_f64(),
+                "p90": p90.as_secs_f64(),
+                "p99": p99.as_secs_f64(),
+                "max": max.as_secs_f64(),
+            })
+        });
+        json!({
+            "succeeded": self.succeeded(),
+            "failed": self.failed(),
+            "skipped": self.skipped,
+            "latency_seconds": latency,
+            "throughput_per_second": self.throughput(),
+            "total_seconds": self.elapsed.as_secs_f64(),
+            "errors": self.errors,
+        })
+    }
+}
+
+/// Displays the events emitted by the contract, one per line.
+fn display_events(metadata: Option<&Value>, events: &[Vec<u8>]) -> String {
+    let mut out = format!("\n\t{}", "Events:".bold());
+    for data in events {
+        out.push_str(&format!("\n\t  {}", display_contract_event(metadata, data)));
+    }
+    out
+}
+
+/// Returns the nearest-rank `p`th percentile of the ascending, non-empty `sorted`.
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.max(1) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(millis: &[u64]) -> Vec<Duration> {
+        millis.iter().copied().map(Duration::from_millis).collect()
+    }
+
+    #[test]
+    fn percentiles_use_the_nearest_rank() {
+        let sorted = millis(&(1..=100).collect::<Vec<_>>());
+
+        assert_eq!(percentile(&sorted, 50.0), Duration::from_millis(50));
+        assert_eq!(percentile(&sorted, 99.0), Duration::from_millis(99));
+        assert_eq!(percentile(&millis(&[7]), 1.0), Duration::from_millis(7));
+        assert_eq!(
+            percentile(&millis(&[1, 2, 3]), 50.0),
+            Duration::from_millis(2)
+        );
+    }
+
+    #[test]
+    fn report_summarizes_the_calls() {
+        let report = LoadTestReport {
+            latencies: millis(&[3000, 1000, 2000, 4000]),
+            errors: vec!["Runtime error: ContractTrapped".into()],
+            skipped: 5,
+            events: Vec::new(),
+            elapsed: Duration::from_secs(2),
+        };
+
+        assert_eq!(
+            report.to_json(),
+            json!({
+                "succeeded": 4,
+                "failed": 1,
+                "skipped": 5,
+                "latency_seconds": { "p50": 2.0, "p90": 4.0, "p99": 4.0, "max": 4.0 },
+                "throughput_per_second": 2.0,
+                "total_seconds": 2.0,
+                "errors": ["Runtime error: ContractTrapped"],
+            })
+        );
+    }
+
+    #[test]
+    fn query_results_are_read_from_the_response() {
+        let success = json!({ "success": { "flags": 0, "data": "0x01", "gas_consumed": 1 } });
+        let reverted = json!({ "success": { "flags": 1, "data": "0x", "gas_consumed": 1 } });
+        let error = json!({ "error": "ContractTrapped" });
+
+        assert_eq!(query_result(&success).unwrap(), vec![1]);
+        assert_eq!(
+            query_result(&reverted).unwrap_err().to_string(),
+            "the contract reverted"
+        );
+        assert_eq!(
+            query_result(&error).unwrap_err().to_string(),
+            "ContractTrapped"
+        );
+    }
+
+    #[test]
+    fn events_are_decoded_with_the_metadata() {
+        let metadata = json!({
+            "spec": {
+                "events": [
+                    { "name": "Flipped", "args": [ { "name": "value", "type": { "type": 1 } } ] }
+                ]
+            },
+            "types": [ { "def": { "primitive": "bool" } } ]
+        });
+        let events = vec![vec![0, 1], vec![7]];
+
+        assert_eq!(
+            display_events(Some(&metadata), &events),
+            format!(
+                "\n\t{}\n\t  {} {{ value: true }}\n\t  0x07",
+                "Events:".bold(),
+                "Flipped".bright_green().bold()
+            )
+        );
+        assert_eq!(
+            display_events(None, &events),
+            format!("\n\t{}\n\t  0x0001\n\t  0x07", "Events:".bold())
+        );
+    }
+
+    #[test]
+    fn report_without_successful_calls_has_no_latencies() {
+        let report = LoadTestReport {
+            errors: vec!["Pool error".into()],
+            ..Default::default()
+        };
+
+        assert_eq!(report.percentiles(), None);
+        assert_eq!(report.to_json()["latency_seconds"], Value::Null);
+    }
+}
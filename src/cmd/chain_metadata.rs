@@ -0,0 +1,240 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Caches the runtime metadata of the nodes the extrinsic commands connect to.
+//!
+//! subxt fetches the runtime metadata whenever it builds a client, which is slow on remote nodes.
+//! The transport of the RPC client handed to subxt answers the `state_getMetadata` request from
+//! a cache in the user cache dir instead, keyed by the genesis hash and the runtime version of
+//! the node.
+
+use std::{
+    collections::VecDeque,
+    fs,
+    future::Future,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+use futures::future;
+use jsonrpsee::{
+    common::{self, to_value, Params},
+    raw::RawClient,
+    transport::{ws::WsTransportClient, TransportClient},
+};
+use serde_json::Value;
+use sp_core::H256;
+
+/// The RPC method returning the runtime metadata.
+const GET_METADATA: &str = "state_getMetadata";
+
+/// Connects to the node at `url`, answering the `state_getMetadata` requests from the cache if
+/// it has the metadata of the node's genesis hash and runtime version.
+///
+/// With `refresh` the cache is not read, the metadata fetched from the node replaces the cached
+/// one.
+pub(crate) async fn cached_client(url: &url::Url, refresh: bool) -> Result<jsonrpsee::Client> {
+    let cache = Arc::new(Mutex::new(MetadataCache::default()));
+    let transport = CachingTransport {
+        inner: WsTransportClient::new(url.as_str()).await?,
+        cache: cache.clone(),
+        cached: VecDeque::new(),
+    };
+    let rpc: jsonrpsee::Client = RawClient::new(transport).into();
+
+    let params = Params::Array(vec![to_value(0u32)?]);
+    let genesis: H256 = rpc.request("chain_getBlockHash", params).await?;
+    let version: Value = rpc.request("state_getRuntimeVersion", Params::None).await?;
+    let path = dirs::cache_dir()
+        .and_then(|dir| cache_file(&dir.join("cargo-contract"), &genesis, &version));
+    if let Some(path) = path {
+        let mut cache = cache.lock().expect("the metadata cache is never poisoned");
+        if !refresh {
+            cache.metadata = fs::read(&path)
+                .ok()
+                .and_then(|metadata| serde_json::from_slice(&metadata).ok());
+        }
+        cache.path = Some(path);
+    }
+    Ok(rpc)
+}
+
+/// Returns the cache file of the metadata of the chain with the `genesis` hash and the runtime
+/// `version`, as returned by `state_getRuntimeVersion`.
+///
+/// Returns `None` if the version lacks the `specName` or the `specVersion`.
+fn cache_file(cache_dir: &Path, genesis: &H256, version: &Value) -> Option<PathBuf> {
+    let spec_name = version.get("specName")?.as_str()?;
+    let spec_version = version.get("specVersion")?.as_u64()?;
+    let spec_name = spec_name
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect::<String>();
+    Some(
+        cache_dir
+            .join("metadata")
+            .join(format!("{:x}-{}-{}.json", genesis, spec_name, spec_version)),
+    )
+}
+
+/// The cached metadata of the node a client is connected to.
+#[derive(Debug, Default)]
+struct MetadataCache {
+    /// The cache file of the node's genesis hash and runtime version, `None` without a cache
+    /// dir.
+    path: Option<PathBuf>,
+    /// The result of `state_getMetadata`, if it is cached.
+    metadata: Option<Value>,
+    /// The id of the `state_getMetadata` request forwarded to the node.
+    pending: Option<common::Id>,
+}
+
+impl MetadataCache {
+    /// Writes the `result` of the request `id` to the cache file if it is the forwarded
+    /// `state_getMetadata` request.
+    fn store(&mut self, id: &common::Id, result: &Value) {
+        if self.pending.as_ref() != Some(id) {
+            return;
+        }
+        self.pending = None;
+        if let Some(path) = self.path.as_ref() {
+            // a cache which cannot be written only slows down the next command
+            let written = path
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|_| fs::write(path, result.to_string()));
+            if written.is_ok() {
+                self.metadata = Some(result.clone());
+            }
+        }
+    }
+}
+
+/// Forwards the requests to the node, except the `state_getMetadata` requests which are answered
+/// from the cache if it has the metadata.
+struct CachingTransport<T> {
+    inner: T,
+    cache: Arc<Mutex<MetadataCache>>,
+    /// The responses answered from the cache, returned before the next response of the node.
+    cached: VecDeque<common::Response>,
+}
+
+impl<T> TransportClient for CachingTransport<T>
+where
+    T: TransportClient + Send,
+{
+    type Error = T::Error;
+
+    fn send_request<'s>(
+        &'s mut self,
+        request: common::Request,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send + 's>> {
+        if let common::Request::Single(common::Call::MethodCall(call)) = &request {
+            if call.method == GET_METADATA {
+                let mut cache = self
+                    .cache
+                    .lock()
+                    .expect("the metadata cache is never poisoned");
+                match cache.metadata.as_ref() {
+                    Some(metadata) => {
+                        self.cached
+                            .push_back(success(call.id.clone(), metadata.clone()));
+                        return Box::pin(future::ready(Ok(())));
+                    }
+                    None => cache.pending = Some(call.id.clone()),
+                }
+            }
+        }
+        self.inner.send_request(request)
+    }
+
+    fn next_response<'s>(
+        &'s mut self,
+    ) -> Pin<Box<dyn Future<Output = Result<common::Response, Self::Error>> + Send + 's>> {
+        Box::pin(async move {
+            if let Some(response) = self.cached.pop_front() {
+                return Ok(response);
+            }
+            let response = self.inner.next_response().await?;
+            if let common::Response::Single(common::Output::Success(success)) = &response {
+                self.cache
+                    .lock()
+                    .expect("the metadata cache is never poisoned")
+                    .store(&success.id, &success.result);
+            }
+            Ok(response)
+        })
+    }
+}
+
+/// Returns the successful response to the request `id`.
+fn success(id: common::Id, result: Value) -> common::Response {
+    common::Response::Single(common::Output::Success(common::Success {
+        jsonrpc: Some(common::Version::V2),
+        result,
+        id,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::with_tmp_dir;
+    use serde_json::json;
+
+    #[test]
+    fn metadata_is_cached_by_genesis_hash_and_runtime_version() {
+        let dir = Path::new("cache");
+        let genesis = H256::repeat_byte(0xab);
+        let version = json!({ "specName": "node/../x", "specVersion": 265, "implVersion": 0 });
+
+        assert_eq!(
+            cache_file(dir, &genesis, &version),
+            Some(
+                dir.join("metadata")
+                    .join(format!("{}-nodex-265.json", "ab".repeat(32)))
+            )
+        );
+        assert_eq!(
+            cache_file(dir, &genesis, &json!({ "specName": "node" })),
+            None
+        );
+    }
+
+    #[test]
+    fn only_the_metadata_response_is_stored() {
+        with_tmp_dir(|path| {
+            let file = path.join("metadata").join("node.json");
+            let mut cache = MetadataCache {
+                path: Some(file.clone()),
+                metadata: None,
+                pending: Some(common::Id::Num(2)),
+            };
+
+            cache.store(&common::Id::Num(1), &json!("0x01"));
+            assert!(!file.exists());
+            assert_eq!(cache.metadata, None);
+
+            cache.store(&common::Id::Num(2), &json!("0x6d657461"));
+            assert_eq!(fs::read_to_string(&file)?, "\"0x6d657461\"");
+            assert_eq!(cache.metadata, Some(json!("0x6d657461")));
+            assert_eq!(cache.pending, None);
+            Ok(())
+        })
+    }
+}
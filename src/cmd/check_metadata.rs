@@ -0,0 +1,100 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use structopt::StructOpt;
+
+use super::metadata::read_metadata;
+
+/// Validates a metadata file against the schema of its `metadataVersion`.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "check-metadata")]
+pub struct CheckMetadataCommand {
+    /// The metadata to validate, a `metadata.json`, a `metadata.yaml` or a `.contract` bundle
+    #[structopt(parse(from_os_str))]
+    path: PathBuf,
+}
+
+impl CheckMetadataCommand {
+    pub fn exec(&self) -> Result<String> {
+        let metadata = read_metadata(&self.path)?;
+        if let Err(errors) = contract_metadata::validate(&metadata) {
+            let errors = errors
+                .iter()
+                .map(|error| format!("  {}", error))
+                .collect::<Vec<_>>();
+            anyhow::bail!(
+                "{} does not conform to the metadata schema:\n{}",
+                self.path.display(),
+                errors.join("\n")
+            );
+        }
+        Ok(format!(
+            "{} conforms to the schema of metadata version {}",
+            self.path.display(),
+            metadata["metadataVersion"]
+                .as_str()
+                .expect("the metadata version was validated")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CheckMetadataCommand;
+    use crate::util::tests::with_tmp_dir;
+    use serde_json::json;
+
+    #[test]
+    fn reports_every_violation() {
+        with_tmp_dir(|path| {
+            let metadata = path.join("metadata.json");
+            let check = CheckMetadataCommand {
+                path: metadata.clone(),
+            };
+            let mut json = json!({
+                "metadataVersion": "0.1.0",
+                "source": {
+                    "hash": format!("0x{}", "00".repeat(32)),
+                    "language": "ink! 3.0.0-rc2",
+                    "compiler": "rustc 1.49.0-nightly"
+                },
+                "contract": { "name": "flipper", "version": "0.1.0", "authors": [] },
+                "spec": {},
+                "storage": {},
+                "types": []
+            });
+            std::fs::write(&metadata, json.to_string())?;
+            assert!(check.exec()?.ends_with("metadata version 0.1.0"));
+
+            json["contract"]["authors"] = json!("alice");
+            json["source"]["wasm"] = json!("0x123");
+            std::fs::write(&metadata, json.to_string())?;
+            assert_eq!(
+                check.exec().unwrap_err().to_string(),
+                format!(
+                    "{} does not conform to the metadata schema:\n  \
+                     /contract/authors: expected an array, found a string\n  \
+                     /source/wasm: expected bytes, hex encoded with a 0x prefix",
+                    metadata.display()
+                )
+            );
+            Ok(())
+        })
+    }
+}
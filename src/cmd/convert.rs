@@ -0,0 +1,219 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use contract_metadata::{ContractMetadata, SourceWasm};
+use structopt::StructOpt;
+
+use super::metadata::blake2_hash;
+use crate::ConvertTarget;
+
+/// Converts between a `<name>.contract` bundle and a separate `<name>.wasm` and `<name>.json`.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "convert")]
+pub struct ConvertCommand {
+    /// The `.contract` bundle to split, or the `.wasm` code to join with its metadata
+    #[structopt(parse(from_os_str))]
+    input: PathBuf,
+    /// The metadata to join with the Wasm code, defaults to the `<name>.json` next to it
+    #[structopt(long, parse(from_os_str))]
+    metadata: Option<PathBuf>,
+    /// The representation to convert to, detected from the extension of the input by default.
+    ///
+    /// - `split`: The bundle is split into `<name>.wasm` and `<name>.json`.
+    ///
+    /// - `bundle`: The Wasm code and its metadata are joined into `<name>.contract`.
+    #[structopt(long, value_name = "bundle | split", verbatim_doc_comment)]
+    to: Option<ConvertTarget>,
+    /// The directory to write to, defaults to the directory of the input
+    #[structopt(long, parse(from_os_str))]
+    output_dir: Option<PathBuf>,
+}
+
+impl ConvertCommand {
+    pub fn exec(&self) -> Result<String> {
+        let to = match self.to {
+            Some(to) => to,
+            None => match self.input.extension().and_then(|ext| ext.to_str()) {
+                Some("contract") => ConvertTarget::Split,
+                Some("wasm") => ConvertTarget::Bundle,
+                _ => anyhow::bail!(
+                    "Cannot tell how to convert {}, pass --to bundle or --to split",
+                    self.input.display()
+                ),
+            },
+        };
+        let output_dir = match self.output_dir.as_ref() {
+            Some(output_dir) => output_dir.clone(),
+            None => self
+                .input
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+        };
+        let output = |extension: &str| {
+            let name = self.input.file_stem().unwrap_or_default();
+            output_dir.join(name).with_extension(extension)
+        };
+
+        match to {
+            ConvertTarget::Split => {
+                let (wasm, metadata) = (output("wasm"), output("json"));
+                split_bundle(&self.input, &wasm, &metadata)?;
+                Ok(format!(
+                    "Split {} into {} and {}",
+                    self.input.display(),
+                    wasm.display(),
+                    metadata.display()
+                ))
+            }
+            ConvertTarget::Bundle => {
+                let metadata = match self.metadata.as_ref() {
+                    Some(metadata) => metadata.clone(),
+                    None => self.input.with_extension("json"),
+                };
+                let bundle = output("contract");
+                join_bundle(&self.input, &metadata, &bundle)?;
+                Ok(format!(
+                    "Joined {} and {} into {}",
+                    self.input.display(),
+                    metadata.display(),
+                    bundle.display()
+                ))
+            }
+        }
+    }
+}
+
+/// Writes the code and the metadata of the bundle to separate files.
+fn split_bundle(bundle: &Path, wasm_path: &Path, metadata_path: &Path) -> Result<()> {
+    let mut metadata = read_contract_metadata(bundle)?;
+    let wasm = metadata
+        .source_wasm()
+        .ok_or(anyhow::anyhow!(
+            "{} is not a bundle, it contains no code",
+            bundle.display()
+        ))?
+        .to_vec();
+    check_code_hash(&metadata, &wasm)?;
+
+    metadata.remove_source_wasm_attribute();
+    fs::write(wasm_path, wasm)?;
+    fs::write(metadata_path, serde_json::to_string_pretty(&metadata)?)?;
+    Ok(())
+}
+
+/// Writes a bundle of the code and its metadata.
+fn join_bundle(wasm_path: &Path, metadata_path: &Path, bundle: &Path) -> Result<()> {
+    let wasm = fs::read(wasm_path).context(format!("Failed to read {}", wasm_path.display()))?;
+    let mut metadata = read_contract_metadata(metadata_path)?;
+    check_code_hash(&metadata, &wasm)?;
+
+    metadata.set_source_wasm(SourceWasm::new(wasm));
+    fs::write(bundle, serde_json::to_string(&metadata)?)?;
+    Ok(())
+}
+
+fn read_contract_metadata(path: &Path) -> Result<ContractMetadata> {
+    let contents = fs::read(path).context(format!("Failed to read {}", path.display()))?;
+    serde_json::from_slice(&contents).context(format!(
+        "Failed to parse {}, only the current metadata layout can be converted",
+        path.display()
+    ))
+}
+
+/// Fails if the code does not match the code hash of the metadata.
+fn check_code_hash(metadata: &ContractMetadata, wasm: &[u8]) -> Result<()> {
+    let code_hash = blake2_hash(wasm);
+    if code_hash != *metadata.code_hash() {
+        anyhow::bail!(
+            "The code hash of the metadata {} does not match the code hash {}",
+            metadata.code_hash(),
+            code_hash
+        )
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::with_tmp_dir;
+    use contract_metadata::*;
+    use semver::Version;
+    use serde_json::{Map, Value};
+
+    const WASM: &[u8] = &[0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+
+    fn bundle() -> ContractMetadata {
+        let source = Source::new(
+            Some(SourceWasm::new(WASM.to_vec())),
+            blake2_hash(WASM),
+            SourceLanguage::new(Language::Ink, Version::new(3, 0, 0)),
+            SourceCompiler::new(Compiler::RustC, Version::parse("1.49.0-nightly").unwrap()),
+        );
+        let contract = Contract::builder()
+            .name("flipper".to_string())
+            .version(Version::new(0, 1, 0))
+            .authors(vec!["Parity Technologies <admin@parity.io>".to_string()])
+            .build()
+            .unwrap();
+        ContractMetadata::new(source, contract, None, Map::new())
+    }
+
+    #[test]
+    fn split_and_join_round_trip() {
+        with_tmp_dir(|path| {
+            let bundle_path = path.join("flipper.contract");
+            fs::write(&bundle_path, serde_json::to_string(&bundle())?)?;
+            let (wasm_path, metadata_path) = (path.join("flipper.wasm"), path.join("flipper.json"));
+
+            split_bundle(&bundle_path, &wasm_path, &metadata_path)?;
+            let metadata: Value = serde_json::from_slice(&fs::read(&metadata_path)?)?;
+            assert_eq!(fs::read(&wasm_path)?, WASM);
+            assert!(metadata["source"].get("wasm").is_none());
+
+            let joined_path = path.join("joined.contract");
+            join_bundle(&wasm_path, &metadata_path, &joined_path)?;
+            let joined: Value = serde_json::from_slice(&fs::read(&joined_path)?)?;
+            assert_eq!(joined, serde_json::to_value(&bundle())?);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn mismatched_code_hash_is_rejected() {
+        with_tmp_dir(|path| {
+            let wasm_path = path.join("flipper.wasm");
+            let metadata_path = path.join("flipper.json");
+            let mut metadata = bundle();
+            metadata.remove_source_wasm_attribute();
+            fs::write(&wasm_path, [0x00, 0x61, 0x73, 0x6d])?;
+            fs::write(&metadata_path, serde_json::to_string(&metadata)?)?;
+
+            let err = join_bundle(&wasm_path, &metadata_path, &path.join("flipper.contract"))
+                .unwrap_err();
+
+            assert!(err.to_string().starts_with("The code hash of the metadata"));
+            Ok(())
+        })
+    }
+}
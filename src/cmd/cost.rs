@@ -0,0 +1,168 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Costs of the extrinsics submitted by a command, summarized at the end of an invocation.
+
+use anyhow::Result;
+use serde_json::{json, Value};
+use subxt::{system::ExtrinsicSuccessEventExt, DefaultNodeRuntime, ExtrinsicSuccess};
+
+/// The cost of a single extrinsic.
+///
+/// The contracts pallet does not charge a storage deposit, the balance locked with a contract is
+/// its endowment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OperationCost {
+    /// Describes the operation, e.g. `deploy` or `instantiate <name>`.
+    pub operation: String,
+    /// Weight consumed by the extrinsic, including the gas used by the contract.
+    pub weight: u64,
+    /// Balance transferred to the instantiated contract.
+    pub endowment: u128,
+}
+
+impl OperationCost {
+    /// Reads the consumed weight from the `ExtrinsicSuccess` event of the submitted extrinsic.
+    pub fn from_events(
+        operation: impl Into<String>,
+        events: &ExtrinsicSuccess<DefaultNodeRuntime>,
+        endowment: u128,
+    ) -> Result<Self> {
+        let success = events
+            .extrinsic_success()?
+            .ok_or(anyhow::anyhow!("Failed to find ExtrinsicSuccess event"))?;
+        Ok(OperationCost {
+            operation: operation.into(),
+            weight: success.info.weight,
+            endowment,
+        })
+    }
+}
+
+/// Accumulates the costs of all operations performed by one invocation.
+#[derive(Debug, Default)]
+pub struct CostReport {
+    operations: Vec<OperationCost>,
+}
+
+impl CostReport {
+    pub fn add(&mut self, cost: OperationCost) {
+        self.operations.push(cost)
+    }
+
+    /// Displays the cost of every operation, followed by the totals if there is more than one.
+    pub fn display(&self) -> String {
+        let total = OperationCost {
+            operation: "Total".into(),
+            weight: self.operations.iter().map(|op| op.weight).sum(),
+            endowment: self.operations.iter().map(|op| op.endowment).sum(),
+        };
+        let mut rows = self.operations.iter().collect::<Vec<_>>();
+        if rows.len() > 1 {
+            rows.push(&total);
+        }
+        let width = rows
+            .iter()
+            .map(|op| op.operation.len())
+            .max()
+            .unwrap_or(0)
+            .max("Operation".len());
+
+        let mut lines = vec![format!(
+            "{:width$}  {:>20}  {:>20}",
+            "Operation",
+            "Weight",
+            "Endowment",
+            width = width
+        )];
+        for op in rows {
+            lines.push(format!(
+                "{:width$}  {:>20}  {:>20}",
+                op.operation,
+                op.weight,
+                op.endowment,
+                width = width
+            ));
+        }
+        lines.join("\n\t")
+    }
+
+    /// Returns the cost of every operation as JSON. The endowment is a string, a `u128` does not
+    /// fit the numbers of most JSON parsers.
+    pub fn to_json(&self) -> Value {
+        self.operations
+            .iter()
+            .map(|op| {
+                json!({
+                    "operation": op.operation,
+                    "weight": op.weight,
+                    "endowment": op.endowment.to_string(),
+                })
+            })
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cost(operation: &str, weight: u64, endowment: u128) -> OperationCost {
+        OperationCost {
+            operation: operation.into(),
+            weight,
+            endowment,
+        }
+    }
+
+    #[test]
+    fn single_operation_has_no_total() {
+        let mut report = CostReport::default();
+        report.add(cost("deploy", 10, 0));
+
+        assert!(!report.display().contains("Total"));
+    }
+
+    #[test]
+    fn totals_are_summed() {
+        let mut report = CostReport::default();
+        report.add(cost("instantiate first", 10, 100));
+        report.add(cost("instantiate second", 5, 50));
+
+        let total = report.display().lines().last().unwrap().to_string();
+
+        assert_eq!(
+            total,
+            format!("\tTotal               {:>20}  {:>20}", 15, 150)
+        );
+    }
+
+    #[test]
+    fn costs_are_serialized_as_json() {
+        let mut report = CostReport::default();
+        report.add(cost("instantiate", 10, u128::MAX));
+
+        assert_eq!(
+            report.to_json(),
+            json!([{
+                "operation": "instantiate",
+                "weight": 10,
+                "endowment": u128::MAX.to_string(),
+            }])
+        );
+    }
+}
@@ -0,0 +1,124 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use serde_json::{Map, Value};
+use structopt::StructOpt;
+
+use super::{
+    metadata::read_metadata,
+    transcode::{decode_call, decode_event},
+};
+use crate::{DecodeType, HexData};
+
+/// Decodes SCALE encoded data of a contract with its metadata, printing it as JSON.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "decode")]
+pub struct DecodeCommand {
+    /// The metadata of the contract, a `metadata.json` or a `.contract` bundle
+    #[structopt(long, parse(from_os_str))]
+    metadata: PathBuf,
+    /// What the data is.
+    ///
+    /// - `event`: An event emitted by the contract, e.g. read from a block.
+    ///
+    /// - `message`: The data of a call of a message, starting with its selector.
+    ///
+    /// - `constructor`: The data of a call of a constructor, starting with its selector.
+    #[structopt(
+        long = "type",
+        value_name = "event | message | constructor",
+        verbatim_doc_comment
+    )]
+    ty: DecodeType,
+    /// The hex encoded data, optionally prefixed by `0x`
+    #[structopt(long)]
+    data: HexData,
+}
+
+impl DecodeCommand {
+    pub fn exec(&self) -> Result<String> {
+        let metadata = read_metadata(&self.metadata)?;
+        let json = decode(&metadata, self.ty, &self.data.0)?;
+        Ok(serde_json::to_string_pretty(&json)?)
+    }
+}
+
+/// Decodes the data into its name and arguments, e.g.
+/// `{ "message": "transfer", "args": { "to": "0x..", "value": "100" } }`.
+fn decode(metadata: &Value, ty: DecodeType, data: &[u8]) -> Result<Value> {
+    let (kind, (name, args)) = match ty {
+        DecodeType::Event => ("event", decode_event(metadata, data)?),
+        DecodeType::Message => ("message", decode_call(metadata, "messages", data)?),
+        DecodeType::Constructor => ("constructor", decode_call(metadata, "constructors", data)?),
+    };
+    let args = args
+        .into_iter()
+        .map(|(name, value)| (name, Value::from(value)))
+        .collect::<Map<_, _>>();
+    Ok(serde_json::json!({ kind: name, "args": args }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode;
+    use crate::DecodeType;
+    use serde_json::json;
+
+    #[test]
+    fn decodes_events_and_calls() {
+        let metadata = json!({
+            "spec": {
+                "constructors": [
+                    { "name": ["new"], "selector": "0xd183512b", "args": [
+                        { "name": "init_value", "type": { "type": 1 } }
+                    ] }
+                ],
+                "messages": [{ "name": ["flip"], "selector": "0x633aa551", "args": [] }],
+                "events": [{ "name": "Flipped", "args": [
+                    { "name": "value", "type": { "type": 1 } }
+                ] }]
+            },
+            "types": [{ "def": { "primitive": "bool" } }]
+        });
+
+        assert_eq!(
+            decode(&metadata, DecodeType::Event, &[0, 1]).unwrap(),
+            json!({ "event": "Flipped", "args": { "value": "true" } })
+        );
+        assert_eq!(
+            decode(&metadata, DecodeType::Message, &[0x63, 0x3a, 0xa5, 0x51]).unwrap(),
+            json!({ "message": "flip", "args": {} })
+        );
+        assert_eq!(
+            decode(
+                &metadata,
+                DecodeType::Constructor,
+                &[0xd1, 0x83, 0x51, 0x2b, 0]
+            )
+            .unwrap(),
+            json!({ "constructor": "new", "args": { "init_value": "false" } })
+        );
+        assert!(decode(
+            &metadata,
+            DecodeType::Constructor,
+            &[0x63, 0x3a, 0xa5, 0x51]
+        )
+        .is_err());
+    }
+}
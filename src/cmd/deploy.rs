@@ -14,61 +14,375 @@
 // You should have received a copy of the GNU General Public License
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::{fs, io::Read, path::PathBuf};
+use std::{
+    convert::TryFrom,
+    fs,
+    io::Read,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
 
 use anyhow::{Context, Result};
-use sp_core::H256;
-use subxt::{contracts::*, ClientBuilder, DefaultNodeRuntime};
+use colored::Colorize;
+use parity_wasm::elements::Module;
+use serde_json::json;
+use sp_core::{crypto::AccountId32, H256};
+use structopt::StructOpt;
+use subxt::{contracts::*, ClientBuilder, DefaultNodeRuntime, Signer};
 
-use crate::{crate_metadata, ExtrinsicOpts};
+use super::{
+    balance::check_balance,
+    cost::{CostReport, OperationCost},
+    dry_run::dry_run_deploy,
+    git::require_clean_git,
+    metadata::read_metadata,
+    required_features::{require_features, required_features},
+    signer::ExtrinsicSigner,
+    signing::{submit_signed, unsigned_extrinsic},
+    storage::code_exists,
+};
+use crate::{crate_metadata::CrateMetadata, workspace::ManifestPath, ExtrinsicOpts};
 
-/// Load the wasm blob from the specified path.
+/// Upload the smart contract code to the chain.
 ///
-/// Defaults to the target contract wasm in the current project, inferred via the crate metadata.
-fn load_contract_code(path: Option<&PathBuf>) -> Result<Vec<u8>> {
-    let contract_wasm_path = match path {
-        Some(path) => path.clone(),
-        None => {
-            let metadata = crate_metadata::CrateMetadata::collect(&Default::default())?;
-            metadata.dest_wasm
+/// The code is taken from exactly one of: the build output of a contract project (the default),
+/// a Wasm file optionally accompanied by its metadata, or a `.contract` bundle.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "deploy")]
+pub struct DeployCommand {
+    #[structopt(flatten)]
+    extrinsic_opts: ExtrinsicOpts,
+    /// Path to wasm contract code, defaults to `./target/ink/<name>.wasm`
+    #[structopt(parse(from_os_str), conflicts_with_all = &["wasm", "bundle", "manifest-path"])]
+    wasm_path: Option<PathBuf>,
+    /// Path to the Cargo.toml of the contract project whose build output should be deployed
+    #[structopt(long, parse(from_os_str), conflicts_with_all = &["wasm", "bundle", "metadata"])]
+    manifest_path: Option<PathBuf>,
+    /// Path to the wasm contract code, built elsewhere
+    #[structopt(long, parse(from_os_str), conflicts_with = "bundle")]
+    wasm: Option<PathBuf>,
+    /// Path to the metadata of the contract, the code hash is checked against the wasm code
+    #[structopt(long, parse(from_os_str), conflicts_with = "bundle")]
+    metadata: Option<PathBuf>,
+    /// Path to a `<name>.contract` bundle containing both the code and the metadata
+    #[structopt(long, parse(from_os_str))]
+    bundle: Option<PathBuf>,
+    /// Submit the code even if it is already present on chain
+    #[structopt(long)]
+    force_upload: bool,
+    /// Remove the custom sections, e.g. `name` and `producers`, from the wasm code before
+    /// hashing and uploading it.
+    ///
+    /// Code built by `cargo contract build` has no custom sections, this is for code built
+    /// elsewhere.
+    #[structopt(long)]
+    strip_custom_sections: bool,
+    /// Refuse to deploy if the git working tree of the contract has uncommitted changes
+    ///
+    /// Outside of a git working tree this only warns, unless `--require-git` is passed.
+    #[structopt(long)]
+    require_clean_git: bool,
+    /// Refuse to deploy if the contract is not inside a git working tree
+    #[structopt(long, requires = "require-clean-git")]
+    require_git: bool,
+    /// Refuse to deploy if the chain does not support the runtime features the contract
+    /// requires, as listed in `contract.required_features` of the metadata
+    ///
+    /// `pallet:<Name>` features are checked against the runtime metadata, `rpc:<method>`
+    /// features against the RPC methods of the node. Other features are only warned about.
+    #[structopt(long)]
+    check_required_features: bool,
+    /// Only warn about required features the chain does not support
+    #[structopt(long, requires = "check-required-features")]
+    allow_missing_features: bool,
+    /// The SS58 address of the account to deploy from, for signing the extrinsic externally
+    #[structopt(long, requires = "generate-call-data")]
+    from: Option<AccountId32>,
+    /// Print the payload to sign for the account given by `--from` instead of submitting the
+    /// code, the secret key is not needed.
+    ///
+    /// Sign the payload externally, then submit the signed extrinsic with `--submit-signed`.
+    #[structopt(long, requires = "from")]
+    generate_call_data: bool,
+    /// Submit a hex encoded extrinsic which was signed externally
+    #[structopt(
+        long,
+        value_name = "signed-hex",
+        conflicts_with_all = &["from", "wasm-path", "wasm", "bundle", "manifest-path", "metadata"]
+    )]
+    submit_signed: Option<String>,
+    /// Only estimate the fee of uploading the code, nothing is submitted
+    #[structopt(long, conflicts_with_all = &["generate-call-data", "submit-signed"])]
+    dry_run: bool,
+    /// Print the signer, the code hash and the cost of the upload as JSON
+    #[structopt(long, conflicts_with_all = &["generate-call-data", "submit-signed", "dry-run"])]
+    output_json: bool,
+}
+
+impl DeployCommand {
+    pub fn exec(&self) -> Result<String> {
+        if let Some(signed) = self.submit_signed.as_ref() {
+            self.extrinsic_opts.confirm_chain()?;
+            let hash = async_std::task::block_on(async move {
+                let rpc = jsonrpsee::ws_client(self.extrinsic_opts.url()?.as_str()).await?;
+                submit_signed(&rpc, signed).await
+            })?;
+            return Ok(format!("Extrinsic hash: {:?}", hash));
+        }
+
+        if self.require_clean_git {
+            let dir = self
+                .manifest_path
+                .as_ref()
+                .and_then(|manifest_path| manifest_path.parent())
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .unwrap_or_else(|| Path::new("."));
+            require_clean_git(dir, self.require_git)?;
+        }
+        if self.check_required_features {
+            let required = self.load_required_features()?;
+            require_features(
+                &self.extrinsic_opts.url()?,
+                &required,
+                self.allow_missing_features,
+            )?;
+        }
+        let code = self.load_code()?;
+        if let Some(from) = self.from.as_ref() {
+            return self.generate_call_data(from, &code);
+        }
+        let signer = self.extrinsic_opts.signer()?;
+        if !self.output_json {
+            self.extrinsic_opts.print_signer(&signer)?;
         }
-    };
+        if self.dry_run {
+            return dry_run_deploy(&self.extrinsic_opts, &signer, &code);
+        }
+        self.extrinsic_opts.confirm_chain()?;
+        let (code_hash, cost) =
+            execute_deploy(&self.extrinsic_opts, &signer, &code, self.force_upload)?;
+        let mut costs = CostReport::default();
+        let uploaded = cost.is_some();
+        if let Some(cost) = cost {
+            costs.add(cost);
+        }
+        if self.output_json {
+            let json = json!({
+                "signer": signer.account_id().to_string(),
+                "code_hash": format!("{:?}", code_hash),
+                "costs": costs.to_json(),
+            });
+            return Ok(serde_json::to_string_pretty(&json)?);
+        }
+        if !uploaded {
+            return Ok(format!("Code hash: {:?}", code_hash));
+        }
+        Ok(format!(
+            "Code hash: {:?}\n\n\t{}",
+            code_hash,
+            costs.display()
+        ))
+    }
+
+    /// Returns the payload for deploying the code, to be signed externally by `from`.
+    fn generate_call_data(&self, from: &AccountId32, code: &[u8]) -> Result<String> {
+        let unsigned = async_std::task::block_on(async move {
+            let rpc = self.extrinsic_opts.rpc_client().await?;
+            let cli = ClientBuilder::<DefaultNodeRuntime>::new()
+                .set_client(rpc.clone())
+                .build()
+                .await?;
+            let call = PutCodeCall {
+                _runtime: PhantomData,
+                code,
+            };
+            unsigned_extrinsic(&cli, &rpc, from, call).await
+        })?;
+        Ok(format!(
+            "Code hash: {:?}\n\tAccount: {}, nonce: {}\n\tCall data: 0x{}\n\t\
+             Signing payload: 0x{}",
+            H256(sp_core::blake2_256(code)),
+            from,
+            unsigned.nonce,
+            hex::encode(&unsigned.call_data),
+            hex::encode(&unsigned.signing_payload)
+        ))
+    }
+
+    /// Reads the runtime features the contract requires from its metadata, or its bundle.
+    fn load_required_features(&self) -> Result<Vec<String>> {
+        let path = match self.bundle.as_ref().or_else(|| self.metadata.as_ref()) {
+            Some(path) => path.clone(),
+            None if self.wasm.is_some() || self.wasm_path.is_some() => anyhow::bail!(
+                "Checking the required features needs the metadata of the code, pass --metadata \
+                 or --bundle"
+            ),
+            None => {
+                let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+                CrateMetadata::collect(&manifest_path)?.dest_metadata
+            }
+        };
+        required_features(&read_metadata(&path)?).context(format!(
+            "Failed to read the required features from {}",
+            path.display()
+        ))
+    }
+
+    /// Loads the code to deploy, stripping its custom sections if requested.
+    fn load_code(&self) -> Result<Vec<u8>> {
+        let mut code = self.load_contract_code()?;
+        if self.strip_custom_sections {
+            let stripped = strip_code(&code)?;
+            println!(
+                "{} {}",
+                "Stripped custom sections:".bold(),
+                format!("saved {} bytes", code.len() - stripped.len())
+            );
+            if stripped != code && (self.metadata.is_some() || self.bundle.is_some()) {
+                println!(
+                    "{} {}",
+                    "warning:".yellow().bold(),
+                    "the code hash of the stripped code no longer matches the hash in the metadata"
+                        .bold()
+                );
+            }
+            code = stripped;
+        }
+        Ok(code)
+    }
+
+    /// Load the wasm blob from the input specified on the command line.
+    ///
+    /// Defaults to the target contract wasm in the current project, inferred via the crate metadata.
+    fn load_contract_code(&self) -> Result<Vec<u8>> {
+        if let Some(bundle) = self.bundle.as_ref() {
+            return load_bundle_code(bundle);
+        }
+        let contract_wasm_path = match self.wasm.as_ref().or_else(|| self.wasm_path.as_ref()) {
+            Some(path) => path.clone(),
+            None => {
+                let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+                CrateMetadata::collect(&manifest_path)?.dest_wasm
+            }
+        };
+        let code = load_wasm_code(&contract_wasm_path)?;
+        if let Some(metadata) = self.metadata.as_ref() {
+            check_metadata_code_hash(metadata, &code)?;
+        }
+        Ok(code)
+    }
+}
+
+/// Load the wasm blob from the specified path.
+pub(super) fn load_wasm_code(contract_wasm_path: &Path) -> Result<Vec<u8>> {
     log::info!("Contract code path: {}", contract_wasm_path.display());
     let mut data = Vec::new();
-    let mut file = fs::File::open(&contract_wasm_path)
+    let mut file = fs::File::open(contract_wasm_path)
         .context(format!("Failed to open {}", contract_wasm_path.display()))?;
     file.read_to_end(&mut data)?;
 
     Ok(data)
 }
 
+/// Returns the hex string of a `source` field of the contract metadata, without the `0x` prefix.
+fn source_field<'a>(metadata: &'a serde_json::Value, field: &str) -> Result<&'a str> {
+    let value = metadata
+        .get("source")
+        .and_then(|source| source.get(field))
+        .and_then(|value| value.as_str())
+        .ok_or(anyhow::anyhow!(
+            "source.{} not found in the metadata",
+            field
+        ))?;
+    Ok(value.trim_start_matches("0x"))
+}
+
+/// Load the wasm blob embedded in a `<name>.contract` bundle.
+fn load_bundle_code(bundle_path: &Path) -> Result<Vec<u8>> {
+    log::info!("Contract bundle path: {}", bundle_path.display());
+    let bundle = read_metadata(bundle_path)?;
+    super::bundle::bundle_code(&bundle)
+        .context(format!("{} is not a valid bundle", bundle_path.display()))
+}
+
+/// Checks that the code hash in the metadata is the hash of the supplied code.
+fn check_metadata_code_hash(metadata_path: &Path, code: &[u8]) -> Result<()> {
+    let metadata = read_metadata(metadata_path)?;
+    let expected = source_field(&metadata, "hash")?;
+    let actual = hex::encode(sp_core::blake2_256(code));
+    if expected != actual {
+        anyhow::bail!(
+            "The code hash in {} (0x{}) does not match the hash of the wasm code (0x{})",
+            metadata_path.display(),
+            expected,
+            actual
+        );
+    }
+    Ok(())
+}
+
+/// Removes all custom sections from the wasm code.
+///
+/// The contracts pallet does not read any custom sections, so none of them are required on chain.
+pub(super) fn strip_code(code: &[u8]) -> Result<Vec<u8>> {
+    let mut module: Module =
+        parity_wasm::deserialize_buffer(code).context("Failed to parse the wasm code")?;
+    super::build::strip_custom_sections(&mut module);
+    let stripped = parity_wasm::serialize(module)?;
+    // the stripped code must still be a valid module
+    parity_wasm::deserialize_buffer::<Module>(&stripped)
+        .context("Stripping custom sections produced invalid wasm code")?;
+    Ok(stripped)
+}
+
 /// Put contract code to a smart contract enabled substrate chain.
-/// Returns the code hash of the deployed contract if successful.
+/// Returns a tuple of `(code_hash, maybe_cost)` if successful, there is no cost if the upload was
+/// skipped.
 ///
-/// Optionally supply the contract wasm path, defaults to destination contract file inferred from
-/// Cargo.toml of the current contract project.
+/// Unless `force_upload` is set the upload is skipped if the code is already present on chain,
+/// which would otherwise fail with `CodeAlreadyExists` after paying for the extrinsic.
 ///
 /// Creates an extrinsic with the `Contracts::put_code` Call, submits via RPC, then waits for
 /// the `ContractsEvent::CodeStored` event.
 pub(crate) fn execute_deploy(
     extrinsic_opts: &ExtrinsicOpts,
-    contract_wasm_path: Option<&PathBuf>,
-) -> Result<H256> {
-    let code = load_contract_code(contract_wasm_path)?;
+    signer: &ExtrinsicSigner,
+    code: &[u8],
+    force_upload: bool,
+) -> Result<(H256, Option<OperationCost>)> {
+    let code_hash = H256(sp_core::blake2_256(code));
 
     async_std::task::block_on(async move {
+        let rpc = extrinsic_opts.rpc_client().await?;
         let cli = ClientBuilder::<DefaultNodeRuntime>::new()
-            .set_url(&extrinsic_opts.url.to_string())
+            .set_client(rpc.clone())
             .build()
             .await?;
-        let signer = extrinsic_opts.signer()?;
 
-        let events = cli.put_code_and_watch(&signer, &code).await?;
+        if !force_upload && code_exists(&cli, &code_hash).await? {
+            println!(
+                "{} {}",
+                "Code already present on chain, skipping upload.".bold(),
+                "Use --force-upload to submit it anyway."
+            );
+            return Ok((code_hash, None));
+        }
+
+        if extrinsic_opts.check_balance {
+            let call = PutCodeCall {
+                _runtime: PhantomData,
+                code,
+            };
+            check_balance(&cli, &rpc, signer, call, 0).await?;
+        }
+
+        let events = cli.put_code_and_watch(signer, code).await?;
         let code_stored = events
             .code_stored()?
             .ok_or(anyhow::anyhow!("Failed to find CodeStored event"))?;
+        let cost = OperationCost::from_events("deploy", &events, 0)?;
 
-        Ok(code_stored.code_hash)
+        Ok((code_stored.code_hash, Some(cost)))
     })
 }
 
@@ -76,7 +390,8 @@ pub(crate) fn execute_deploy(
 mod tests {
     use std::{fs, io::Write};
 
-    use crate::{cmd::deploy::execute_deploy, util::tests::with_tmp_dir, ExtrinsicOpts};
+    use super::{check_metadata_code_hash, load_bundle_code, strip_code};
+    use crate::{cmd::deploy::execute_deploy, util::tests::with_tmp_dir, ExtrinsicOpts, NodeOpts};
     use assert_matches::assert_matches;
 
     const CONTRACT: &str = r#"
@@ -89,23 +404,81 @@ mod tests {
     #[test]
     #[ignore] // depends on a local substrate node running
     fn deploy_contract() {
+        let wasm = wabt::wat2wasm(CONTRACT).expect("invalid wabt");
+
+        let url = url::Url::parse("ws://localhost:9944").unwrap();
+        let extrinsic_opts = ExtrinsicOpts {
+            node: NodeOpts {
+                url: Some(url),
+                network: None,
+            },
+            suri: Some("//Alice".into()),
+            account: None,
+            keystore_path: None,
+            keystore: None,
+            password: None,
+            scheme: None,
+            check_balance: false,
+            prompt_on_mainnet: false,
+            yes: false,
+            refresh_metadata: false,
+            verbosity: Default::default(),
+        };
+        let signer = extrinsic_opts.signer().unwrap();
+        let result = execute_deploy(&extrinsic_opts, &signer, &wasm, false);
+
+        assert_matches!(result, Ok(_));
+    }
+
+    #[test]
+    fn loads_code_from_bundle() {
         with_tmp_dir(|path| {
-            let wasm = wabt::wat2wasm(CONTRACT).expect("invalid wabt");
+            let bundle_path = path.join("test.contract");
+            let mut file = fs::File::create(&bundle_path)?;
+            file.write_all(br#"{ "metadataVersion": "0.1.0", "source": { "hash": "0x00", "wasm": "0x0061736d" } }"#)?;
 
-            let wasm_path = path.join("test.wasm");
-            let mut file = fs::File::create(&wasm_path).unwrap();
-            let _ = file.write_all(&wasm);
+            let code = load_bundle_code(&bundle_path)?;
 
-            let url = url::Url::parse("ws://localhost:9944").unwrap();
-            let extrinsic_opts = ExtrinsicOpts {
-                url,
-                suri: "//Alice".into(),
-                password: None,
-            };
-            let result = execute_deploy(&extrinsic_opts, Some(&wasm_path));
+            assert_eq!(code, vec![0x00, 0x61, 0x73, 0x6d]);
 
-            assert_matches!(result, Ok(_));
+            fs::write(
+                &bundle_path,
+                r#"{ "metadataVersion": "0.1.0", "source": { "hash": "0x00", "contract_binary": "0x0061736d" } }"#,
+            )?;
+            assert_eq!(load_bundle_code(&bundle_path)?, code);
             Ok(())
         })
     }
+
+    #[test]
+    fn metadata_code_hash_must_match() {
+        with_tmp_dir(|path| {
+            let code = [0x00, 0x61, 0x73, 0x6d];
+            let metadata_path = path.join("metadata.json");
+            let metadata = format!(
+                r#"{{ "source": {{ "hash": "0x{}" }} }}"#,
+                hex::encode(sp_core::blake2_256(&code))
+            );
+            fs::write(&metadata_path, metadata)?;
+
+            assert_matches!(check_metadata_code_hash(&metadata_path, &code), Ok(()));
+            assert_matches!(check_metadata_code_hash(&metadata_path, &code[1..]), Err(_));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn strips_custom_sections() {
+        // the magic number and version of an empty module
+        let wasm = vec![0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+        let mut with_custom_section = wasm.clone();
+        // a custom section (id 0) named `producers` with a single byte of payload
+        with_custom_section.extend_from_slice(&[0, 11, 9]);
+        with_custom_section.extend_from_slice(b"producers");
+        with_custom_section.push(0);
+
+        let stripped = strip_code(&with_custom_section).expect("stripping should succeed");
+
+        assert_eq!(stripped, wasm);
+    }
 }
@@ -0,0 +1,178 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Refuses to build from inputs which would make the build irreproducible.
+//!
+//! A build is only reproducible if
+//!
+//! - the toolchain is pinned to a dated nightly or an exact version by a `rust-toolchain` file,
+//! - the sources are committed to git,
+//! - the dependencies are pinned by a `Cargo.lock` and cargo runs offline, so that nothing is
+//!   resolved or fetched during the build,
+//! - the version of the `binaryen` crate bundling `wasm-opt` is in the range attested with
+//!   `--require-binaryen-crate-version`, or matches the one of the attestation of `--verify-env`.
+
+use std::{env, fs, path::Path};
+
+use anyhow::Result;
+use semver::VersionReq;
+
+use super::{
+    build::check_binaryen_crate_version,
+    git::{git_status, GitStatus},
+    verify_env::verify_env,
+};
+
+/// The file pinning the toolchain.
+const TOOLCHAIN_FILE: &str = "rust-toolchain";
+
+/// The version of the `binaryen` crate bundling wasm-opt the build is required to use.
+pub(crate) struct BinaryenAttestation<'a> {
+    /// The range of `--require-binaryen-crate-version`.
+    pub required: Option<&'a VersionReq>,
+    /// The attestation of `--verify-env`.
+    pub attestation: Option<&'a Path>,
+}
+
+/// Fails if any input of the build would break its reproducibility, reporting each one.
+pub(crate) fn require_deterministic(
+    package_dir: &Path,
+    workspace_root: &Path,
+    binaryen: BinaryenAttestation,
+) -> Result<()> {
+    let mut violations = Vec::new();
+
+    let toolchain = [package_dir, workspace_root]
+        .iter()
+        .find_map(|dir| fs::read_to_string(dir.join(TOOLCHAIN_FILE)).ok());
+    match toolchain.as_deref().map(str::trim) {
+        Some(toolchain) if is_pinned(toolchain) => (),
+        Some(toolchain) => violations.push(format!(
+            "the toolchain `{}` of the {} file is not pinned to a dated nightly, e.g. \
+             `nightly-2020-10-16`, or an exact version",
+            toolchain, TOOLCHAIN_FILE
+        )),
+        None => violations.push(format!(
+            "the toolchain is not pinned, add a {} file to {}",
+            TOOLCHAIN_FILE,
+            workspace_root.display()
+        )),
+    }
+
+    match git_status(package_dir)? {
+        GitStatus::Clean => (),
+        GitStatus::Dirty(files) => violations.push(format!(
+            "the git working tree has uncommitted changes: {}",
+            files.join(", ")
+        )),
+        GitStatus::NotARepository => violations.push(format!(
+            "{} is not inside a git working tree, its sources cannot be identified",
+            package_dir.display()
+        )),
+    }
+
+    if !workspace_root.join("Cargo.lock").exists() {
+        violations.push("the dependencies are not pinned by a Cargo.lock".to_string());
+    }
+    if !is_offline(env::var("CARGO_NET_OFFLINE").ok().as_deref()) {
+        violations.push(
+            "the build may access the network, fetch the dependencies with `cargo fetch` and \
+             build with --offline"
+                .to_string(),
+        );
+    }
+
+    if binaryen.required.is_none() && binaryen.attestation.is_none() {
+        violations.push(
+            "the binaryen crate version is not attested, pass --require-binaryen-crate-version \
+             or --verify-env"
+                .to_string(),
+        );
+    }
+    if let Some(Err(err)) = binaryen.required.map(check_binaryen_crate_version) {
+        violations.push(err.to_string());
+    }
+    if let Some(Err(err)) = binaryen.attestation.map(verify_env) {
+        violations.push(err.to_string());
+    }
+
+    if !violations.is_empty() {
+        anyhow::bail!(
+            "Refusing a --deterministic-only build, it would not be reproducible:\n  - {}",
+            violations.join("\n  - ")
+        )
+    }
+    Ok(())
+}
+
+/// Returns whether the toolchain is a dated nightly, beta or an exact stable version.
+fn is_pinned(toolchain: &str) -> bool {
+    let is_date = |date: &str| {
+        let parts = date.split('-').collect::<Vec<_>>();
+        parts.len() == 3 && parts.iter().all(|part| part.parse::<u32>().is_ok())
+    };
+    let is_version = |version: &str| {
+        let parts = version.split('.').collect::<Vec<_>>();
+        parts.len() == 3 && parts.iter().all(|part| part.parse::<u32>().is_ok())
+    };
+    let mut parts = toolchain.splitn(2, '-');
+    match (parts.next(), parts.next()) {
+        (Some("nightly"), Some(date)) | (Some("beta"), Some(date)) => is_date(date),
+        _ => is_version(toolchain),
+    }
+}
+
+/// Returns whether cargo runs offline with the value of `CARGO_NET_OFFLINE`.
+fn is_offline(cargo_net_offline: Option<&str>) -> bool {
+    matches!(cargo_net_offline, Some("true") | Some("1"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_dated_or_exact_toolchains_are_pinned() {
+        assert!(is_pinned("nightly-2020-10-16"));
+        assert!(is_pinned("1.48.0"));
+        assert!(!is_pinned("nightly"));
+        assert!(!is_pinned("stable"));
+        assert!(!is_pinned("1.48"));
+        assert!(!is_pinned("nightly-2020-10"));
+        assert!(is_offline(Some("true")));
+        assert!(!is_offline(Some("false")));
+        assert!(!is_offline(None));
+    }
+
+    #[test]
+    fn each_violation_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(TOOLCHAIN_FILE), "nightly\n").unwrap();
+        let binaryen = BinaryenAttestation {
+            required: None,
+            attestation: None,
+        };
+
+        let err = require_deterministic(dir.path(), dir.path(), binaryen)
+            .unwrap_err()
+            .to_string();
+
+        assert!(err.contains("the toolchain `nightly` of the rust-toolchain file is not pinned"));
+        assert!(err.contains("is not inside a git working tree"));
+        assert!(err.contains("not pinned by a Cargo.lock"));
+        assert!(err.contains("the binaryen crate version is not attested"));
+    }
+}
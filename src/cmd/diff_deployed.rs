@@ -0,0 +1,291 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Compares a local build with a deployed contract.
+//!
+//! The code hash of the contract is read from the chain. Nodes do not store the metadata of
+//! contracts, so the abi of the deployed contract can only be compared if its metadata is
+//! supplied.
+
+use std::{convert::TryFrom, path::PathBuf};
+
+use anyhow::Result;
+use serde_json::Value;
+use sp_core::{crypto::AccountId32, H256};
+use structopt::StructOpt;
+use subxt::{ClientBuilder, DefaultNodeRuntime};
+
+use super::{
+    bindings::spec_name,
+    deploy::{load_wasm_code, strip_code},
+    metadata::read_metadata,
+    storage::contract_code_hash,
+};
+use crate::{crate_metadata::CrateMetadata, workspace::ManifestPath};
+
+/// Checks whether a deployed contract runs the code, and has the abi, of the local build.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "diff-deployed")]
+pub struct DiffDeployedCommand {
+    /// Websockets url of a substrate node
+    #[structopt(
+        name = "url",
+        long,
+        parse(try_from_str),
+        default_value = "ws://localhost:9944"
+    )]
+    url: url::Url,
+    /// The address of the deployed contract
+    #[structopt(long)]
+    contract: AccountId32,
+    /// Path to the Cargo.toml of the local contract
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    /// Path to the local wasm code, defaults to the code built for the contract
+    #[structopt(long, parse(from_os_str))]
+    wasm: Option<PathBuf>,
+    /// Path to the local metadata, defaults to the metadata built for the contract
+    #[structopt(long, parse(from_os_str))]
+    metadata: Option<PathBuf>,
+    /// Path to the metadata of the deployed contract, the abi is only compared if it is given
+    #[structopt(long, parse(from_os_str))]
+    abi: Option<PathBuf>,
+}
+
+impl DiffDeployedCommand {
+    pub fn exec(&self) -> Result<String> {
+        let code = match self.wasm.as_ref() {
+            Some(wasm) => load_wasm_code(wasm)?,
+            None => load_wasm_code(&self.crate_metadata()?.dest_wasm)?,
+        };
+        let deployed_hash = self.fetch_code_hash()?;
+        let code_hash = compare_code(&code, &deployed_hash)?;
+
+        let abi = match self.abi.as_ref() {
+            Some(abi) => {
+                let metadata = match self.metadata.as_ref() {
+                    Some(metadata) => metadata.clone(),
+                    None => self.crate_metadata()?.dest_metadata,
+                };
+                let differences = diff_abi(&read_metadata(&metadata)?, &read_metadata(abi)?);
+                display_abi_differences(&differences)
+            }
+            None => "not compared, the node does not store the metadata of contracts. Pass the \
+                     deployed metadata with --abi"
+                .to_string(),
+        };
+        Ok(format!(
+            "Contract {}\n\tCode hash: {}\n\tABI: {}",
+            self.contract, code_hash, abi
+        ))
+    }
+
+    fn crate_metadata(&self) -> Result<CrateMetadata> {
+        let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+        CrateMetadata::collect(&manifest_path)
+    }
+
+    /// Fetches the hash of the code the contract is instantiated from.
+    fn fetch_code_hash(&self) -> Result<H256> {
+        async_std::task::block_on(async move {
+            let rpc = jsonrpsee::ws_client(self.url.as_str()).await?;
+            let cli = ClientBuilder::<DefaultNodeRuntime>::new()
+                .set_client(rpc)
+                .build()
+                .await?;
+            contract_code_hash(&cli, &self.contract).await
+        })
+    }
+}
+
+/// Describes whether `code` is the deployed code.
+///
+/// The code may have been deployed with `--strip-custom-sections`, so the hash of the stripped
+/// code is compared as well.
+fn compare_code(code: &[u8], deployed_hash: &H256) -> Result<String> {
+    let code_hash = H256(sp_core::blake2_256(code));
+    if code_hash == *deployed_hash {
+        return Ok(format!("match ({:?})", code_hash));
+    }
+    let stripped_hash = H256(sp_core::blake2_256(&strip_code(code)?));
+    if stripped_hash == *deployed_hash {
+        return Ok(format!(
+            "match ({:?}), the code was deployed without its custom sections",
+            stripped_hash
+        ));
+    }
+    Ok(format!(
+        "MISMATCH, the local code has {:?} but the deployed code {:?}",
+        code_hash, deployed_hash
+    ))
+}
+
+/// Returns the differences of the constructors and messages of the `local` metadata to the
+/// `deployed` one.
+///
+/// Types are compared by their display names, the ids of the type registry differ between
+/// builds.
+fn diff_abi(local: &Value, deployed: &Value) -> Vec<String> {
+    let mut differences = Vec::new();
+    for (kind, pointer) in &[
+        ("constructor", "/spec/constructors"),
+        ("message", "/spec/messages"),
+    ] {
+        let specs = |metadata: &Value| {
+            metadata
+                .pointer(pointer)
+                .and_then(Value::as_array)
+                .cloned()
+                .unwrap_or_default()
+        };
+        let (local, deployed) = (specs(local), specs(deployed));
+        let find = |specs: &[Value], name: &str| {
+            specs.iter().find(|spec| spec_name(spec) == name).cloned()
+        };
+
+        for spec in &local {
+            let name = spec_name(spec);
+            let deployed = match find(&deployed, &name) {
+                Some(deployed) => deployed,
+                None => {
+                    differences.push(format!("{} `{}` is not deployed", kind, name));
+                    continue;
+                }
+            };
+            let selector = |spec: &Value| spec["selector"].as_str().unwrap_or("?").to_string();
+            let (selector, deployed_selector) = (selector(spec), selector(&deployed));
+            if selector != deployed_selector {
+                differences.push(format!(
+                    "{} `{}` has the selector {} but the deployed one {}",
+                    kind, name, selector, deployed_selector
+                ));
+            }
+            let (signature, deployed_signature) = (signature(spec), signature(&deployed));
+            if signature != deployed_signature {
+                differences.push(format!(
+                    "{} `{}{}` was deployed as `{}{}`",
+                    kind, name, signature, name, deployed_signature
+                ));
+            }
+        }
+        for spec in &deployed {
+            let name = spec_name(spec);
+            if find(&local, &name).is_none() {
+                differences.push(format!("{} `{}` is missing locally", kind, name));
+            }
+        }
+    }
+    differences
+}
+
+/// Returns the signature of a constructor or message, e.g. `(value: u32) -> bool`.
+fn signature(spec: &Value) -> String {
+    let args = spec
+        .get("args")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .map(|arg| format!("{}: {}", spec_name(arg), display_name(&arg["type"])))
+        .collect::<Vec<_>>();
+    match spec.get("returnType").filter(|ty| !ty.is_null()) {
+        Some(ty) => format!("({}) -> {}", args.join(", "), display_name(ty)),
+        None => format!("({})", args.join(", ")),
+    }
+}
+
+fn display_name(ty: &Value) -> String {
+    ty.get("displayName")
+        .and_then(Value::as_array)
+        .map(|segments| {
+            segments
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+                .join("::")
+        })
+        .unwrap_or_else(|| "?".to_string())
+}
+
+fn display_abi_differences(differences: &[String]) -> String {
+    if differences.is_empty() {
+        return "identical".to_string();
+    }
+    format!(
+        "{} differences\n\t  - {}",
+        differences.len(),
+        differences.join("\n\t  - ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn metadata(messages: Value) -> Value {
+        json!({
+            "spec": {
+                "constructors": [
+                    { "args": [], "name": ["new"], "selector": "0x9bae9d5e" }
+                ],
+                "messages": messages
+            }
+        })
+    }
+
+    #[test]
+    fn abi_differences_are_reported() {
+        let local = metadata(json!([
+            { "args": [], "name": ["get"], "selector": "0x1e5ca456",
+              "returnType": { "displayName": ["bool"], "type": 1 } },
+            { "args": [ { "name": "value", "type": { "displayName": ["u64"], "type": 2 } } ],
+              "name": ["set"], "selector": "0x2a3b4c5d", "returnType": null },
+            { "args": [], "name": ["flip"], "selector": "0x633aa551", "returnType": null }
+        ]));
+        let deployed = metadata(json!([
+            { "args": [], "name": ["get"], "selector": "0x1e5ca456",
+              "returnType": { "displayName": ["bool"], "type": 3 } },
+            { "args": [ { "name": "value", "type": { "displayName": ["u32"], "type": 1 } } ],
+              "name": ["set"], "selector": "0x2a3b4c5e", "returnType": null },
+            { "args": [], "name": ["reset"], "selector": "0x0b0c0d0e", "returnType": null }
+        ]));
+
+        assert_eq!(
+            diff_abi(&local, &deployed),
+            vec![
+                "message `set` has the selector 0x2a3b4c5d but the deployed one 0x2a3b4c5e",
+                "message `set(value: u64)` was deployed as `set(value: u32)`",
+                "message `flip` is not deployed",
+                "message `reset` is missing locally",
+            ]
+        );
+        assert!(diff_abi(&local, &local).is_empty());
+    }
+
+    #[test]
+    fn code_hash_is_compared() {
+        let code = wabt::wat2wasm("(module)").expect("invalid wabt");
+        let code_hash = H256(sp_core::blake2_256(&code));
+
+        assert!(compare_code(&code, &code_hash)
+            .unwrap()
+            .starts_with("match"));
+        assert!(compare_code(&code, &H256::zero())
+            .unwrap()
+            .starts_with("MISMATCH"));
+    }
+}
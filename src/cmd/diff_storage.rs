@@ -0,0 +1,433 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Compares the storage layouts of two versions of a contract, for checking that an upgrade can
+//! still read the existing storage.
+//!
+//! The storage entries are matched by the path of their field, e.g. `balances.total_supply`. An
+//! entry is a cell, the discriminant of an enum, or the root of a hash map or array whose
+//! nested entries are relative to it. The type ids differ between metadata files, so the types
+//! are compared by their names resolved from the type registry.
+//!
+//! Adding an entry is compatible. Removing an entry, moving it to another key or changing its
+//! type breaks the existing storage.
+
+use std::{collections::BTreeMap, path::PathBuf};
+
+use anyhow::Result;
+use serde_json::Value;
+use structopt::StructOpt;
+
+use super::metadata::read_metadata;
+
+/// Reports the changes of the storage layout between two versions of a contract.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "diff-storage")]
+pub struct DiffStorageCommand {
+    /// The metadata of the deployed version, a `metadata.json` or a `.contract` bundle
+    #[structopt(long, parse(from_os_str))]
+    old: PathBuf,
+    /// The metadata of the upgraded version, a `metadata.json` or a `.contract` bundle
+    #[structopt(long, parse(from_os_str))]
+    new: PathBuf,
+}
+
+impl DiffStorageCommand {
+    pub fn exec(&self) -> Result<String> {
+        let old = storage_entries(&read_metadata(&self.old)?)?;
+        let new = storage_entries(&read_metadata(&self.new)?)?;
+        let changes = diff(&old, &new);
+        if changes.is_empty() {
+            return Ok("The storage layout is unchanged".to_string());
+        }
+        let report = changes
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n\t");
+        let breaking = changes.iter().filter(|change| change.is_breaking()).count();
+        if breaking > 0 {
+            anyhow::bail!(
+                "The storage layout changed incompatibly, {} of {} changes break the existing \
+                 storage:\n\t{}",
+                breaking,
+                changes.len(),
+                report
+            );
+        }
+        Ok(format!(
+            "The storage layout changed compatibly:\n\t{}",
+            report
+        ))
+    }
+}
+
+/// An entry of the storage layout.
+#[derive(Clone, Debug, PartialEq)]
+struct StorageEntry {
+    /// The key of the entry, relative to the enclosing hash map or array if nested in one.
+    key: String,
+    /// What the entry stores, e.g. the name of the type of a cell.
+    ty: String,
+}
+
+/// A change of the storage layout.
+#[derive(Debug, PartialEq)]
+enum Change {
+    Added(String, StorageEntry),
+    Removed(String, StorageEntry),
+    Moved {
+        path: String,
+        old: String,
+        new: String,
+    },
+    Retyped {
+        path: String,
+        old: String,
+        new: String,
+    },
+}
+
+impl Change {
+    fn is_breaking(&self) -> bool {
+        !matches!(self, Change::Added(..))
+    }
+}
+
+impl std::fmt::Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Change::Added(path, entry) => {
+                write!(f, "added   {}: {} at {}", path, entry.ty, entry.key)
+            }
+            Change::Removed(path, entry) => {
+                write!(f, "removed {}: {} at {}", path, entry.ty, entry.key)
+            }
+            Change::Moved { path, old, new } => write!(f, "moved   {}: {} -> {}", path, old, new),
+            Change::Retyped { path, old, new } => {
+                write!(f, "retyped {}: {} -> {}", path, old, new)
+            }
+        }
+    }
+}
+
+/// Returns the changes from the `old` to the `new` entries, removed and changed entries in the
+/// order of their paths followed by the added entries.
+fn diff(old: &BTreeMap<String, StorageEntry>, new: &BTreeMap<String, StorageEntry>) -> Vec<Change> {
+    let mut changes = Vec::new();
+    for (path, old_entry) in old {
+        match new.get(path) {
+            None => changes.push(Change::Removed(path.clone(), old_entry.clone())),
+            Some(new_entry) => {
+                if old_entry.key != new_entry.key {
+                    changes.push(Change::Moved {
+                        path: path.clone(),
+                        old: old_entry.key.clone(),
+                        new: new_entry.key.clone(),
+                    });
+                }
+                if old_entry.ty != new_entry.ty {
+                    changes.push(Change::Retyped {
+                        path: path.clone(),
+                        old: old_entry.ty.clone(),
+                        new: new_entry.ty.clone(),
+                    });
+                }
+            }
+        }
+    }
+    for (path, new_entry) in new {
+        if !old.contains_key(path) {
+            changes.push(Change::Added(path.clone(), new_entry.clone()));
+        }
+    }
+    changes
+}
+
+/// Returns the entries of the storage layout of the metadata, keyed by their path.
+fn storage_entries(metadata: &Value) -> Result<BTreeMap<String, StorageEntry>> {
+    let layout = metadata
+        .get("storage")
+        .ok_or(anyhow::anyhow!("storage layout not found in the metadata"))?;
+    let types = metadata
+        .get("types")
+        .and_then(Value::as_array)
+        .ok_or(anyhow::anyhow!("type registry not found in the metadata"))?;
+    let mut entries = BTreeMap::new();
+    collect_entries(layout, "", types, &mut entries)?;
+    Ok(entries)
+}
+
+/// Collects the entries of a layout, recursing into structs, enums, hash maps and arrays.
+fn collect_entries(
+    layout: &Value,
+    path: &str,
+    types: &[Value],
+    entries: &mut BTreeMap<String, StorageEntry>,
+) -> Result<()> {
+    let key = |value: Option<&Value>, what: &str| {
+        value
+            .and_then(Value::as_str)
+            .map(ToString::to_string)
+            .ok_or(anyhow::anyhow!("{}: {} should be a string", path, what))
+    };
+    if let Some(cell) = layout.get("cell") {
+        let ty = cell
+            .get("ty")
+            .and_then(Value::as_u64)
+            .ok_or(anyhow::anyhow!("{}: cell type should be a number", path))?;
+        let entry = StorageEntry {
+            key: key(cell.get("key"), "cell key")?,
+            ty: type_name(types, ty),
+        };
+        entries.insert(path.to_string(), entry);
+    } else if let Some(fields) = layout
+        .get("struct")
+        .and_then(|s| s.get("fields"))
+        .and_then(Value::as_array)
+    {
+        collect_fields(fields, path, types, entries)?;
+    } else if let Some(layout) = layout.get("enum") {
+        let entry = StorageEntry {
+            key: key(layout.get("dispatchKey"), "enum dispatch key")?,
+            ty: "enum discriminant".to_string(),
+        };
+        entries.insert(path.to_string(), entry);
+        let variants = layout
+            .get("variants")
+            .and_then(Value::as_object)
+            .ok_or(anyhow::anyhow!(
+                "{}: enum variants should be an object",
+                path
+            ))?;
+        for (variant, fields) in variants {
+            let fields = fields
+                .get("fields")
+                .and_then(Value::as_array)
+                .ok_or(anyhow::anyhow!("{}: enum variant should have fields", path))?;
+            collect_fields(fields, &format!("{}::{}", path, variant), types, entries)?;
+        }
+    } else if let Some(layout) = layout.get("hash") {
+        let hasher = layout
+            .get("strategy")
+            .and_then(|strategy| strategy.get("hasher"))
+            .and_then(Value::as_str)
+            .unwrap_or("unknown");
+        let entry = StorageEntry {
+            key: key(layout.get("offset"), "hash offset")?,
+            ty: format!("hash map by {}", hasher),
+        };
+        entries.insert(path.to_string(), entry);
+        if let Some(nested) = layout.get("layout") {
+            collect_entries(nested, &format!("{}[..]", path), types, entries)?;
+        }
+    } else if let Some(layout) = layout.get("array") {
+        let len = layout.get("len").and_then(Value::as_u64).unwrap_or(0);
+        let entry = StorageEntry {
+            key: key(layout.get("offset"), "array offset")?,
+            ty: format!("array of {} elements", len),
+        };
+        entries.insert(path.to_string(), entry);
+        if let Some(nested) = layout.get("layout") {
+            collect_entries(nested, &format!("{}[..]", path), types, entries)?;
+        }
+    } else {
+        anyhow::bail!("{}: unknown storage layout {}", path, layout);
+    }
+    Ok(())
+}
+
+fn collect_fields(
+    fields: &[Value],
+    path: &str,
+    types: &[Value],
+    entries: &mut BTreeMap<String, StorageEntry>,
+) -> Result<()> {
+    for (index, field) in fields.iter().enumerate() {
+        let name = field
+            .get("name")
+            .and_then(Value::as_str)
+            .map(ToString::to_string)
+            .unwrap_or_else(|| index.to_string());
+        let field_path = if path.is_empty() {
+            name
+        } else {
+            format!("{}.{}", path, name)
+        };
+        if let Some(field_layout) = field.get("layout") {
+            collect_entries(field_layout, &field_path, types, entries)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the name of a type of the registry, e.g. `Vec<u8>` or `ink_env::types::AccountId`.
+///
+/// Named types are identified by their path and type parameters, anonymous types by their
+/// structure.
+fn type_name(types: &[Value], ty: u64) -> String {
+    // type ids in the registry are 1-based
+    let entry = match (ty as usize)
+        .checked_sub(1)
+        .and_then(|index| types.get(index))
+    {
+        Some(entry) => entry,
+        None => return format!("<unknown type {}>", ty),
+    };
+    let id = |value: &Value| value.as_u64().or_else(|| value.get("type")?.as_u64());
+    let names = |ids: Option<&Value>| {
+        ids.and_then(Value::as_array)
+            .map(|ids| {
+                ids.iter()
+                    .filter_map(id)
+                    .map(|ty| type_name(types, ty))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default()
+    };
+
+    let path = entry
+        .get("path")
+        .and_then(Value::as_array)
+        .map(|path| path.iter().filter_map(Value::as_str).collect::<Vec<_>>())
+        .unwrap_or_default();
+    if !path.is_empty() {
+        let params = names(entry.get("params"));
+        return if params.is_empty() {
+            path.join("::")
+        } else {
+            format!("{}<{}>", path.join("::"), params.join(", "))
+        };
+    }
+
+    let def = entry.get("def").unwrap_or(&Value::Null);
+    let element = |def: &Value| {
+        def.get("type")
+            .and_then(Value::as_u64)
+            .map_or_else(|| "?".to_string(), |ty| type_name(types, ty))
+    };
+    if let Some(primitive) = def.get("primitive").and_then(Value::as_str) {
+        primitive.to_string()
+    } else if let Some(sequence) = def.get("sequence") {
+        format!("Vec<{}>", element(sequence))
+    } else if let Some(array) = def.get("array") {
+        let len = array.get("len").and_then(Value::as_u64).unwrap_or(0);
+        format!("[{}; {}]", element(array), len)
+    } else if let Some(compact) = def.get("compact") {
+        format!("Compact<{}>", element(compact))
+    } else if let Some(tuple) = def.get("tuple") {
+        format!("({})", names(Some(tuple)).join(", "))
+    } else if let Some(fields) = def
+        .get("composite")
+        .and_then(|composite| composite.get("fields"))
+    {
+        format!("{{ {} }}", names(Some(fields)).join(", "))
+    } else {
+        def.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn cell(key: u8, ty: u64) -> Value {
+        json!({ "cell": { "key": format!("0x{:064x}", key), "ty": ty } })
+    }
+
+    fn metadata(fields: Value) -> Value {
+        json!({
+            "storage": { "struct": { "fields": fields } },
+            "types": [
+                { "def": { "primitive": "u64" } },
+                { "def": { "primitive": "u128" } },
+                { "path": ["ink_env", "types", "AccountId"], "def": { "composite": { "fields": [] } } },
+                { "def": { "sequence": { "type": 5 } } },
+                { "def": { "primitive": "u8" } }
+            ]
+        })
+    }
+
+    #[test]
+    fn entries_are_named_by_path_and_type() {
+        let metadata = metadata(json!([
+            { "name": "owner", "layout": cell(0, 3) },
+            { "name": "balances", "layout": { "hash": {
+                "offset": format!("0x{:064x}", 1),
+                "strategy": { "hasher": "Blake2x256", "prefix": "0x", "postfix": "" },
+                "layout": cell(0, 4)
+            } } }
+        ]));
+
+        let entries = storage_entries(&metadata).unwrap();
+
+        assert_eq!(entries["owner"].ty, "ink_env::types::AccountId");
+        assert_eq!(entries["balances"].ty, "hash map by Blake2x256");
+        assert_eq!(entries["balances[..]"].ty, "Vec<u8>");
+    }
+
+    #[test]
+    fn breaking_changes_are_reported() {
+        let old = storage_entries(&metadata(json!([
+            { "name": "owner", "layout": cell(0, 3) },
+            { "name": "total_supply", "layout": cell(1, 1) },
+            { "name": "paused", "layout": cell(2, 1) }
+        ])))
+        .unwrap();
+        let new = storage_entries(&metadata(json!([
+            { "name": "total_supply", "layout": cell(1, 2) },
+            { "name": "owner", "layout": cell(2, 3) },
+            { "name": "decimals", "layout": cell(3, 5) }
+        ])))
+        .unwrap();
+
+        let changes = diff(&old, &new)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+
+        let key = |key: u8| format!("0x{:064x}", key);
+        assert_eq!(
+            changes,
+            vec![
+                format!("moved   owner: {} -> {}", key(0), key(2)),
+                format!("removed paused: u64 at {}", key(2)),
+                "retyped total_supply: u64 -> u128".to_string(),
+                format!("added   decimals: u8 at {}", key(3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn additions_are_compatible() {
+        let old = storage_entries(&metadata(
+            json!([{ "name": "owner", "layout": cell(0, 3) }]),
+        ))
+        .unwrap();
+        let new = storage_entries(&metadata(json!([
+            { "name": "owner", "layout": cell(0, 3) },
+            { "name": "decimals", "layout": cell(1, 5) }
+        ])))
+        .unwrap();
+
+        let changes = diff(&old, &new);
+
+        assert_eq!(changes.len(), 1);
+        assert!(!changes[0].is_breaking());
+        assert!(diff(&old, &old).is_empty());
+    }
+}
@@ -0,0 +1,233 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Generates a Dockerfile which builds the contract reproducibly.
+//!
+//! The image pins the rust toolchain the contract is built with locally and the version of
+//! cargo-contract generating the file, which bundles the `wasm-opt` optimizer with a fixed version
+//! of the `binaryen` crate. The build checks that version with `--require-binaryen-crate-version`.
+
+use std::{
+    convert::TryFrom,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{Context, Result};
+use rustc_version::Channel;
+use structopt::StructOpt;
+
+use super::build::binaryen_crate_version;
+use crate::{crate_metadata::CrateMetadata, workspace::ManifestPath};
+
+/// The version of cargo-contract installed in the image.
+const CARGO_CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Generates a Dockerfile which builds the contract with the same toolchain and tools.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "generate-dockerfile")]
+pub struct GenerateDockerfileCommand {
+    /// Path to the Cargo.toml of the contract
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    /// The rust toolchain to pin, e.g. `nightly-2020-10-16`, instead of the one active for the
+    /// contract
+    #[structopt(long)]
+    toolchain: Option<String>,
+    /// Write the Dockerfile to this file, defaults to `Dockerfile` next to the Cargo.toml
+    #[structopt(long, parse(from_os_str))]
+    output: Option<PathBuf>,
+    /// Overwrite an existing Dockerfile
+    #[structopt(long)]
+    force: bool,
+}
+
+impl GenerateDockerfileCommand {
+    pub fn exec(&self) -> Result<String> {
+        let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+        let crate_metadata = CrateMetadata::collect(&manifest_path)?;
+        let contract_dir = manifest_path
+            .directory()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        let toolchain = match self.toolchain.as_ref() {
+            Some(toolchain) => toolchain.clone(),
+            None => active_toolchain(&contract_dir)?,
+        };
+
+        let output = match self.output.as_ref() {
+            Some(output) => output.clone(),
+            None => contract_dir.join("Dockerfile"),
+        };
+        if output.exists() && !self.force {
+            anyhow::bail!(
+                "{} already exists, pass --force to overwrite it",
+                output.display()
+            )
+        }
+        let contents = dockerfile(
+            &crate_metadata.package_name,
+            &toolchain,
+            CARGO_CONTRACT_VERSION,
+            &binaryen_crate_version().to_string(),
+        );
+        fs::write(&output, contents)?;
+        Ok(format!(
+            "Dockerfile building with {} written to {}",
+            toolchain,
+            output.display()
+        ))
+    }
+}
+
+/// Returns the rust toolchain used for building the contract in `dir`.
+///
+/// The toolchain is taken from a `rust-toolchain` file, else from rustup. Without rustup only a
+/// stable toolchain can be pinned, by its version.
+pub(crate) fn active_toolchain(dir: &Path) -> Result<String> {
+    if let Ok(contents) = fs::read_to_string(dir.join("rust-toolchain")) {
+        if let Some(toolchain) = contents
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+        {
+            return Ok(toolchain.to_string());
+        }
+    }
+
+    let meta = rustc_version::version_meta()?;
+    let mut rustup = Command::new("rustup");
+    if !dir.as_os_str().is_empty() {
+        rustup.current_dir(dir);
+    }
+    match rustup.args(&["show", "active-toolchain"]).output() {
+        Ok(output) if output.status.success() => {
+            let active = String::from_utf8(output.stdout)?;
+            parse_active_toolchain(&active, &meta.host)
+                .context("Failed to parse the output of `rustup show active-toolchain`")
+        }
+        _ if meta.channel == Channel::Stable => Ok(meta.semver.to_string()),
+        _ => anyhow::bail!(
+            "The {:?} toolchain cannot be determined without rustup, pass --toolchain",
+            meta.channel
+        ),
+    }
+}
+
+/// Returns the toolchain name from the output of `rustup show active-toolchain`, without the
+/// `host` triple, e.g. `nightly-2020-10-16` from
+/// `nightly-2020-10-16-x86_64-unknown-linux-gnu (default)`.
+fn parse_active_toolchain(output: &str, host: &str) -> Result<String> {
+    let toolchain = output
+        .split_whitespace()
+        .next()
+        .ok_or(anyhow::anyhow!("no active toolchain"))?;
+    let suffix = format!("-{}", host);
+    Ok(toolchain.trim_end_matches(suffix.as_str()).to_string())
+}
+
+/// Returns the Dockerfile building the contract `name` with the pinned versions.
+fn dockerfile(
+    name: &str,
+    toolchain: &str,
+    cargo_contract_version: &str,
+    binaryen_crate_version: &str,
+) -> String {
+    format!(
+        r#"# Generated by cargo-contract {cargo_contract} for the contract `{name}`.
+#
+# Builds the contract reproducibly with the versions it was built with:
+#
+#   rust toolchain: {toolchain}
+#   cargo-contract: {cargo_contract}
+#   binaryen crate: {binaryen} (bundling wasm-opt with cargo-contract)
+#
+# Build the image from the contract directory, then copy the artifacts out of it:
+#
+#   docker build -t {name}-build .
+#   docker cp $(docker create {name}-build):/contract/target/ink ./target/ink-docker
+FROM debian:buster-slim
+
+RUN apt-get update \
+    && apt-get install -y --no-install-recommends \
+        build-essential ca-certificates cmake curl git pkg-config libssl-dev \
+    && rm -rf /var/lib/apt/lists/*
+
+ENV RUSTUP_HOME=/usr/local/rustup \
+    CARGO_HOME=/usr/local/cargo \
+    PATH=/usr/local/cargo/bin:$PATH
+
+RUN curl --proto '=https' --tlsv1.2 -sSf https://sh.rustup.rs \
+        | sh -s -- -y --profile minimal --default-toolchain {toolchain} \
+    && rustup component add rust-src --toolchain {toolchain} \
+    && rustup target add wasm32-unknown-unknown --toolchain {toolchain}
+
+RUN cargo install cargo-contract --version ={cargo_contract} --locked
+
+WORKDIR /contract
+COPY . .
+RUN cargo contract build --require-binaryen-crate-version ={binaryen}
+"#,
+        name = name,
+        toolchain = toolchain,
+        cargo_contract = cargo_contract_version,
+        binaryen = binaryen_crate_version,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn active_toolchain_is_parsed_without_the_host() {
+        let host = "x86_64-unknown-linux-gnu";
+
+        assert_eq!(
+            parse_active_toolchain(
+                "nightly-2020-10-16-x86_64-unknown-linux-gnu (directory override)\n",
+                host
+            )
+            .unwrap(),
+            "nightly-2020-10-16"
+        );
+        assert_eq!(
+            parse_active_toolchain("1.48.0-x86_64-unknown-linux-gnu (default)", host).unwrap(),
+            "1.48.0"
+        );
+        assert!(parse_active_toolchain("", host).is_err());
+    }
+
+    #[test]
+    fn rust_toolchain_file_is_preferred() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("rust-toolchain"), "\nnightly-2020-10-16\n").unwrap();
+
+        assert_eq!(active_toolchain(dir.path()).unwrap(), "nightly-2020-10-16");
+    }
+
+    #[test]
+    fn dockerfile_pins_the_versions_and_builds_the_contract() {
+        let dockerfile = dockerfile("flipper", "nightly-2020-10-16", "0.8.0", "0.12.0");
+
+        assert!(dockerfile.contains("--default-toolchain nightly-2020-10-16"));
+        assert!(dockerfile.contains("cargo install cargo-contract --version =0.8.0 --locked"));
+        assert!(dockerfile
+            .ends_with("RUN cargo contract build --require-binaryen-crate-version =0.12.0\n"));
+        assert!(dockerfile.contains("docker build -t flipper-build ."));
+    }
+}
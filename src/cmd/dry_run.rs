@@ -0,0 +1,516 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Dry runs of `deploy --dry-run` and `instantiate --dry-run`, which estimate the costs of the
+//! extrinsic without submitting it, and the gas estimation of `--gas auto`.
+//!
+//! The instantiation is executed by the node with the `contracts_instantiate` RPC, a call with
+//! the `contracts_call` RPC, both report the gas consumed. There is no such RPC for uploading
+//! code, so a deploy dry run only estimates the fee and weight of the signed extrinsic with
+//! `payment_queryInfo`.
+
+use std::marker::PhantomData;
+
+use anyhow::Result;
+use codec::Decode;
+use colored::Colorize;
+use jsonrpsee::common::{to_value, Params};
+use serde_json::{json, Value};
+use sp_core::{crypto::AccountId32, H256};
+use subxt::{contracts::*, ClientBuilder, DefaultNodeRuntime, Signer};
+
+use super::{
+    balance::{estimate_fee, FeeEstimate},
+    instantiate::InstantiateWithSaltCall,
+    signer::ExtrinsicSigner,
+    storage::code_exists,
+};
+use crate::{ExtrinsicOpts, GasLimit, HexData, Verbosity};
+
+/// The flag of the return value set by `ink_env::return_value` on revert.
+const REVERT_FLAG: u64 = 1;
+
+/// The gas limit of a dry run with `--gas auto`, the most the contracts RPCs accept.
+const DRY_RUN_GAS_LIMIT: u64 = 5_000_000_000_000;
+
+/// The result of dry running a constructor with the `contracts_instantiate` RPC.
+#[derive(Debug, PartialEq)]
+struct InstantiateOutcome {
+    gas_consumed: u64,
+    /// The account of the contract, if reported by the node.
+    contract: Option<String>,
+}
+
+/// Estimates the fee of uploading the `code` signed by `signer`, nothing is submitted.
+pub(crate) fn dry_run_deploy(
+    extrinsic_opts: &ExtrinsicOpts,
+    signer: &ExtrinsicSigner,
+    code: &[u8],
+) -> Result<String> {
+    let code_hash = H256(sp_core::blake2_256(code));
+    let (exists, estimate) = async_std::task::block_on(async move {
+        let rpc = extrinsic_opts.rpc_client().await?;
+        let cli = ClientBuilder::<DefaultNodeRuntime>::new()
+            .set_client(rpc.clone())
+            .build()
+            .await?;
+        let call = PutCodeCall {
+            _runtime: PhantomData,
+            code,
+        };
+        let estimate = estimate_fee(&cli, &rpc, signer, call).await?;
+        Ok::<_, anyhow::Error>((code_exists(&cli, &code_hash).await?, estimate))
+    })?;
+
+    let mut lines = vec![
+        format!("Code hash: {:?}", code_hash),
+        display_fee(&estimate),
+    ];
+    if exists {
+        lines.push(format!(
+            "{} {}",
+            "warning:".yellow().bold(),
+            "the code is already present on chain, the upload would be skipped".bold()
+        ));
+    }
+    Ok(display_dry_run(&lines))
+}
+
+/// Dry runs the constructor of the code at `code_hash` with the `contracts_instantiate` RPC,
+/// returning the gas it consumes and the account of the contract, nothing is submitted.
+pub(crate) fn dry_run_instantiate(
+    extrinsic_opts: &ExtrinsicOpts,
+    signer: &ExtrinsicSigner,
+    endowment: u128,
+    gas_limit: GasLimit,
+    code_hash: H256,
+    data: HexData,
+    salt: HexData,
+) -> Result<String> {
+    let gas_limit = match gas_limit {
+        GasLimit::Limit(limit) => limit,
+        GasLimit::Auto => DRY_RUN_GAS_LIMIT,
+    };
+    let origin = signer.account_id().clone();
+    let (response, estimate) = async_std::task::block_on(async {
+        let rpc = extrinsic_opts.rpc_client().await?;
+        let response = instantiate_rpc(
+            &rpc,
+            extrinsic_opts,
+            &origin,
+            endowment,
+            gas_limit,
+            &code_hash,
+            &data.0,
+            &salt.0,
+        )
+        .await?;
+
+        let cli = ClientBuilder::<DefaultNodeRuntime>::new()
+            .set_client(rpc.clone())
+            .build()
+            .await?;
+        let estimate = if salt.0.is_empty() {
+            let call = InstantiateCall {
+                endowment,
+                gas_limit,
+                code_hash: &code_hash,
+                data: &data.0,
+            };
+            estimate_fee(&cli, &rpc, signer, call).await?
+        } else {
+            let call = InstantiateWithSaltCall {
+                endowment,
+                gas_limit,
+                code_hash: &code_hash,
+                data: &data.0,
+                salt: &salt.0,
+            };
+            estimate_fee(&cli, &rpc, signer, call).await?
+        };
+        Ok::<_, anyhow::Error>((response, estimate))
+    })?;
+
+    let outcome = instantiate_outcome(&response)?;
+    let contract = outcome
+        .contract
+        .unwrap_or_else(|| contract_address(&code_hash, &data.0, &salt.0, &origin).to_string());
+    Ok(display_dry_run(&[
+        format!(
+            "Gas consumed: {} of the limit {}",
+            outcome.gas_consumed, gas_limit
+        ),
+        format!("Contract account: {}", contract),
+        display_fee(&estimate),
+    ]))
+}
+
+/// Returns the gas limit of instantiating the code at `code_hash` from `origin`. For `--gas auto`
+/// it is the gas consumed by a dry run of the constructor plus `margin` percent, fails if the dry
+/// run fails.
+pub(crate) fn instantiate_gas_limit(
+    extrinsic_opts: &ExtrinsicOpts,
+    gas_limit: GasLimit,
+    margin: u64,
+    origin: &AccountId32,
+    endowment: u128,
+    code_hash: H256,
+    data: &[u8],
+    salt: &[u8],
+) -> Result<u64> {
+    if let GasLimit::Limit(limit) = gas_limit {
+        return Ok(limit);
+    }
+    let response = async_std::task::block_on(async {
+        let rpc = jsonrpsee::ws_client(extrinsic_opts.url()?.as_str()).await?;
+        instantiate_rpc(
+            &rpc,
+            extrinsic_opts,
+            origin,
+            endowment,
+            DRY_RUN_GAS_LIMIT,
+            &code_hash,
+            data,
+            salt,
+        )
+        .await
+    })?;
+    let gas_consumed = instantiate_outcome(&response)?.gas_consumed;
+    estimated_gas_limit(extrinsic_opts, gas_consumed, margin)
+}
+
+/// Returns the gas limit of calling the `contract` with `data`. For `--gas auto` it is the gas
+/// consumed by a dry run of the call plus `margin` percent, fails if the dry run fails.
+pub(crate) fn call_gas_limit(
+    extrinsic_opts: &ExtrinsicOpts,
+    gas_limit: GasLimit,
+    margin: u64,
+    origin: &AccountId32,
+    contract: &AccountId32,
+    value: u128,
+    data: &[u8],
+) -> Result<u64> {
+    if let GasLimit::Limit(limit) = gas_limit {
+        return Ok(limit);
+    }
+    let response = async_std::task::block_on(async {
+        let rpc = jsonrpsee::ws_client(extrinsic_opts.url()?.as_str()).await?;
+        let call = json!({
+            "origin": origin.to_string(),
+            "dest": contract.to_string(),
+            "value": value,
+            "gasLimit": DRY_RUN_GAS_LIMIT,
+            "inputData": format!("0x{}", hex::encode(data)),
+        });
+        let response: Value = rpc
+            .request("contracts_call", Params::Array(vec![to_value(call)?]))
+            .await?;
+        Ok::<_, anyhow::Error>(response)
+    })?;
+    let gas_consumed = call_gas_consumed(&response)?;
+    estimated_gas_limit(extrinsic_opts, gas_consumed, margin)
+}
+
+/// Adds the `margin` to the estimated gas, printing both unless `--quiet` is passed.
+fn estimated_gas_limit(
+    extrinsic_opts: &ExtrinsicOpts,
+    gas_consumed: u64,
+    margin: u64,
+) -> Result<u64> {
+    let gas_limit = with_margin(gas_consumed, margin);
+    if !matches!(extrinsic_opts.verbosity()?, Some(Verbosity::Quiet)) {
+        println!(
+            "{} {}, using the limit {} with a {}% margin",
+            "Estimated gas:".bold(),
+            gas_consumed,
+            gas_limit,
+            margin
+        );
+    }
+    Ok(gas_limit)
+}
+
+/// Returns the `gas` plus `margin` percent of it.
+fn with_margin(gas: u64, margin: u64) -> u64 {
+    gas.saturating_add(gas.saturating_mul(margin) / 100)
+}
+
+/// Dry runs the constructor with the `contracts_instantiate` RPC, returning the raw response.
+async fn instantiate_rpc(
+    rpc: &jsonrpsee::Client,
+    extrinsic_opts: &ExtrinsicOpts,
+    origin: &AccountId32,
+    endowment: u128,
+    gas_limit: u64,
+    code_hash: &H256,
+    data: &[u8],
+    salt: &[u8],
+) -> Result<Value> {
+    let methods: Value = rpc.request("rpc_methods", Params::None).await?;
+    if !has_method(&methods, "contracts_instantiate") {
+        anyhow::bail!(
+            "The node at {} does not expose the `contracts_instantiate` RPC, which the dry run of \
+             the constructor needs",
+            extrinsic_opts.url()?
+        )
+    }
+    let request = json!({
+        "origin": origin.to_string(),
+        "endowment": endowment,
+        "gasLimit": gas_limit,
+        "code": { "existing": code_hash },
+        "data": format!("0x{}", hex::encode(data)),
+        "salt": format!("0x{}", hex::encode(salt)),
+    });
+    let response: Value = rpc
+        .request(
+            "contracts_instantiate",
+            Params::Array(vec![to_value(request)?]),
+        )
+        .await?;
+    Ok(response)
+}
+
+/// Returns the account of a contract instantiated by `origin` from the code at `code_hash`.
+///
+/// Without a `salt` it is derived from the constructor `data`, as by the `SimpleAddressDeterminer`
+/// of the contracts pallet. With a salt it is derived from the salt instead, as by the versions of
+/// the contracts pallet which take one.
+fn contract_address(
+    code_hash: &H256,
+    data: &[u8],
+    salt: &[u8],
+    origin: &AccountId32,
+) -> AccountId32 {
+    let mut buf = Vec::new();
+    if salt.is_empty() {
+        buf.extend_from_slice(code_hash.as_ref());
+        buf.extend_from_slice(&sp_core::blake2_256(data));
+        buf.extend_from_slice(origin.as_ref());
+    } else {
+        buf.extend_from_slice(origin.as_ref());
+        buf.extend_from_slice(code_hash.as_ref());
+        buf.extend_from_slice(salt);
+    }
+    AccountId32::from(sp_core::blake2_256(&buf))
+}
+
+/// Returns `true` if the response of the `rpc_methods` RPC contains the `method`.
+fn has_method(methods: &Value, method: &str) -> bool {
+    methods
+        .get("methods")
+        .and_then(Value::as_array)
+        .map_or(false, |methods| {
+            methods.iter().any(|m| m.as_str() == Some(method))
+        })
+}
+
+/// Reads the gas consumed and the contract account of a `contracts_instantiate` response, fails
+/// if the constructor failed or reverted.
+fn instantiate_outcome(response: &Value) -> Result<InstantiateOutcome> {
+    let gas_consumed = response
+        .get("gasConsumed")
+        .and_then(Value::as_u64)
+        .ok_or(anyhow::anyhow!("unexpected response {}", response))?;
+    let result = response
+        .get("result")
+        .ok_or(anyhow::anyhow!("unexpected response {}", response))?;
+    if let Some(error) = result.get("Err") {
+        anyhow::bail!(
+            "The constructor failed after consuming {} gas: {}",
+            gas_consumed,
+            error
+        )
+    }
+    let success = result.get("Ok").unwrap_or(result);
+    if let Some(reason) = success.get("result").and_then(revert_reason) {
+        anyhow::bail!(
+            "The constructor reverted after consuming {} gas{}",
+            gas_consumed,
+            reason
+        )
+    }
+    Ok(InstantiateOutcome {
+        gas_consumed,
+        contract: success
+            .get("accountId")
+            .and_then(Value::as_str)
+            .map(ToString::to_string),
+    })
+}
+
+/// Reads the gas consumed of a `contracts_call` response, fails if the call failed or reverted.
+fn call_gas_consumed(response: &Value) -> Result<u64> {
+    if let Some(error) = response.get("error") {
+        anyhow::bail!("The dry run of the call failed: {}", error)
+    }
+    let success = response
+        .get("success")
+        .ok_or(anyhow::anyhow!("unexpected response {}", response))?;
+    if let Some(reason) = revert_reason(success) {
+        anyhow::bail!("The dry run of the call reverted{}", reason)
+    }
+    success
+        .get("gasConsumed")
+        .or_else(|| success.get("gas_consumed"))
+        .and_then(Value::as_u64)
+        .ok_or(anyhow::anyhow!("unexpected response {}", response))
+}
+
+/// Returns `Some` if the execution `result` has the revert flag set, with the returned data as
+/// the reason: `: <reason>` if it is a SCALE encoded string, `: 0x<data>` otherwise, or an empty
+/// string if nothing was returned.
+fn revert_reason(result: &Value) -> Option<String> {
+    let flags = result.get("flags").and_then(Value::as_u64).unwrap_or(0);
+    if flags & REVERT_FLAG == 0 {
+        return None;
+    }
+    let data = result
+        .get("data")
+        .and_then(Value::as_str)
+        .and_then(|data| hex::decode(data.trim_start_matches("0x")).ok())
+        .unwrap_or_default();
+    if data.is_empty() {
+        return Some(String::new());
+    }
+    let mut input = &data[..];
+    match String::decode(&mut input) {
+        Ok(reason) if input.is_empty() && !reason.is_empty() => Some(format!(": {}", reason)),
+        _ => Some(format!(": 0x{}", hex::encode(&data))),
+    }
+}
+
+fn display_fee(estimate: &FeeEstimate) -> String {
+    format!(
+        "Estimated fee: {} (weight {})",
+        estimate.fee, estimate.weight
+    )
+}
+
+fn display_dry_run(lines: &[String]) -> String {
+    format!(
+        "{} nothing was submitted.\n\t{}",
+        "Dry run:".bright_green().bold(),
+        lines.join("\n\t")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contract_address_depends_on_code_data_salt_and_origin() {
+        let code_hash = H256::repeat_byte(1);
+        let alice = AccountId32::from([2; 32]);
+        let bob = AccountId32::from([3; 32]);
+
+        let address = contract_address(&code_hash, &[0x01], &[], &alice);
+
+        let mut buf = vec![1; 32];
+        buf.extend_from_slice(&sp_core::blake2_256(&[0x01]));
+        buf.extend_from_slice(&[2; 32]);
+        assert_eq!(address, AccountId32::from(sp_core::blake2_256(&buf)));
+        assert_ne!(address, contract_address(&code_hash, &[0x00], &[], &alice));
+        assert_ne!(address, contract_address(&code_hash, &[0x01], &[], &bob));
+
+        let salted = contract_address(&code_hash, &[0x01], &[0x07], &alice);
+        let mut buf = vec![2; 32];
+        buf.extend_from_slice(&[1; 32]);
+        buf.push(0x07);
+        assert_eq!(salted, AccountId32::from(sp_core::blake2_256(&buf)));
+        assert_eq!(
+            salted,
+            contract_address(&code_hash, &[0x00], &[0x07], &alice)
+        );
+        assert_ne!(
+            salted,
+            contract_address(&code_hash, &[0x01], &[0x08], &alice)
+        );
+    }
+
+    #[test]
+    fn instantiate_responses_are_read() {
+        let response = json!({
+            "gasConsumed": 4000,
+            "result": { "Ok": {
+                "result": { "flags": 0, "data": "0x" },
+                "accountId": "5Grwva",
+                "rentProjection": null,
+            }},
+        });
+        assert_eq!(
+            instantiate_outcome(&response).unwrap(),
+            InstantiateOutcome {
+                gas_consumed: 4000,
+                contract: Some("5Grwva".into()),
+            }
+        );
+
+        let reverted = json!({ "gasConsumed": 10, "result": { "Ok": {
+            "result": { "flags": 1, "data": "0x" },
+        }}});
+        assert_eq!(
+            instantiate_outcome(&reverted).unwrap_err().to_string(),
+            "The constructor reverted after consuming 10 gas"
+        );
+
+        let with_reason = json!({ "gasConsumed": 10, "result": { "Ok": {
+            "result": { "flags": 1, "data": "0x30696e73756666696369656e74" },
+        }}});
+        assert_eq!(
+            instantiate_outcome(&with_reason).unwrap_err().to_string(),
+            "The constructor reverted after consuming 10 gas: insufficient"
+        );
+
+        let failed = json!({ "gasConsumed": 10, "result": { "Err": "ContractTrapped" } });
+        assert_eq!(
+            instantiate_outcome(&failed).unwrap_err().to_string(),
+            "The constructor failed after consuming 10 gas: \"ContractTrapped\""
+        );
+    }
+
+    #[test]
+    fn call_gas_is_estimated_with_a_margin() {
+        let success = json!({ "success": { "flags": 0, "data": "0x", "gas_consumed": 1000 } });
+        let reverted = json!({ "success": { "flags": 1, "data": "0x0102", "gas_consumed": 1 } });
+        let error = json!({ "error": "ContractTrapped" });
+
+        assert_eq!(call_gas_consumed(&success).unwrap(), 1000);
+        assert_eq!(
+            call_gas_consumed(&reverted).unwrap_err().to_string(),
+            "The dry run of the call reverted: 0x0102"
+        );
+        assert_eq!(
+            call_gas_consumed(&error).unwrap_err().to_string(),
+            "The dry run of the call failed: \"ContractTrapped\""
+        );
+        assert_eq!(with_margin(1000, 20), 1200);
+        assert_eq!(with_margin(1000, 0), 1000);
+        assert_eq!(with_margin(u64::MAX, 20), u64::MAX);
+        assert_eq!("auto".parse::<GasLimit>().unwrap(), GasLimit::Auto);
+        assert_eq!("500".parse::<GasLimit>().unwrap(), GasLimit::Limit(500));
+        assert!("lots".parse::<GasLimit>().is_err());
+    }
+
+    #[test]
+    fn rpc_methods_are_looked_up() {
+        let methods = json!({ "version": 1, "methods": ["contracts_call", "rpc_methods"] });
+
+        assert!(has_method(&methods, "contracts_call"));
+        assert!(!has_method(&methods, "contracts_instantiate"));
+    }
+}
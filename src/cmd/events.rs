@@ -0,0 +1,348 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Prints the events emitted by a contract as the blocks containing them are finalized.
+//!
+//! Every finalized head announced by the node triggers reading the events of all blocks since
+//! the last one read, so a backfill with `--from-block` and a reconnection after the
+//! subscription dropped leave no gaps. The contracts pallet deposits the events of contracts as
+//! `Contracts::ContractExecution`, renamed to `ContractEmitted` by later versions.
+
+use std::{convert::TryFrom, path::PathBuf, time::Duration};
+
+use anyhow::Result;
+use codec::Decode;
+use colored::Colorize;
+use jsonrpsee::{
+    common::{to_value, Params},
+    Client as RpcClient,
+};
+use serde_json::{json, Map, Value};
+use sp_core::{
+    crypto::AccountId32,
+    storage::{StorageData, StorageKey},
+    twox_128, H256,
+};
+use structopt::StructOpt;
+use subxt::{Client, ClientBuilder, DefaultNodeRuntime, Raw};
+
+use super::{metadata::read_metadata, transcode::decode_event};
+use crate::{crate_metadata::CrateMetadata, workspace::ManifestPath};
+
+/// The variants of the event deposited for an event of a contract.
+const CONTRACT_EVENT_VARIANTS: &[&str] = &["ContractExecution", "ContractEmitted"];
+
+/// The subscription is considered dropped if no block is finalized for this long.
+const HEAD_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The longest delay between reconnection attempts.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// Prints the events of a contract as they are emitted, until interrupted.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "events")]
+pub struct EventsCommand {
+    /// Websockets url of a substrate node
+    #[structopt(
+        name = "url",
+        long,
+        parse(try_from_str),
+        default_value = "ws://localhost:9944"
+    )]
+    url: url::Url,
+    /// The address of the contract whose events are printed
+    #[structopt(long)]
+    contract: AccountId32,
+    /// Print the events of the finalized blocks from this one on before following new blocks
+    #[structopt(long, value_name = "number")]
+    from_block: Option<u32>,
+    /// Path to the contract metadata the events are decoded with, defaults to the metadata
+    /// built for the contract in the current directory
+    #[structopt(long, parse(from_os_str))]
+    metadata: Option<PathBuf>,
+    /// Print one JSON object per event and line
+    #[structopt(long)]
+    output_json: bool,
+}
+
+/// How far the events have been read, kept across reconnections.
+struct Cursor {
+    /// The next block whose events are read, `None` until the first finalized head is known.
+    next_block: Option<u32>,
+    /// Whether the node was connected to at least once.
+    connected: bool,
+}
+
+impl EventsCommand {
+    pub fn exec(&self) -> Result<String> {
+        let metadata = self.load_metadata()?;
+        let mut cursor = Cursor {
+            next_block: self.from_block,
+            connected: false,
+        };
+        async_std::task::block_on(async move {
+            let mut delay = Duration::from_secs(1);
+            loop {
+                let err = match self.follow(&metadata, &mut cursor).await {
+                    Ok(()) => continue,
+                    Err(err) => err,
+                };
+                // a node which cannot be reached at all is most likely a wrong url
+                if !cursor.connected {
+                    return Err(err);
+                }
+                eprintln!(
+                    "{} {}",
+                    "warning:".yellow().bold(),
+                    format!(
+                        "the subscription to {} dropped ({}), reconnecting in {}s",
+                        self.url,
+                        err,
+                        delay.as_secs()
+                    )
+                    .bold()
+                );
+                async_std::task::sleep(delay).await;
+                delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+            }
+        })
+    }
+
+    /// Reads the contract metadata, the target `metadata.json` of the current project by default.
+    fn load_metadata(&self) -> Result<Value> {
+        let path = match self.metadata.as_ref() {
+            Some(path) => path.clone(),
+            None => {
+                let manifest_path = ManifestPath::try_from(None::<PathBuf>)?;
+                CrateMetadata::collect(&manifest_path)?.dest_metadata
+            }
+        };
+        read_metadata(&path)
+    }
+
+    /// Prints the events of every block up to the latest finalized one, until the subscription
+    /// to the finalized heads fails.
+    async fn follow(&self, metadata: &Value, cursor: &mut Cursor) -> Result<()> {
+        let rpc = jsonrpsee::ws_client(self.url.as_str()).await?;
+        let cli = ClientBuilder::<DefaultNodeRuntime>::new()
+            .set_client(rpc.clone())
+            .build()
+            .await?;
+        let mut heads = cli.subscribe_finalized_blocks().await?;
+        cursor.connected = true;
+        loop {
+            let head = async_std::future::timeout(HEAD_TIMEOUT, heads.next())
+                .await
+                .map_err(|_| {
+                    anyhow::anyhow!("no block was finalized for {}s", HEAD_TIMEOUT.as_secs())
+                })?;
+            let first = cursor.next_block.unwrap_or(head.number);
+            for number in first..=head.number {
+                for event in self.contract_events(&rpc, &cli, number).await? {
+                    println!("{}", self.display_event(metadata, number, &event)?);
+                }
+                cursor.next_block = Some(number + 1);
+            }
+        }
+    }
+
+    /// Returns the data of the events the contract emitted in the block `number`.
+    async fn contract_events(
+        &self,
+        rpc: &RpcClient,
+        cli: &Client<DefaultNodeRuntime>,
+        number: u32,
+    ) -> Result<Vec<Vec<u8>>> {
+        let hash: Option<H256> = rpc
+            .request("chain_getBlockHash", Params::Array(vec![to_value(number)?]))
+            .await?;
+        let hash = hash.ok_or(anyhow::anyhow!("Block {} not found", number))?;
+        let key = StorageKey([twox_128(b"System"), twox_128(b"Events")].concat());
+        let events: Option<StorageData> = rpc
+            .request(
+                "state_getStorage",
+                Params::Array(vec![to_value(key)?, to_value(hash)?]),
+            )
+            .await?;
+        let events = match events {
+            Some(events) => events,
+            None => return Ok(Vec::new()),
+        };
+        let mut contract_events = Vec::new();
+        for (_, raw) in cli.events_decoder().decode_events(&mut &events.0[..])? {
+            if let Raw::Event(event) = raw {
+                if let Some(data) =
+                    emitted_by(&event.module, &event.variant, &event.data, &self.contract)
+                {
+                    contract_events.push(data);
+                }
+            }
+        }
+        Ok(contract_events)
+    }
+
+    /// Displays an event decoded with the metadata, as hex if it cannot be decoded.
+    fn display_event(&self, metadata: &Value, block: u32, data: &[u8]) -> Result<String> {
+        if self.output_json {
+            let mut json = json!({ "block": block, "contract": self.contract.to_string() });
+            if let Value::Object(fields) = &mut json {
+                fields.extend(contract_event_json(Some(metadata), data));
+            }
+            return Ok(serde_json::to_string(&json)?);
+        }
+        Ok(format!(
+            "{} {}",
+            format!("#{}", block).bold(),
+            display_contract_event(Some(metadata), data)
+        ))
+    }
+}
+
+/// Displays the data of an event of a contract decoded with the metadata, e.g.
+/// `Transfer { to: 0x.., value: 100 }`, as hex without metadata or if it cannot be decoded.
+pub(super) fn display_contract_event(metadata: Option<&Value>, data: &[u8]) -> String {
+    match metadata.and_then(|metadata| decode_event(metadata, data).ok()) {
+        Some((name, args)) => {
+            let args = args
+                .iter()
+                .map(|(name, value)| format!("{}: {}", name, value))
+                .collect::<Vec<_>>();
+            format!("{} {{ {} }}", name.bright_green().bold(), args.join(", "))
+        }
+        None => format!("0x{}", hex::encode(data)),
+    }
+}
+
+/// Returns the fields describing an event of a contract in JSON output, the `event` name and
+/// its `args`, or the hex `data` if it cannot be decoded.
+pub(super) fn contract_event_json(metadata: Option<&Value>, data: &[u8]) -> Map<String, Value> {
+    let mut json = Map::new();
+    match metadata.and_then(|metadata| decode_event(metadata, data).ok()) {
+        Some((name, args)) => {
+            let args = args
+                .into_iter()
+                .map(|(name, value)| (name, Value::from(value)))
+                .collect::<Map<_, _>>();
+            json.insert("event".into(), name.into());
+            json.insert("args".into(), args.into());
+        }
+        None => {
+            json.insert("data".into(), format!("0x{}", hex::encode(data)).into());
+        }
+    }
+    json
+}
+
+/// Returns the data of the event of a contract if the runtime event `module::variant` is one,
+/// emitted by `contract`.
+pub(super) fn emitted_by(
+    module: &str,
+    variant: &str,
+    data: &[u8],
+    contract: &AccountId32,
+) -> Option<Vec<u8>> {
+    if module != "Contracts" || !CONTRACT_EVENT_VARIANTS.contains(&variant) {
+        return None;
+    }
+    let (emitter, data) = <(AccountId32, Vec<u8>)>::decode(&mut &data[..]).ok()?;
+    Some(data).filter(|_| emitter == *contract)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::Encode;
+
+    #[test]
+    fn only_events_of_the_contract_are_selected() {
+        let contract = AccountId32::from([1; 32]);
+        let other = AccountId32::from([2; 32]);
+        let emitted = |emitter: &AccountId32| (emitter.clone(), vec![0u8, 1]).encode();
+
+        assert_eq!(
+            emitted_by(
+                "Contracts",
+                "ContractExecution",
+                &emitted(&contract),
+                &contract
+            ),
+            Some(vec![0, 1])
+        );
+        assert_eq!(
+            emitted_by(
+                "Contracts",
+                "ContractEmitted",
+                &emitted(&contract),
+                &contract
+            ),
+            Some(vec![0, 1])
+        );
+        assert_eq!(
+            emitted_by(
+                "Contracts",
+                "ContractExecution",
+                &emitted(&other),
+                &contract
+            ),
+            None
+        );
+        assert_eq!(
+            emitted_by("Contracts", "Instantiated", &emitted(&contract), &contract),
+            None
+        );
+        assert_eq!(
+            emitted_by(
+                "Balances",
+                "ContractExecution",
+                &emitted(&contract),
+                &contract
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn events_are_printed_as_json_lines() {
+        let metadata = json!({
+            "spec": {
+                "events": [
+                    { "name": "Flipped", "args": [ { "name": "value", "type": { "type": 1 } } ] }
+                ]
+            },
+            "types": [ { "def": { "primitive": "bool" } } ]
+        });
+        let command = EventsCommand {
+            url: url::Url::parse("ws://localhost:9944").unwrap(),
+            contract: AccountId32::from([1; 32]),
+            from_block: None,
+            metadata: None,
+            output_json: true,
+        };
+
+        let line = command.display_event(&metadata, 42, &[0, 1]).unwrap();
+        let undecoded = command.display_event(&metadata, 42, &[7]).unwrap();
+
+        let json: Value = serde_json::from_str(&line).unwrap();
+        assert!(!line.contains('\n'));
+        assert_eq!(json["block"], 42);
+        assert_eq!(json["event"], "Flipped");
+        assert_eq!(json["args"]["value"], "true");
+        assert_eq!(
+            serde_json::from_str::<Value>(&undecoded).unwrap()["data"],
+            "0x07"
+        );
+    }
+}
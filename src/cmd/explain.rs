@@ -0,0 +1,193 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Explains common failures of `cargo build` in plain language, set by `build --explain-error`.
+//!
+//! Each known failure is an entry of [`EXPLANATIONS`], recognized by signatures which must all
+//! appear in the stderr of cargo. To explain another failure add an entry to the table.
+
+use std::{
+    io::{self, Write},
+    process::Output,
+};
+
+use anyhow::Result;
+
+use crate::Verbosity;
+
+/// A known build failure.
+struct Explanation {
+    /// Substrings of the stderr of cargo, all of them identify the failure.
+    signatures: &'static [&'static str],
+    /// What went wrong.
+    explanation: &'static str,
+    /// How to fix it.
+    fix: &'static str,
+}
+
+const EXPLANATIONS: &[Explanation] = &[
+    Explanation {
+        signatures: &["the `wasm32-unknown-unknown` target may not be installed"],
+        explanation: "The Rust toolchain cannot compile to Wasm, its `wasm32-unknown-unknown` \
+                      target is not installed.",
+        fix: "rustup target add wasm32-unknown-unknown --toolchain nightly",
+    },
+    Explanation {
+        signatures: &["unable to build with the standard library"],
+        explanation: "Contracts are built with their own copy of the standard library, which \
+                      needs the source code of the Rust standard library.",
+        fix: "rustup component add rust-src --toolchain nightly",
+    },
+    Explanation {
+        signatures: &["duplicate lang item"],
+        explanation: "The standard library leaked into the contract, some dependency is built \
+                      with its `std` feature.",
+        fix: "add `default-features = false` to the dependency in Cargo.toml and enable its \
+              `std` feature only from the `std` feature of the contract",
+    },
+    Explanation {
+        signatures: &["can't find crate for `std`"],
+        explanation: "The standard library leaked into the contract, the contract or one of its \
+                      dependencies uses `std` when built for Wasm.",
+        fix: "add `#![cfg_attr(not(feature = \"std\"), no_std)]` to the top of lib.rs and \
+              `default-features = false` to the dependencies in Cargo.toml",
+    },
+    Explanation {
+        signatures: &["does not have these features"],
+        explanation: "A feature enabled in Cargo.toml does not exist in the dependency, often \
+                      the ink! dependencies differ in version.",
+        fix: "use the same version for all `ink_*` dependencies and check the features listed \
+              in Cargo.toml against those of the dependency",
+    },
+    Explanation {
+        signatures: &["--offline was specified"],
+        explanation: "The build runs offline, but a dependency is neither vendored nor in the \
+                      cargo cache.",
+        fix: "run `cargo fetch` with network access before building offline, or vendor the \
+              dependencies with `cargo vendor`",
+    },
+    Explanation {
+        signatures: &["help: set `edition"],
+        explanation: "The contract uses Rust 2018 syntax but its crate is compiled as an \
+                      older edition.",
+        fix: "set `edition = \"2018\"` in the `[package]` section of Cargo.toml",
+    },
+];
+
+/// Returns the explanations of the failures recognized in `stderr`.
+fn explain(stderr: &str) -> Vec<&'static Explanation> {
+    EXPLANATIONS
+        .iter()
+        .filter(|explanation| {
+            explanation
+                .signatures
+                .iter()
+                .all(|signature| stderr.contains(signature))
+        })
+        .collect()
+}
+
+/// Checks the output of `cargo build`, explaining its failure.
+///
+/// The stderr of cargo is printed unless a failure was explained, then only with `--verbose`.
+pub(crate) fn check_build_output(output: &Output, verbosity: Option<Verbosity>) -> Result<()> {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if output.status.success() {
+        io::stderr().write_all(&output.stderr)?;
+        return Ok(());
+    }
+
+    let explanations = explain(&stderr);
+    if explanations.is_empty() || Verbosity::at_least(verbosity, Verbosity::Verbose) {
+        io::stderr().write_all(&output.stderr)?;
+    }
+    let mut message = format!(
+        "`cargo build` failed with exit code: {:?}",
+        output.status.code()
+    );
+    for explanation in &explanations {
+        message.push_str(&format!(
+            "\n\n{}\nTo fix it: {}",
+            explanation.explanation, explanation.fix
+        ));
+    }
+    anyhow::bail!(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixes(stderr: &str) -> Vec<&'static str> {
+        explain(stderr)
+            .iter()
+            .map(|explanation| explanation.fix)
+            .collect()
+    }
+
+    #[test]
+    fn missing_target_is_explained() {
+        let stderr = "error[E0463]: can't find crate for `core`\n  \
+                      = note: the `wasm32-unknown-unknown` target may not be installed";
+
+        assert_eq!(
+            fixes(stderr),
+            vec!["rustup target add wasm32-unknown-unknown --toolchain nightly"]
+        );
+    }
+
+    #[test]
+    fn std_leak_is_explained() {
+        let stderr = "error: duplicate lang item in crate `std`: `panic_impl`.";
+
+        assert_eq!(explain(stderr).len(), 1);
+        assert!(fixes(stderr)[0].contains("default-features = false"));
+    }
+
+    #[test]
+    fn missing_feature_is_explained() {
+        let stderr = "the package `flipper` depends on `ink_lang`, with features: \
+                      `ink-as-dependency` but `ink_lang` does not have these features.";
+
+        assert_eq!(explain(stderr).len(), 1);
+        assert!(fixes(stderr)[0].contains("`ink_*` dependencies"));
+    }
+
+    #[test]
+    fn missing_offline_dependency_is_explained() {
+        let stderr = "error: failed to download `ink_env v3.0.0-rc2`\n\nCaused by:\n  \
+                      attempting to make an HTTP request, but --offline was specified";
+
+        assert_eq!(explain(stderr).len(), 1);
+        assert!(fixes(stderr)[0].starts_with("run `cargo fetch`"));
+    }
+
+    #[test]
+    fn wrong_edition_is_explained() {
+        let stderr = "error[E0670]: `async fn` is not permitted in the 2015 edition\n  \
+                      = help: set `edition = \"2018\"` in `Cargo.toml`";
+
+        assert_eq!(
+            fixes(stderr),
+            vec!["set `edition = \"2018\"` in the `[package]` section of Cargo.toml"]
+        );
+    }
+
+    #[test]
+    fn unknown_failures_are_not_explained() {
+        assert!(explain("error[E0308]: mismatched types").is_empty());
+    }
+}
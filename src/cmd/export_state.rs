@@ -0,0 +1,301 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{collections::HashMap, convert::TryFrom, path::PathBuf};
+
+use anyhow::Result;
+use futures::future;
+use jsonrpsee::common::{to_value, Params};
+use serde_json::Value;
+use sp_core::{
+    crypto::AccountId32,
+    storage::{StorageData, StorageKey},
+};
+use structopt::StructOpt;
+use subxt::{ClientBuilder, DefaultNodeRuntime};
+
+use super::{
+    metadata::read_metadata,
+    storage::{contract_info, ContractInfo},
+    transcode::decode_primitive,
+};
+use crate::{crate_metadata::CrateMetadata, workspace::ManifestPath, NodeOpts};
+
+/// Dumps the storage of a deployed contract as key/value pairs.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "export-state")]
+pub struct ExportStateCommand {
+    #[structopt(flatten)]
+    node_opts: NodeOpts,
+    /// The address of the contract whose storage is exported
+    #[structopt(long)]
+    contract: AccountId32,
+    /// Decode the values of the storage cells described by the storage layout of the metadata
+    #[structopt(long)]
+    decoded: bool,
+    /// Path to the contract metadata used by `--decoded`, defaults to the `metadata.json` built
+    /// for the contract in the current directory
+    #[structopt(long, parse(from_os_str))]
+    metadata: Option<PathBuf>,
+    /// Number of storage keys to read per `childstate_getKeysPaged` request, their values are
+    /// read by as many concurrent requests
+    #[structopt(long, default_value = "100")]
+    page_size: u32,
+}
+
+impl ExportStateCommand {
+    pub fn exec(&self) -> Result<String> {
+        let layout = if self.decoded {
+            Some(StorageLayout::new(&self.load_metadata()?)?)
+        } else {
+            None
+        };
+        let entries = self.fetch_storage()?;
+
+        let lines = entries
+            .iter()
+            .map(|(key, value)| match layout.as_ref() {
+                Some(layout) => layout.display_entry(key, value),
+                None => display_raw_entry(key, value),
+            })
+            .collect::<Vec<_>>();
+        Ok(lines.join("\n\t"))
+    }
+
+    /// Reads the contract metadata, the target `metadata.json` of the current project by default.
+    fn load_metadata(&self) -> Result<Value> {
+        let path = match self.metadata.as_ref() {
+            Some(path) => path.clone(),
+            None => {
+                let manifest_path = ManifestPath::try_from(None::<PathBuf>)?;
+                CrateMetadata::collect(&manifest_path)?.dest_metadata
+            }
+        };
+        read_metadata(&path)
+    }
+
+    /// Reads all key/value pairs from the child trie holding the contract storage.
+    ///
+    /// The keys are read in pages of `page_size`, the values of a page by concurrent requests.
+    fn fetch_storage(&self) -> Result<Vec<(StorageKey, StorageData)>> {
+        let page_size = self.page_size.max(1);
+        async_std::task::block_on(async move {
+            let rpc = jsonrpsee::ws_client(self.node_opts.url()?.as_str()).await?;
+            let cli = ClientBuilder::<DefaultNodeRuntime>::new()
+                .set_client(rpc.clone())
+                .build()
+                .await?;
+
+            let child_storage_key = match contract_info(&cli, &self.contract).await? {
+                ContractInfo::Alive(info) => info.child_storage_key(),
+                ContractInfo::Tombstone(_) => anyhow::bail!(
+                    "The contract at {} has been evicted, only its tombstone remains",
+                    self.contract
+                ),
+            };
+            let child_storage_key = to_value(&child_storage_key)?;
+
+            let mut entries = Vec::new();
+            let mut start_key: Option<StorageKey> = None;
+            loop {
+                let page: Vec<StorageKey> = rpc
+                    .request(
+                        "childstate_getKeysPaged",
+                        Params::Array(vec![
+                            child_storage_key.clone(),
+                            Value::Null,
+                            to_value(page_size)?,
+                            to_value(&start_key)?,
+                            Value::Null,
+                        ]),
+                    )
+                    .await?;
+                log::info!("Read a page of {} storage keys", page.len());
+
+                let requests = page
+                    .iter()
+                    .map(|key| {
+                        let params = Params::Array(vec![
+                            child_storage_key.clone(),
+                            to_value(key)?,
+                            Value::Null,
+                        ]);
+                        Ok(rpc.request("childstate_getStorage", params))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let values: Vec<Option<StorageData>> = future::try_join_all(requests).await?;
+                entries.extend(
+                    page.iter()
+                        .cloned()
+                        .zip(values)
+                        .filter_map(|(key, value)| value.map(|value| (key, value))),
+                );
+                // a short page is the last one
+                if page.len() < page_size as usize {
+                    break;
+                }
+                start_key = page.last().cloned();
+            }
+            Ok(entries)
+        })
+    }
+}
+
+fn display_raw_entry(key: &StorageKey, value: &StorageData) -> String {
+    format!("0x{}: 0x{}", hex::encode(&key.0), hex::encode(&value.0))
+}
+
+/// A storage cell described by the storage layout of the contract metadata.
+#[derive(Debug, PartialEq)]
+struct LayoutCell {
+    /// The path of the field holding the cell, e.g. `balances.total_supply`.
+    path: String,
+    /// The id of the type of the cell value in the type registry.
+    ty: u64,
+}
+
+/// The storage layout and type registry of the contract metadata, used to decode storage values.
+struct StorageLayout {
+    /// The cells of the layout, keyed by the child trie key under which they are stored.
+    cells: HashMap<Vec<u8>, LayoutCell>,
+    types: Vec<Value>,
+}
+
+impl StorageLayout {
+    fn new(metadata: &Value) -> Result<Self> {
+        let layout = metadata
+            .get("storage")
+            .ok_or(anyhow::anyhow!("storage layout not found in the metadata"))?;
+        let types = metadata
+            .get("types")
+            .and_then(Value::as_array)
+            .ok_or(anyhow::anyhow!("type registry not found in the metadata"))?
+            .clone();
+        let mut cells = HashMap::new();
+        collect_layout_cells(layout, "", &mut cells)?;
+        Ok(StorageLayout { cells, types })
+    }
+
+    fn display_entry(&self, key: &StorageKey, value: &StorageData) -> String {
+        match self.cells.get(&key.0) {
+            Some(cell) => {
+                let decoded = decode_primitive(&self.types, cell.ty, &value.0)
+                    .unwrap_or_else(|| format!("0x{}", hex::encode(&value.0)));
+                format!("{}: {}", cell.path, decoded)
+            }
+            None => display_raw_entry(key, value),
+        }
+    }
+}
+
+/// Collects the cells of a storage layout, recursing into structs.
+///
+/// The contracts pallet stores each cell under the blake2 hash of its key. Cells of dynamic
+/// layouts such as hash maps cannot be enumerated and are skipped.
+fn collect_layout_cells(
+    layout: &Value,
+    path: &str,
+    cells: &mut HashMap<Vec<u8>, LayoutCell>,
+) -> Result<()> {
+    if let Some(cell) = layout.get("cell") {
+        let key = cell
+            .get("key")
+            .and_then(Value::as_str)
+            .ok_or(anyhow::anyhow!("{}: cell key should be a string", path))?;
+        let key = hex::decode(key.trim_start_matches("0x"))?;
+        let ty = cell
+            .get("ty")
+            .and_then(Value::as_u64)
+            .ok_or(anyhow::anyhow!("{}: cell type should be a number", path))?;
+        let cell = LayoutCell {
+            path: path.to_string(),
+            ty,
+        };
+        cells.insert(sp_core::blake2_256(&key).to_vec(), cell);
+    } else if let Some(fields) = layout
+        .get("struct")
+        .and_then(|s| s.get("fields"))
+        .and_then(Value::as_array)
+    {
+        for (index, field) in fields.iter().enumerate() {
+            let name = field
+                .get("name")
+                .and_then(Value::as_str)
+                .map(ToString::to_string)
+                .unwrap_or_else(|| index.to_string());
+            let field_path = if path.is_empty() {
+                name
+            } else {
+                format!("{}.{}", path, name)
+            };
+            if let Some(field_layout) = field.get("layout") {
+                collect_layout_cells(field_layout, &field_path, cells)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::Encode;
+    use serde_json::json;
+
+    #[test]
+    fn decodes_cells_of_the_storage_layout() {
+        let metadata = json! {
+            {
+                "storage": {
+                    "struct": {
+                        "fields": [
+                            {
+                                "layout": {
+                                    "cell": {
+                                        "key": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                                        "ty": 1
+                                    }
+                                },
+                                "name": "value"
+                            }
+                        ]
+                    }
+                },
+                "types": [
+                    { "def": { "primitive": "bool" } }
+                ]
+            }
+        };
+        let layout = StorageLayout::new(&metadata).unwrap();
+        let key = StorageKey(sp_core::blake2_256(&[0u8; 32]).to_vec());
+
+        let decoded = layout.display_entry(&key, &StorageData(true.encode()));
+
+        assert_eq!(decoded, "value: true");
+    }
+
+    #[test]
+    fn unknown_keys_are_displayed_raw() {
+        let layout = StorageLayout {
+            cells: HashMap::new(),
+            types: Vec::new(),
+        };
+
+        let displayed = layout.display_entry(&StorageKey(vec![1, 2]), &StorageData(vec![3]));
+
+        assert_eq!(displayed, "0x0102: 0x03");
+    }
+}
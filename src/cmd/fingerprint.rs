@@ -0,0 +1,355 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Detects whether the code of a contract is unchanged since its last build.
+//!
+//! Doc comments end up in the metadata, but not in the Wasm. The fingerprint of the code hashes
+//! the Rust sources without their comments and all other files of the package as they are. If
+//! the fingerprint matches the one recorded by the last build, the Wasm of that build is reused
+//! and only the metadata is regenerated.
+//!
+//! If the fingerprint cannot prove the code unchanged there is none and the contract is built
+//! in full, e.g. for a source which uses the position of code with `line!()`, which shifts with
+//! the doc comments, or for a package with path dependencies, which are not tracked.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::metadata::blake2_hash;
+use crate::{crate_metadata::CrateMetadata, OptimizationResult};
+
+/// Macros and attributes which embed the position of code, which doc comments shift.
+const POSITION_DEPENDENT: &[&str] = &["line!", "column!", "Location::caller", "track_caller"];
+
+/// The fingerprint of the code of the last build and the Wasm it produced.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct BuildFingerprint {
+    code: String,
+    wasm: String,
+    original_size: f64,
+    optimized_size: f64,
+    kept_original: bool,
+    #[serde(default)]
+    optimization_level: String,
+}
+
+/// Returns the fingerprint of the code of the contract, `None` if it cannot prove the code
+/// unchanged.
+///
+/// The `settings` of the build which affect the Wasm are part of the fingerprint.
+pub(crate) fn code_fingerprint(
+    crate_metadata: &CrateMetadata,
+    settings: &str,
+) -> Result<Option<String>> {
+    if let Some(dep) = crate_metadata
+        .root_package
+        .dependencies
+        .iter()
+        .find(|dep| dep.source.is_none())
+    {
+        log::info!("Path dependency {} is not fingerprinted", dep.name);
+        return Ok(None);
+    }
+    let package_dir = crate_metadata
+        .root_package
+        .manifest_path
+        .parent()
+        .expect("the manifest is in the package directory")
+        .to_path_buf();
+    let mut files = Vec::new();
+    collect_files(
+        &package_dir,
+        &crate_metadata.cargo_meta.target_directory,
+        &mut files,
+    )?;
+    files.sort();
+    let lock_file = crate_metadata.cargo_meta.workspace_root.join("Cargo.lock");
+    if lock_file.exists() && !files.contains(&lock_file) {
+        files.push(lock_file);
+    }
+
+    let mut fingerprinted = settings.as_bytes().to_vec();
+    for file in &files {
+        let contents = fs::read(file).context(format!("Failed to read {}", file.display()))?;
+        let contents = if file.extension().map_or(false, |ext| ext == "rs") {
+            let code = match String::from_utf8(contents)
+                .ok()
+                .and_then(|source| code(&source))
+            {
+                Some(code) => code,
+                None => {
+                    log::info!("{} cannot be fingerprinted", file.display());
+                    return Ok(None);
+                }
+            };
+            code.into_bytes()
+        } else {
+            contents
+        };
+        let relative = file.strip_prefix(&package_dir).unwrap_or(file);
+        fingerprinted.extend(relative.to_string_lossy().as_bytes());
+        fingerprinted.push(0);
+        fingerprinted.extend(&blake2_hash(&contents).0);
+    }
+    Ok(Some(blake2_hash(&fingerprinted).to_string()))
+}
+
+/// Returns the optimization result of the last build if its code has the same fingerprint and
+/// its Wasm is still in place.
+pub(crate) fn reusable_build(
+    crate_metadata: &CrateMetadata,
+    code: &str,
+) -> Option<OptimizationResult> {
+    let path = fingerprint_path(crate_metadata);
+    let recorded: BuildFingerprint = serde_json::from_slice(&fs::read(path).ok()?).ok()?;
+    let wasm = fs::read(&crate_metadata.dest_wasm).ok()?;
+    if recorded.code != code || recorded.wasm != blake2_hash(&wasm).to_string() {
+        return None;
+    }
+    Some(OptimizationResult {
+        original_size: recorded.original_size,
+        optimized_size: recorded.optimized_size,
+        kept_original: recorded.kept_original,
+        optimization_level: recorded.optimization_level,
+    })
+}
+
+/// Records the fingerprint of the code of a build and the Wasm it produced.
+pub(crate) fn record_build(
+    crate_metadata: &CrateMetadata,
+    code: &str,
+    optimization_result: &OptimizationResult,
+) -> Result<()> {
+    let wasm = fs::read(&crate_metadata.dest_wasm)?;
+    let fingerprint = BuildFingerprint {
+        code: code.to_string(),
+        wasm: blake2_hash(&wasm).to_string(),
+        original_size: optimization_result.original_size,
+        optimized_size: optimization_result.optimized_size,
+        kept_original: optimization_result.kept_original,
+        optimization_level: optimization_result.optimization_level.clone(),
+    };
+    let path = fingerprint_path(crate_metadata);
+    fs::write(path, serde_json::to_string_pretty(&fingerprint)?)?;
+    Ok(())
+}
+
+/// The file in the target directory recording the fingerprint of the last build of the contract,
+/// `{target_dir}/.{package_name}.code-fingerprint.json`.
+fn fingerprint_path(crate_metadata: &CrateMetadata) -> PathBuf {
+    crate_metadata.target_directory.join(format!(
+        ".{}.code-fingerprint.json",
+        crate_metadata.package_name
+    ))
+}
+
+/// Collects the files of the package, skipping hidden directories and the target directory.
+fn collect_files(dir: &Path, target_dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir).context(format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        let hidden = path
+            .file_name()
+            .map_or(false, |name| name.to_string_lossy().starts_with('.'));
+        if hidden || path == target_dir || path.ends_with("target") {
+            continue;
+        }
+        if path.is_dir() {
+            collect_files(&path, target_dir, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Returns the Rust `source` without comments and with whitespace collapsed, `None` if it cannot
+/// be lexed or embeds the position of code.
+fn code(source: &str) -> Option<String> {
+    let chars = source.chars().collect::<Vec<_>>();
+    let mut code = String::with_capacity(source.len());
+    let mut i = 0;
+    let push_space = |code: &mut String| {
+        if !code.is_empty() && !code.ends_with(' ') {
+            code.push(' ');
+        }
+    };
+    while i < chars.len() {
+        let next = chars.get(i + 1).copied();
+        match chars[i] {
+            '/' if next == Some('/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                push_space(&mut code);
+            }
+            '/' if next == Some('*') => {
+                let mut depth = 0;
+                loop {
+                    match (chars.get(i)?, chars.get(i + 1)) {
+                        ('/', Some('*')) => {
+                            depth += 1;
+                            i += 2;
+                        }
+                        ('*', Some('/')) => {
+                            depth -= 1;
+                            i += 2;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        _ => i += 1,
+                    }
+                }
+                push_space(&mut code);
+            }
+            c if c.is_whitespace() => {
+                push_space(&mut code);
+                i += 1;
+            }
+            '"' => i = copy_string(&chars, i, &mut code)?,
+            'r' if raw_string_hashes(&chars, i).is_some() && !is_ident_char(&chars, i) => {
+                let hashes = raw_string_hashes(&chars, i)?;
+                let start = i;
+                i += 2 + hashes;
+                loop {
+                    if chars.get(i)? == &'"'
+                        && (1..=hashes).all(|offset| chars.get(i + offset) == Some(&'#'))
+                    {
+                        i += 1 + hashes;
+                        break;
+                    }
+                    i += 1;
+                }
+                code.extend(&chars[start..i]);
+            }
+            '\'' => {
+                let end = match (next, chars.get(i + 2)) {
+                    (Some('\\'), _) => (i + 3..chars.len()).find(|&end| chars[end] == '\'')? + 1,
+                    (Some(_), Some('\'')) => i + 3,
+                    // a lifetime
+                    _ => i + 1,
+                };
+                code.extend(&chars[i..end]);
+                i = end;
+            }
+            c => {
+                code.push(c);
+                i += 1;
+            }
+        }
+    }
+    if POSITION_DEPENDENT
+        .iter()
+        .any(|position| code.contains(position))
+    {
+        return None;
+    }
+    Some(code.trim_end().to_string())
+}
+
+/// Copies the string literal starting at `start` to `code`, returns the index after it.
+fn copy_string(chars: &[char], start: usize, code: &mut String) -> Option<usize> {
+    let mut i = start + 1;
+    loop {
+        match chars.get(i)? {
+            '\\' => i += 2,
+            '"' => break,
+            _ => i += 1,
+        }
+    }
+    code.extend(&chars[start..=i]);
+    Some(i + 1)
+}
+
+/// Returns the number of `#` if a raw string literal `r#"` starts at `i`.
+fn raw_string_hashes(chars: &[char], i: usize) -> Option<usize> {
+    let hashes = chars[i + 1..].iter().take_while(|c| **c == '#').count();
+    if chars.get(i + 1 + hashes) == Some(&'"') {
+        Some(hashes)
+    } else {
+        None
+    }
+}
+
+/// Returns whether the char before `i` continues an identifier, e.g. the `r` of `for"`.
+///
+/// The `b` of a raw byte string `br"` does not count.
+fn is_ident_char(chars: &[char], i: usize) -> bool {
+    let continues = |c: &char| c.is_alphanumeric() || *c == '_';
+    match i.checked_sub(1).map(|prev| chars[prev]) {
+        Some('b') => i
+            .checked_sub(2)
+            .map_or(false, |prev| continues(&chars[prev])),
+        Some(prev) => continues(&prev),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r##"
+        /// Flips the value.
+        #[ink(message)]
+        pub fn flip(&mut self) {
+            // invert it
+            self.value = !self.value; /* inline */
+            let _ = "a // string /* with */ comment markers";
+            let _ = r#"a "raw" // string"#;
+            let _ = ('"', '/', '\'', b"//");
+        }
+    "##;
+
+    #[test]
+    fn comments_are_not_part_of_the_code() {
+        let documented = SOURCE
+            .replace(
+                "/// Flips the value.",
+                "/// Flips the value.\n/// Twice as good.",
+            )
+            .replace("// invert it", "/* nested /* block */ comment */");
+
+        assert_eq!(code(SOURCE), code(&documented));
+        assert_eq!(
+            code(SOURCE).unwrap(),
+            "#[ink(message)] pub fn flip(&mut self) { self.value = !self.value; \
+             let _ = \"a // string /* with */ comment markers\"; \
+             let _ = r#\"a \"raw\" // string\"#; let _ = ('\"', '/', '\\'', b\"//\"); }"
+        );
+    }
+
+    #[test]
+    fn code_changes_are_detected() {
+        let changed_string = SOURCE.replace("comment markers", "comment marker");
+        let changed_code = SOURCE.replace("!self.value", "self.value");
+
+        assert_ne!(code(SOURCE), code(&changed_string));
+        assert_ne!(code(SOURCE), code(&changed_code));
+    }
+
+    #[test]
+    fn position_dependent_or_unlexable_code_has_no_fingerprint() {
+        assert_eq!(code("fn line() -> u32 { line!() }"), None);
+        assert_eq!(code("fn f() { /* unterminated }"), None);
+        assert_eq!(code("const S: &str = \"unterminated;"), None);
+    }
+}
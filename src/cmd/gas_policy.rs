@@ -0,0 +1,142 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Per message gas limits, read from a policy file.
+//!
+//! The policy file is a JSON object which maps message names, named like in the bindings, to gas
+//! limits, e.g. `{ "flip": 200000000, "transfer": 800000000 }`. Every entry must name a message
+//! of the contract. The message called is recognized by the selector the call data starts with.
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+use super::{bindings::spec_name, suggest::did_you_mean};
+
+/// The gas limit of each message, keyed by its name.
+pub(crate) type GasPolicy = BTreeMap<String, u64>;
+
+/// Reads a policy file and checks that its entries name messages of the metadata.
+pub(crate) fn load_gas_policy(path: &Path, metadata: &Value) -> Result<GasPolicy> {
+    let file = fs::File::open(path).context(format!("Failed to open {}", path.display()))?;
+    let policy: GasPolicy = serde_json::from_reader(file).context(format!(
+        "Failed to parse {}, it should map message names to gas limits",
+        path.display()
+    ))?;
+    let names = messages(metadata)?
+        .iter()
+        .map(spec_name)
+        .collect::<Vec<_>>();
+    let unknown = policy
+        .keys()
+        .filter(|name| !names.contains(name))
+        .map(|name| match did_you_mean(name, &names) {
+            Some(suggestion) => format!("{} ({})", name, suggestion),
+            None => name.clone(),
+        })
+        .collect::<Vec<_>>();
+    if !unknown.is_empty() {
+        anyhow::bail!(
+            "The gas policy {} names unknown messages: {}",
+            path.display(),
+            unknown.join(", ")
+        )
+    }
+    Ok(policy)
+}
+
+/// Returns the policy entry `(name, gas_limit)` of the message called with `data`, if any.
+pub(crate) fn policy_gas_limit<'a>(
+    policy: &'a GasPolicy,
+    metadata: &Value,
+    data: &[u8],
+) -> Result<Option<(&'a str, u64)>> {
+    let selector = format!("0x{}", hex::encode(&data[..data.len().min(4)]));
+    let message = messages(metadata)?
+        .iter()
+        .find(|message| message.get("selector").and_then(Value::as_str) == Some(selector.as_str()));
+    Ok(message.and_then(|message| {
+        policy
+            .get_key_value(&spec_name(message))
+            .map(|(name, gas_limit)| (name.as_str(), *gas_limit))
+    }))
+}
+
+fn messages(metadata: &Value) -> Result<&[Value]> {
+    metadata
+        .pointer("/spec/messages")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .ok_or(anyhow::anyhow!("spec.messages not found in the metadata"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn metadata() -> Value {
+        json!({
+            "spec": {
+                "messages": [
+                    { "name": ["flip"], "selector": "0x633aa551" },
+                    { "name": ["get"], "selector": "0x2f865bd9" }
+                ]
+            }
+        })
+    }
+
+    fn write_policy(dir: &Path, policy: Value) -> std::path::PathBuf {
+        let path = dir.join("gas-policy.json");
+        fs::write(&path, policy.to_string()).unwrap();
+        path
+    }
+
+    #[test]
+    fn applies_the_entry_of_the_called_message() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_policy(dir.path(), json!({ "flip": 200000000 }));
+
+        let policy = load_gas_policy(&path, &metadata()).unwrap();
+
+        assert_eq!(
+            policy_gas_limit(&policy, &metadata(), &[0x63, 0x3a, 0xa5, 0x51, 0x01]).unwrap(),
+            Some(("flip", 200000000))
+        );
+        assert_eq!(
+            policy_gas_limit(&policy, &metadata(), &[0x2f, 0x86, 0x5b, 0xd9]).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn unknown_messages_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_policy(dir.path(), json!({ "flip": 1, "transfer": 2 }));
+
+        let err = load_gas_policy(&path, &metadata()).unwrap_err();
+
+        assert!(err
+            .to_string()
+            .ends_with("names unknown messages: transfer"));
+        let path = write_policy(dir.path(), json!({ "flpi": 1 }));
+        let err = load_gas_policy(&path, &metadata()).unwrap_err();
+        assert!(err
+            .to_string()
+            .ends_with("names unknown messages: flpi (did you mean `flip`?)"));
+    }
+}
@@ -0,0 +1,189 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Refuses to deploy, or to build deterministically, code from a git working tree with
+//! uncommitted changes, and clones the templates of `new --git`.
+
+use std::{path::Path, process::Command};
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+
+/// The state of the git working tree of a directory.
+#[derive(Debug, PartialEq)]
+pub(crate) enum GitStatus {
+    /// The directory is not inside a git working tree.
+    NotARepository,
+    /// There are no uncommitted changes.
+    Clean,
+    /// The changed and untracked files, as listed by `git status --porcelain`.
+    Dirty(Vec<String>),
+}
+
+/// Returns the state of the git working tree which contains `dir`.
+pub(crate) fn git_status(dir: &Path) -> Result<GitStatus> {
+    let inside = Command::new("git")
+        .args(&["rev-parse", "--is-inside-work-tree"])
+        .current_dir(dir)
+        .output()
+        .context("Error invoking `git`")?;
+    if !inside.status.success() {
+        return Ok(GitStatus::NotARepository);
+    }
+
+    let output = Command::new("git")
+        .args(&["status", "--porcelain"])
+        .current_dir(dir)
+        .output()
+        .context("Error invoking `git`")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "`git status` failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let dirty = String::from_utf8(output.stdout)?
+        .lines()
+        .map(|line| line.trim().to_string())
+        .collect::<Vec<_>>();
+    if dirty.is_empty() {
+        Ok(GitStatus::Clean)
+    } else {
+        Ok(GitStatus::Dirty(dirty))
+    }
+}
+
+/// Fails if the git working tree containing `dir` has uncommitted changes, listing them.
+///
+/// Outside of a git working tree this only warns, unless `require_git` is set.
+#[cfg_attr(not(feature = "extrinsics"), allow(dead_code))]
+pub(crate) fn require_clean_git(dir: &Path, require_git: bool) -> Result<()> {
+    match git_status(dir)? {
+        GitStatus::Clean => Ok(()),
+        GitStatus::Dirty(files) => anyhow::bail!(
+            "Refusing to deploy from a dirty git working tree, commit or stash the changes:\n{}",
+            files.join("\n")
+        ),
+        GitStatus::NotARepository if require_git => anyhow::bail!(
+            "Refusing to deploy, {} is not inside a git working tree",
+            dir.display()
+        ),
+        GitStatus::NotARepository => {
+            println!(
+                "{} {}",
+                "warning:".yellow().bold(),
+                format!(
+                    "{} is not inside a git working tree, --require-clean-git has no effect",
+                    dir.display()
+                )
+                .bold()
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Clones the latest commit of the repository at `url` into the new directory `dest`.
+pub(crate) fn clone_shallow(url: &str, dest: &Path) -> Result<()> {
+    let output = Command::new("git")
+        .args(&["clone", "--quiet", "--depth", "1", url])
+        .arg(dest)
+        .output()
+        .context("Error invoking `git`")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Cloning {} failed: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Returns the author configured for git as `name <email>`, like `cargo new` does.
+pub(crate) fn git_author() -> Option<String> {
+    let config = |key: &str| {
+        let output = Command::new("git").args(&["config", key]).output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let value = String::from_utf8(output.stdout).ok()?.trim().to_string();
+        Some(value).filter(|value| !value.is_empty())
+    };
+    let name = config("user.name")?;
+    Some(match config("user.email") {
+        Some(email) => format!("{} <{}>", name, email),
+        None => name,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(&["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    fn committed_repository(dir: &Path) {
+        git(dir, &["init", "--quiet"]);
+        fs::write(dir.join("lib.rs"), "").unwrap();
+        git(dir, &["add", "lib.rs"]);
+        git(dir, &["commit", "--quiet", "-m", "initial"]);
+    }
+
+    #[test]
+    fn clean_working_tree_passes() {
+        let dir = tempfile::tempdir().unwrap();
+        committed_repository(dir.path());
+
+        assert_eq!(git_status(dir.path()).unwrap(), GitStatus::Clean);
+        assert!(require_clean_git(dir.path(), true).is_ok());
+    }
+
+    #[test]
+    fn dirty_working_tree_is_refused_with_its_files() {
+        let dir = tempfile::tempdir().unwrap();
+        committed_repository(dir.path());
+        fs::write(dir.path().join("lib.rs"), "// changed").unwrap();
+        fs::write(dir.path().join("new.rs"), "").unwrap();
+
+        assert_eq!(
+            git_status(dir.path()).unwrap(),
+            GitStatus::Dirty(vec!["M lib.rs".into(), "?? new.rs".into()])
+        );
+        let err = require_clean_git(dir.path(), false)
+            .unwrap_err()
+            .to_string();
+        assert!(err.ends_with("M lib.rs\n?? new.rs"), "{}", err);
+    }
+
+    #[test]
+    fn outside_of_a_repository_only_warns_unless_git_is_required() {
+        let dir = tempfile::tempdir().unwrap();
+
+        assert_eq!(git_status(dir.path()).unwrap(), GitStatus::NotARepository);
+        assert!(require_clean_git(dir.path(), false).is_ok());
+        assert!(require_clean_git(dir.path(), true).is_err());
+    }
+}
@@ -14,47 +14,589 @@
 // You should have received a copy of the GNU General Public License
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
-use anyhow::Result;
-use subxt::{balances::Balances, contracts::*, system::System, ClientBuilder, DefaultNodeRuntime};
+use std::{
+    convert::TryFrom,
+    fs,
+    path::{Path, PathBuf},
+};
 
-use crate::{ExtrinsicOpts, HexData};
+use anyhow::{Context, Result};
+use codec::Encode;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sp_core::H256;
+use structopt::StructOpt;
+use subxt::{
+    balances::Balances, contracts::*, system::System, Call, ClientBuilder, DefaultNodeRuntime,
+    Signer,
+};
+
+use super::{
+    balance::check_balance,
+    cost::{CostReport, OperationCost},
+    deploy::{execute_deploy, load_wasm_code},
+    dry_run::{dry_run_instantiate, instantiate_gas_limit},
+    metadata::read_metadata,
+    signer::ExtrinsicSigner,
+    token::Balance,
+    transcode::{constructor, encode_call},
+};
+use crate::{
+    crate_metadata::CrateMetadata, workspace::ManifestPath, ExtrinsicOpts, GasLimit, HexData,
+};
+
+/// Instantiate a deployed smart contract
+#[derive(Debug, StructOpt)]
+#[structopt(name = "instantiate")]
+pub struct InstantiateCommand {
+    #[structopt(flatten)]
+    extrinsic_opts: ExtrinsicOpts,
+    /// Transfers an initial balance to the instantiated contract, in the base unit or with a
+    /// unit of the chain's token, e.g. `1.5UNIT` or `2000mUNIT`
+    #[structopt(name = "endowment", long, alias = "value", default_value = "0")]
+    endowment: Balance,
+    /// The decimals of the chain's token for an `--endowment` with a unit, instead of the
+    /// `tokenDecimals` reported by the node
+    #[structopt(long, value_name = "decimals")]
+    token_decimals: Option<u32>,
+    /// Maximum amount of gas to be used for this command, or `auto` to estimate it with a
+    /// dry run of the constructor
+    #[structopt(name = "gas", long, default_value = "500000000")]
+    gas_limit: GasLimit,
+    /// The safety margin added to the gas estimated by `--gas auto`
+    #[structopt(long, default_value = "20", value_name = "percent")]
+    gas_margin: u64,
+    /// The hash of the smart contract code already uploaded to the chain
+    #[structopt(
+        long,
+        parse(try_from_str = parse_code_hash),
+        required_unless_one = &["wasm", "manifest-path"],
+        conflicts_with_all = &["wasm", "manifest-path"]
+    )]
+    code_hash: Option<H256>,
+    /// Path to wasm contract code which is uploaded before instantiating it, unless it is
+    /// already on chain
+    #[structopt(long, parse(from_os_str), conflicts_with = "manifest-path")]
+    wasm: Option<PathBuf>,
+    /// Path to the Cargo.toml of the contract project whose build output is uploaded before
+    /// instantiating it, unless it is already on chain
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    /// Hex encoded data to call a contract constructor
+    #[structopt(long, required_unless = "batch")]
+    data: Option<HexData>,
+    /// Hex encoded salt from which the contract account is derived, to instantiate the same
+    /// code several times with the same data
+    ///
+    /// Only runtimes whose contracts pallet takes a salt accept it. The default is no salt.
+    #[structopt(long, default_value = "")]
+    salt: HexData,
+    /// Path to a JSON file with one entry per instantiation, for deploying several instances.
+    ///
+    /// The file contains an array of `{ "name": "...", "data": "<hex>", "endowment": <u128>,
+    /// "salt": "<hex>" }` objects, the `endowment` and the `salt` are optional. Instead of the
+    /// `data` an entry may name a `"constructor"` with its `"args": [...]`, encoded from the
+    /// `--metadata`. A table of the names, salts and contract accounts is printed.
+    #[structopt(long, parse(from_os_str), conflicts_with = "data")]
+    batch: Option<PathBuf>,
+    /// Path to the contract metadata the constructors of the `--batch` entries are encoded with,
+    /// defaults to the `metadata.json` built for `--manifest-path` or for the contract in the
+    /// current directory
+    #[structopt(long, parse(from_os_str), requires = "batch")]
+    metadata: Option<PathBuf>,
+    /// Abort a batch at the first failed instantiation, instead of reporting it and continuing
+    #[structopt(long, requires = "batch")]
+    fail_fast: bool,
+    /// Only dry run the constructor and print the gas it consumes and the contract account,
+    /// nothing is submitted
+    #[structopt(long, conflicts_with = "batch")]
+    dry_run: bool,
+    /// Print the signer, the code hash, the contract account and the costs as JSON
+    #[structopt(long, conflicts_with_all = &["batch", "dry-run"])]
+    output_json: bool,
+}
+
+impl InstantiateCommand {
+    pub fn exec(&self) -> Result<String> {
+        let extrinsic_opts = &self.extrinsic_opts;
+        let endowment = self
+            .endowment
+            .resolve(extrinsic_opts, self.token_decimals)?;
+        let data = self.data.clone().unwrap_or_default();
+        let code = InstantiateCode::from_args(
+            self.code_hash,
+            self.wasm.as_ref(),
+            self.manifest_path.as_ref(),
+        )?;
+        let code_hash = code.code_hash();
+        // a batch entry which cannot be encoded fails before anything is submitted
+        let batch = match self.batch.as_ref() {
+            Some(path) => Some(self.resolve_batch(path, endowment)?),
+            None => None,
+        };
+        let signer = extrinsic_opts.signer()?;
+        if !self.output_json {
+            extrinsic_opts.print_signer(&signer)?;
+        }
+        if self.dry_run {
+            return dry_run_instantiate(
+                extrinsic_opts,
+                &signer,
+                endowment,
+                self.gas_limit,
+                code_hash,
+                data,
+                self.salt.clone(),
+            );
+        }
+        let mut costs = CostReport::default();
+        let upload = matches!(code, InstantiateCode::Upload(_));
+        if upload {
+            extrinsic_opts.confirm_chain()?;
+            if let Some(cost) = code.upload(extrinsic_opts, &signer)? {
+                costs.add(cost);
+            }
+        }
+        if let Some(batch) = batch {
+            if !upload {
+                extrinsic_opts.confirm_chain()?;
+            }
+            return execute_instantiate_batch(
+                extrinsic_opts,
+                &signer,
+                self.gas_limit,
+                self.gas_margin,
+                code_hash,
+                &batch,
+                self.fail_fast,
+            );
+        }
+        // a constructor which reverts in the dry run fails before it is submitted
+        let gas_limit = instantiate_gas_limit(
+            extrinsic_opts,
+            self.gas_limit,
+            self.gas_margin,
+            signer.account_id(),
+            endowment,
+            code_hash,
+            &data.0,
+            &self.salt.0,
+        )?;
+        if !upload {
+            extrinsic_opts.confirm_chain()?;
+        }
+        let (contract_account, cost) = execute_instantiate(
+            extrinsic_opts,
+            &signer,
+            endowment,
+            gas_limit,
+            code_hash,
+            data,
+            self.salt.clone(),
+        )?;
+        costs.add(cost);
+        if self.output_json {
+            let json = json!({
+                "signer": signer.account_id().to_string(),
+                "code_hash": format!("{:?}", code_hash),
+                "contract": contract_account.to_string(),
+                "costs": costs.to_json(),
+            });
+            return Ok(serde_json::to_string_pretty(&json)?);
+        }
+        Ok(format!(
+            "Contract account: {:?}\n\n\t{}",
+            contract_account,
+            costs.display()
+        ))
+    }
+
+    /// Reads the entries of the batch file at `path`, the metadata is only read if an entry
+    /// names a constructor.
+    fn resolve_batch(&self, path: &Path, endowment: u128) -> Result<Vec<BatchInstantiation>> {
+        let entries = load_batch(path)?;
+        let metadata = if entries.iter().any(|entry| entry.constructor.is_some()) {
+            Some(read_metadata(&self.metadata_path()?)?)
+        } else {
+            None
+        };
+        entries
+            .into_iter()
+            .map(|entry| {
+                let name = entry.name.clone();
+                entry
+                    .resolve(metadata.as_ref(), endowment, &self.salt)
+                    .context(format!("Invalid batch entry '{}'", name))
+            })
+            .collect()
+    }
+
+    /// Returns the path of `--metadata`, the `metadata.json` of `--manifest-path` or of the
+    /// current project by default.
+    fn metadata_path(&self) -> Result<PathBuf> {
+        match self.metadata.as_ref() {
+            Some(path) => Ok(path.clone()),
+            None => {
+                let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+                Ok(CrateMetadata::collect(&manifest_path)?.dest_metadata)
+            }
+        }
+    }
+}
+
+/// The code to instantiate, given by `--code-hash`, `--wasm` or `--manifest-path`.
+#[derive(Debug, PartialEq)]
+pub(crate) enum InstantiateCode {
+    /// The hash of code already uploaded, e.g. by `deploy`.
+    Uploaded(H256),
+    /// Code which is uploaded before instantiating it.
+    Upload(Vec<u8>),
+}
+
+impl InstantiateCode {
+    /// Returns the code given by exactly one of the arguments, the Wasm of `manifest_path` is the
+    /// build output of the contract.
+    pub(crate) fn from_args(
+        code_hash: Option<H256>,
+        wasm: Option<&PathBuf>,
+        manifest_path: Option<&PathBuf>,
+    ) -> Result<Self> {
+        match (code_hash, wasm, manifest_path) {
+            (Some(code_hash), None, None) => Ok(InstantiateCode::Uploaded(code_hash)),
+            (None, Some(wasm), None) => Ok(InstantiateCode::Upload(load_wasm_code(wasm)?)),
+            (None, None, Some(manifest_path)) => {
+                let manifest_path = ManifestPath::try_from(manifest_path)?;
+                let dest_wasm = CrateMetadata::collect(&manifest_path)?.dest_wasm;
+                Ok(InstantiateCode::Upload(load_wasm_code(&dest_wasm)?))
+            }
+            (None, None, None) => anyhow::bail!(
+                "Pass --code-hash of uploaded code, or --wasm or --manifest-path of the code to \
+                 upload"
+            ),
+            _ => anyhow::bail!("Pass only one of --code-hash, --wasm and --manifest-path"),
+        }
+    }
+
+    pub(crate) fn code_hash(&self) -> H256 {
+        match self {
+            InstantiateCode::Uploaded(code_hash) => *code_hash,
+            InstantiateCode::Upload(code) => H256(sp_core::blake2_256(code)),
+        }
+    }
+
+    /// Uploads the code unless it is already on chain, returning the cost of the upload.
+    pub(crate) fn upload(
+        &self,
+        extrinsic_opts: &ExtrinsicOpts,
+        signer: &ExtrinsicSigner,
+    ) -> Result<Option<OperationCost>> {
+        match self {
+            InstantiateCode::Uploaded(_) => Ok(None),
+            InstantiateCode::Upload(code) => {
+                Ok(execute_deploy(extrinsic_opts, signer, code, false)?.1)
+            }
+        }
+    }
+}
+
+/// An instantiation of a batch, read from the `--batch` JSON file.
+#[derive(Debug, Deserialize, PartialEq)]
+struct BatchEntry {
+    /// Identifies the entry in the output table.
+    name: String,
+    /// Hex encoded data to call the contract constructor with.
+    #[serde(default)]
+    data: Option<String>,
+    /// The name of the constructor to call, encoded with its selector and `args` from the
+    /// metadata instead of the `data`.
+    #[serde(default)]
+    constructor: Option<String>,
+    /// The arguments of the `constructor`.
+    #[serde(default)]
+    args: Vec<String>,
+    /// Overrides the endowment passed on the command line for this entry.
+    #[serde(default)]
+    endowment: Option<u128>,
+    /// Hex encoded salt, overrides the salt passed on the command line for this entry.
+    #[serde(default)]
+    salt: Option<String>,
+}
+
+impl BatchEntry {
+    /// Returns the instantiation of the entry, with the `data` decoded or the `constructor`
+    /// encoded from the `metadata`, and the `endowment` and `salt` of the command line unless
+    /// the entry overrides them.
+    fn resolve(
+        self,
+        metadata: Option<&Value>,
+        endowment: u128,
+        salt: &HexData,
+    ) -> Result<BatchInstantiation> {
+        let data = match (self.data.as_ref(), self.constructor.as_ref()) {
+            (Some(_), Some(_)) => anyhow::bail!("Pass either `data` or `constructor`, not both"),
+            (Some(data), None) => {
+                if !self.args.is_empty() {
+                    anyhow::bail!("`args` are only passed with a `constructor`")
+                }
+                hex::decode(data.trim_start_matches("0x")).context("data should be hex encoded")?
+            }
+            (None, Some(name)) => {
+                let metadata = metadata.ok_or(anyhow::anyhow!("No metadata to encode with"))?;
+                let constructor = constructor(metadata, name)?;
+                encode_call(metadata, constructor, &self.args)
+                    .context(format!("Failed to encode the constructor `{}`", name))?
+            }
+            (None, None) => anyhow::bail!("Pass either `data` or `constructor`"),
+        };
+        let salt = match self.salt.as_ref() {
+            Some(salt) => HexData(
+                hex::decode(salt.trim_start_matches("0x")).context("salt should be hex encoded")?,
+            ),
+            None => salt.clone(),
+        };
+        Ok(BatchInstantiation {
+            name: self.name,
+            data,
+            endowment: self.endowment.unwrap_or(endowment),
+            salt,
+        })
+    }
+}
+
+/// A batch entry ready to be instantiated.
+#[derive(Debug, PartialEq)]
+struct BatchInstantiation {
+    name: String,
+    data: Vec<u8>,
+    endowment: u128,
+    salt: HexData,
+}
+
+/// `Contracts::instantiate` with a salt, which `InstantiateCall` lacks. Only runtimes whose
+/// contracts pallet derives the contract address from a salt accept it.
+#[derive(Encode)]
+pub(super) struct InstantiateWithSaltCall<'a> {
+    #[codec(compact)]
+    pub endowment: u128,
+    #[codec(compact)]
+    pub gas_limit: u64,
+    pub code_hash: &'a <DefaultNodeRuntime as System>::Hash,
+    pub data: &'a [u8],
+    pub salt: &'a [u8],
+}
+
+impl Call<DefaultNodeRuntime> for InstantiateWithSaltCall<'_> {
+    const MODULE: &'static str = "Contracts";
+    const FUNCTION: &'static str = "instantiate";
+}
+
+/// Reads the entries of a batch file, a JSON array of `{ "name", "data" | "constructor", "args"?,
+/// "endowment"?, "salt"? }` objects.
+fn load_batch(path: &Path) -> Result<Vec<BatchEntry>> {
+    let file = fs::File::open(path).context(format!("Failed to open {}", path.display()))?;
+    serde_json::from_reader(file).context(format!("Failed to parse {}", path.display()))
+}
+
+fn parse_code_hash(input: &str) -> Result<H256> {
+    let bytes = hex::decode(input)?;
+    if bytes.len() != 32 {
+        anyhow::bail!("Code hash should be 32 bytes in length")
+    }
+    let mut arr = [0u8; 32];
+    arr.copy_from_slice(&bytes);
+    Ok(H256(arr))
+}
 
 /// Instantiate a contract stored at the supplied code hash.
-/// Returns the account id of the instantiated contract if successful.
+/// Returns a tuple of `(contract_account, cost)` if successful.
 ///
 /// Creates an extrinsic with the `Contracts::instantiate` Call, submits via RPC, then waits for
 /// the `ContractsEvent::Instantiated` event.
+///
+/// A non-empty `salt` is appended to the params of the call, an empty salt instantiates without
+/// one.
 pub(crate) fn execute_instantiate(
     extrinsic_opts: &ExtrinsicOpts,
+    signer: &ExtrinsicSigner,
     endowment: <DefaultNodeRuntime as Balances>::Balance,
     gas_limit: u64,
     code_hash: <DefaultNodeRuntime as System>::Hash,
     data: HexData,
-) -> Result<<DefaultNodeRuntime as System>::AccountId> {
+    salt: HexData,
+) -> Result<(<DefaultNodeRuntime as System>::AccountId, OperationCost)> {
     async_std::task::block_on(async move {
+        let rpc = extrinsic_opts.rpc_client().await?;
         let cli = ClientBuilder::<DefaultNodeRuntime>::new()
-            .set_url(&extrinsic_opts.url.to_string())
+            .set_client(rpc.clone())
             .build()
             .await?;
-        let signer = extrinsic_opts.signer()?;
+        let salted = || InstantiateWithSaltCall {
+            endowment,
+            gas_limit,
+            code_hash: &code_hash,
+            data: &data.0,
+            salt: &salt.0,
+        };
+        if extrinsic_opts.check_balance {
+            if salt.0.is_empty() {
+                let call = InstantiateCall {
+                    endowment,
+                    gas_limit,
+                    code_hash: &code_hash,
+                    data: &data.0,
+                };
+                check_balance(&cli, &rpc, signer, call, endowment).await?;
+            } else {
+                check_balance(&cli, &rpc, signer, salted(), endowment).await?;
+            }
+        }
 
-        let events = cli
-            .instantiate_and_watch(&signer, endowment, gas_limit, &code_hash, &data.0)
-            .await?;
+        let events = if salt.0.is_empty() {
+            cli.instantiate_and_watch(signer, endowment, gas_limit, &code_hash, &data.0)
+                .await?
+        } else {
+            cli.watch(salted(), signer).await?
+        };
         let instantiated = events
             .instantiated()?
             .ok_or(anyhow::anyhow!("Failed to find Instantiated event"))?;
+        let cost = OperationCost::from_events("instantiate", &events, endowment)?;
 
-        Ok(instantiated.contract)
+        Ok((instantiated.contract, cost))
     })
 }
 
+/// Instantiates the contract stored at the supplied code hash once for every entry of the batch,
+/// in sequence. Returns a table of the entry names, salts and the resulting contract accounts,
+/// followed by a summary of the costs of the successful instantiations.
+///
+/// A failed entry is reported in the table without aborting the rest of the batch, unless
+/// `fail_fast` is set. With `--gas auto` the gas of each entry is estimated separately.
+fn execute_instantiate_batch(
+    extrinsic_opts: &ExtrinsicOpts,
+    signer: &ExtrinsicSigner,
+    gas_limit: GasLimit,
+    gas_margin: u64,
+    code_hash: <DefaultNodeRuntime as System>::Hash,
+    entries: &[BatchInstantiation],
+    fail_fast: bool,
+) -> Result<String> {
+    let mut results = Vec::with_capacity(entries.len());
+    let mut costs = CostReport::default();
+    for entry in entries {
+        let result = instantiate_gas_limit(
+            extrinsic_opts,
+            gas_limit,
+            gas_margin,
+            signer.account_id(),
+            entry.endowment,
+            code_hash,
+            &entry.data,
+            &entry.salt.0,
+        )
+        .and_then(|gas_limit| {
+            execute_instantiate(
+                extrinsic_opts,
+                signer,
+                entry.endowment,
+                gas_limit,
+                code_hash,
+                HexData(entry.data.clone()),
+                entry.salt.clone(),
+            )
+        })
+        .map(|(account, cost)| {
+            costs.add(OperationCost {
+                operation: format!("instantiate {}", entry.name),
+                ..cost
+            });
+            account.to_string()
+        });
+        if fail_fast {
+            if let Err(err) = result {
+                return Err(err.context(format!("Failed to instantiate '{}'", entry.name)));
+            }
+        }
+        results.push((entry.name.as_str(), &entry.salt, result));
+    }
+
+    let table = format!(
+        "{}\n\n\t{}",
+        display_batch_results(&results),
+        costs.display()
+    );
+    let failed = results
+        .iter()
+        .filter(|(_, _, result)| result.is_err())
+        .count();
+    if failed > 0 {
+        println!("\t{}", table);
+        anyhow::bail!("{} of {} instantiations failed", failed, results.len());
+    }
+    Ok(table)
+}
+
+/// Formats the name, the salt and the contract account, or the error, of each batch entry as a
+/// table. An empty salt is displayed as `-`.
+fn display_batch_results(results: &[(&str, &HexData, Result<String>)]) -> String {
+    let salts = results
+        .iter()
+        .map(|(_, salt, _)| {
+            if salt.0.is_empty() {
+                "-".to_string()
+            } else {
+                format!("0x{}", hex::encode(&salt.0))
+            }
+        })
+        .collect::<Vec<_>>();
+    let width = results
+        .iter()
+        .map(|(name, _, _)| name.len())
+        .max()
+        .unwrap_or(0)
+        .max("Name".len());
+    let salt_width = salts
+        .iter()
+        .map(String::len)
+        .max()
+        .unwrap_or(0)
+        .max("Salt".len());
+    let mut lines = vec![format!(
+        "{:width$}  {:salt_width$}  Contract account",
+        "Name",
+        "Salt",
+        width = width,
+        salt_width = salt_width
+    )];
+    for ((name, _, result), salt) in results.iter().zip(&salts) {
+        let outcome = match result {
+            Ok(account) => account.clone(),
+            Err(err) => format!("ERROR: {}", err),
+        };
+        lines.push(format!(
+            "{:width$}  {:salt_width$}  {}",
+            name,
+            salt,
+            outcome,
+            width = width,
+            salt_width = salt_width
+        ));
+    }
+    lines.join("\n\t")
+}
+
 #[cfg(test)]
 mod tests {
-    use std::{fs, io::Write};
+    use std::fs;
 
-    use crate::{cmd::deploy::execute_deploy, util::tests::with_tmp_dir, ExtrinsicOpts, HexData};
+    use super::{
+        display_batch_results, load_batch, BatchEntry, BatchInstantiation, InstantiateCode,
+    };
+    use crate::{
+        cmd::deploy::execute_deploy, util::tests::with_tmp_dir, ExtrinsicOpts, HexData, NodeOpts,
+    };
     use assert_matches::assert_matches;
+    use serde_json::json;
 
     const CONTRACT: &str = r#"
 (module
@@ -66,33 +608,188 @@ mod tests {
     #[test]
     #[ignore] // depends on a local substrate node running
     fn instantiate_contract() {
+        let wasm = wabt::wat2wasm(CONTRACT).expect("invalid wabt");
+
+        let url = url::Url::parse("ws://localhost:9944").unwrap();
+        let extrinsic_opts = ExtrinsicOpts {
+            node: NodeOpts {
+                url: Some(url),
+                network: None,
+            },
+            suri: Some("//Alice".into()),
+            account: None,
+            keystore_path: None,
+            keystore: None,
+            password: None,
+            scheme: None,
+            check_balance: false,
+            prompt_on_mainnet: false,
+            yes: false,
+            refresh_metadata: false,
+            verbosity: Default::default(),
+        };
+        let signer = extrinsic_opts.signer().unwrap();
+        let (code_hash, _) =
+            execute_deploy(&extrinsic_opts, &signer, &wasm, false).expect("Deploy should succeed");
+
+        let gas_limit = 500_000_000;
+        let result = super::execute_instantiate(
+            &extrinsic_opts,
+            &signer,
+            100000000000000,
+            gas_limit,
+            code_hash,
+            HexData::default(),
+            HexData::default(),
+        );
+
+        assert_matches!(result, Ok(_));
+    }
+
+    #[test]
+    fn loads_batch_entries() {
         with_tmp_dir(|path| {
-            let wasm = wabt::wat2wasm(CONTRACT).expect("invalid wabt");
+            let batch_path = path.join("params.json");
+            fs::write(
+                &batch_path,
+                r#"[
+                    { "name": "first", "data": "0x00" },
+                    { "name": "second", "data": "01", "endowment": 42, "salt": "0x02" },
+                    { "name": "third", "constructor": "new", "args": ["true"] }
+                ]"#,
+            )?;
 
-            let wasm_path = path.join("test.wasm");
-            let mut file = fs::File::create(&wasm_path).unwrap();
-            let _ = file.write_all(&wasm);
-
-            let url = url::Url::parse("ws://localhost:9944").unwrap();
-            let extrinsic_opts = ExtrinsicOpts {
-                url,
-                suri: "//Alice".into(),
-                password: None,
-            };
-            let code_hash =
-                execute_deploy(&extrinsic_opts, Some(&wasm_path)).expect("Deploy should succeed");
-
-            let gas_limit = 500_000_000;
-            let result = super::execute_instantiate(
-                &extrinsic_opts,
-                100000000000000,
-                gas_limit,
-                code_hash,
-                HexData::default(),
+            let entries = load_batch(&batch_path)?;
+
+            assert_eq!(
+                entries,
+                vec![
+                    BatchEntry {
+                        name: "first".into(),
+                        data: Some("0x00".into()),
+                        constructor: None,
+                        args: Vec::new(),
+                        endowment: None,
+                        salt: None,
+                    },
+                    BatchEntry {
+                        name: "second".into(),
+                        data: Some("01".into()),
+                        constructor: None,
+                        args: Vec::new(),
+                        endowment: Some(42),
+                        salt: Some("0x02".into()),
+                    },
+                    BatchEntry {
+                        name: "third".into(),
+                        data: None,
+                        constructor: Some("new".into()),
+                        args: vec!["true".into()],
+                        endowment: None,
+                        salt: None,
+                    },
+                ]
             );
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn batch_constructors_are_encoded_from_the_metadata() {
+        let metadata = json!({
+            "spec": {
+                "constructors": [
+                    {
+                        "name": ["new"],
+                        "selector": "0x9bae9d5e",
+                        "args": [ { "name": "init_value", "type": { "type": 1 } } ]
+                    }
+                ]
+            },
+            "types": [ { "def": { "primitive": "bool" } } ]
+        });
+        let entry = |data: Option<&str>, constructor: Option<&str>| BatchEntry {
+            name: "flipper".into(),
+            data: data.map(Into::into),
+            constructor: constructor.map(Into::into),
+            args: constructor.map(|_| vec!["true".into()]).unwrap_or_default(),
+            endowment: None,
+            salt: Some("0x02".into()),
+        };
+
+        assert_eq!(
+            entry(None, Some("new"))
+                .resolve(Some(&metadata), 42, &HexData::default())
+                .unwrap(),
+            BatchInstantiation {
+                name: "flipper".into(),
+                data: vec![0x9b, 0xae, 0x9d, 0x5e, 1],
+                endowment: 42,
+                salt: HexData(vec![2]),
+            }
+        );
+        assert_eq!(
+            entry(Some("0x00"), None)
+                .resolve(None, 42, &HexData::default())
+                .unwrap()
+                .data,
+            vec![0]
+        );
+        assert!(entry(None, Some("default"))
+            .resolve(Some(&metadata), 42, &HexData::default())
+            .is_err());
+        assert!(entry(Some("0x00"), Some("new"))
+            .resolve(Some(&metadata), 42, &HexData::default())
+            .is_err());
+        assert!(entry(None, None)
+            .resolve(Some(&metadata), 42, &HexData::default())
+            .is_err());
+    }
+
+    #[test]
+    fn code_is_given_by_exactly_one_argument() {
+        with_tmp_dir(|path| {
+            let wasm = wabt::wat2wasm(CONTRACT).expect("invalid wabt");
+            let wasm_path = path.join("flipper.wasm");
+            fs::write(&wasm_path, &wasm)?;
+            let code_hash = sp_core::H256::repeat_byte(1);
 
-            assert_matches!(result, Ok(_));
+            let uploaded = InstantiateCode::from_args(Some(code_hash), None, None)?;
+            let upload = InstantiateCode::from_args(None, Some(&wasm_path), None)?;
+
+            assert_eq!(uploaded, InstantiateCode::Uploaded(code_hash));
+            assert_eq!(uploaded.code_hash(), code_hash);
+            assert_eq!(
+                upload.code_hash(),
+                sp_core::H256(sp_core::blake2_256(&wasm))
+            );
+            assert!(InstantiateCode::from_args(None, None, None).is_err());
+            assert_eq!(
+                InstantiateCode::from_args(Some(code_hash), Some(&wasm_path), None)
+                    .unwrap_err()
+                    .to_string(),
+                "Pass only one of --code-hash, --wasm and --manifest-path"
+            );
             Ok(())
         })
     }
+
+    #[test]
+    fn batch_results_are_displayed_as_table() {
+        let salt = HexData(vec![0x01, 0x02]);
+        let no_salt = HexData::default();
+        let results = vec![
+            ("first", &salt, Ok("5Grwva".to_string())),
+            ("second-entry", &no_salt, Err(anyhow::anyhow!("out of gas"))),
+        ];
+
+        let table = display_batch_results(&results);
+
+        assert_eq!(
+            table,
+            "Name          Salt    Contract account\n\t\
+             first         0x0102  5Grwva\n\t\
+             second-entry  -       ERROR: out of gas"
+        );
+    }
 }
@@ -0,0 +1,237 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! End-to-end tests of the extrinsic commands against a local contracts node.
+//!
+//! The node is configured by environment variables:
+//!
+//! - `CONTRACTS_NODE`: the node binary which is launched with `--dev --tmp`, defaults to `canvas`
+//! - `CONTRACTS_NODE_URL`: the websockets url of an already running node, no node is launched
+//!   if it is set
+
+use std::{
+    fs,
+    net::{TcpListener, TcpStream},
+    process::{Child, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use jsonrpsee::common::{to_value, Params};
+use serde_json::{json, Value};
+use sp_core::{
+    crypto::{AccountId32, Pair},
+    sr25519, H256,
+};
+use subxt::{ClientBuilder, DefaultNodeRuntime};
+
+use super::{
+    bindings::spec_name,
+    deploy::execute_deploy,
+    instantiate::execute_instantiate,
+    storage::{contract_info, ContractInfo},
+};
+use crate::{
+    cmd, util::tests::with_tmp_dir, BuildArtifacts, BuildOptions, ExtrinsicOpts, HexData,
+    ManifestPath, MetadataCompat, NodeOpts,
+};
+
+/// How long to wait for a launched node to accept connections.
+const NODE_STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A local contracts node, killed when dropped unless it was already running.
+struct TestNode {
+    url: url::Url,
+    process: Option<Child>,
+}
+
+impl TestNode {
+    /// Connects to `CONTRACTS_NODE_URL`, or launches `CONTRACTS_NODE` on free ports.
+    fn start() -> Result<Self> {
+        if let Ok(url) = std::env::var("CONTRACTS_NODE_URL") {
+            return Ok(Self {
+                url: url::Url::parse(&url)?,
+                process: None,
+            });
+        }
+        let binary = std::env::var("CONTRACTS_NODE").unwrap_or_else(|_| "canvas".into());
+        let ws_port = free_port()?;
+        let process = Command::new(&binary)
+            .args(&["--dev", "--tmp", "--ws-port"])
+            .arg(ws_port.to_string())
+            .arg("--rpc-port")
+            .arg(free_port()?.to_string())
+            .arg("--port")
+            .arg(free_port()?.to_string())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .context(format!(
+                "Failed to launch the contracts node `{}`, set CONTRACTS_NODE to its binary",
+                binary
+            ))?;
+        let node = Self {
+            url: url::Url::parse(&format!("ws://127.0.0.1:{}", ws_port))?,
+            process: Some(process),
+        };
+
+        let start = Instant::now();
+        while TcpStream::connect(("127.0.0.1", ws_port)).is_err() {
+            if start.elapsed() > NODE_STARTUP_TIMEOUT {
+                anyhow::bail!(
+                    "The contracts node did not accept connections on {}",
+                    node.url
+                )
+            }
+            thread::sleep(Duration::from_millis(200));
+        }
+        Ok(node)
+    }
+
+    fn extrinsic_opts(&self) -> ExtrinsicOpts {
+        ExtrinsicOpts {
+            node: NodeOpts {
+                url: Some(self.url.clone()),
+                network: None,
+            },
+            suri: Some("//Alice".into()),
+            account: None,
+            keystore_path: None,
+            keystore: None,
+            password: None,
+            scheme: None,
+            check_balance: false,
+            prompt_on_mainnet: false,
+            yes: false,
+            refresh_metadata: false,
+            verbosity: Default::default(),
+        }
+    }
+}
+
+impl Drop for TestNode {
+    fn drop(&mut self) {
+        if let Some(process) = self.process.as_mut() {
+            process.kill().ok();
+            process.wait().ok();
+        }
+    }
+}
+
+/// Returns a port which is free at the time of the call.
+fn free_port() -> Result<u16> {
+    Ok(TcpListener::bind("127.0.0.1:0")?.local_addr()?.port())
+}
+
+/// Returns the selector of the constructor or message `name` in the metadata.
+fn selector(metadata: &Value, kind: &str, name: &str) -> Result<Vec<u8>> {
+    let selector = metadata["spec"][kind]
+        .as_array()
+        .and_then(|specs| specs.iter().find(|spec| spec_name(spec) == name))
+        .and_then(|spec| spec["selector"].as_str())
+        .ok_or(anyhow::anyhow!(
+            "{} `{}` not found in the metadata",
+            kind,
+            name
+        ))?;
+    Ok(hex::decode(selector.trim_start_matches("0x"))?)
+}
+
+/// Calls a message of a contract with the `contracts_call` RPC, nothing is submitted.
+fn rpc_call(url: &url::Url, dest: &AccountId32, data: &[u8]) -> Result<Value> {
+    async_std::task::block_on(async move {
+        let rpc = jsonrpsee::ws_client(url.as_str()).await?;
+        let alice = sr25519::Pair::from_string("//Alice", None)
+            .map_err(|_| anyhow::anyhow!("Secret string error"))?
+            .public();
+        let call = json!({
+            "origin": AccountId32::from(alice).to_string(),
+            "dest": dest.to_string(),
+            "value": 0,
+            "gasLimit": 500_000_000,
+            "inputData": format!("0x{}", hex::encode(data)),
+        });
+        let result: Value = rpc
+            .request("contracts_call", Params::Array(vec![to_value(call)?]))
+            .await?;
+        Ok(result)
+    })
+}
+
+#[test]
+fn build_deploy_instantiate_and_call_the_template() {
+    let node = TestNode::start().expect("starting the contracts node failed");
+    let extrinsic_opts = node.extrinsic_opts();
+
+    with_tmp_dir(|path| {
+        cmd::new::execute("flipper", Some(path), Default::default())
+            .expect("new project creation failed");
+        let manifest_path = ManifestPath::new(&path.join("flipper").join("Cargo.toml"))?;
+        let build = cmd::metadata::execute(
+            &manifest_path,
+            None,
+            BuildArtifacts::All,
+            MetadataCompat::Current,
+            false,
+            BuildOptions::default(),
+        )?;
+        let code = fs::read(build.dest_wasm.expect("wasm not built"))?;
+        let metadata: Value =
+            serde_json::from_slice(&fs::read(build.dest_metadata.expect("metadata not built"))?)?;
+
+        let signer = extrinsic_opts.signer()?;
+        let (code_hash, _) = execute_deploy(&extrinsic_opts, &signer, &code, false)?;
+        assert_eq!(code_hash, H256(sp_core::blake2_256(&code)));
+
+        let mut data = selector(&metadata, "constructors", "new")?;
+        data.push(0x01); // `init_value: true`
+        let (contract, _) = execute_instantiate(
+            &extrinsic_opts,
+            &signer,
+            100000000000000,
+            500_000_000,
+            code_hash,
+            HexData(data),
+            HexData::default(),
+        )?;
+        let info = async_std::task::block_on(async {
+            let rpc = jsonrpsee::ws_client(node.url.as_str()).await?;
+            let cli = ClientBuilder::<DefaultNodeRuntime>::new()
+                .set_client(rpc)
+                .build()
+                .await?;
+            contract_info(&cli, &contract).await
+        })?;
+        match info {
+            ContractInfo::Alive(info) => assert_eq!(info.code_hash, code_hash),
+            ContractInfo::Tombstone(_) => panic!("the instantiated contract is a tombstone"),
+        }
+
+        let result = rpc_call(
+            &node.url,
+            &contract,
+            &selector(&metadata, "messages", "get")?,
+        )?;
+        assert_eq!(
+            result["success"]["data"], "0x01",
+            "unexpected result {}",
+            result
+        );
+
+        Ok(())
+    })
+}
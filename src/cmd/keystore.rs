@@ -0,0 +1,257 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Loads signing keys from a Substrate keystore directory.
+//!
+//! The keystore of a node holds one file per key, named by the hex encoded key type followed by
+//! the hex encoded public key, e.g. `6163636f` (`acco`) and 32 bytes of public key. The file
+//! contains the secret phrase or seed as a JSON string. The password is applied when deriving the
+//! key pair, so a wrong password yields a pair whose public key does not match the file name.
+//!
+//! A single keyfile can also be used on its own, e.g. copied out of the keystore.
+
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use sp_core::{
+    crypto::{Pair, Ss58Codec},
+    ed25519, sr25519,
+};
+
+/// The length of the hex encoded key type prefix of a keyfile name.
+const KEY_TYPE_HEX_LEN: usize = 8;
+
+/// The password does not derive the key of a keyfile, it is wrong or missing.
+#[derive(Debug)]
+pub(crate) struct WrongPassword(String);
+
+impl fmt::Display for WrongPassword {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Wrong password for account '{}'", self.0)
+    }
+}
+
+impl std::error::Error for WrongPassword {}
+
+/// Reads the password of the signer from the terminal, without echoing it.
+///
+/// Fails without a terminal to ask on.
+pub(crate) fn prompt_password() -> Result<String> {
+    if !atty::is(atty::Stream::Stdin) {
+        anyhow::bail!(
+            "The password cannot be prompted for without a terminal, pass --password <password>"
+        )
+    }
+    rpassword::read_password_from_tty(Some("Password: ")).context("Failed to read the password")
+}
+
+/// Returns the key pair of `account` in the keystore at `keystore_path`.
+///
+/// The account is either the name of a keyfile or the SS58 address of its public key.
+pub(crate) fn keystore_pair(
+    keystore_path: &Path,
+    account: &str,
+    password: Option<&str>,
+) -> Result<sr25519::Pair> {
+    let (path, public) = find_keyfile(keystore_path, account)?;
+    read_keyfile(&path, Some(&public[..]), account, password)
+}
+
+/// Returns the key pair of the single keyfile at `path`.
+///
+/// If the file is named like a keyfile of a keystore the password is checked against the public
+/// key of its name, otherwise a wrong password cannot be detected.
+pub(crate) fn keyfile_pair(path: &Path, password: Option<&str>) -> Result<sr25519::Pair> {
+    let public = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(keyfile_public);
+    read_keyfile(
+        path,
+        public.as_deref(),
+        &path.display().to_string(),
+        password,
+    )
+}
+
+/// Derives the key pair of `account` from the secret in the keyfile at `path`, checking it
+/// against the `public` key of the file name if known.
+fn read_keyfile(
+    path: &Path,
+    public: Option<&[u8]>,
+    account: &str,
+    password: Option<&str>,
+) -> Result<sr25519::Pair> {
+    let contents =
+        fs::read_to_string(&path).context(format!("Failed to read {}", path.display()))?;
+    let secret: String = serde_json::from_str(&contents).map_err(|_| {
+        anyhow::anyhow!(
+            "Unsupported keyfile {}, it should contain the secret as a JSON string",
+            path.display()
+        )
+    })?;
+
+    let pair = sr25519::Pair::from_string(&secret, password)
+        .map_err(|_| anyhow::anyhow!("Secret string error in {}", path.display()))?;
+    let public = match public {
+        Some(public) => public,
+        None => return Ok(pair),
+    };
+    if pair.public().0[..] == public[..] {
+        return Ok(pair);
+    }
+    let is_ed25519 = ed25519::Pair::from_string(&secret, password)
+        .map(|pair| pair.public().0[..] == public[..])
+        .unwrap_or(false);
+    if is_ed25519 {
+        anyhow::bail!(
+            "Unsupported key type of account '{}', it is an ed25519 key but only sr25519 is \
+             supported",
+            account
+        )
+    }
+    Err(WrongPassword(account.to_string()).into())
+}
+
+/// Returns the path and the public key of the keyfile of `account`.
+fn find_keyfile(keystore_path: &Path, account: &str) -> Result<(PathBuf, Vec<u8>)> {
+    let address = sr25519::Public::from_ss58check(account)
+        .ok()
+        .map(|public| hex::encode(public.0));
+    let entries = fs::read_dir(keystore_path).context(format!(
+        "Failed to read the keystore {}",
+        keystore_path.display()
+    ))?;
+    for entry in entries {
+        let path = entry?.path();
+        let name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) => name,
+            None => continue,
+        };
+        let public = match keyfile_public(name) {
+            Some(public) => public,
+            None => continue,
+        };
+        let matches_address = address
+            .as_ref()
+            .map(|address| *address == name[KEY_TYPE_HEX_LEN..])
+            .unwrap_or(false);
+        if name == account || matches_address {
+            return Ok((path, public));
+        }
+    }
+    anyhow::bail!(
+        "Account '{}' not found in the keystore {}",
+        account,
+        keystore_path.display()
+    )
+}
+
+/// Returns the public key encoded in the name of a keyfile, `None` if it is no keyfile.
+fn keyfile_public(name: &str) -> Option<Vec<u8>> {
+    if name.len() <= KEY_TYPE_HEX_LEN {
+        return None;
+    }
+    hex::decode(&name[..KEY_TYPE_HEX_LEN]).ok()?;
+    hex::decode(&name[KEY_TYPE_HEX_LEN..]).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHRASE: &str = "bottom drive obey lake curtain smoke basket hold race lonely fit walk";
+
+    /// Writes a keyfile of `PHRASE` named after `public`, returns its name.
+    fn write_keyfile(dir: &Path, public: &[u8]) -> String {
+        let name = format!("6163636f{}", hex::encode(public));
+        fs::write(dir.join(&name), format!("\"{}\"", PHRASE)).unwrap();
+        name
+    }
+
+    fn sr25519_public(password: Option<&str>) -> sr25519::Public {
+        sr25519::Pair::from_string(PHRASE, password)
+            .unwrap()
+            .public()
+    }
+
+    #[test]
+    fn finds_account_by_name_and_address() {
+        let dir = tempfile::tempdir().unwrap();
+        let public = sr25519_public(Some("secret"));
+        let name = write_keyfile(dir.path(), &public.0);
+
+        let by_name = keystore_pair(dir.path(), &name, Some("secret")).unwrap();
+        let by_address = keystore_pair(dir.path(), &public.to_ss58check(), Some("secret")).unwrap();
+
+        assert_eq!(by_name.public(), public);
+        assert_eq!(by_address.public(), public);
+    }
+
+    #[test]
+    fn missing_account_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        write_keyfile(dir.path(), &sr25519_public(None).0);
+
+        let err = keystore_pair(dir.path(), "6163636f00", None).unwrap_err();
+
+        assert!(err.to_string().contains("not found in the keystore"));
+    }
+
+    #[test]
+    fn wrong_password_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let name = write_keyfile(dir.path(), &sr25519_public(Some("secret")).0);
+
+        let err = keystore_pair(dir.path(), &name, Some("guess")).unwrap_err();
+
+        assert!(err.is::<WrongPassword>());
+        assert!(err.to_string().contains("Wrong password"));
+    }
+
+    #[test]
+    fn single_keyfile_is_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let public = sr25519_public(Some("secret"));
+        let name = write_keyfile(dir.path(), &public.0);
+        let renamed = dir.path().join("signer.json");
+        fs::copy(dir.path().join(&name), &renamed).unwrap();
+
+        let pair = keyfile_pair(&dir.path().join(&name), Some("secret")).unwrap();
+        let err = keyfile_pair(&dir.path().join(&name), Some("guess")).unwrap_err();
+
+        assert_eq!(pair.public(), public);
+        assert!(err.to_string().contains("Wrong password"));
+        assert_eq!(
+            keyfile_pair(&renamed, Some("secret")).unwrap().public(),
+            public
+        );
+    }
+
+    #[test]
+    fn ed25519_key_is_unsupported() {
+        let dir = tempfile::tempdir().unwrap();
+        let public = ed25519::Pair::from_string(PHRASE, None).unwrap().public();
+        let name = write_keyfile(dir.path(), &public.0);
+
+        let err = keystore_pair(dir.path(), &name, None).unwrap_err();
+
+        assert!(err.to_string().contains("Unsupported key type"));
+    }
+}
@@ -0,0 +1,83 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A minimal record of where a build came from, for `build --emit-lock-summary`.
+//!
+//! Unlike the `source` section of the metadata, it only records the toolchain, the ink! version
+//! and the code hash. It is written to its own file, so the metadata and its hash are the same
+//! with and without it.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use semver::Version;
+use serde_json::Value;
+
+use super::{dockerfile::active_toolchain, metadata::blake2_hash};
+use crate::crate_metadata::CrateMetadata;
+
+/// Writes the lock summary of the built Wasm `dest_wasm`, returning its path.
+pub(crate) fn write_lock_summary(
+    crate_metadata: &CrateMetadata,
+    dest_wasm: &Path,
+) -> Result<PathBuf> {
+    let package_dir = crate_metadata
+        .manifest_path
+        .directory()
+        .unwrap_or_else(|| Path::new(""));
+    let toolchain = active_toolchain(package_dir)?;
+    let code = fs::read(dest_wasm).context(format!("Failed to read {}", dest_wasm.display()))?;
+    let summary = lock_summary(&toolchain, crate_metadata.ink_version.as_ref(), &code);
+
+    let dest_lock_summary = &crate_metadata.dest_lock_summary;
+    fs::write(dest_lock_summary, serde_json::to_string_pretty(&summary)?)?;
+    Ok(dest_lock_summary.clone())
+}
+
+/// Returns the lock summary of the `code` built with `toolchain` against `ink_version`, which is
+/// `null` for a contract in another language.
+fn lock_summary(toolchain: &str, ink_version: Option<&Version>, code: &[u8]) -> Value {
+    serde_json::json!({
+        "toolchain": toolchain,
+        "ink_version": ink_version.map(ToString::to_string),
+        "code_hash": blake2_hash(code),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lock_summary_records_toolchain_ink_version_and_code_hash() {
+        let code = b"\0asm\x01\0\0\0";
+        let ink_version = Version::parse("3.0.0-rc2").unwrap();
+
+        let summary = lock_summary("nightly-2020-10-16", Some(&ink_version), code);
+
+        let expected = serde_json::json!({
+            "toolchain": "nightly-2020-10-16",
+            "ink_version": "3.0.0-rc2",
+            "code_hash": blake2_hash(code),
+        });
+        assert_eq!(summary, expected);
+        assert_eq!(summary.as_object().unwrap().len(), 3);
+        assert!(summary["code_hash"].as_str().unwrap().starts_with("0x"));
+    }
+}
@@ -0,0 +1,139 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Guards against submitting extrinsics to a production chain by accident.
+//!
+//! The chain is identified by its genesis hash, production chains require a confirmation.
+
+use std::io::{self, BufRead, Write};
+
+use anyhow::Result;
+use colored::Colorize;
+use jsonrpsee::common::{to_value, Params};
+use sp_core::H256;
+
+/// The genesis hashes of the known production chains.
+const MAINNETS: &[(&str, &str)] = &[
+    (
+        "Polkadot",
+        "91b171bb158e2d3848fa23a9f1c25182fb8e20313b2c1eb49219da7a70ce90c3",
+    ),
+    (
+        "Kusama",
+        "b0a8d493285c2df73290dfb7e61f870f17b41801197a149ca93654499ea3dafe",
+    ),
+];
+
+/// Returns the name of the production chain with the `genesis` hash, if it is a known one.
+fn mainnet_name(genesis: &H256) -> Option<&'static str> {
+    let genesis = format!("{:x}", genesis);
+    MAINNETS
+        .iter()
+        .find(|(_, hash)| *hash == genesis)
+        .map(|(name, _)| *name)
+}
+
+/// Asks for a confirmation before submitting extrinsics if the node at `url` runs a production
+/// chain.
+///
+/// With `yes` no confirmation is needed. Without a terminal to ask on, `yes` is required.
+pub(crate) fn confirm_mainnet(url: &url::Url, yes: bool) -> Result<()> {
+    let genesis = async_std::task::block_on(async move {
+        let rpc = jsonrpsee::ws_client(url.as_str()).await?;
+        let params = Params::Array(vec![to_value(0u32)?]);
+        let genesis: H256 = rpc.request("chain_getBlockHash", params).await?;
+        Ok::<_, anyhow::Error>(genesis)
+    })?;
+    match mainnet_name(&genesis) {
+        Some(chain) => confirm(
+            chain,
+            url,
+            yes,
+            atty::is(atty::Stream::Stdin),
+            &mut io::stdin().lock(),
+        ),
+        None => Ok(()),
+    }
+}
+
+/// Reads the confirmation to submit to `chain` from `input`.
+fn confirm<R: BufRead>(
+    chain: &str,
+    url: &url::Url,
+    yes: bool,
+    is_tty: bool,
+    input: &mut R,
+) -> Result<()> {
+    if yes {
+        return Ok(());
+    }
+    if !is_tty {
+        anyhow::bail!(
+            "The node at {} runs {}, which cannot be confirmed without a terminal. Pass --yes to \
+             submit anyway",
+            url,
+            chain
+        )
+    }
+    print!(
+        "{} {} Type `yes` to continue: ",
+        "warning:".yellow().bold(),
+        format!("The node at {} runs the production chain {}.", url, chain).bold()
+    );
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+    if answer.trim() != "yes" {
+        anyhow::bail!("Aborted, nothing was submitted to {}", chain)
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{io::Cursor, str::FromStr};
+
+    #[test]
+    fn mainnets_are_recognized_by_genesis_hash() {
+        let kusama =
+            H256::from_str("b0a8d493285c2df73290dfb7e61f870f17b41801197a149ca93654499ea3dafe")
+                .unwrap();
+
+        assert_eq!(mainnet_name(&kusama), Some("Kusama"));
+        assert_eq!(mainnet_name(&H256::zero()), None);
+    }
+
+    #[test]
+    fn submitting_to_a_mainnet_needs_a_confirmation() {
+        let url = url::Url::parse("wss://rpc.polkadot.io").unwrap();
+        let answer = |is_tty, input: &str| {
+            confirm(
+                "Polkadot",
+                &url,
+                false,
+                is_tty,
+                &mut Cursor::new(input.as_bytes()),
+            )
+        };
+
+        assert!(answer(true, "yes\n").is_ok());
+        assert!(answer(true, "y\n").is_err());
+        assert!(answer(true, "").is_err());
+        assert!(answer(false, "yes\n").is_err());
+        assert!(confirm("Polkadot", &url, true, false, &mut Cursor::new(Vec::new())).is_ok());
+    }
+}
@@ -14,32 +14,40 @@
 // You should have received a copy of the GNU General Public License
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
+use super::progress::BuildStep;
 use crate::{
-    crate_metadata::CrateMetadata,
+    crate_metadata::{ContractLanguage, CrateMetadata},
     util,
     workspace::{ManifestPath, Workspace},
-    BuildArtifacts, BuildResult, OptimizationResult, UnstableFlags, Verbosity,
+    BuildArtifacts, BuildOptions, BuildResult, BuildTimings, MetadataCompat, MetadataFormat,
+    OptimizationResult, Verbosity,
 };
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use blake2::digest::{Update as _, VariableOutput as _};
 use colored::Colorize;
 use contract_metadata::{
     CodeHash, Compiler, Contract, ContractMetadata, Language, Source, SourceCompiler,
-    SourceLanguage, SourceWasm, User,
+    SourceLanguage, SourceWasm, User, WasmCompression,
 };
 use semver::Version;
-use std::{fs, path::PathBuf};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 use url::Url;
 
-const METADATA_FILE: &str = "metadata.json";
-
 /// Executes the metadata generation process
 struct GenerateMetadataCommand {
     crate_metadata: CrateMetadata,
     verbosity: Option<Verbosity>,
     build_artifact: BuildArtifacts,
-    unstable_options: UnstableFlags,
+    metadata_compat: MetadataCompat,
+    ignore_metadata_errors: bool,
+    build_options: BuildOptions,
 }
 
 /// Result of generating the extended contract project metadata
@@ -53,83 +61,164 @@ struct ExtendedMetadataResult {
 
 impl GenerateMetadataCommand {
     pub fn exec(&self) -> Result<BuildResult> {
-        util::assert_channel()?;
+        if self.crate_metadata.language == ContractLanguage::Ink {
+            util::assert_channel()?;
+        }
 
         let target_directory = self.crate_metadata.target_directory.clone();
-        let out_path_metadata = target_directory.join(METADATA_FILE);
+        let metadata_format = self.build_options.metadata_format;
+        let out_path_metadata = match metadata_format {
+            MetadataFormat::Json | MetadataFormat::CompactJson => {
+                self.crate_metadata.dest_metadata.clone()
+            }
+            MetadataFormat::Yaml => self.crate_metadata.dest_metadata.with_extension("yaml"),
+        };
 
         let fname_bundle = format!("{}.contract", self.crate_metadata.package_name);
         let out_path_bundle = target_directory.join(fname_bundle);
 
         // build the extended contract project metadata
+        let mut timings = BuildTimings::default();
         let ExtendedMetadataResult {
             dest_wasm,
             source,
             contract,
             user,
             optimization_result,
-        } = self.extended_metadata()?;
+        } = self.extended_metadata(&mut timings)?;
 
+        let metadata_start = Instant::now();
+        let mut bundle_duration = Duration::default();
         let generate_metadata = |manifest_path: &ManifestPath| -> Result<()> {
-            let mut current_progress = 4;
-            println!(
-                " {} {}",
-                format!("[{}/{}]", current_progress, self.build_artifact.steps()).bold(),
-                "Generating metadata".bright_green().bold()
-            );
-            let target_dir_arg = format!("--target-dir={}", target_directory.to_string_lossy());
-            let stdout = util::invoke_cargo(
-                "run",
-                &[
-                    "--package",
-                    "metadata-gen",
-                    &manifest_path.cargo_arg(),
-                    &target_dir_arg,
-                    "--release",
-                ],
-                self.crate_metadata.manifest_path.directory(),
+            // the metadata and the bundle are the last steps
+            let mut current_progress = self.steps() - 1;
+            let step = BuildStep::start(
                 self.verbosity,
-            )?;
-
-            let ink_meta: serde_json::Map<String, serde_json::Value> =
-                serde_json::from_slice(&stdout)?;
-            let metadata = ContractMetadata::new(source, contract, user, ink_meta);
+                current_progress,
+                self.steps(),
+                "Generating metadata",
+            );
+            let mut ink_meta: serde_json::Map<String, serde_json::Value> =
+                match self.crate_metadata.language {
+                    ContractLanguage::Ink => {
+                        let target_dir_arg =
+                            format!("--target-dir={}", target_directory.to_string_lossy());
+                        let mut args = vec![
+                            "--package".to_string(),
+                            "metadata-gen".to_string(),
+                            manifest_path.cargo_arg(),
+                            target_dir_arg,
+                            "--release".to_string(),
+                        ];
+                        // the contract is the `contract` dependency of `metadata-gen`
+                        args.extend(super::build::features_arg(
+                            &self.build_options.features,
+                            "contract/",
+                        ));
+                        let stdout = util::invoke_cargo(
+                            "run",
+                            &args,
+                            self.crate_metadata.manifest_path.directory(),
+                            self.verbosity,
+                            &[],
+                        )?;
+                        serde_json::from_slice(&stdout)?
+                    }
+                    // solang already generated the abi when compiling
+                    ContractLanguage::Solidity { .. } => super::solang::abi(&self.crate_metadata)?,
+                };
+            if let Some(weights) = self.build_options.weights.as_ref() {
+                super::weights::merge_weights(&mut ink_meta, weights)?;
+            }
+            let mut metadata = ContractMetadata::new(source, contract, user, ink_meta);
+            if self.build_options.compress_wasm {
+                metadata.set_wasm_compression(WasmCompression::Deflate);
+            }
             {
                 let mut metadata = metadata.clone();
                 metadata.remove_source_wasm_attribute();
-                let contents = serde_json::to_string_pretty(&metadata)?;
+                let contents = match self.metadata_compat {
+                    MetadataCompat::Current => serialize_metadata(&metadata, metadata_format)?,
+                    MetadataCompat::Legacy => {
+                        serialize_metadata(&metadata.legacy(), metadata_format)?
+                    }
+                };
                 fs::write(&out_path_metadata, contents)?;
                 current_progress += 1;
             }
+            drop(step);
 
             if self.build_artifact == BuildArtifacts::All {
-                println!(
-                    " {} {}",
-                    format!("[{}/{}]", current_progress, self.build_artifact.steps()).bold(),
-                    "Generating bundle".bright_green().bold()
+                let _step = BuildStep::start(
+                    self.verbosity,
+                    current_progress,
+                    self.steps(),
+                    "Generating bundle",
                 );
-                let contents = serde_json::to_string(&metadata)?;
+                let bundle_start = Instant::now();
+                let contents = match self.metadata_compat {
+                    MetadataCompat::Current => serde_json::to_string(&metadata)?,
+                    MetadataCompat::Legacy => serde_json::to_string(&metadata.legacy())?,
+                };
                 fs::write(&out_path_bundle, contents)?;
+                bundle_duration = bundle_start.elapsed();
             }
 
             Ok(())
         };
 
-        if self.unstable_options.original_manifest {
-            generate_metadata(&self.crate_metadata.manifest_path)?;
+        let metadata_result = if self.build_options.unstable_flags.original_manifest
+            || self.crate_metadata.language != ContractLanguage::Ink
+        {
+            generate_metadata(&self.crate_metadata.manifest_path)
         } else {
             Workspace::new(
                 &self.crate_metadata.cargo_meta,
                 &self.crate_metadata.root_package.id,
-            )?
-            .with_root_package_manifest(|manifest| {
-                manifest
-                    .with_added_crate_type("rlib")?
-                    .with_profile_release_lto(false)?;
-                Ok(())
-            })?
-            .with_metadata_gen_package()?
-            .using_temp(generate_metadata)?;
+            )
+            .and_then(|mut workspace| {
+                workspace
+                    .with_root_package_manifest(|manifest| {
+                        manifest
+                            .with_added_crate_type("rlib")?
+                            .with_profile_release_lto(false)?;
+                        Ok(())
+                    })?
+                    .with_metadata_gen_package()?
+                    .using_temp(generate_metadata)
+            })
+        };
+
+        timings.record(
+            "Generating metadata",
+            metadata_start.elapsed() - bundle_duration,
+        );
+        if self.build_artifact == BuildArtifacts::All {
+            timings.record("Generating bundle", bundle_duration);
+        }
+
+        if let Err(err) = metadata_result {
+            discard_failed_metadata(
+                err,
+                self.ignore_metadata_errors,
+                self.verbosity,
+                &[&out_path_metadata, &out_path_bundle],
+            )?;
+            return Ok(BuildResult {
+                dest_metadata: None,
+                dest_wasm,
+                dest_wat: None,
+                dest_bundle: None,
+                dest_mime: None,
+                dest_slim_metadata: None,
+                dest_lock_summary: None,
+                optimization_result,
+                size_report: None,
+                target_directory,
+                build_artifact: self.build_artifact,
+                build_mode: self.build_options.build_mode,
+                timings,
+            });
         }
 
         let dest_bundle = if self.build_artifact == BuildArtifacts::All {
@@ -140,20 +229,31 @@ impl GenerateMetadataCommand {
         Ok(BuildResult {
             dest_metadata: Some(out_path_metadata),
             dest_wasm,
+            dest_wat: None,
             dest_bundle,
+            dest_mime: None,
+            dest_slim_metadata: None,
+            dest_lock_summary: None,
             optimization_result,
+            size_report: None,
             target_directory,
             build_artifact: self.build_artifact,
+            build_mode: self.build_options.build_mode,
+            timings,
         })
     }
 
     /// Generate the extended contract project metadata
-    fn extended_metadata(&self) -> Result<ExtendedMetadataResult> {
+    fn extended_metadata(&self, timings: &mut BuildTimings) -> Result<ExtendedMetadataResult> {
         let contract_package = &self.crate_metadata.root_package;
-        let ink_version = &self.crate_metadata.ink_version;
-        let rust_version = Version::parse(&rustc_version::version()?.to_string())?;
-        let contract_name = contract_package.name.clone();
-        let contract_version = Version::parse(&contract_package.version.to_string())?;
+        let contract_name = match self.build_options.contract_name.as_ref() {
+            Some(name) => name.clone(),
+            None => contract_package.name.clone(),
+        };
+        let contract_version = match self.build_options.contract_version.as_ref() {
+            Some(version) => version.clone(),
+            None => Version::parse(&contract_package.version.to_string())?,
+        };
         let contract_authors = contract_package.authors.clone();
         // optional
         let description = contract_package.description.clone();
@@ -164,11 +264,17 @@ impl GenerateMetadataCommand {
             .map(|repo| Url::parse(&repo))
             .transpose()?;
         let homepage = self.crate_metadata.homepage.clone();
+        let release_notes = self.crate_metadata.release_notes.clone();
         let license = contract_package.license.clone();
-        let (dest_wasm, hash, optimization_result) = self.wasm_hash()?;
+        let mut required_features = self.crate_metadata.required_features.clone();
+        for feature in &self.build_options.required_features {
+            if !required_features.contains(feature) {
+                required_features.push(feature.clone());
+            }
+        }
+        let (dest_wasm, hash, optimization_result) = self.wasm_hash(timings)?;
         let source = {
-            let lang = SourceLanguage::new(Language::Ink, ink_version.clone());
-            let compiler = SourceCompiler::new(Compiler::RustC, rust_version);
+            let (lang, compiler) = self.source_language()?;
             let maybe_wasm = if self.build_artifact == BuildArtifacts::All {
                 let wasm = fs::read(&self.crate_metadata.dest_wasm)?;
                 // The Wasm which we read must have the same hash as `source.hash`
@@ -180,7 +286,17 @@ impl GenerateMetadataCommand {
             } else {
                 None
             };
-            Source::new(maybe_wasm, hash, lang, compiler)
+            let mut source =
+                Source::with_build_info(maybe_wasm, hash, lang, compiler, build_info());
+            if let ContractLanguage::Ink = self.crate_metadata.language {
+                source.set_toolchain(rustc_version::version_meta()?.short_version_string);
+            }
+            if !self.build_options.reproducible {
+                source.set_build_timestamp(build_timestamp(
+                    std::env::var("SOURCE_DATE_EPOCH").ok().as_deref(),
+                )?);
+            }
+            source
         };
 
         // Required contract fields
@@ -206,10 +322,18 @@ impl GenerateMetadataCommand {
             builder.homepage(homepage);
         }
 
+        if let Some(release_notes) = release_notes {
+            builder.release_notes(release_notes);
+        }
+
         if let Some(license) = license {
             builder.license(license);
         }
 
+        if !required_features.is_empty() {
+            builder.required_features(required_features);
+        }
+
         let contract = builder
             .build()
             .map_err(|err| anyhow::anyhow!("Invalid contract metadata builder state: {}", err))?;
@@ -222,31 +346,159 @@ impl GenerateMetadataCommand {
             source,
             contract,
             user,
-            optimization_result: Some(optimization_result),
+            optimization_result,
         })
     }
 
+    /// Returns the language of the contract and the compiler it is compiled with.
+    fn source_language(&self) -> Result<(SourceLanguage, SourceCompiler)> {
+        match &self.crate_metadata.language {
+            ContractLanguage::Ink => {
+                let ink_version = self
+                    .crate_metadata
+                    .ink_version
+                    .clone()
+                    .expect("the ink! version of an ink! contract is known");
+                let rust_version = Version::parse(&rustc_version::version()?.to_string())?;
+                Ok((
+                    SourceLanguage::new(Language::Ink, ink_version),
+                    SourceCompiler::new(Compiler::RustC, rust_version),
+                ))
+            }
+            ContractLanguage::Solidity { source } => Ok((
+                SourceLanguage::new(Language::Solidity, super::solang::solidity_version(source)?),
+                SourceCompiler::new(Compiler::Solang, super::solang::solang_version()?),
+            )),
+        }
+    }
+
     /// Compile the contract and then hash the resulting Wasm.
     ///
-    /// Return a tuple of `(dest_wasm, hash, optimization_result)`.
-    fn wasm_hash(&self) -> Result<(PathBuf, CodeHash, OptimizationResult)> {
+    /// Return a tuple of `(dest_wasm, hash, optimization_result)`, a debug build is not
+    /// optimized.
+    fn wasm_hash(
+        &self,
+        timings: &mut BuildTimings,
+    ) -> Result<(PathBuf, CodeHash, Option<OptimizationResult>)> {
+        let fingerprint = if self.build_options.reuse_wasm {
+            super::fingerprint::code_fingerprint(&self.crate_metadata, &self.build_settings()?)?
+        } else {
+            None
+        };
+        if let Some(fingerprint) = fingerprint.as_ref() {
+            if let Some(optimization_res) =
+                super::fingerprint::reusable_build(&self.crate_metadata, fingerprint)
+            {
+                BuildStep::start(
+                    self.verbosity,
+                    1,
+                    self.steps(),
+                    "Reusing the wasm file of the last build, the code is unchanged",
+                );
+                let wasm = fs::read(&self.crate_metadata.dest_wasm)?;
+                return Ok((
+                    self.crate_metadata.dest_wasm.clone(),
+                    blake2_hash(wasm.as_slice()),
+                    Some(optimization_res),
+                ));
+            }
+        }
+
         let (maybe_dest_wasm, maybe_optimization_res) = super::build::execute_with_crate_metadata(
             &self.crate_metadata,
             self.verbosity,
             true, // for the hash we always use the optimized version of the contract
             self.build_artifact,
-            self.unstable_options.clone(),
+            self.build_options.clone(),
+            timings,
         )?;
 
         let wasm = fs::read(&self.crate_metadata.dest_wasm)?;
         let dest_wasm = maybe_dest_wasm.expect("dest wasm must exist");
-        let optimization_res = maybe_optimization_res.expect("optimization result must exist");
-        Ok((dest_wasm, blake2_hash(wasm.as_slice()), optimization_res))
+        if let (Some(fingerprint), Some(optimization_res)) =
+            (fingerprint.as_ref(), maybe_optimization_res.as_ref())
+        {
+            super::fingerprint::record_build(&self.crate_metadata, fingerprint, optimization_res)?;
+        }
+        Ok((
+            dest_wasm,
+            blake2_hash(wasm.as_slice()),
+            maybe_optimization_res,
+        ))
+    }
+
+    /// The number of steps of the build.
+    fn steps(&self) -> usize {
+        self.build_artifact.steps(self.build_options.build_mode)
+    }
+
+    /// The settings of the build which affect the Wasm, a change of them requires a full build.
+    fn build_settings(&self) -> Result<String> {
+        Ok(format!(
+            "rustc {}, cargo-contract {}, shared memory {}, debug symbols {}, original manifest {}, \
+             max relative size increase {}, optimizer {:?}, profile {}, reproducible {}, features {:?}",
+            rustc_version::version()?,
+            env!("CARGO_PKG_VERSION"),
+            self.build_options.shared_memory,
+            self.build_options.keep_debug_symbols,
+            self.build_options.unstable_flags.original_manifest,
+            self.build_options.max_relative_size_increase,
+            self.build_options.optimizer,
+            self.build_options.build_mode.profile(),
+            self.build_options.reproducible,
+            self.build_options.features
+        ))
+    }
+}
+
+/// Serializes the metadata in the given format.
+fn serialize_metadata<T: Serialize>(metadata: &T, format: MetadataFormat) -> Result<String> {
+    match format {
+        MetadataFormat::Json => Ok(serde_json::to_string_pretty(metadata)?),
+        MetadataFormat::CompactJson => Ok(serde_json::to_string(metadata)?),
+        MetadataFormat::Yaml => Ok(serde_yaml::to_string(metadata)?),
     }
 }
 
+/// Reads a metadata file written in either format, YAML if its extension is `yaml`.
+pub(crate) fn read_metadata(path: &Path) -> Result<Value> {
+    let contents = fs::read(path).context(format!("Failed to read {}", path.display()))?;
+    let metadata = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("yaml") => serde_yaml::from_slice(&contents).map_err(anyhow::Error::from),
+        _ => serde_json::from_slice(&contents).map_err(anyhow::Error::from),
+    };
+    metadata.context(format!("Failed to parse {}", path.display()))
+}
+
+/// Information about the environment the contract was built with, recorded in `source.build_info`.
+pub(crate) fn build_info() -> Map<String, Value> {
+    let mut build_info = Map::new();
+    build_info.insert(
+        "binaryen_crate_version".into(),
+        super::build::binaryen_crate_version().to_string().into(),
+    );
+    build_info.insert("target".into(), super::build::TARGET.into());
+    build_info
+}
+
+/// The time of the build in ISO-8601, the `SOURCE_DATE_EPOCH` if given to keep the metadata
+/// reproducible.
+fn build_timestamp(source_date_epoch: Option<&str>) -> Result<String> {
+    let time = match source_date_epoch {
+        Some(epoch) => {
+            let seconds = epoch.trim().parse().context(format!(
+                "SOURCE_DATE_EPOCH must be the seconds since the Unix epoch, not '{}'",
+                epoch
+            ))?;
+            std::time::UNIX_EPOCH + Duration::from_secs(seconds)
+        }
+        None => std::time::SystemTime::now(),
+    };
+    Ok(humantime::format_rfc3339_seconds(time).to_string())
+}
+
 /// Returns the blake2 hash of the submitted slice.
-fn blake2_hash(code: &[u8]) -> CodeHash {
+pub(crate) fn blake2_hash(code: &[u8]) -> CodeHash {
     let mut output = [0u8; 32];
     let mut blake2 = blake2::VarBlake2b::new_keyed(&[], 32);
     blake2.update(code);
@@ -261,26 +513,108 @@ pub(crate) fn execute(
     manifest_path: &ManifestPath,
     verbosity: Option<Verbosity>,
     build_artifact: BuildArtifacts,
-    unstable_options: UnstableFlags,
+    metadata_compat: MetadataCompat,
+    ignore_metadata_errors: bool,
+    build_options: BuildOptions,
 ) -> Result<BuildResult> {
     let crate_metadata = CrateMetadata::collect(manifest_path)?;
     let res = GenerateMetadataCommand {
         crate_metadata,
         verbosity,
         build_artifact,
-        unstable_options,
+        metadata_compat,
+        ignore_metadata_errors,
+        build_options,
     }
     .exec()?;
     Ok(res)
 }
 
-#[cfg(feature = "test-ci-only")]
+/// Removes the metadata and the bundle a failed generation may have written, so no partial or
+/// stale outputs are left. Returns the error `err` unless `--ignore-metadata-errors` is set.
+fn discard_failed_metadata(
+    err: anyhow::Error,
+    ignore_metadata_errors: bool,
+    verbosity: Option<Verbosity>,
+    outputs: &[&Path],
+) -> Result<()> {
+    for output in outputs {
+        if output.exists() {
+            fs::remove_file(output).context(format!(
+                "Failed to remove {} after the metadata generation failed: {:?}",
+                output.display(),
+                err
+            ))?;
+        }
+    }
+    if !ignore_metadata_errors {
+        return Err(err);
+    }
+    util::maybe_println(
+        verbosity,
+        format!(
+            "{} {}",
+            "warning:".yellow().bold(),
+            format!(
+                "metadata generation failed, skipped the metadata and the bundle: {:?}",
+                err
+            )
+            .bold()
+        ),
+    );
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
+    use super::{build_timestamp, discard_failed_metadata};
+    use crate::util::tests::with_tmp_dir;
+    use std::fs;
+
+    #[test]
+    fn failed_metadata_generation_leaves_no_outputs() {
+        with_tmp_dir(|path| {
+            let metadata = path.join("flipper.json");
+            let bundle = path.join("flipper.contract");
+            let outputs = [metadata.as_path(), bundle.as_path()];
+
+            // the metadata was written before generating the bundle failed
+            fs::write(&metadata, "{}")?;
+            let err = discard_failed_metadata(anyhow::anyhow!("bundle"), false, None, &outputs);
+            assert_eq!(err.unwrap_err().to_string(), "bundle");
+            assert!(!metadata.exists());
+
+            // the outputs of the last build are stale
+            fs::write(&metadata, "{}")?;
+            fs::write(&bundle, "{}")?;
+            discard_failed_metadata(anyhow::anyhow!("metadata"), true, None, &outputs)?;
+            assert!(!metadata.exists());
+            assert!(!bundle.exists());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn build_timestamp_honors_source_date_epoch() {
+        assert_eq!(
+            build_timestamp(Some("1592740800")).unwrap(),
+            "2020-06-21T12:00:00Z"
+        );
+        assert!(build_timestamp(Some("yesterday"))
+            .unwrap_err()
+            .to_string()
+            .starts_with("SOURCE_DATE_EPOCH must be the seconds since the Unix epoch"));
+        assert!(build_timestamp(None).unwrap().ends_with('Z'));
+    }
+}
+
+#[cfg(feature = "test-ci-only")]
+#[cfg(test)]
+mod tests_ci_only {
     use crate::cmd::metadata::blake2_hash;
     use crate::{
         cmd, crate_metadata::CrateMetadata, util::tests::with_tmp_dir, BuildArtifacts,
-        ManifestPath, UnstableFlags,
+        BuildOptions, ManifestPath, MetadataCompat,
     };
     use contract_metadata::*;
     use serde_json::{Map, Value};
@@ -308,12 +642,7 @@ mod tests {
                 .ok_or(anyhow::anyhow!("package section should be a table"))
         }
 
-        /// Add a key/value to the `[package.metadata.contract.user]` section
-        fn add_user_metadata_value(
-            &mut self,
-            key: &'static str,
-            value: value::Value,
-        ) -> anyhow::Result<()> {
+        fn contract_metadata_mut(&mut self) -> anyhow::Result<&mut value::Table> {
             self.package_mut()?
                 .entry("metadata")
                 .or_insert(value::Value::Table(Default::default()))
@@ -324,7 +653,26 @@ mod tests {
                 .as_table_mut()
                 .ok_or(anyhow::anyhow!(
                     "metadata.contract section should be a table"
-                ))?
+                ))
+        }
+
+        /// Add a key/value to the `[package.metadata.contract]` section
+        fn add_contract_metadata_value(
+            &mut self,
+            key: &'static str,
+            value: value::Value,
+        ) -> anyhow::Result<()> {
+            self.contract_metadata_mut()?.insert(key.into(), value);
+            Ok(())
+        }
+
+        /// Add a key/value to the `[package.metadata.contract.user]` section
+        fn add_user_metadata_value(
+            &mut self,
+            key: &'static str,
+            value: value::Value,
+        ) -> anyhow::Result<()> {
+            self.contract_metadata_mut()?
                 .entry("user")
                 .or_insert(value::Value::Table(Default::default()))
                 .as_table_mut()
@@ -354,7 +702,8 @@ mod tests {
     fn generate_metadata() {
         env_logger::try_init().ok();
         with_tmp_dir(|path| {
-            cmd::new::execute("new_project", Some(path)).expect("new project creation failed");
+            cmd::new::execute("new_project", Some(path), Default::default())
+                .expect("new project creation failed");
             let working_dir = path.join("new_project");
             let manifest_path = ManifestPath::new(working_dir.join("Cargo.toml"))?;
 
@@ -365,6 +714,8 @@ mod tests {
             test_manifest.add_package_value("repository", "http://repository.com".into())?;
             test_manifest.add_package_value("homepage", "http://homepage.com".into())?;
             test_manifest.add_package_value("license", "Apache-2.0".into())?;
+            test_manifest
+                .add_contract_metadata_value("release-notes", "http://releases.com/0.1.0".into())?;
             test_manifest
                 .add_user_metadata_value("some-user-provided-field", "and-its-value".into())?;
             test_manifest.add_user_metadata_value(
@@ -374,14 +725,15 @@ mod tests {
             test_manifest.write()?;
 
             let crate_metadata = CrateMetadata::collect(&test_manifest.manifest_path)?;
-            let dest_bundle = cmd::metadata::execute(
+            let result = cmd::metadata::execute(
                 &test_manifest.manifest_path,
                 None,
                 BuildArtifacts::All,
-                UnstableFlags::default(),
-            )?
-            .dest_bundle
-            .expect("bundle file not found");
+                MetadataCompat::Current,
+                false,
+                BuildOptions::default(),
+            )?;
+            let dest_bundle = result.dest_bundle.expect("bundle file not found");
             let metadata_json: Map<String, Value> =
                 serde_json::from_slice(&fs::read(&dest_bundle)?)?;
 
@@ -395,6 +747,14 @@ mod tests {
             let language = source.get("language").expect("source.language not found");
             let compiler = source.get("compiler").expect("source.compiler not found");
             let wasm = source.get("wasm").expect("source.wasm not found");
+            // only the bundle embeds the Wasm, the metadata file is kept small
+            let dest_metadata = result.dest_metadata.expect("metadata file not found");
+            let metadata_file: Map<String, Value> =
+                serde_json::from_slice(&fs::read(&dest_metadata)?)?;
+            assert!(metadata_file["source"].get("wasm").is_none());
+            let build_info = source
+                .get("build_info")
+                .expect("source.build_info not found");
 
             let contract = metadata_json.get("contract").expect("contract not found");
             let name = contract.get("name").expect("contract.name not found");
@@ -419,6 +779,9 @@ mod tests {
             let homepage = contract
                 .get("homepage")
                 .expect("contract.homepage not found");
+            let release_notes = contract
+                .get("release_notes")
+                .expect("contract.release_notes not found");
             let license = contract.get("license").expect("contract.license not found");
 
             let user = metadata_json.get("user").expect("user section not found");
@@ -429,7 +792,7 @@ mod tests {
             let expected_wasm = build_byte_str(&fs_wasm);
 
             let expected_language =
-                SourceLanguage::new(Language::Ink, crate_metadata.ink_version).to_string();
+                SourceLanguage::new(Language::Ink, crate_metadata.ink_version.unwrap()).to_string();
             let expected_rustc_version =
                 semver::Version::parse(&rustc_version::version()?.to_string())?;
             let expected_compiler =
@@ -448,6 +811,16 @@ mod tests {
             assert_eq!(expected_wasm, wasm.as_str().unwrap());
             assert_eq!(expected_language, language.as_str().unwrap());
             assert_eq!(expected_compiler, compiler.as_str().unwrap());
+            assert_eq!(
+                rustc_version::version_meta()?.short_version_string,
+                source["toolchain"].as_str().unwrap()
+            );
+            assert!(source["build_timestamp"].as_str().is_some());
+            assert_eq!(
+                cmd::build::BINARYEN_CRATE_VERSION,
+                build_info["binaryen_crate_version"].as_str().unwrap()
+            );
+            assert_eq!(cmd::build::TARGET, build_info["target"].as_str().unwrap());
             assert_eq!(crate_metadata.package_name, name.as_str().unwrap());
             assert_eq!(
                 crate_metadata.root_package.version.to_string(),
@@ -458,6 +831,7 @@ mod tests {
             assert_eq!("http://documentation.com/", documentation.as_str().unwrap());
             assert_eq!("http://repository.com/", repository.as_str().unwrap());
             assert_eq!("http://homepage.com/", homepage.as_str().unwrap());
+            assert_eq!("http://releases.com/0.1.0", release_notes.as_str().unwrap());
             assert_eq!("Apache-2.0", license.as_str().unwrap());
             assert_eq!(&expected_user_metadata, user.as_object().unwrap());
 
@@ -465,6 +839,83 @@ mod tests {
         })
     }
 
+    #[test]
+    fn generate_metadata_with_contract_identity_overrides() {
+        env_logger::try_init().ok();
+        with_tmp_dir(|path| {
+            cmd::new::execute("new_project", Some(path), Default::default())
+                .expect("new project creation failed");
+            let manifest_path = ManifestPath::new(path.join("new_project").join("Cargo.toml"))?;
+
+            let dest_bundle = cmd::metadata::execute(
+                &manifest_path,
+                None,
+                BuildArtifacts::All,
+                MetadataCompat::Current,
+                false,
+                BuildOptions {
+                    contract_name: Some("published_name".into()),
+                    contract_version: Some(semver::Version::parse("2.1.0-beta.1")?),
+                    ..Default::default()
+                },
+            )?
+            .dest_bundle
+            .expect("bundle file not found");
+            let metadata_json: Map<String, Value> =
+                serde_json::from_slice(&fs::read(&dest_bundle)?)?;
+
+            let contract = metadata_json.get("contract").expect("contract not found");
+            assert_eq!("published_name", contract["name"].as_str().unwrap());
+            assert_eq!("2.1.0-beta.1", contract["version"].as_str().unwrap());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn generate_metadata_with_required_features() {
+        env_logger::try_init().ok();
+        with_tmp_dir(|path| {
+            cmd::new::execute("new_project", Some(path), Default::default())
+                .expect("new project creation failed");
+            let working_dir = path.join("new_project");
+            let manifest_path = ManifestPath::new(working_dir.join("Cargo.toml"))?;
+
+            let mut test_manifest = TestContractManifest::new(manifest_path)?;
+            test_manifest.add_contract_metadata_value(
+                "required-features",
+                vec!["pallet:Contracts".to_string()].into(),
+            )?;
+            test_manifest.write()?;
+
+            let crate_metadata = CrateMetadata::collect(&test_manifest.manifest_path)?;
+            let dest_bundle = cmd::metadata::execute(
+                &test_manifest.manifest_path,
+                None,
+                BuildArtifacts::All,
+                MetadataCompat::Current,
+                false,
+                BuildOptions {
+                    required_features: vec!["pallet:Contracts".into(), "rpc:contracts_call".into()],
+                    ..Default::default()
+                },
+            )?
+            .dest_bundle
+            .expect("bundle file not found");
+            let metadata_json: Map<String, Value> =
+                serde_json::from_slice(&fs::read(&dest_bundle)?)?;
+
+            assert_eq!(crate_metadata.required_features, vec!["pallet:Contracts"]);
+            let contract = metadata_json.get("contract").expect("contract not found");
+            assert_eq!(
+                contract["required_features"],
+                serde_json::json!(["pallet:Contracts", "rpc:contracts_call"])
+            );
+
+            Ok(())
+        })
+    }
+
     fn build_byte_str(bytes: &[u8]) -> String {
         let mut str = String::new();
         write!(str, "0x").expect("failed writing to string");
@@ -14,14 +14,114 @@
 // You should have received a copy of the GNU General Public License
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
+#[cfg(feature = "extrinsics")]
+mod account;
+mod affected;
+mod analyze;
+#[cfg(feature = "extrinsics")]
+mod balance;
+mod bindings;
 pub mod build;
 #[cfg(feature = "extrinsics")]
+mod bundle;
+#[cfg(feature = "extrinsics")]
+mod call;
+#[cfg(feature = "extrinsics")]
+mod chain_metadata;
+mod check_metadata;
+mod convert;
+#[cfg(feature = "extrinsics")]
+mod cost;
+#[cfg(feature = "extrinsics")]
+mod decode;
+#[cfg(feature = "extrinsics")]
 mod deploy;
+mod deterministic;
+#[cfg(feature = "extrinsics")]
+mod diff_deployed;
+mod diff_storage;
+mod dockerfile;
+#[cfg(feature = "extrinsics")]
+mod dry_run;
+#[cfg(feature = "extrinsics")]
+mod events;
+mod explain;
+#[cfg(feature = "extrinsics")]
+mod export_state;
+mod fingerprint;
+#[cfg(feature = "extrinsics")]
+mod gas_policy;
+mod git;
 #[cfg(feature = "extrinsics")]
 mod instantiate;
+#[cfg(all(test, feature = "integration-tests"))]
+mod integration_tests;
+#[cfg(feature = "extrinsics")]
+mod keystore;
+mod lock_summary;
+#[cfg(feature = "extrinsics")]
+mod mainnet;
 pub mod metadata;
 pub mod new;
+mod optimizer;
+mod progress;
+mod publish;
+#[cfg(feature = "extrinsics")]
+mod required_features;
+#[cfg(feature = "extrinsics")]
+mod signer;
+#[cfg(feature = "extrinsics")]
+mod signing;
+mod size_report;
+mod solang;
+#[cfg(feature = "extrinsics")]
+mod storage;
+#[cfg(feature = "extrinsics")]
+mod submit;
+#[cfg(feature = "extrinsics")]
+mod suggest;
+mod test;
+#[cfg(feature = "extrinsics")]
+mod token;
+#[cfg(feature = "extrinsics")]
+mod transcode;
+#[cfg(feature = "extrinsics")]
+mod upgrade;
+#[cfg(feature = "extrinsics")]
+mod verify;
+mod verify_env;
+mod weights;
 
-pub(crate) use self::build::{BuildCommand, CheckCommand};
 #[cfg(feature = "extrinsics")]
-pub(crate) use self::{deploy::execute_deploy, instantiate::execute_instantiate};
+pub(crate) use self::{
+    account::AccountCommand,
+    call::CallCommand,
+    chain_metadata::cached_client,
+    decode::DecodeCommand,
+    deploy::DeployCommand,
+    diff_deployed::DiffDeployedCommand,
+    events::EventsCommand,
+    export_state::ExportStateCommand,
+    instantiate::InstantiateCommand,
+    keystore::{keyfile_pair, keystore_pair, prompt_password, WrongPassword},
+    mainnet::confirm_mainnet,
+    signer::{CryptoScheme, ExtrinsicSigner},
+    submit::SubmitCommand,
+    upgrade::UpgradeCommand,
+    verify::VerifyCommand,
+};
+pub(crate) use self::{
+    affected::workspace_contracts,
+    analyze::AnalyzeCommand,
+    bindings::GenerateBindingsCommand,
+    build::{BuildCommand, CheckCommand},
+    check_metadata::CheckMetadataCommand,
+    convert::ConvertCommand,
+    diff_storage::DiffStorageCommand,
+    dockerfile::GenerateDockerfileCommand,
+    optimizer::OptimizerConfig,
+    publish::PublishCommand,
+    size_report::SizeReport,
+    test::TestCommand,
+    weights::Weights,
+};
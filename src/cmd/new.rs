@@ -17,13 +17,84 @@
 use std::{
     env, fs,
     io::{Cursor, Read, Seek, SeekFrom, Write},
-    path::Path,
+    path::{Path, PathBuf},
+    str::FromStr,
 };
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use heck::CamelCase as _;
+use toml_edit::{Array, Document, Item, RawString, Table};
 
-pub(crate) fn execute<P>(name: &str, dir: Option<P>) -> Result<String>
+/// The crate attribute a contract needs to be compiled to Wasm without the standard library.
+const NO_STD_ATTRIBUTE: &str = "#![cfg_attr(not(feature = \"std\"), no_std)]";
+
+/// The starter contract of a new project, chosen by `--template`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Template {
+    /// A contract storing a `bool` which can be flipped.
+    Flipper,
+    /// A fungible token following ERC-20.
+    Erc20,
+    /// A non-fungible token following ERC-721.
+    Erc721,
+    /// A contract without storage and with a single message.
+    Empty,
+}
+
+impl Template {
+    const ALL: [Template; 4] = [
+        Template::Flipper,
+        Template::Erc20,
+        Template::Erc721,
+        Template::Empty,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Template::Flipper => "flipper",
+            Template::Erc20 => "erc20",
+            Template::Erc721 => "erc721",
+            Template::Empty => "empty",
+        }
+    }
+
+    /// Returns the `lib.rs` of the template, `None` for the one of the template archive.
+    fn lib(&self) -> Option<&'static str> {
+        match self {
+            Template::Flipper => None,
+            Template::Erc20 => Some(include_str!("../../templates/contracts/erc20.rs")),
+            Template::Erc721 => Some(include_str!("../../templates/contracts/erc721.rs")),
+            Template::Empty => Some(include_str!("../../templates/contracts/empty.rs")),
+        }
+    }
+}
+
+impl Default for Template {
+    fn default() -> Self {
+        Template::Flipper
+    }
+}
+
+impl FromStr for Template {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        Template::ALL
+            .iter()
+            .copied()
+            .find(|template| template.name() == input)
+            .ok_or_else(|| {
+                let names = Template::ALL.iter().map(Template::name).collect::<Vec<_>>();
+                anyhow::anyhow!(
+                    "Unknown template `{}`, the available templates are: {}",
+                    input,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+pub(crate) fn execute<P>(name: &str, dir: Option<P>, template: Template) -> Result<String>
 where
     P: AsRef<Path>,
 {
@@ -41,21 +112,18 @@ where
         fs::create_dir(&out_dir)?;
     }
 
-    let template = include_bytes!(concat!(env!("OUT_DIR"), "/template.zip"));
-    let mut cursor = Cursor::new(Vec::new());
-    cursor.write_all(template)?;
-    cursor.seek(SeekFrom::Start(0))?;
-
-    let mut archive = zip::ZipArchive::new(cursor)?;
+    let mut archive = template_archive()?;
 
     for i in 0..archive.len() {
         let mut file = archive.by_index(i)?;
         let mut contents = String::new();
         file.read_to_string(&mut contents)?;
-
-        // replace template placeholders
-        let contents = contents.replace("{{name}}", name);
-        let contents = contents.replace("{{camel_name}}", &name.to_camel_case());
+        if file.name() == "lib.rs" {
+            if let Some(lib) = template.lib() {
+                contents = lib.to_string();
+            }
+        }
+        let contents = replace_placeholders(&contents, name);
 
         let outpath = out_dir.join(file.name());
 
@@ -96,15 +164,373 @@ where
     Ok(format!("Created contract {}", name))
 }
 
+/// Creates the contract project `name` from the template in the git repository at `url`.
+///
+/// The placeholders `{{name}}`, `{{camel_name}}` and `{{authors}}` are replaced in the contents
+/// and names of the files, the `.git` directory of the template is not copied. Fails if the
+/// project directory exists.
+pub(crate) fn execute_from_git<P>(name: &str, dir: Option<P>, url: &str) -> Result<String>
+where
+    P: AsRef<Path>,
+{
+    if name.contains('-') {
+        anyhow::bail!("Contract names cannot contain hyphens");
+    }
+    let out_dir = dir
+        .map_or(env::current_dir()?, |p| p.as_ref().to_path_buf())
+        .join(name);
+    if out_dir.exists() {
+        anyhow::bail!(
+            "{} already exists, refusing to overwrite it",
+            out_dir.display()
+        );
+    }
+
+    let clone = tempfile::tempdir()?;
+    let template_dir = clone.path().join("template");
+    super::git::clone_shallow(url, &template_dir)?;
+    let authors =
+        super::git::git_author().unwrap_or_else(|| "[your_name] <[your_email]>".to_string());
+    let replace =
+        |contents: &str| replace_placeholders(contents, name).replace("{{authors}}", &authors);
+    fs::create_dir_all(&out_dir)?;
+    copy_template(&template_dir, &out_dir, &replace)?;
+
+    Ok(format!("Created contract {} from {}", name, url))
+}
+
+/// Copies the files of the template directory `from` into `to`, replacing the placeholders in
+/// the names and the contents. Files which are not UTF-8 are copied unchanged.
+fn copy_template(from: &Path, to: &Path, replace: &dyn Fn(&str) -> String) -> Result<()> {
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        if file_name == ".git" {
+            continue;
+        }
+        let dest = to.join(replace(&file_name.to_string_lossy()));
+        if entry.file_type()?.is_dir() {
+            fs::create_dir_all(&dest)?;
+            copy_template(&entry.path(), &dest, replace)?;
+            continue;
+        }
+        let contents = fs::read(entry.path())?;
+        match String::from_utf8(contents) {
+            Ok(text) => fs::write(&dest, replace(&text))?,
+            Err(binary) => fs::write(&dest, binary.into_bytes())?,
+        }
+    }
+    Ok(())
+}
+
+/// Adds a contract as a module `src/<name>.rs` of the existing library crate in `host_dir`.
+///
+/// The ink! dependencies and features of the template which the host manifest lacks are added,
+/// the `cdylib` crate type is added so the crate can be built as a contract. Existing entries
+/// are kept. The comments and formatting of the host manifest are preserved.
+///
+/// Returns a report of every modified file.
+pub(crate) fn execute_as_module(name: &str, host_dir: &Path, template: Template) -> Result<String> {
+    if name.contains('-') {
+        anyhow::bail!("Contract names cannot contain hyphens");
+    }
+
+    let manifest_path = host_dir.join("Cargo.toml");
+    let manifest = fs::read_to_string(&manifest_path)
+        .context(format!("No Cargo package found in {}", host_dir.display()))?;
+    let mut manifest: Document = manifest
+        .parse()
+        .context(format!("Failed to parse {}", manifest_path.display()))?;
+    if !manifest.contains_key("package") {
+        anyhow::bail!("{} has no [package] section", manifest_path.display());
+    }
+    let lib_root = host_dir.join(
+        manifest
+            .get("lib")
+            .and_then(|lib| lib.get("path"))
+            .and_then(Item::as_str)
+            .unwrap_or("src/lib.rs"),
+    );
+    if !lib_root.exists() {
+        anyhow::bail!(
+            "The crate in {} has no library target, a contract module needs one",
+            host_dir.display()
+        );
+    }
+    let module_path = lib_root
+        .parent()
+        .map_or_else(|| PathBuf::from(name), |dir| dir.join(name))
+        .with_extension("rs");
+    if module_path.exists() {
+        anyhow::bail!("New contract file {} already exists", module_path.display());
+    }
+
+    let mut archive = template_archive()?;
+    let template_lib = match template.lib() {
+        Some(lib) => replace_placeholders(lib, name),
+        None => template_file(&mut archive, "lib.rs", name)?,
+    };
+    let template_manifest: Document = template_file(&mut archive, "Cargo.toml", name)?.parse()?;
+
+    let mut report = vec![format!("created {}", module_path.display())];
+
+    // the crate attribute is only allowed in the crate root
+    let module = template_lib
+        .trim_start_matches(NO_STD_ATTRIBUTE)
+        .trim_start()
+        .to_string();
+    fs::write(&module_path, module)?;
+
+    let lib = fs::read_to_string(&lib_root)?;
+    let mut lib_additions = Vec::new();
+    let mut lib_contents = lib.clone();
+    if !lib.contains(NO_STD_ATTRIBUTE) {
+        lib_contents = format!("{}\n{}", NO_STD_ATTRIBUTE, lib_contents);
+        lib_additions.push(format!("`{}`", NO_STD_ATTRIBUTE));
+    }
+    lib_contents.push_str(&format!("\npub mod {};\n", name));
+    lib_additions.push(format!("`pub mod {};`", name));
+    fs::write(&lib_root, lib_contents)?;
+    report.push(format!(
+        "added {} to {}",
+        lib_additions.join(" and "),
+        lib_root.display()
+    ));
+
+    let manifest_changes = merge_template_manifest(&mut manifest, &template_manifest)?;
+    if !manifest_changes.is_empty() {
+        fs::write(&manifest_path, manifest.to_string())?;
+        report.push(format!(
+            "updated {}:\n{}",
+            manifest_path.display(),
+            manifest_changes
+                .iter()
+                .map(|change| format!("    - {}", change))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ));
+    }
+
+    Ok(format!(
+        "Created contract module {}\n{}",
+        name,
+        report
+            .iter()
+            .map(|line| format!("  - {}", line))
+            .collect::<Vec<_>>()
+            .join("\n")
+    ))
+}
+
+/// Adds the crate in `member_dir` to the `members` of the workspace with the root manifest
+/// `workspace_manifest`, unless it is a member already, also by a `dir/*` glob.
+///
+/// The comments and formatting of the root manifest are preserved. Returns a report of the
+/// change.
+pub(crate) fn add_workspace_member(workspace_manifest: &Path, member_dir: &Path) -> Result<String> {
+    let manifest = fs::read_to_string(workspace_manifest)
+        .context(format!("Failed to read {}", workspace_manifest.display()))?;
+    let mut manifest: Document = manifest
+        .parse()
+        .context(format!("Failed to parse {}", workspace_manifest.display()))?;
+    let workspace_root = match workspace_manifest.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.canonicalize()?,
+        _ => env::current_dir()?,
+    };
+    let member = member_dir
+        .canonicalize()?
+        .strip_prefix(&workspace_root)
+        .map_err(|_| {
+            anyhow::anyhow!(
+                "{} is not inside the workspace {}",
+                member_dir.display(),
+                workspace_root.display()
+            )
+        })?
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    let workspace = manifest
+        .get_mut("workspace")
+        .and_then(Item::as_table_mut)
+        .ok_or(anyhow::anyhow!(
+            "{} has no [workspace] section, it is not the root manifest of a workspace",
+            workspace_manifest.display()
+        ))?;
+    let members = workspace
+        .entry("members")
+        .or_insert(toml_edit::value(Array::new()))
+        .as_array_mut()
+        .ok_or(anyhow::anyhow!("workspace.members should be an array"))?;
+    let parent = member.rsplitn(2, '/').nth(1).unwrap_or("");
+    let is_member = members
+        .iter()
+        .filter_map(toml_edit::Value::as_str)
+        .any(|existing| existing == member || existing.strip_suffix("/*") == Some(parent));
+    if is_member {
+        return Ok(format!(
+            "{} is already a member of the workspace {}",
+            member,
+            workspace_manifest.display()
+        ));
+    }
+    push_formatted(members, &member);
+    fs::write(workspace_manifest, manifest.to_string())?;
+    Ok(format!(
+        "Added {} to the members of the workspace {}",
+        member,
+        workspace_manifest.display()
+    ))
+}
+
+/// Appends `entry` to the array. If the last entry is on a line of its own, so is `entry`, with
+/// the same indentation and after the comment of the last entry.
+fn push_formatted(array: &mut Array, entry: &str) {
+    let raw = |raw: Option<&RawString>| raw.and_then(RawString::as_str).unwrap_or("").to_string();
+    let indent = array
+        .iter()
+        .last()
+        .map(|last| raw(last.decor().prefix()))
+        .and_then(|prefix| prefix.rfind('\n').map(|line| prefix[line..].to_string()));
+    let indent = match indent {
+        Some(indent) => indent,
+        None => return array.push(entry),
+    };
+    // the comment of the last entry follows its comma, or the entry itself without one
+    let after_last = if array.trailing_comma() {
+        raw(Some(array.trailing()))
+    } else {
+        let last = array.len() - 1;
+        let decor = array
+            .get_mut(last)
+            .expect("the array has entries")
+            .decor_mut();
+        let suffix = raw(decor.suffix());
+        decor.set_suffix("");
+        suffix
+    };
+    let (comment, rest) = after_last.split_at(after_last.find('\n').unwrap_or(after_last.len()));
+    let prefix = format!("{}{}", comment, indent);
+    if array.trailing_comma() {
+        array.push_formatted(toml_edit::Value::from(entry).decorated(prefix, ""));
+        array.set_trailing(rest);
+    } else {
+        array.push_formatted(toml_edit::Value::from(entry).decorated(prefix, rest));
+    }
+}
+
+/// Adds the dependencies, features and crate types of the template manifest which `manifest`
+/// lacks. Returns a description of each change.
+fn merge_template_manifest(manifest: &mut Document, template: &Document) -> Result<Vec<String>> {
+    let mut changes = Vec::new();
+
+    let template_dependencies = template
+        .get("dependencies")
+        .and_then(Item::as_table)
+        .cloned()
+        .unwrap_or_default();
+    let dependencies = table_mut(manifest, "dependencies")?;
+    for (dependency, spec) in template_dependencies.iter() {
+        if !dependencies.contains_key(dependency) {
+            changes.push(format!("added dependency `{}`", dependency));
+            dependencies.insert(dependency, spec.clone());
+        }
+    }
+
+    let template_features = template
+        .get("features")
+        .and_then(Item::as_table)
+        .cloned()
+        .unwrap_or_default();
+    let features = table_mut(manifest, "features")?;
+    for (feature, template_entries) in template_features.iter() {
+        let template_entries = template_entries.as_array().cloned().unwrap_or_default();
+        match features.get_mut(feature) {
+            None => {
+                changes.push(format!("added feature `{}`", feature));
+                features.insert(feature, toml_edit::value(template_entries));
+            }
+            // a host with a `default` feature keeps its own defaults
+            Some(_) if feature == "default" => (),
+            Some(entries) => {
+                let entries = entries
+                    .as_array_mut()
+                    .ok_or(anyhow::anyhow!("feature `{}` should be an array", feature))?;
+                for entry in template_entries.iter().filter_map(toml_edit::Value::as_str) {
+                    if !entries
+                        .iter()
+                        .any(|existing| existing.as_str() == Some(entry))
+                    {
+                        changes.push(format!("added `{}` to feature `{}`", entry, feature));
+                        push_formatted(entries, entry);
+                    }
+                }
+            }
+        }
+    }
+
+    let crate_types = table_mut(manifest, "lib")?
+        .entry("crate-type")
+        // without a crate type the library is an `rlib`, which the host may still rely on
+        .or_insert_with(|| toml_edit::value(std::iter::once("rlib").collect::<Array>()))
+        .as_array_mut()
+        .ok_or(anyhow::anyhow!("crate-type should be an array"))?;
+    if !crate_types.iter().any(|ty| ty.as_str() == Some("cdylib")) {
+        changes.push("added crate type `cdylib`".to_string());
+        push_formatted(crate_types, "cdylib");
+    }
+    Ok(changes)
+}
+
+/// Returns the table `key` of the manifest, inserting an empty one if missing.
+fn table_mut<'a>(manifest: &'a mut Table, key: &str) -> Result<&'a mut Table> {
+    manifest
+        .entry(key)
+        .or_insert(toml_edit::table())
+        .as_table_mut()
+        .ok_or(anyhow::anyhow!("[{}] should be a table", key))
+}
+
+/// Returns the archive of the contract template, embedded by the build script.
+fn template_archive() -> Result<zip::ZipArchive<Cursor<Vec<u8>>>> {
+    let template = include_bytes!(concat!(env!("OUT_DIR"), "/template.zip"));
+    let mut cursor = Cursor::new(Vec::new());
+    cursor.write_all(template)?;
+    cursor.seek(SeekFrom::Start(0))?;
+    Ok(zip::ZipArchive::new(cursor)?)
+}
+
+/// Returns the contents of a file of the template, with its placeholders replaced.
+fn template_file(
+    archive: &mut zip::ZipArchive<Cursor<Vec<u8>>>,
+    file_name: &str,
+    name: &str,
+) -> Result<String> {
+    let mut contents = String::new();
+    archive.by_name(file_name)?.read_to_string(&mut contents)?;
+    Ok(replace_placeholders(&contents, name))
+}
+
+/// Replaces the placeholders of a template file.
+fn replace_placeholders(contents: &str, name: &str) -> String {
+    contents
+        .replace("{{name}}", name)
+        .replace("{{camel_name}}", &name.to_camel_case())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{cmd, util::tests::with_tmp_dir};
+    use toml::value;
 
     #[test]
     fn rejects_hyphenated_name() {
         with_tmp_dir(|path| {
-            let result = cmd::new::execute("rejects-hyphenated-name", Some(path));
+            let result =
+                cmd::new::execute("rejects-hyphenated-name", Some(path), Template::Flipper);
             assert!(result.is_err(), "Should fail");
             assert_eq!(
                 result.err().unwrap().to_string(),
@@ -118,8 +544,8 @@ mod tests {
     fn contract_cargo_project_already_exists() {
         with_tmp_dir(|path| {
             let name = "test_contract_cargo_project_already_exists";
-            let _ = execute(name, Some(path));
-            let result = cmd::new::execute(name, Some(path));
+            let _ = execute(name, Some(path), Template::Flipper);
+            let result = cmd::new::execute(name, Some(path), Template::Flipper);
 
             assert!(result.is_err(), "Should fail");
             assert_eq!(
@@ -137,7 +563,7 @@ mod tests {
             let dir = path.join(name);
             fs::create_dir_all(&dir).unwrap();
             fs::File::create(dir.join(".gitignore")).unwrap();
-            let result = cmd::new::execute(name, Some(path));
+            let result = cmd::new::execute(name, Some(path), Template::Flipper);
 
             assert!(result.is_err(), "Should fail");
             assert_eq!(
@@ -147,4 +573,261 @@ mod tests {
             Ok(())
         })
     }
+
+    #[test]
+    fn adds_contract_module_to_host_crate() {
+        with_tmp_dir(|path| {
+            fs::create_dir_all(path.join("src"))?;
+            fs::write(
+                path.join("Cargo.toml"),
+                "[package]\nname = \"host\"\nversion = \"0.1.0\"\n\n\
+                 [dependencies]\nink_env = \"3.0.0\"\n\n\
+                 [features]\ndefault = []\nstd = [\"ink_env/std\"]\n",
+            )?;
+            fs::write(path.join("src").join("lib.rs"), "pub fn host() {}\n")?;
+
+            let report = execute_as_module("flipper", path, Template::Flipper)?;
+
+            let module = fs::read_to_string(path.join("src").join("flipper.rs"))?;
+            assert!(module.starts_with("use ink_lang as ink;"));
+            let lib = fs::read_to_string(path.join("src").join("lib.rs"))?;
+            assert!(lib.starts_with(NO_STD_ATTRIBUTE));
+            assert!(lib.ends_with("pub mod flipper;\n"));
+
+            let manifest: value::Table =
+                toml::from_str(&fs::read_to_string(path.join("Cargo.toml"))?)?;
+            assert_eq!(manifest["dependencies"]["ink_env"].as_str(), Some("3.0.0"));
+            assert!(manifest["dependencies"].get("ink_lang").is_some());
+            assert_eq!(manifest["features"]["default"], value::Value::Array(vec![]));
+            assert!(manifest["features"]["std"]
+                .as_array()
+                .unwrap()
+                .contains(&"ink_storage/std".into()));
+            assert_eq!(
+                manifest["lib"]["crate-type"],
+                value::Value::Array(vec!["rlib".into(), "cdylib".into()])
+            );
+
+            assert!(report.contains("added dependency `ink_lang`"));
+            assert!(!report.contains("added dependency `ink_env`"));
+            assert!(report.contains("added crate type `cdylib`"));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn adding_contract_module_preserves_the_host_manifest() {
+        with_tmp_dir(|path| {
+            fs::create_dir_all(path.join("src"))?;
+            fs::write(
+                path.join("Cargo.toml"),
+                "# The host crate\n\
+                 [package]\n\
+                 name = \"host\"\n\
+                 version = \"0.1.0\"\n\
+                 \n\
+                 [dependencies]\n\
+                 ink_env = { version = \"3.0.0\", default-features = false } # pinned\n\
+                 \n\
+                 [features]\n\
+                 default = []\n\
+                 std = [\n\
+                 \x20   \"ink_env/std\", # the environment\n\
+                 ]\n",
+            )?;
+            fs::write(path.join("src").join("lib.rs"), "pub fn host() {}\n")?;
+
+            execute_as_module("flipper", path, Template::Flipper)?;
+
+            let manifest = fs::read_to_string(path.join("Cargo.toml"))?;
+            assert!(manifest.starts_with("# The host crate\n[package]\n"));
+            assert!(manifest.contains(
+                "ink_env = { version = \"3.0.0\", default-features = false } # pinned\n"
+            ));
+            assert!(manifest.contains(
+                "std = [\n    \"ink_env/std\", # the environment\n    \"ink_metadata/std\",\n"
+            ));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn registers_new_contract_as_workspace_member() {
+        with_tmp_dir(|path| {
+            let workspace_manifest = path.join("Cargo.toml");
+            fs::write(&workspace_manifest, "[workspace]\nmembers = [\"lib\"]\n")?;
+            let contracts = path.join("contracts");
+            fs::create_dir_all(&contracts)?;
+            execute("flipper", Some(&contracts), Template::Flipper)?;
+            let members = || -> Result<value::Value> {
+                let manifest: value::Table =
+                    toml::from_str(&fs::read_to_string(&workspace_manifest)?)?;
+                Ok(manifest["workspace"]["members"].clone())
+            };
+
+            let added = add_workspace_member(&workspace_manifest, &contracts.join("flipper"))?;
+            assert!(added.starts_with("Added contracts/flipper"));
+            let expected = value::Value::Array(vec!["lib".into(), "contracts/flipper".into()]);
+            assert_eq!(members()?, expected);
+
+            let again = add_workspace_member(&workspace_manifest, &contracts.join("flipper"))?;
+            assert!(again.contains("already a member"));
+            assert_eq!(members()?, expected);
+
+            fs::write(
+                &workspace_manifest,
+                "[workspace]\nmembers = [\"contracts/*\"]\n",
+            )?;
+            let globbed = add_workspace_member(&workspace_manifest, &contracts.join("flipper"))?;
+            assert!(globbed.contains("already a member"));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn adding_workspace_member_preserves_the_manifest() {
+        with_tmp_dir(|path| {
+            let workspace_manifest = path.join("Cargo.toml");
+            fs::write(
+                &workspace_manifest,
+                "# The contracts of the project\n\
+                 [workspace]\n\
+                 members = [\n\
+                 \x20   \"lib\", # shared types\n\
+                 ]\n\
+                 \n\
+                 [profile.release]\n\
+                 overflow-checks = false # checked by the contracts\n",
+            )?;
+            execute("flipper", Some(path), Template::Flipper)?;
+
+            add_workspace_member(&workspace_manifest, &path.join("flipper"))?;
+
+            assert_eq!(
+                fs::read_to_string(&workspace_manifest)?,
+                "# The contracts of the project\n\
+                 [workspace]\n\
+                 members = [\n\
+                 \x20   \"lib\", # shared types\n\
+                 \x20   \"flipper\",\n\
+                 ]\n\
+                 \n\
+                 [profile.release]\n\
+                 overflow-checks = false # checked by the contracts\n"
+            );
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn workspace_member_requires_a_workspace() {
+        with_tmp_dir(|path| {
+            let manifest = path.join("Cargo.toml");
+            fs::write(
+                &manifest,
+                "[package]\nname = \"host\"\nversion = \"0.1.0\"\n",
+            )?;
+            execute("flipper", Some(path), Template::Flipper)?;
+
+            let err = add_workspace_member(&manifest, &path.join("flipper")).unwrap_err();
+            assert!(err.to_string().contains("has no [workspace] section"));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn creates_contract_from_template() {
+        with_tmp_dir(|path| {
+            execute("token", Some(path), "erc20".parse()?)?;
+
+            let lib = fs::read_to_string(path.join("token").join("lib.rs"))?;
+            assert!(lib.contains("mod token {"));
+            assert!(lib.contains("pub struct Token {"));
+            assert!(lib.contains("pub fn transfer_from("));
+            assert!(!lib.contains("{{"));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn creates_contract_from_git_template() {
+        with_tmp_dir(|path| {
+            let git = |dir: &Path, args: &[&str]| {
+                let status = std::process::Command::new("git")
+                    .args(&["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                    .args(args)
+                    .current_dir(dir)
+                    .status()?;
+                assert!(status.success(), "git {:?} failed", args);
+                Ok::<_, anyhow::Error>(())
+            };
+            let template = path.join("template");
+            fs::create_dir_all(template.join("src"))?;
+            fs::write(
+                template.join("Cargo.toml"),
+                "[package]\nname = \"{{name}}\"\nauthors = [\"{{authors}}\"]\n",
+            )?;
+            fs::write(
+                template.join("src").join("{{name}}.rs"),
+                "pub struct {{camel_name}};\n",
+            )?;
+            git(&template, &["init", "--quiet"])?;
+            git(&template, &["add", "."])?;
+            git(&template, &["commit", "--quiet", "-m", "template"])?;
+            let url = template.to_string_lossy();
+
+            execute_from_git("my_token", Some(path), &url)?;
+
+            let project = path.join("my_token");
+            let manifest = fs::read_to_string(project.join("Cargo.toml"))?;
+            assert!(manifest.contains("name = \"my_token\""));
+            assert!(!manifest.contains("{{authors}}"));
+            assert_eq!(
+                fs::read_to_string(project.join("src").join("my_token.rs"))?,
+                "pub struct MyToken;\n"
+            );
+            assert!(!project.join(".git").exists());
+            assert_eq!(
+                execute_from_git("my_token", Some(path), &url)
+                    .unwrap_err()
+                    .to_string(),
+                format!(
+                    "{} already exists, refusing to overwrite it",
+                    project.display()
+                )
+            );
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn unknown_template_lists_the_templates() {
+        assert_eq!("erc721".parse::<Template>().unwrap(), Template::Erc721);
+        assert_eq!(
+            "erc1155".parse::<Template>().unwrap_err().to_string(),
+            "Unknown template `erc1155`, the available templates are: flipper, erc20, erc721, \
+             empty"
+        );
+    }
+
+    #[test]
+    fn host_crate_without_library_is_rejected() {
+        with_tmp_dir(|path| {
+            fs::write(
+                path.join("Cargo.toml"),
+                "[package]\nname = \"host\"\nversion = \"0.1.0\"\n",
+            )?;
+
+            let result = execute_as_module("flipper", path, Template::Flipper);
+
+            assert_eq!(
+                result.err().unwrap().to_string(),
+                format!(
+                    "The crate in {} has no library target, a contract module needs one",
+                    path.display()
+                )
+            );
+            Ok(())
+        })
+    }
 }
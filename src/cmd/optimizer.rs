@@ -0,0 +1,402 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Configures the `wasm-opt` optimizer, from a config file shared across contracts and from the
+//! command line.
+//!
+//! A config file, e.g. `wasm-opt.toml`, may contain:
+//!
+//! ```toml
+//! # 0 to 4, like `-O0` to `-O4` of `wasm-opt`
+//! optimization-level = 3
+//! # 0 to 2, no shrinking, `-Os` or `-Oz`
+//! shrink-level = 1
+//! # keep the names section
+//! debug-info = false
+//! # run these passes instead of the default passes of the optimization level
+//! passes = ["dce", "vacuum"]
+//! # wasm features the optimizer may use in addition to the ones of the module
+//! features = ["sign-ext", "mutable-globals"]
+//! ```
+//!
+//! Unknown keys, passes and features are rejected.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    fs,
+    os::raw::c_char,
+    path::Path,
+    str::FromStr,
+};
+
+use anyhow::{Context, Result};
+use binaryen::ffi::{self, passes::OptimizationPass, BinaryenFeatures};
+use serde::Deserialize;
+
+/// The wasm features which can be enabled, named like the `--enable-*` flags of `wasm-opt`.
+const FEATURES: &[(&str, unsafe extern "C" fn() -> BinaryenFeatures)] = &[
+    ("threads", ffi::BinaryenFeatureAtomics),
+    ("bulk-memory", ffi::BinaryenFeatureBulkMemory),
+    ("mutable-globals", ffi::BinaryenFeatureMutableGlobals),
+    (
+        "nontrapping-float-to-int",
+        ffi::BinaryenFeatureNontrappingFPToInt,
+    ),
+    ("sign-ext", ffi::BinaryenFeatureSignExt),
+    ("simd", ffi::BinaryenFeatureSIMD128),
+    ("exception-handling", ffi::BinaryenFeatureExceptionHandling),
+    ("tail-call", ffi::BinaryenFeatureTailCall),
+    ("reference-types", ffi::BinaryenFeatureReferenceTypes),
+    ("multivalue", ffi::BinaryenFeatureMultivalue),
+];
+
+/// The features the code of a module with a shared memory uses, built with `--shared-memory`.
+const SHARED_MEMORY_FEATURES: [&str; 2] = ["threads", "bulk-memory"];
+
+/// The optimization levels of `wasm-opt`, its `-O` flags.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum OptimizationPasses {
+    Zero,
+    One,
+    Two,
+    Three,
+    Four,
+    /// Optimize for size.
+    S,
+    /// Optimize aggressively for size.
+    Z,
+}
+
+impl OptimizationPasses {
+    const ALL: [OptimizationPasses; 7] = [
+        OptimizationPasses::Zero,
+        OptimizationPasses::One,
+        OptimizationPasses::Two,
+        OptimizationPasses::Three,
+        OptimizationPasses::Four,
+        OptimizationPasses::S,
+        OptimizationPasses::Z,
+    ];
+
+    /// Returns the `(optimization_level, shrink_level)` of the flag, as set by `wasm-opt`.
+    pub fn levels(self) -> (u32, u32) {
+        match self {
+            OptimizationPasses::Zero => (0, 0),
+            OptimizationPasses::One => (1, 0),
+            OptimizationPasses::Two => (2, 0),
+            OptimizationPasses::Three => (3, 0),
+            OptimizationPasses::Four => (4, 0),
+            OptimizationPasses::S => (2, 1),
+            OptimizationPasses::Z => (2, 2),
+        }
+    }
+}
+
+impl FromStr for OptimizationPasses {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        OptimizationPasses::ALL
+            .iter()
+            .copied()
+            .find(|passes| passes.to_string()[2..] == *input)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Unknown optimization passes `{}`, expected 0, 1, 2, 3, 4, s or z",
+                    input
+                )
+            })
+    }
+}
+
+impl Display for OptimizationPasses {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let level = match self {
+            OptimizationPasses::Zero => "0",
+            OptimizationPasses::One => "1",
+            OptimizationPasses::Two => "2",
+            OptimizationPasses::Three => "3",
+            OptimizationPasses::Four => "4",
+            OptimizationPasses::S => "s",
+            OptimizationPasses::Z => "z",
+        };
+        write!(f, "-O{}", level)
+    }
+}
+
+/// The settings of the optimizer, unset ones take the defaults of `cargo contract build`.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub(crate) struct OptimizerConfig {
+    /// The optimization level, 3 by default.
+    pub optimization_level: Option<u32>,
+    /// The shrink level, 1 by default.
+    pub shrink_level: Option<u32>,
+    /// Keep the names section, off by default.
+    pub debug_info: Option<bool>,
+    /// The passes to run instead of the default passes of the optimization level.
+    pub passes: Option<Vec<String>>,
+    /// The wasm features enabled in addition to the ones of the module.
+    pub features: Option<Vec<String>>,
+}
+
+impl OptimizerConfig {
+    /// Reads and validates a config file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents =
+            fs::read_to_string(path).context(format!("Failed to read {}", path.display()))?;
+        let config: Self =
+            toml::from_str(&contents).context(format!("Failed to parse {}", path.display()))?;
+        config
+            .validate()
+            .context(format!("Invalid optimizer config {}", path.display()))?;
+        Ok(config)
+    }
+
+    /// Returns the config with the settings of `overrides` taking precedence.
+    pub fn merge(self, overrides: OptimizerConfig) -> Self {
+        OptimizerConfig {
+            optimization_level: overrides.optimization_level.or(self.optimization_level),
+            shrink_level: overrides.shrink_level.or(self.shrink_level),
+            debug_info: overrides.debug_info.or(self.debug_info),
+            passes: overrides.passes.or(self.passes),
+            features: overrides.features.or(self.features),
+        }
+    }
+
+    /// Returns the config with the features of a shared memory module enabled.
+    ///
+    /// The post-processing strips the `target_features` section, so `wasm-opt` would otherwise
+    /// read the atomic and bulk memory instructions with only the MVP features enabled.
+    pub fn with_shared_memory_features(mut self) -> Self {
+        let features = self.features.get_or_insert_with(Vec::new);
+        for feature in SHARED_MEMORY_FEATURES.iter() {
+            if !features.iter().any(|enabled| enabled == feature) {
+                features.push(feature.to_string());
+            }
+        }
+        self
+    }
+
+    /// Fails for levels out of range and unknown passes or features.
+    pub fn validate(&self) -> Result<()> {
+        if let Some(level) = self.optimization_level.filter(|level| *level > 4) {
+            anyhow::bail!("The optimization level must be 0 to 4, not {}", level)
+        }
+        if let Some(level) = self.shrink_level.filter(|level| *level > 2) {
+            anyhow::bail!("The shrink level must be 0 to 2, not {}", level)
+        }
+        let unknown_passes = self
+            .passes
+            .iter()
+            .flatten()
+            .filter(|pass| OptimizationPass::from_str(pass).is_err())
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        if !unknown_passes.is_empty() {
+            anyhow::bail!("Unknown optimization passes: {}", unknown_passes.join(", "))
+        }
+        let unknown_features = self
+            .features
+            .iter()
+            .flatten()
+            .filter(|feature| !FEATURES.iter().any(|(name, _)| *name == feature.as_str()))
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        if !unknown_features.is_empty() {
+            let known = FEATURES.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+            anyhow::bail!(
+                "Unknown wasm features: {}. The known features are: {}",
+                unknown_features.join(", "),
+                known.join(", ")
+            )
+        }
+        Ok(())
+    }
+
+    /// Returns the levels of the config, with the defaults of `cargo contract build`.
+    pub fn codegen_config(&self) -> binaryen::CodegenConfig {
+        let (optimization_level, shrink_level) = self.levels();
+        binaryen::CodegenConfig {
+            optimization_level,
+            shrink_level,
+            // the default
+            debug_info: self.debug_info.unwrap_or(false),
+        }
+    }
+
+    /// Returns the `(optimization_level, shrink_level)` of the config.
+    fn levels(&self) -> (u32, u32) {
+        (
+            // execute -O3 optimization passes (spends potentially a lot of time optimizing)
+            self.optimization_level.unwrap_or(3),
+            // the default
+            self.shrink_level.unwrap_or(1),
+        )
+    }
+
+    /// Describes the optimization like the flags of `wasm-opt`, e.g. `-Oz`.
+    ///
+    /// Levels without a flag of their own, like the default, are described by both levels.
+    pub fn describe(&self) -> String {
+        if self.passes.is_some() {
+            return "custom passes".into();
+        }
+        let levels = self.levels();
+        match OptimizationPasses::ALL
+            .iter()
+            .find(|passes| passes.levels() == levels)
+        {
+            Some(passes) => passes.to_string(),
+            None => format!("-O{} with shrink level {}", levels.0, levels.1),
+        }
+    }
+
+    /// Optimizes the wasm code.
+    pub fn optimize(&self, wasm: &[u8]) -> Result<Vec<u8>> {
+        let mut module = self.read_module(wasm)?;
+        let codegen_config = self.codegen_config();
+        match self.passes.as_ref() {
+            Some(passes) => module
+                .run_optimization_passes(passes, &codegen_config)
+                .map_err(|_| anyhow::anyhow!("binaryen failed to run the optimization passes"))?,
+            None => module.optimize(&codegen_config),
+        }
+        Ok(module.write())
+    }
+
+    /// Reads the module, enabling the configured features in addition to its own.
+    ///
+    /// The `binaryen` crate cannot set the features of a module, so the module is read through
+    /// its FFI if there are any.
+    fn read_module(&self, wasm: &[u8]) -> Result<binaryen::Module> {
+        let features = match self.features.as_ref() {
+            Some(features) if !features.is_empty() => features,
+            _ => {
+                return binaryen::Module::read(wasm)
+                    .map_err(|_| anyhow::anyhow!("binaryen failed to read file content"))
+            }
+        };
+        let enabled = FEATURES
+            .iter()
+            .filter(|(name, _)| features.iter().any(|feature| feature == name))
+            .fold(0, |enabled, (_, feature)| enabled | unsafe { (*feature)() });
+        // SAFETY: the module is only handed to `Module::from_raw` if it was read successfully,
+        // which then owns and disposes it.
+        unsafe {
+            let raw = ffi::BinaryenModuleSafeRead(wasm.as_ptr() as *const c_char, wasm.len());
+            if raw.is_null() {
+                anyhow::bail!("binaryen failed to read file content")
+            }
+            ffi::BinaryenModuleSetFeatures(raw, ffi::BinaryenModuleGetFeatures(raw) | enabled);
+            Ok(binaryen::Module::from_raw(raw))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::with_tmp_dir;
+
+    const SAMPLE_CONFIG: &str = r#"
+optimization-level = 2
+shrink-level = 2
+debug-info = true
+passes = ["dce", "vacuum"]
+features = ["sign-ext", "mutable-globals"]
+"#;
+
+    #[test]
+    fn sample_config_is_parsed() {
+        with_tmp_dir(|path| {
+            let config_path = path.join("wasm-opt.toml");
+            fs::write(&config_path, SAMPLE_CONFIG)?;
+
+            let config = OptimizerConfig::load(&config_path)?;
+
+            assert_eq!(
+                config,
+                OptimizerConfig {
+                    optimization_level: Some(2),
+                    shrink_level: Some(2),
+                    debug_info: Some(true),
+                    passes: Some(vec!["dce".into(), "vacuum".into()]),
+                    features: Some(vec!["sign-ext".into(), "mutable-globals".into()]),
+                }
+            );
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn invalid_configs_are_rejected() {
+        let parse = |config: &str| {
+            toml::from_str::<OptimizerConfig>(config)
+                .map_err(anyhow::Error::from)
+                .and_then(|config| config.validate())
+        };
+
+        assert!(parse("optimization-level = 3").is_ok());
+        assert!(parse("optimisation-level = 3").is_err());
+        assert!(parse("optimization-level = 5").is_err());
+        assert!(parse("shrink-level = 3").is_err());
+        let err = parse(r#"passes = ["dce", "make-it-fast"]"#).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown optimization passes: make-it-fast");
+        let err = parse(r#"features = ["gc"]"#).unwrap_err();
+        assert!(err.to_string().starts_with("Unknown wasm features: gc."));
+    }
+
+    #[test]
+    fn command_line_overrides_the_config_file() {
+        let file: OptimizerConfig = toml::from_str(SAMPLE_CONFIG).unwrap();
+        let cli = OptimizerConfig {
+            optimization_level: Some(3),
+            passes: Some(Vec::new()),
+            ..Default::default()
+        };
+
+        let merged = file.merge(cli);
+
+        assert_eq!(merged.optimization_level, Some(3));
+        assert_eq!(merged.shrink_level, Some(2));
+        assert_eq!(merged.passes, Some(Vec::new()));
+        assert_eq!(merged.codegen_config().optimization_level, 3);
+    }
+
+    #[test]
+    fn optimization_passes_set_the_levels_of_wasm_opt() {
+        let config = |passes: &str| {
+            let (optimization_level, shrink_level) =
+                OptimizationPasses::from_str(passes).unwrap().levels();
+            OptimizerConfig {
+                optimization_level: Some(optimization_level),
+                shrink_level: Some(shrink_level),
+                ..Default::default()
+            }
+        };
+
+        assert_eq!(config("z").describe(), "-Oz");
+        assert_eq!(config("3").describe(), "-O3");
+        assert_eq!(config("s").codegen_config().shrink_level, 1);
+        assert_eq!(
+            OptimizerConfig::default().describe(),
+            "-O3 with shrink level 1"
+        );
+        assert!(OptimizationPasses::from_str("5").is_err());
+        assert!(OptimizationPasses::from_str("-Oz").is_err());
+    }
+}
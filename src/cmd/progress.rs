@@ -0,0 +1,70 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::time::Instant;
+
+use colored::Colorize;
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+use crate::Verbosity;
+
+/// A step of the build, e.g. `[1/5] Building cargo project`, which ends when dropped.
+///
+/// If stdout is a terminal the step is shown as a spinner with the time elapsed while it runs,
+/// and printed with its duration once it ends. Otherwise, e.g. on CI, the step is printed as a
+/// line when it starts.
+pub(crate) struct BuildStep {
+    line: String,
+    running: Option<(ProgressBar, Instant)>,
+}
+
+impl BuildStep {
+    /// Starts step `current` of `total`, showing nothing if the verbosity is quiet.
+    pub fn start(verbosity: Option<Verbosity>, current: usize, total: usize, name: &str) -> Self {
+        let line = format!(
+            " {} {}",
+            format!("[{}/{}]", current, total).bold(),
+            name.bright_green().bold()
+        );
+        let running = match verbosity {
+            Some(Verbosity::Quiet) => None,
+            _ if atty::is(atty::Stream::Stdout) => {
+                let bar = ProgressBar::with_draw_target(0, ProgressDrawTarget::stdout());
+                bar.set_style(
+                    ProgressStyle::default_spinner().template("{msg} {spinner} {elapsed}"),
+                );
+                bar.set_message(&line);
+                bar.enable_steady_tick(100);
+                Some((bar, Instant::now()))
+            }
+            _ => {
+                println!("{}", line);
+                None
+            }
+        };
+        BuildStep { line, running }
+    }
+}
+
+impl Drop for BuildStep {
+    fn drop(&mut self) {
+        if let Some((bar, start)) = self.running.take() {
+            bar.finish_and_clear();
+            let duration = format!("({:.1}s)", start.elapsed().as_secs_f64());
+            println!("{} {}", self.line, duration.dimmed());
+        }
+    }
+}
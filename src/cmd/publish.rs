@@ -0,0 +1,222 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Publishes a `<name>.contract` bundle to a contract registry.
+//!
+//! The bundle is uploaded with `POST <registry>/contracts` and the JSON body
+//!
+//! ```json
+//! { "name": "flipper", "version": "0.1.0", "code_hash": "0x…", "bundle": { … } }
+//! ```
+//!
+//! where `bundle` is the bundle as it is. The token is sent as `Authorization: Bearer <token>`.
+//! The registry answers with
+//!
+//! - `201 Created` and `{ "reference": "flipper@0.1.0", "url": "https://…" }`, the `url` is
+//!   optional,
+//! - `409 Conflict` if a bundle with the same code hash is published already, with the reference
+//!   of the published bundle in the same format,
+//! - `401 Unauthorized` or `403 Forbidden` if the token is missing or invalid.
+
+use std::{convert::TryFrom, fs, path::PathBuf};
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde_json::{json, Value};
+use structopt::StructOpt;
+
+use crate::{crate_metadata::CrateMetadata, workspace::ManifestPath};
+
+/// How long to wait for the registry to accept the connection, in milliseconds.
+const CONNECT_TIMEOUT: u64 = 10_000;
+
+/// Uploads the bundle of a built contract to a contract registry.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "publish")]
+pub struct PublishCommand {
+    /// Path to the Cargo.toml of the contract
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    /// Path to the `<name>.contract` bundle, defaults to the bundle built for the contract
+    #[structopt(long, parse(from_os_str))]
+    bundle: Option<PathBuf>,
+    /// The url of the contract registry
+    #[structopt(long, env = "CONTRACT_REGISTRY", parse(try_from_str))]
+    registry: url::Url,
+    /// The token authenticating the upload
+    #[structopt(long, env = "CONTRACT_REGISTRY_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+}
+
+impl PublishCommand {
+    pub fn exec(&self) -> Result<String> {
+        let bundle_path = match self.bundle.as_ref() {
+            Some(bundle) => bundle.clone(),
+            None => {
+                let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+                let crate_metadata = CrateMetadata::collect(&manifest_path)?;
+                crate_metadata
+                    .target_directory
+                    .join(format!("{}.contract", crate_metadata.package_name))
+            }
+        };
+        let bundle = fs::read(&bundle_path).context(format!(
+            "Failed to read the bundle {}, build it with `cargo contract build`",
+            bundle_path.display()
+        ))?;
+        let bundle: Value = serde_json::from_slice(&bundle).context(format!(
+            "Failed to parse the bundle {}",
+            bundle_path.display()
+        ))?;
+        let upload = upload(&bundle)?;
+
+        let mut endpoint = self.registry.clone();
+        endpoint
+            .path_segments_mut()
+            .map_err(|_| anyhow::anyhow!("Invalid registry url {}", self.registry))?
+            .pop_if_empty()
+            .push("contracts");
+        let mut request = ureq::post(endpoint.as_str());
+        request.timeout_connect(CONNECT_TIMEOUT);
+        if let Some(token) = self.token.as_ref() {
+            request.set("Authorization", &format!("Bearer {}", token));
+        }
+        let response = request.send_json(upload);
+        if let Some(err) = response.synthetic_error() {
+            anyhow::bail!("Failed to reach the registry at {}: {}", self.registry, err)
+        }
+        publish_result(&self.registry, response)
+    }
+}
+
+/// Returns the body of the upload of the `bundle`.
+fn upload(bundle: &Value) -> Result<Value> {
+    let field = |pointer: &str| {
+        bundle
+            .pointer(pointer)
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow::anyhow!("The bundle has no {}", pointer))
+    };
+    Ok(json!({
+        "name": field("/contract/name")?,
+        "version": field("/contract/version")?,
+        "code_hash": field("/source/hash")?,
+        "bundle": bundle,
+    }))
+}
+
+/// Describes the answer of the registry to an upload.
+fn publish_result(registry: &url::Url, response: ureq::Response) -> Result<String> {
+    let status = response.status();
+    let status_line = response.status_line().to_string();
+    let location = response.header("Location").map(ToString::to_string);
+    let body = response.into_string().unwrap_or_default();
+    let published = serde_json::from_str::<Value>(&body).unwrap_or(Value::Null);
+    let reference = published["reference"]
+        .as_str()
+        .map(ToString::to_string)
+        .or(location);
+    let url = published["url"].as_str();
+
+    match status {
+        200..=299 => {
+            let reference = reference.ok_or_else(|| {
+                anyhow::anyhow!(
+                    "The registry at {} accepted the bundle, but returned no reference",
+                    registry
+                )
+            })?;
+            Ok(match url {
+                Some(url) => format!("{} {} ({})", "Published".bold(), reference, url),
+                None => format!("{} {}", "Published".bold(), reference),
+            })
+        }
+        409 => {
+            println!(
+                "{} {}",
+                "warning:".yellow().bold(),
+                "A bundle with the same code hash is published already, nothing was uploaded"
+                    .bold()
+            );
+            Ok(format!(
+                "{} {}",
+                "Already published as".bold(),
+                reference.unwrap_or_else(|| "an unknown reference".to_string())
+            ))
+        }
+        401 | 403 => anyhow::bail!(
+            "The registry at {} rejected the upload with {}, pass a valid token with --token or \
+             CONTRACT_REGISTRY_TOKEN",
+            registry,
+            status_line
+        ),
+        _ => anyhow::bail!(
+            "The registry at {} failed to publish the bundle with {}: {}",
+            registry,
+            status_line,
+            body.trim()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> url::Url {
+        url::Url::parse("https://registry.example.com/api/").unwrap()
+    }
+
+    #[test]
+    fn upload_contains_the_bundle() {
+        let bundle = json!({
+            "source": { "hash": "0x1234", "wasm": "0x0061736d" },
+            "contract": { "name": "flipper", "version": "0.1.0" }
+        });
+
+        let upload = upload(&bundle).unwrap();
+
+        assert_eq!(upload["name"], "flipper");
+        assert_eq!(upload["code_hash"], "0x1234");
+        assert_eq!(upload["bundle"], bundle);
+        let err = super::upload(&json!({ "contract": {} })).unwrap_err();
+        assert_eq!(err.to_string(), "The bundle has no /contract/name");
+    }
+
+    #[test]
+    fn registry_responses_are_handled() {
+        let respond = |status, status_text, body| {
+            publish_result(&registry(), ureq::Response::new(status, status_text, body))
+        };
+
+        let published = respond(
+            201,
+            "Created",
+            r#"{ "reference": "flipper@0.1.0", "url": "https://registry.example.com/flipper" }"#,
+        )
+        .unwrap();
+        assert!(published.ends_with("flipper@0.1.0 (https://registry.example.com/flipper)"));
+        let conflict = respond(409, "Conflict", r#"{ "reference": "flipper@0.0.9" }"#).unwrap();
+        assert!(conflict.ends_with("flipper@0.0.9"));
+        let unauthorized = respond(401, "Unauthorized", "").unwrap_err();
+        assert!(unauthorized.to_string().contains("pass a valid token"));
+        let failed = respond(500, "Internal Server Error", "disk full\n").unwrap_err();
+        assert!(failed
+            .to_string()
+            .ends_with("500 Internal Server Error: disk full"));
+        assert!(respond(201, "Created", "").is_err());
+    }
+}
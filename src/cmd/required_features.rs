@@ -0,0 +1,188 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Checks that a chain supports the runtime features a contract requires.
+//!
+//! The features are listed in `contract.required_features` of the metadata. Two kinds of them
+//! can be queried from the chain: `pallet:<Name>`, a pallet of the runtime, and `rpc:<method>`,
+//! an RPC method exposed by the node. Any other feature, e.g. a chain extension, cannot be
+//! checked and is only reported.
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use jsonrpsee::common::Params;
+use serde_json::Value;
+use subxt::{ClientBuilder, DefaultNodeRuntime};
+
+/// The outcome of checking the required features against a chain.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct FeatureCheck {
+    /// The features the chain does not support.
+    pub missing: Vec<String>,
+    /// The features which cannot be queried from the chain.
+    pub unchecked: Vec<String>,
+}
+
+/// Returns the `required_features` of the metadata, or of a bundle.
+///
+/// The legacy flat layout has the fields of the `contract` section at the top level.
+pub(crate) fn required_features(metadata: &Value) -> Result<Vec<String>> {
+    let contract = metadata.get("contract").unwrap_or(metadata);
+    match contract.get("required_features") {
+        None => Ok(Vec::new()),
+        Some(features) => serde_json::from_value(features.clone())
+            .context("contract.required_features should be an array of strings"),
+    }
+}
+
+/// Checks the `required` features against the pallets of the runtime and the response of the
+/// `rpc_methods` RPC.
+pub(crate) fn check_features<F>(
+    required: &[String],
+    has_pallet: F,
+    rpc_methods: &Value,
+) -> FeatureCheck
+where
+    F: Fn(&str) -> bool,
+{
+    let methods = rpc_methods
+        .get("methods")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let mut check = FeatureCheck::default();
+    for feature in required {
+        let supported = if let Some(pallet) = feature.strip_prefix("pallet:") {
+            has_pallet(pallet)
+        } else if let Some(method) = feature.strip_prefix("rpc:") {
+            methods.iter().any(|available| available == method)
+        } else {
+            check.unchecked.push(feature.clone());
+            continue;
+        };
+        if !supported {
+            check.missing.push(feature.clone());
+        }
+    }
+    check
+}
+
+/// Fails if the chain at `url` does not support all of the `required` features.
+///
+/// With `allow_missing` the missing features are only warned about.
+pub(crate) fn require_features(
+    url: &url::Url,
+    required: &[String],
+    allow_missing: bool,
+) -> Result<()> {
+    if required.is_empty() {
+        return Ok(());
+    }
+    let check = async_std::task::block_on(async move {
+        let rpc = jsonrpsee::ws_client(url.as_str()).await?;
+        let methods: Value = rpc.request("rpc_methods", Params::None).await?;
+        let cli = ClientBuilder::<DefaultNodeRuntime>::new()
+            .set_client(rpc)
+            .build()
+            .await?;
+        let metadata = cli.metadata();
+        Ok::<_, anyhow::Error>(check_features(
+            required,
+            |pallet| metadata.module(pallet).is_ok(),
+            &methods,
+        ))
+    })?;
+
+    if !check.unchecked.is_empty() {
+        println!(
+            "{} {}",
+            "warning:".yellow().bold(),
+            format!(
+                "the chain cannot be queried for the required features: {}",
+                check.unchecked.join(", ")
+            )
+            .bold()
+        );
+    }
+    if check.missing.is_empty() {
+        return Ok(());
+    }
+    let missing = format!(
+        "the chain at {} does not support the required features: {}",
+        url,
+        check.missing.join(", ")
+    );
+    if allow_missing {
+        println!("{} {}", "warning:".yellow().bold(), missing.bold());
+        Ok(())
+    } else {
+        anyhow::bail!(
+            "Refusing to deploy, {}. Pass --allow-missing-features to deploy anyway",
+            missing
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn features(features: &[&str]) -> Vec<String> {
+        features.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn required_features_are_read_from_both_layouts() {
+        let current = json!({ "contract": { "required_features": ["pallet:Contracts"] } });
+        let legacy = json!({ "name": "flipper", "required_features": ["pallet:Contracts"] });
+
+        assert_eq!(
+            required_features(&current).unwrap(),
+            features(&["pallet:Contracts"])
+        );
+        assert_eq!(
+            required_features(&legacy).unwrap(),
+            features(&["pallet:Contracts"])
+        );
+        assert!(required_features(&json!({ "contract": {} }))
+            .unwrap()
+            .is_empty());
+        assert!(required_features(&json!({ "contract": { "required_features": [1] } })).is_err());
+    }
+
+    #[test]
+    fn features_are_checked_against_pallets_and_rpc_methods() {
+        let required = features(&[
+            "pallet:Contracts",
+            "pallet:Assets",
+            "rpc:contracts_call",
+            "rpc:contracts_rentProjection",
+            "chain-extension:rand",
+        ]);
+        let methods = json!({ "version": 1, "methods": ["contracts_call", "rpc_methods"] });
+
+        let check = check_features(&required, |pallet| pallet == "Contracts", &methods);
+
+        assert_eq!(
+            check,
+            FeatureCheck {
+                missing: features(&["pallet:Assets", "rpc:contracts_rentProjection"]),
+                unchecked: features(&["chain-extension:rand"]),
+            }
+        );
+    }
+}
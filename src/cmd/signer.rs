@@ -0,0 +1,186 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Signs extrinsics with a key pair of any of the signature schemes supported by the runtime.
+
+use std::{fmt, future::Future, pin::Pin, str::FromStr};
+
+use anyhow::Result;
+use sp_core::{crypto::Pair, ecdsa, ed25519, sr25519};
+use subxt::{
+    extrinsic::{SignedPayload, UncheckedExtrinsic},
+    system::System,
+    DefaultNodeRuntime, PairSigner, Signer,
+};
+
+/// The signature scheme of the signing key, selected with `--scheme`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CryptoScheme {
+    Sr25519,
+    Ed25519,
+    Ecdsa,
+}
+
+impl Default for CryptoScheme {
+    fn default() -> Self {
+        CryptoScheme::Sr25519
+    }
+}
+
+impl FromStr for CryptoScheme {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        match input {
+            "sr25519" => Ok(CryptoScheme::Sr25519),
+            "ed25519" => Ok(CryptoScheme::Ed25519),
+            "ecdsa" => Ok(CryptoScheme::Ecdsa),
+            _ => anyhow::bail!(
+                "Unknown signature scheme `{}`, expected sr25519, ed25519 or ecdsa",
+                input
+            ),
+        }
+    }
+}
+
+impl fmt::Display for CryptoScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scheme = match self {
+            CryptoScheme::Sr25519 => "sr25519",
+            CryptoScheme::Ed25519 => "ed25519",
+            CryptoScheme::Ecdsa => "ecdsa",
+        };
+        write!(f, "{}", scheme)
+    }
+}
+
+impl CryptoScheme {
+    /// Returns the signer of the key pair of this scheme derived from the secret key URI.
+    pub fn signer(self, suri: &str, password: Option<&str>) -> Result<ExtrinsicSigner> {
+        let secret_error = |_| anyhow::anyhow!("Secret string error for a {} key", self);
+        Ok(match self {
+            CryptoScheme::Sr25519 => ExtrinsicSigner::Sr25519(PairSigner::new(
+                sr25519::Pair::from_string(suri, password).map_err(secret_error)?,
+            )),
+            CryptoScheme::Ed25519 => ExtrinsicSigner::Ed25519(PairSigner::new(
+                ed25519::Pair::from_string(suri, password).map_err(secret_error)?,
+            )),
+            CryptoScheme::Ecdsa => ExtrinsicSigner::Ecdsa(PairSigner::new(
+                ecdsa::Pair::from_string(suri, password).map_err(secret_error)?,
+            )),
+        })
+    }
+}
+
+/// Signs extrinsics with a key pair of one of the [`CryptoScheme`]s.
+///
+/// Unlike a boxed [`Signer`] it can be cloned to sign with explicit nonces, e.g. for the calls
+/// in flight at the same time of `call --repeat`.
+#[derive(Clone)]
+pub enum ExtrinsicSigner {
+    Sr25519(PairSigner<DefaultNodeRuntime, sr25519::Pair>),
+    Ed25519(PairSigner<DefaultNodeRuntime, ed25519::Pair>),
+    Ecdsa(PairSigner<DefaultNodeRuntime, ecdsa::Pair>),
+}
+
+impl ExtrinsicSigner {
+    /// Signs the following extrinsics with the `nonce` instead of the current nonce of the
+    /// account.
+    pub fn set_nonce(&mut self, nonce: <DefaultNodeRuntime as System>::Index) {
+        match self {
+            ExtrinsicSigner::Sr25519(signer) => signer.set_nonce(nonce),
+            ExtrinsicSigner::Ed25519(signer) => signer.set_nonce(nonce),
+            ExtrinsicSigner::Ecdsa(signer) => signer.set_nonce(nonce),
+        }
+    }
+
+    fn signer(&self) -> &(dyn Signer<DefaultNodeRuntime> + Send + Sync) {
+        match self {
+            ExtrinsicSigner::Sr25519(signer) => signer,
+            ExtrinsicSigner::Ed25519(signer) => signer,
+            ExtrinsicSigner::Ecdsa(signer) => signer,
+        }
+    }
+}
+
+impl Signer<DefaultNodeRuntime> for ExtrinsicSigner {
+    fn account_id(&self) -> &<DefaultNodeRuntime as System>::AccountId {
+        self.signer().account_id()
+    }
+
+    fn nonce(&self) -> Option<<DefaultNodeRuntime as System>::Index> {
+        self.signer().nonce()
+    }
+
+    fn sign(
+        &self,
+        extrinsic: SignedPayload<DefaultNodeRuntime>,
+    ) -> Pin<
+        Box<
+            dyn Future<Output = Result<UncheckedExtrinsic<DefaultNodeRuntime>, String>>
+                + Send
+                + Sync,
+        >,
+    > {
+        self.signer().sign(extrinsic)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CryptoScheme;
+    use sp_core::{
+        crypto::{AccountId32, Pair},
+        ecdsa, ed25519,
+        hashing::blake2_256,
+    };
+    use subxt::Signer;
+
+    #[test]
+    fn signer_account_depends_on_the_scheme() {
+        let account =
+            |scheme: CryptoScheme| scheme.signer("//Alice", None).unwrap().account_id().clone();
+        let ed25519 = ed25519::Pair::from_string("//Alice", None).unwrap();
+        let ecdsa = ecdsa::Pair::from_string("//Alice", None).unwrap();
+
+        assert_eq!(
+            account(CryptoScheme::Ed25519),
+            AccountId32::from(ed25519.public().0)
+        );
+        // ecdsa accounts are the hash of the compressed public key
+        assert_eq!(
+            account(CryptoScheme::Ecdsa),
+            AccountId32::from(blake2_256(&ecdsa.public().0))
+        );
+        assert_ne!(
+            account(CryptoScheme::Sr25519),
+            account(CryptoScheme::Ed25519)
+        );
+    }
+
+    #[test]
+    fn scheme_is_parsed() {
+        assert_eq!(
+            "ecdsa".parse::<CryptoScheme>().unwrap(),
+            CryptoScheme::Ecdsa
+        );
+        assert_eq!(
+            "ed25519".parse::<CryptoScheme>().unwrap().to_string(),
+            "ed25519"
+        );
+        assert!("secp256k1".parse::<CryptoScheme>().is_err());
+    }
+}
@@ -0,0 +1,109 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Splits signing an extrinsic from submitting it, for keys held outside of this machine.
+
+use anyhow::{Context, Result};
+use codec::Encode;
+use jsonrpsee::common::{to_value, Params};
+use serde_json::Value;
+use sp_core::{crypto::AccountId32, H256};
+use subxt::{
+    extrinsic::{DefaultExtra, SignedExtra, SignedPayload},
+    system::AccountStoreExt,
+    Call, Client, DefaultNodeRuntime,
+};
+
+/// The data needed to sign an extrinsic externally.
+pub(crate) struct UnsignedExtrinsic {
+    /// The nonce of the signing account used in the payload.
+    pub nonce: u32,
+    /// The SCALE encoded call.
+    pub call_data: Vec<u8>,
+    /// The bytes to sign, the payload is hashed with blake2 if it is longer than 256 bytes.
+    pub signing_payload: Vec<u8>,
+}
+
+/// Builds the payload of `call` for signing by the account `from`, fetching its current nonce.
+pub(crate) async fn unsigned_extrinsic<C>(
+    cli: &Client<DefaultNodeRuntime>,
+    rpc: &jsonrpsee::Client,
+    from: &AccountId32,
+    call: C,
+) -> Result<UnsignedExtrinsic>
+where
+    C: Call<DefaultNodeRuntime>,
+{
+    let nonce = cli.account(from, None).await?.nonce;
+    let version: Value = rpc
+        .request("state_getRuntimeVersion", Params::Array(vec![]))
+        .await?;
+    let version_field = |field: &str| {
+        version
+            .get(field)
+            .and_then(Value::as_u64)
+            .map(|version| version as u32)
+            .ok_or(anyhow::anyhow!(
+                "{} not found in the runtime version",
+                field
+            ))
+    };
+    let extra = DefaultExtra::<DefaultNodeRuntime>::new(
+        version_field("specVersion")?,
+        version_field("transactionVersion")?,
+        nonce,
+        *cli.genesis(),
+    );
+
+    let call = cli.encode(call)?;
+    let call_data = call.0.clone();
+    let payload = SignedPayload::<DefaultNodeRuntime>::new(call, extra.extra())
+        .map_err(|err| anyhow::anyhow!("Failed to create the signing payload: {:?}", err))?;
+    let signing_payload = payload.using_encoded(|payload| payload.to_vec());
+    Ok(UnsignedExtrinsic {
+        nonce,
+        call_data,
+        signing_payload,
+    })
+}
+
+/// Submits an extrinsic which was signed externally. Returns the extrinsic hash.
+///
+/// The node only checks the extrinsic when adding it to the transaction pool, its inclusion in a
+/// block is not awaited.
+pub(crate) async fn submit_signed(rpc: &jsonrpsee::Client, signed_hex: &str) -> Result<H256> {
+    let signed = decode_hex(signed_hex)?;
+    let params = Params::Array(vec![to_value(format!("0x{}", hex::encode(signed)))?]);
+    let hash: H256 = rpc.request("author_submitExtrinsic", params).await?;
+    Ok(hash)
+}
+
+fn decode_hex(input: &str) -> Result<Vec<u8>> {
+    hex::decode(input.trim_start_matches("0x"))
+        .context("The signed extrinsic should be hex encoded")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode_hex;
+
+    #[test]
+    fn signed_extrinsic_is_hex_decoded() {
+        assert_eq!(decode_hex("0x0102").unwrap(), vec![1, 2]);
+        assert_eq!(decode_hex("0102").unwrap(), vec![1, 2]);
+        assert!(decode_hex("0xzz").is_err());
+    }
+}
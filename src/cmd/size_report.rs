@@ -0,0 +1,273 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Breaks the size of a Wasm down by section and by function, for finding what bloats a contract.
+//!
+//! The sizes are read from the binary as is, re-serializing it with `parity-wasm` would change
+//! the encoding of the sizes and so the sizes themselves. Only the names of the functions are
+//! taken from the parsed module.
+
+use std::{collections::HashMap, convert::TryFrom};
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use parity_wasm::elements::{ImportCountType, Module};
+
+/// How many of the largest functions are reported.
+const LARGEST_FUNCTIONS: usize = 10;
+
+/// The size of a Wasm by section, and its largest functions.
+#[derive(Debug)]
+pub struct SizeReport {
+    /// The size of the whole Wasm, in bytes.
+    total: usize,
+    /// The name and size of each section in the order of the module, including its header.
+    sections: Vec<(String, usize)>,
+    /// The name and body size of the largest functions, largest first.
+    functions: Vec<(String, usize)>,
+}
+
+impl SizeReport {
+    /// Breaks down the size of the `wasm`.
+    pub fn new(wasm: &[u8]) -> Result<Self> {
+        let mut reader = Reader { wasm, offset: 0 };
+        if reader.bytes(8).ok().map(|preamble| &preamble[..4]) != Some(&b"\0asm"[..]) {
+            anyhow::bail!("Not a Wasm module, the magic number is missing");
+        }
+
+        let mut sections = Vec::new();
+        let mut body_sizes = Vec::new();
+        while reader.offset < wasm.len() {
+            let start = reader.offset;
+            let id = reader.bytes(1)?[0];
+            let size = reader.leb()?;
+            let mut payload = Reader {
+                wasm: reader.bytes(size)?,
+                offset: 0,
+            };
+            let name = match id {
+                0 => format!("custom \"{}\"", payload.name()?),
+                1 => "type".into(),
+                2 => "import".into(),
+                3 => "function".into(),
+                4 => "table".into(),
+                5 => "memory".into(),
+                6 => "global".into(),
+                7 => "export".into(),
+                8 => "start".into(),
+                9 => "element".into(),
+                10 => {
+                    for _ in 0..payload.leb()? {
+                        let body_size = payload.leb()?;
+                        payload.bytes(body_size)?;
+                        body_sizes.push(body_size);
+                    }
+                    "code".into()
+                }
+                11 => "data".into(),
+                12 => "data count".into(),
+                id => anyhow::bail!("Unknown section id {} at offset {}", id, start),
+            };
+            sections.push((name, reader.offset - start));
+        }
+
+        Ok(SizeReport {
+            total: wasm.len(),
+            sections,
+            functions: largest_functions(wasm, body_sizes)?,
+        })
+    }
+
+    /// Displays the size of each section and of the largest functions with their share of the
+    /// total.
+    pub fn display(&self) -> String {
+        let share = |size: usize| size as f64 / self.total as f64 * 100.0;
+        let mut out = String::from("Size by section:");
+        for (section, size) in &self.sections {
+            out.push_str(&format!(
+                "\n  {:<24} {:>8} B {:>5.1}%",
+                section,
+                size,
+                share(*size)
+            ));
+        }
+        out.push_str(&format!("\n  {:<24} {:>8} B", "Total".bold(), self.total));
+        if !self.functions.is_empty() {
+            out.push_str("\n\nLargest functions:");
+            for (function, size) in &self.functions {
+                out.push_str(&format!(
+                    "\n  {:>8} B {:>5.1}%  {}",
+                    size,
+                    share(*size),
+                    function
+                ));
+            }
+        }
+        out
+    }
+
+    /// Returns the size report as JSON.
+    pub fn to_json(&self) -> serde_json::Value {
+        let entries = |entries: &[(String, usize)]| {
+            entries
+                .iter()
+                .map(|(name, size)| serde_json::json!({ "name": name, "size": size }))
+                .collect::<Vec<_>>()
+        };
+        serde_json::json!({
+            "total_size": self.total,
+            "sections": entries(&self.sections),
+            "largest_functions": entries(&self.functions),
+        })
+    }
+}
+
+/// Returns the name and body size of the largest functions, largest first.
+///
+/// Functions are named by the `name` section if the Wasm has one, otherwise by their index.
+fn largest_functions(wasm: &[u8], body_sizes: Vec<usize>) -> Result<Vec<(String, usize)>> {
+    let module: Module =
+        parity_wasm::deserialize_buffer(wasm).context("Failed to parse the Wasm")?;
+    // the imported functions precede the defined functions in the index space
+    let imported = module.import_count(ImportCountType::Function);
+    let module = module.parse_names().unwrap_or_else(|(_, module)| module);
+    let names = module
+        .names_section()
+        .and_then(|section| section.functions())
+        .map(|functions| {
+            functions
+                .names()
+                .iter()
+                .map(|(index, name)| (index, name.clone()))
+                .collect::<HashMap<_, _>>()
+        })
+        .unwrap_or_default();
+
+    let mut functions = body_sizes
+        .into_iter()
+        .enumerate()
+        .map(|(index, size)| {
+            let index = u32::try_from(imported + index).expect("Wasm indices are u32");
+            let name = names
+                .get(&index)
+                .cloned()
+                .unwrap_or_else(|| format!("function {}", index));
+            (name, size)
+        })
+        .collect::<Vec<_>>();
+    functions.sort_by(|(_, a), (_, b)| b.cmp(a));
+    functions.truncate(LARGEST_FUNCTIONS);
+    Ok(functions)
+}
+
+/// Reads the encoding of a Wasm.
+struct Reader<'a> {
+    wasm: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .wasm
+            .get(self.offset..self.offset + len)
+            .ok_or_else(|| {
+                anyhow::anyhow!("Unexpected end of the Wasm at offset {}", self.offset)
+            })?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    /// Reads an unsigned LEB128 encoded integer.
+    fn leb(&mut self) -> Result<usize> {
+        let mut value = 0;
+        for shift in (0..32).step_by(7) {
+            let byte = self.bytes(1)?[0];
+            value |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        anyhow::bail!("Invalid integer at offset {}", self.offset)
+    }
+
+    /// Reads the name of a custom section.
+    fn name(&mut self) -> Result<String> {
+        let len = self.leb()?;
+        Ok(String::from_utf8_lossy(self.bytes(len)?).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SizeReport;
+
+    #[test]
+    fn sizes_are_broken_down_by_section_and_function() {
+        let wasm = wabt::Wat2Wasm::new()
+            .write_debug_names(true)
+            .convert(
+                r#"(module
+                    (import "seal0" "seal_input" (func $seal_input (param i32 i32)))
+                    (memory 1)
+                    (func $small)
+                    (func $large (result i32)
+                        i32.const 1
+                        i32.const 2
+                        i32.add)
+                    (export "call" (func $small))
+                    (data (i32.const 0) "hello"))"#,
+            )
+            .expect("invalid wabt");
+        let wasm = wasm.as_ref();
+        let report = SizeReport::new(wasm).unwrap();
+
+        let sections = report
+            .sections
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sections,
+            [
+                "type",
+                "import",
+                "function",
+                "memory",
+                "export",
+                "code",
+                "data",
+                "custom \"name\""
+            ]
+        );
+        let section_sizes = report.sections.iter().map(|(_, size)| size).sum::<usize>();
+        assert_eq!(section_sizes + 8, wasm.len());
+
+        let functions = report
+            .functions
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(functions, ["large", "small"]);
+        assert!(report.functions[0].1 > report.functions[1].1);
+        assert_eq!(report.to_json()["total_size"], wasm.len());
+    }
+
+    #[test]
+    fn not_a_wasm_is_rejected() {
+        assert!(SizeReport::new(b"(module)").is_err());
+    }
+}
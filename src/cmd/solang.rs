@@ -0,0 +1,192 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Compiles Solidity contracts with `solang`, for a package with
+//! `[package.metadata.contract] language = "solidity"`.
+//!
+//! solang writes the Wasm and a `.contract` bundle, whose abi is taken over into the metadata.
+//! The Wasm is post-processed and optimized like the Wasm of an ink! contract.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{Context, Result};
+use semver::Version;
+use serde_json::{Map, Value};
+
+use crate::{crate_metadata::CrateMetadata, util, Verbosity};
+
+/// The executable of the solang compiler.
+const SOLANG: &str = "solang";
+
+/// The sections of the `.contract` bundle of solang which are not the abi.
+const NON_ABI_SECTIONS: [&str; 4] = ["metadataVersion", "source", "contract", "user"];
+
+/// Compiles the Solidity `source` with solang and copies the Wasm to `dest_wasm`.
+pub(crate) fn compile(
+    crate_metadata: &CrateMetadata,
+    source: &Path,
+    dest_wasm: &Path,
+    verbosity: Option<Verbosity>,
+) -> Result<()> {
+    which::which(SOLANG).context(
+        "The solang compiler is required to build a Solidity contract, see \
+         https://solang.readthedocs.io/en/latest/installing.html",
+    )?;
+    let out_dir = out_dir(crate_metadata);
+    if out_dir.exists() {
+        fs::remove_dir_all(&out_dir)?;
+    }
+    fs::create_dir_all(&out_dir)?;
+
+    let mut solang = Command::new(SOLANG);
+    solang
+        .args(&["--target", "substrate", "--output"])
+        .arg(&out_dir)
+        .arg(source);
+    if Verbosity::at_least(verbosity, Verbosity::Verbose) {
+        solang.arg("--verbose");
+    }
+    util::print_command(verbosity, &solang);
+    let output = solang
+        .output()
+        .context(format!("Error invoking `{:?}`", solang))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "solang failed to compile {}:\n{}",
+            source.display(),
+            String::from_utf8_lossy(&output.stderr)
+        )
+    }
+
+    let wasm = artifact(&out_dir, "wasm")?;
+    fs::copy(&wasm, dest_wasm)?;
+    Ok(())
+}
+
+/// Returns the abi of the contract compiled by `compile`.
+pub(crate) fn abi(crate_metadata: &CrateMetadata) -> Result<Map<String, Value>> {
+    let bundle = artifact(&out_dir(crate_metadata), "contract")?;
+    let contents = fs::read(&bundle).context(format!("Failed to read {}", bundle.display()))?;
+    let mut abi: Map<String, Value> = serde_json::from_slice(&contents)?;
+    for section in &NON_ABI_SECTIONS {
+        abi.remove(*section);
+    }
+    Ok(abi)
+}
+
+/// Returns the version of solang.
+pub(crate) fn solang_version() -> Result<Version> {
+    let output = Command::new(SOLANG)
+        .arg("--version")
+        .output()
+        .context("Error invoking `solang --version`")?;
+    parse_solang_version(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Returns the Solidity version the `source` declares with `pragma solidity`.
+pub(crate) fn solidity_version(source: &Path) -> Result<Version> {
+    let contents =
+        fs::read_to_string(source).context(format!("Failed to read {}", source.display()))?;
+    parse_pragma_version(&contents).ok_or(anyhow::anyhow!(
+        "{} declares no Solidity version, add e.g. `pragma solidity ^0.7.0;`",
+        source.display()
+    ))
+}
+
+/// The directory solang writes the artifacts of the contract to.
+fn out_dir(crate_metadata: &CrateMetadata) -> PathBuf {
+    crate_metadata
+        .target_directory
+        .join("solang")
+        .join(&crate_metadata.package_name)
+}
+
+/// Returns the single artifact with the `extension` solang wrote to `out_dir`.
+fn artifact(out_dir: &Path, extension: &str) -> Result<PathBuf> {
+    let mut artifacts = fs::read_dir(out_dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some(extension))
+        .collect::<Vec<_>>();
+    match (artifacts.pop(), artifacts.is_empty()) {
+        (Some(artifact), true) => Ok(artifact),
+        (Some(_), false) => anyhow::bail!(
+            "solang compiled several contracts, a package must contain a single contract"
+        ),
+        (None, _) => anyhow::bail!("solang wrote no .{} file", extension),
+    }
+}
+
+/// Parses the version of the output of `solang --version`, e.g. `solang version v0.1.7`.
+fn parse_solang_version(output: &str) -> Result<Version> {
+    let version = output
+        .split_whitespace()
+        .map(|word| word.trim_start_matches('v'))
+        .find(|word| word.starts_with(|c: char| c.is_ascii_digit()))
+        .ok_or(anyhow::anyhow!(
+            "No version in the solang output `{}`",
+            output
+        ))?;
+    Version::parse(version).context(format!("Invalid solang version `{}`", version))
+}
+
+/// Returns the lowest version matching the `pragma solidity` requirement, e.g. `0.7.0` of
+/// `pragma solidity >=0.7.0 <0.9.0;`.
+fn parse_pragma_version(source: &str) -> Option<Version> {
+    let requirement = source
+        .lines()
+        .map(str::trim)
+        .find_map(|line| line.strip_prefix("pragma solidity"))?;
+    let version = requirement
+        .trim_start()
+        .trim_start_matches(|c: char| !c.is_ascii_digit())
+        .split(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .next()?;
+    let mut parts = version.split('.').collect::<Vec<_>>();
+    while parts.len() < 3 {
+        parts.push("0");
+    }
+    Version::parse(&parts.join(".")).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_are_parsed() {
+        assert_eq!(
+            parse_solang_version("solang version v0.1.7\n").unwrap(),
+            Version::new(0, 1, 7)
+        );
+        assert!(parse_solang_version("solang").is_err());
+
+        let version = |source| parse_pragma_version(source);
+        assert_eq!(
+            version("// SPDX-License-Identifier: MIT\npragma solidity ^0.7.0;\n"),
+            Some(Version::new(0, 7, 0))
+        );
+        assert_eq!(
+            version("pragma solidity >=0.6.2 <0.9.0;"),
+            Some(Version::new(0, 6, 2))
+        );
+        assert_eq!(version("pragma solidity 0.8;"), Some(Version::new(0, 8, 0)));
+        assert_eq!(version("contract flipper {}"), None);
+    }
+}
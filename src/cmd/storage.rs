@@ -0,0 +1,154 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Storage items of the contracts pallet which are not exposed by `subxt`.
+
+use anyhow::Result;
+use codec::{Decode, Encode};
+use sp_core::{crypto::AccountId32, storage::StorageKey, H256};
+use subxt::{
+    balances::Balances, system::System, Client, DefaultNodeRuntime, Metadata, MetadataError, Store,
+};
+
+const MODULE: &str = "Contracts";
+
+/// Prefix of the storage key of the default child trie, see `sp_core::storage::ChildInfo`.
+const DEFAULT_CHILD_STORAGE_KEY_PREFIX: &[u8] = b":child_storage:default:";
+
+type Balance = <DefaultNodeRuntime as Balances>::Balance;
+type BlockNumber = <DefaultNodeRuntime as System>::BlockNumber;
+
+/// The `PristineCode` storage map, which holds the original code keyed by its code hash.
+#[derive(Encode)]
+pub struct PristineCodeStore<'a> {
+    pub code_hash: &'a H256,
+}
+
+impl Store<DefaultNodeRuntime> for PristineCodeStore<'_> {
+    const MODULE: &'static str = MODULE;
+    const FIELD: &'static str = "PristineCode";
+    type Returns = Vec<u8>;
+
+    fn prefix(metadata: &Metadata) -> Result<StorageKey, MetadataError> {
+        Ok(metadata
+            .module(Self::MODULE)?
+            .storage(Self::FIELD)?
+            .prefix())
+    }
+
+    fn key(&self, metadata: &Metadata) -> Result<StorageKey, MetadataError> {
+        Ok(metadata
+            .module(Self::MODULE)?
+            .storage(Self::FIELD)?
+            .map()?
+            .key(self.code_hash))
+    }
+}
+
+/// The `ContractInfoOf` storage map, which holds the info of a contract keyed by its account.
+#[derive(Encode)]
+pub struct ContractInfoOfStore<'a> {
+    pub account: &'a AccountId32,
+}
+
+impl Store<DefaultNodeRuntime> for ContractInfoOfStore<'_> {
+    const MODULE: &'static str = MODULE;
+    const FIELD: &'static str = "ContractInfoOf";
+    type Returns = ContractInfo;
+
+    fn prefix(metadata: &Metadata) -> Result<StorageKey, MetadataError> {
+        Ok(metadata
+            .module(Self::MODULE)?
+            .storage(Self::FIELD)?
+            .prefix())
+    }
+
+    fn key(&self, metadata: &Metadata) -> Result<StorageKey, MetadataError> {
+        Ok(metadata
+            .module(Self::MODULE)?
+            .storage(Self::FIELD)?
+            .map()?
+            .key(self.account))
+    }
+}
+
+/// Information about a contract, mirrors `pallet_contracts::ContractInfo`.
+#[derive(Debug, Decode)]
+pub enum ContractInfo {
+    Alive(AliveContractInfo),
+    Tombstone(H256),
+}
+
+/// Information about a contract which is alive, mirrors `pallet_contracts::AliveContractInfo`.
+#[derive(Debug, Decode)]
+pub struct AliveContractInfo {
+    /// Unique ID of the child trie holding the contract storage.
+    pub trie_id: Vec<u8>,
+    /// The total number of bytes used by the contract storage.
+    pub storage_size: u32,
+    /// The number of key-value pairs that have values of zero length.
+    pub empty_pair_count: u32,
+    /// The total number of key-value pairs in the contract storage.
+    pub total_pair_count: u32,
+    /// The code the contract is instantiated from.
+    pub code_hash: H256,
+    /// Pay rent at most up to this value.
+    pub rent_allowance: Balance,
+    /// Last block rent has been payed.
+    pub deduct_block: BlockNumber,
+    /// Last block child storage has been written.
+    pub last_write: Option<BlockNumber>,
+}
+
+impl AliveContractInfo {
+    /// The storage key of the child trie holding the contract storage.
+    pub fn child_storage_key(&self) -> StorageKey {
+        let mut key = DEFAULT_CHILD_STORAGE_KEY_PREFIX.to_vec();
+        key.extend_from_slice(&self.trie_id);
+        StorageKey(key)
+    }
+}
+
+/// Returns `true` if code with the given hash has already been uploaded to the chain.
+pub async fn code_exists(cli: &Client<DefaultNodeRuntime>, code_hash: &H256) -> Result<bool> {
+    let code = cli.fetch(&PristineCodeStore { code_hash }, None).await?;
+    Ok(code.is_some())
+}
+
+/// Fetches the info of the contract at the given account, failing if there is none.
+pub async fn contract_info(
+    cli: &Client<DefaultNodeRuntime>,
+    account: &AccountId32,
+) -> Result<ContractInfo> {
+    cli.fetch(&ContractInfoOfStore { account }, None)
+        .await?
+        .ok_or(anyhow::anyhow!("No contract found at {}", account))
+}
+
+/// Fetches the hash of the code the contract at the given account is instantiated from, failing
+/// if the contract has been evicted.
+pub async fn contract_code_hash(
+    cli: &Client<DefaultNodeRuntime>,
+    account: &AccountId32,
+) -> Result<H256> {
+    match contract_info(cli, account).await? {
+        ContractInfo::Alive(info) => Ok(info.code_hash),
+        ContractInfo::Tombstone(_) => anyhow::bail!(
+            "The contract at {} has been evicted, only its tombstone remains",
+            account
+        ),
+    }
+}
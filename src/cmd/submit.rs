@@ -0,0 +1,364 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Signs and submits a call of the contracts pallet which was encoded elsewhere, e.g. the call
+//! data printed by `deploy --generate-call-data`.
+//!
+//! The call data is the SCALE encoded runtime call: the module and call index of
+//! `Contracts::put_code`, `Contracts::call` or `Contracts::instantiate`, followed by its
+//! params. The indices are checked against the runtime metadata of the node and the params must
+//! decode completely, so that nothing unexpected is signed.
+
+use anyhow::{Context, Result};
+use codec::{Decode, Encode, Output};
+use colored::Colorize;
+use sp_core::H256;
+use structopt::StructOpt;
+use subxt::{
+    contracts::*, system::System, Call, Client, ClientBuilder, DefaultNodeRuntime,
+    ExtrinsicSuccess, Metadata,
+};
+
+use super::{
+    balance::check_balance,
+    cost::{CostReport, OperationCost},
+    signer::ExtrinsicSigner,
+};
+use crate::ExtrinsicOpts;
+
+/// The functions of the contracts pallet whose calls can be submitted.
+const FUNCTIONS: &[&str] = &["put_code", "call", "instantiate"];
+
+/// Sign and submit a pre-encoded call of the contracts pallet
+#[derive(Debug, StructOpt)]
+#[structopt(name = "submit")]
+pub struct SubmitCommand {
+    #[structopt(flatten)]
+    extrinsic_opts: ExtrinsicOpts,
+    /// The hex encoded call, e.g. as printed by `deploy --generate-call-data`
+    #[structopt(long, value_name = "hex")]
+    call_data: String,
+}
+
+/// The params of `Contracts::put_code`.
+#[derive(Debug, Decode)]
+struct PutCodeParams {
+    code: Vec<u8>,
+}
+
+/// The params of `Contracts::call`.
+#[derive(Debug, Decode)]
+struct CallParams {
+    dest: <DefaultNodeRuntime as System>::Address,
+    #[codec(compact)]
+    value: u128,
+    #[codec(compact)]
+    gas_limit: u64,
+    data: Vec<u8>,
+}
+
+/// The params of `Contracts::instantiate`.
+#[derive(Debug, Decode)]
+struct InstantiateParams {
+    #[codec(compact)]
+    endowment: u128,
+    #[codec(compact)]
+    gas_limit: u64,
+    code_hash: H256,
+    data: Vec<u8>,
+}
+
+/// A decoded call of the contracts pallet.
+#[derive(Debug)]
+enum ContractsCall {
+    PutCode(PutCodeParams),
+    Call(CallParams),
+    Instantiate(InstantiateParams),
+}
+
+impl ContractsCall {
+    /// The balance transferred by the call.
+    fn transfer(&self) -> u128 {
+        match self {
+            ContractsCall::PutCode(_) => 0,
+            ContractsCall::Call(params) => params.value,
+            ContractsCall::Instantiate(params) => params.endowment,
+        }
+    }
+
+    fn function(&self) -> &'static str {
+        match self {
+            ContractsCall::PutCode(_) => "put_code",
+            ContractsCall::Call(_) => "call",
+            ContractsCall::Instantiate(_) => "instantiate",
+        }
+    }
+
+    fn display(&self) -> String {
+        match self {
+            ContractsCall::PutCode(params) => format!(
+                "Contracts::put_code of {} bytes of code with the hash {:?}",
+                params.code.len(),
+                H256(sp_core::blake2_256(&params.code))
+            ),
+            ContractsCall::Call(params) => format!(
+                "Contracts::call of {:?} with value {}, gas limit {} and data 0x{}",
+                params.dest,
+                params.value,
+                params.gas_limit,
+                hex::encode(&params.data)
+            ),
+            ContractsCall::Instantiate(params) => format!(
+                "Contracts::instantiate of {:?} with endowment {}, gas limit {} and data 0x{}",
+                params.code_hash,
+                params.endowment,
+                params.gas_limit,
+                hex::encode(&params.data)
+            ),
+        }
+    }
+}
+
+/// The params of a call, already encoded.
+#[derive(Clone)]
+struct RawParams(Vec<u8>);
+
+impl Encode for RawParams {
+    fn encode_to<W: Output>(&self, dest: &mut W) {
+        dest.write(&self.0)
+    }
+}
+
+/// A pre-encoded `Contracts::put_code` call, encoding it prepends the indices again.
+#[derive(Encode)]
+struct PreEncodedPutCode(RawParams);
+
+impl Call<DefaultNodeRuntime> for PreEncodedPutCode {
+    const MODULE: &'static str = "Contracts";
+    const FUNCTION: &'static str = "put_code";
+}
+
+/// A pre-encoded `Contracts::call` call, encoding it prepends the indices again.
+#[derive(Encode)]
+struct PreEncodedCall(RawParams);
+
+impl Call<DefaultNodeRuntime> for PreEncodedCall {
+    const MODULE: &'static str = "Contracts";
+    const FUNCTION: &'static str = "call";
+}
+
+/// A pre-encoded `Contracts::instantiate` call, encoding it prepends the indices again.
+#[derive(Encode)]
+struct PreEncodedInstantiate(RawParams);
+
+impl Call<DefaultNodeRuntime> for PreEncodedInstantiate {
+    const MODULE: &'static str = "Contracts";
+    const FUNCTION: &'static str = "instantiate";
+}
+
+impl SubmitCommand {
+    pub fn exec(&self) -> Result<String> {
+        let call_data = hex::decode(self.call_data.trim_start_matches("0x"))
+            .context("The call data should be hex encoded")?;
+        self.extrinsic_opts.confirm_chain()?;
+        let signer = self.extrinsic_opts.signer()?;
+        self.extrinsic_opts.print_signer(&signer)?;
+
+        async_std::task::block_on(async move {
+            let rpc = self.extrinsic_opts.rpc_client().await?;
+            let cli = ClientBuilder::<DefaultNodeRuntime>::new()
+                .set_client(rpc.clone())
+                .build()
+                .await?;
+            let indices = call_indices(cli.metadata())?;
+            let call = decode_call(&call_data, &indices)?;
+            println!("{} {}", "Submitting".bold(), call.display());
+
+            let params = RawParams(call_data[2..].to_vec());
+            let check = self.extrinsic_opts.check_balance;
+            let transfer = call.transfer();
+            let events = match call {
+                ContractsCall::PutCode(_) => {
+                    let pre_encoded = || PreEncodedPutCode(params.clone());
+                    submit(&cli, &rpc, &signer, pre_encoded, check, transfer).await?
+                }
+                ContractsCall::Call(_) => {
+                    let pre_encoded = || PreEncodedCall(params.clone());
+                    submit(&cli, &rpc, &signer, pre_encoded, check, transfer).await?
+                }
+                ContractsCall::Instantiate(_) => {
+                    let pre_encoded = || PreEncodedInstantiate(params.clone());
+                    submit(&cli, &rpc, &signer, pre_encoded, check, transfer).await?
+                }
+            };
+
+            let mut costs = CostReport::default();
+            costs.add(OperationCost::from_events(
+                call.function(),
+                &events,
+                transfer,
+            )?);
+            let outcome = match call {
+                ContractsCall::PutCode(_) => {
+                    let code_stored = events
+                        .code_stored()?
+                        .ok_or(anyhow::anyhow!("Failed to find CodeStored event"))?;
+                    format!("Code hash: {:?}\n\n\t", code_stored.code_hash)
+                }
+                ContractsCall::Instantiate(_) => {
+                    let instantiated = events
+                        .instantiated()?
+                        .ok_or(anyhow::anyhow!("Failed to find Instantiated event"))?;
+                    format!("Contract account: {:?}\n\n\t", instantiated.contract)
+                }
+                ContractsCall::Call(_) => String::new(),
+            };
+            Ok(format!("{}{}", outcome, costs.display()))
+        })
+    }
+}
+
+/// Signs the call, checking the balance of the signer first if requested, and submits it,
+/// returning the events once it is included in a block.
+async fn submit<C, F>(
+    cli: &Client<DefaultNodeRuntime>,
+    rpc: &jsonrpsee::Client,
+    signer: &ExtrinsicSigner,
+    pre_encoded: F,
+    check: bool,
+    transfer: u128,
+) -> Result<ExtrinsicSuccess<DefaultNodeRuntime>>
+where
+    C: Call<DefaultNodeRuntime> + Send + Sync,
+    F: Fn() -> C,
+{
+    if check {
+        check_balance(cli, rpc, signer, pre_encoded(), transfer).await?;
+    }
+    Ok(cli.watch(pre_encoded(), signer).await?)
+}
+
+/// Returns the module and call index of each of the `FUNCTIONS` in the runtime.
+fn call_indices(metadata: &Metadata) -> Result<Vec<(&'static str, [u8; 2])>> {
+    FUNCTIONS
+        .iter()
+        .map(|function| {
+            let encoded = metadata
+                .module_with_calls("Contracts")
+                .and_then(|calls| calls.call(*function, ()))
+                .map_err(|_| {
+                    anyhow::anyhow!(
+                        "The runtime of the node has no `Contracts::{}` call",
+                        function
+                    )
+                })?
+                .encode();
+            let mut indices = [0; 2];
+            indices.copy_from_slice(&encoded[..2]);
+            Ok((*function, indices))
+        })
+        .collect()
+}
+
+/// Decodes the call data, which must be a complete call of one of the functions whose `indices`
+/// are given.
+fn decode_call(call_data: &[u8], indices: &[(&str, [u8; 2])]) -> Result<ContractsCall> {
+    if call_data.len() < 2 {
+        anyhow::bail!("The call data is too short to be a call")
+    }
+    let function = indices
+        .iter()
+        .find(|(_, call_indices)| call_indices[..] == call_data[..2])
+        .map(|(function, _)| *function)
+        .ok_or(anyhow::anyhow!(
+            "The call data is no call of Contracts::{}, it starts with 0x{}",
+            FUNCTIONS.join(", Contracts::"),
+            hex::encode(&call_data[..2])
+        ))?;
+    let mut params = &call_data[2..];
+    let call = match function {
+        "put_code" => Decode::decode(&mut params).map(ContractsCall::PutCode),
+        "call" => Decode::decode(&mut params).map(ContractsCall::Call),
+        _ => Decode::decode(&mut params).map(ContractsCall::Instantiate),
+    }
+    .context(format!(
+        "Failed to decode the params of Contracts::{}",
+        function
+    ))?;
+    if !params.is_empty() {
+        anyhow::bail!(
+            "The call data has {} bytes after the params of Contracts::{}",
+            params.len(),
+            function
+        )
+    }
+    Ok(call)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::Compact;
+    use sp_core::crypto::AccountId32;
+
+    const INDICES: &[(&str, [u8; 2])] = &[
+        ("put_code", [18, 1]),
+        ("call", [18, 2]),
+        ("instantiate", [18, 3]),
+    ];
+
+    #[test]
+    fn contracts_calls_are_decoded() {
+        let dest = <DefaultNodeRuntime as System>::Address::from(AccountId32::from([1; 32]));
+        let mut call_data = vec![18, 2];
+        call_data.extend((dest, Compact(5u128), Compact(100u64), vec![0xab_u8]).encode());
+        let mut put_code = vec![18, 1];
+        put_code.extend(vec![0u8, 0x61, 0x73, 0x6d].encode());
+
+        let call = decode_call(&call_data, INDICES).unwrap();
+        assert_eq!(call.function(), "call");
+        assert_eq!(call.transfer(), 5);
+        assert!(call
+            .display()
+            .ends_with("with value 5, gas limit 100 and data 0xab"));
+        let put_code = decode_call(&put_code, INDICES).unwrap();
+        assert!(put_code
+            .display()
+            .starts_with("Contracts::put_code of 4 bytes of code"));
+    }
+
+    #[test]
+    fn unexpected_call_data_is_refused() {
+        let mut put_code = vec![18, 1];
+        put_code.extend(vec![0u8; 4].encode());
+        let mut trailing = put_code.clone();
+        trailing.push(0);
+
+        let transfer = decode_call(&[5, 0, 0], INDICES).unwrap_err();
+        assert!(transfer.to_string().starts_with(
+            "The call data is no call of Contracts::put_code, Contracts::call, \
+             Contracts::instantiate"
+        ));
+        let truncated = decode_call(&put_code[..4], INDICES).unwrap_err();
+        assert_eq!(
+            truncated.to_string(),
+            "Failed to decode the params of Contracts::put_code"
+        );
+        let trailing = decode_call(&trailing, INDICES).unwrap_err();
+        assert!(trailing.to_string().contains("1 bytes after the params"));
+        assert!(decode_call(&[18], INDICES).is_err());
+    }
+}
@@ -0,0 +1,110 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Suggests the closest names of the metadata for a mistyped constructor or message name.
+
+/// The most names suggested at once.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Returns `did you mean `name`?` with the candidates closest to `name` by edit distance, if
+/// any is close enough to be a typo of it.
+pub(crate) fn did_you_mean<S: AsRef<str>>(name: &str, candidates: &[S]) -> Option<String> {
+    let name = name.to_lowercase();
+    let max_distance = (name.chars().count() / 3).max(1);
+    let distances = candidates
+        .iter()
+        .map(|candidate| {
+            let distance = edit_distance(&name, &candidate.as_ref().to_lowercase());
+            (candidate.as_ref(), distance)
+        })
+        .filter(|(_, distance)| *distance <= max_distance)
+        .collect::<Vec<_>>();
+    let closest = distances.iter().map(|(_, distance)| *distance).min()?;
+    let mut suggestions = Vec::new();
+    for (candidate, distance) in distances {
+        if distance == closest && !suggestions.contains(&candidate) {
+            suggestions.push(candidate);
+        }
+    }
+    suggestions.truncate(MAX_SUGGESTIONS);
+
+    let quoted = suggestions
+        .iter()
+        .map(|suggestion| format!("`{}`", suggestion))
+        .collect::<Vec<_>>();
+    let (last, rest) = quoted.split_last()?;
+    let alternatives = if rest.is_empty() {
+        last.clone()
+    } else {
+        format!("{} or {}", rest.join(", "), last)
+    };
+    Some(format!("did you mean {}?", alternatives))
+}
+
+/// Returns the edit distance of `a` and `b`, the number of inserted, removed, replaced or
+/// swapped adjacent characters turning one into the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let replace = distances[i - 1][j - 1] + if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut distance = replace
+                .min(distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typos_suggest_the_closest_names() {
+        let names = ["new", "default", "flip", "get", "set_a", "set_b"];
+
+        assert_eq!(edit_distance("flpi", "flip"), 1);
+        assert_eq!(edit_distance("flip", "flop"), 1);
+        assert_eq!(edit_distance("", "get"), 3);
+        assert_eq!(did_you_mean("nwe", &names).unwrap(), "did you mean `new`?");
+        assert_eq!(
+            did_you_mean("Flip", &names).unwrap(),
+            "did you mean `flip`?"
+        );
+        assert_eq!(
+            did_you_mean("defualt", &names).unwrap(),
+            "did you mean `default`?"
+        );
+        assert_eq!(
+            did_you_mean("set_c", &names).unwrap(),
+            "did you mean `set_a` or `set_b`?"
+        );
+        assert_eq!(did_you_mean("transfer", &names), None);
+    }
+}
@@ -0,0 +1,145 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{collections::HashMap, convert::TryFrom, path::PathBuf};
+
+use anyhow::Result;
+use colored::Colorize;
+use structopt::StructOpt;
+
+use crate::{
+    crate_metadata::CrateMetadata, util, workspace::ManifestPath, Verbosity, VerbosityFlags,
+};
+
+/// The features of a contract enabled for its off-chain tests, if the contract declares them.
+const TEST_FEATURES: &[&str] = &["std"];
+
+/// Runs the off-chain tests of a contract with `cargo test`.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "test")]
+pub struct TestCommand {
+    /// Path to the Cargo.toml of the contract to test
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    #[structopt(flatten)]
+    verbosity: VerbosityFlags,
+    /// Arguments passed on to `cargo test` as they are, e.g. `cargo contract test -- --release`
+    ///
+    /// The arguments of the test binary follow another `--`, as with `cargo test`, e.g.
+    /// `cargo contract test -- -- --nocapture`.
+    #[structopt(last = true)]
+    args: Vec<String>,
+}
+
+impl TestCommand {
+    pub fn exec(&self) -> Result<String> {
+        let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+        let verbosity: Option<Verbosity> = TryFrom::<&VerbosityFlags>::try_from(&self.verbosity)?;
+        let crate_metadata = CrateMetadata::collect(&manifest_path)?;
+
+        util::maybe_println(
+            verbosity,
+            format!(
+                " {} {}",
+                "[1/1]".bold(),
+                "Running off-chain tests".bright_green().bold()
+            ),
+        );
+        let args = test_args(
+            &manifest_path,
+            &crate_metadata.root_package.features,
+            &self.args,
+        );
+        util::invoke_cargo_streaming("test", &args, None::<PathBuf>, verbosity)?;
+        Ok(format!(
+            "{} {}",
+            "Tests passed for".bold(),
+            crate_metadata.package_name
+        ))
+    }
+}
+
+/// Returns the args of `cargo test`, enabling the test features the contract declares and
+/// appending `args` as they are.
+fn test_args(
+    manifest_path: &ManifestPath,
+    features: &HashMap<String, Vec<String>>,
+    args: &[String],
+) -> Vec<String> {
+    let features = TEST_FEATURES
+        .iter()
+        .filter(|feature| features.contains_key(**feature))
+        .copied()
+        .collect::<Vec<_>>();
+    let mut test_args = vec![manifest_path.cargo_arg()];
+    if !features.is_empty() {
+        test_args.push(format!("--features={}", features.join(",")));
+    }
+    test_args.extend(args.iter().cloned());
+    test_args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn declared_test_features_are_enabled() {
+        let manifest_path = ManifestPath::new("flipper/Cargo.toml").unwrap();
+        let features = [
+            "default",
+            "std",
+            "ink-as-dependency",
+            "ink-experimental-engine",
+        ]
+        .iter()
+        .map(|feature| (feature.to_string(), Vec::new()))
+        .collect::<HashMap<_, _>>();
+        let args = vec!["--".to_string(), "--nocapture".to_string()];
+
+        assert_eq!(
+            test_args(&manifest_path, &features, &args),
+            vec![
+                "--manifest-path=flipper/Cargo.toml",
+                "--features=std",
+                "--",
+                "--nocapture"
+            ]
+        );
+        assert_eq!(
+            test_args(&manifest_path, &HashMap::new(), &[]),
+            vec!["--manifest-path=flipper/Cargo.toml"]
+        );
+    }
+
+    #[test]
+    fn trailing_args_are_passed_to_cargo_test_as_they_are() {
+        let command =
+            TestCommand::from_iter_safe(&["test", "--", "--release", "--", "--nocapture"]).unwrap();
+        let manifest_path = ManifestPath::new("flipper/Cargo.toml").unwrap();
+
+        assert_eq!(command.args, vec!["--release", "--", "--nocapture"]);
+        assert_eq!(
+            test_args(&manifest_path, &HashMap::new(), &command.args),
+            vec![
+                "--manifest-path=flipper/Cargo.toml",
+                "--release",
+                "--",
+                "--nocapture"
+            ]
+        );
+    }
+}
@@ -0,0 +1,244 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Parses balances given with a unit of the chain's token, e.g. `1.5UNIT` or `2000mUNIT`.
+//!
+//! The symbol and the decimals of the token are read from the `system_properties` of the node.
+//! A balance without a unit is an integer of the base unit of the runtime, as before.
+
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use jsonrpsee::common::Params;
+use serde_json::Value;
+
+use crate::ExtrinsicOpts;
+
+/// The symbol of chains which do not report one, e.g. a development node.
+const DEFAULT_SYMBOL: &str = "UNIT";
+
+/// The metric prefixes of the units and their powers of ten relative to the whole token.
+const PREFIXES: &[(&str, i32)] = &[
+    ("M", 6),
+    ("k", 3),
+    ("", 0),
+    ("m", -3),
+    ("u", -6),
+    ("µ", -6),
+    ("n", -9),
+    ("p", -12),
+];
+
+/// A balance given on the command line, an integer of the base unit or a decimal amount of a
+/// unit of the chain's token.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Balance {
+    amount: String,
+    unit: Option<String>,
+}
+
+impl FromStr for Balance {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        let split = input
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(input.len());
+        let (amount, unit) = input.split_at(split);
+        let mut parts = amount.splitn(2, '.');
+        let whole = parts.next().unwrap_or_default();
+        let is_digits =
+            |digits: &str| !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit());
+        let valid = is_digits(whole) && parts.all(is_digits);
+        if unit.is_empty() {
+            if !is_digits(amount) {
+                anyhow::bail!(
+                    "Invalid balance `{}`, a balance without a unit is an integer of the base \
+                     unit",
+                    input
+                );
+            }
+            amount.parse::<u128>().context("The balance is too large")?;
+        } else if !valid {
+            anyhow::bail!("Invalid balance `{}`, expected e.g. `1.5UNIT`", input);
+        }
+        Ok(Balance {
+            amount: amount.to_string(),
+            unit: Some(unit.to_string()).filter(|unit| !unit.is_empty()),
+        })
+    }
+}
+
+impl Balance {
+    /// Returns the balance in the base unit of the runtime.
+    ///
+    /// The token of the chain is only queried if the balance has a unit, `token_decimals`
+    /// overrides the decimals reported by the node.
+    pub fn resolve(
+        &self,
+        extrinsic_opts: &ExtrinsicOpts,
+        token_decimals: Option<u32>,
+    ) -> Result<u128> {
+        let unit = match self.unit.as_ref() {
+            Some(unit) => unit,
+            None => return Ok(self.amount.parse()?),
+        };
+        let url = extrinsic_opts.url()?;
+        let properties = async_std::task::block_on(async {
+            let rpc = jsonrpsee::ws_client(url.as_str()).await?;
+            let properties: Value = rpc
+                .request("system_properties", Params::Array(vec![]))
+                .await?;
+            Ok::<_, anyhow::Error>(properties)
+        })
+        .context("Failed to query the token of the chain")?;
+        let (symbol, decimals) = token(&properties);
+        let decimals = token_decimals.or(decimals).ok_or(anyhow::anyhow!(
+            "The node does not report the decimals of its token, pass --token-decimals"
+        ))?;
+        base_units(&self.amount, unit, &symbol, decimals)
+    }
+}
+
+/// Returns the symbol and the decimals of the token in the `system_properties` of a node.
+///
+/// Chains with several tokens report arrays, the first one is the native token.
+fn token(properties: &Value) -> (String, Option<u32>) {
+    let first = |property: &str| {
+        properties.get(property).and_then(|value| match value {
+            Value::Array(values) => values.first().cloned(),
+            value => Some(value.clone()),
+        })
+    };
+    let symbol = first("tokenSymbol")
+        .and_then(|symbol| symbol.as_str().map(ToString::to_string))
+        .unwrap_or_else(|| DEFAULT_SYMBOL.to_string());
+    let decimals = first("tokenDecimals")
+        .and_then(|decimals| decimals.as_u64())
+        .map(|decimals| decimals as u32);
+    (symbol, decimals)
+}
+
+/// Converts the decimal `amount` of the `unit` of the token with `symbol` and `decimals` into
+/// the base unit.
+fn base_units(amount: &str, unit: &str, symbol: &str, decimals: u32) -> Result<u128> {
+    let exponent = unit
+        .len()
+        .checked_sub(symbol.len())
+        .filter(|prefix_len| unit.is_char_boundary(*prefix_len))
+        .filter(|prefix_len| unit[*prefix_len..].eq_ignore_ascii_case(symbol))
+        .and_then(|prefix_len| {
+            PREFIXES
+                .iter()
+                .find(|(prefix, _)| *prefix == &unit[..prefix_len])
+        })
+        .map(|(_, exponent)| *exponent);
+    let exponent = match exponent {
+        Some(exponent) => exponent,
+        None => {
+            let units = PREFIXES
+                .iter()
+                .map(|(prefix, _)| format!("{}{}", prefix, symbol))
+                .collect::<Vec<_>>();
+            anyhow::bail!(
+                "Unknown unit `{}`, expected one of {} or no unit for the base unit",
+                unit,
+                units.join(", ")
+            )
+        }
+    };
+
+    let fraction_len = amount.find('.').map_or(0, |dot| amount.len() - dot - 1);
+    let digits = amount.replace('.', "").parse::<u128>();
+    let overflow = || anyhow::anyhow!("The balance `{}{}` is too large", amount, unit);
+    let digits = digits.map_err(|_| overflow())?;
+    let shift = decimals as i32 + exponent - fraction_len as i32;
+    let power = |shift: i32| {
+        10u128
+            .checked_pow(shift.unsigned_abs())
+            .ok_or_else(overflow)
+    };
+    if shift >= 0 {
+        digits.checked_mul(power(shift)?).ok_or_else(overflow)
+    } else {
+        let divisor = power(shift)?;
+        if digits % divisor != 0 {
+            anyhow::bail!(
+                "The balance `{}{}` is more precise than the base unit, the token has {} decimals",
+                amount,
+                unit,
+                decimals
+            )
+        }
+        Ok(digits / divisor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{base_units, token, Balance};
+    use serde_json::json;
+
+    #[test]
+    fn balances_are_parsed() {
+        let balance = |input: &str| input.parse::<Balance>();
+
+        assert_eq!(balance("100").unwrap().unit, None);
+        assert_eq!(balance("1.5UNIT").unwrap().unit.as_deref(), Some("UNIT"));
+        assert!(balance("1.5").is_err());
+        assert!(balance(".5UNIT").is_err());
+        assert!(balance("1.2.3UNIT").is_err());
+        assert!(balance("UNIT").is_err());
+    }
+
+    #[test]
+    fn units_are_converted_to_the_base_unit() {
+        assert_eq!(
+            base_units("1.5", "UNIT", "UNIT", 12).unwrap(),
+            1_500_000_000_000
+        );
+        assert_eq!(
+            base_units("2000", "mUNIT", "UNIT", 12).unwrap(),
+            2_000_000_000_000
+        );
+        assert_eq!(base_units("3", "µDOT", "DOT", 10).unwrap(), 30_000);
+        assert_eq!(base_units("2.00", "pUNIT", "UNIT", 12).unwrap(), 2);
+        assert_eq!(base_units("2", "kunit", "UNIT", 0).unwrap(), 2_000);
+    }
+
+    #[test]
+    fn invalid_units_are_rejected() {
+        let err = base_units("1", "DOT", "UNIT", 12).unwrap_err().to_string();
+        assert!(err.contains("expected one of MUNIT, kUNIT, UNIT, mUNIT"));
+        assert!(base_units("1.5", "pUNIT", "UNIT", 12)
+            .unwrap_err()
+            .to_string()
+            .contains("more precise"));
+        assert!(base_units("1", "MUNIT", "UNIT", 40)
+            .unwrap_err()
+            .to_string()
+            .contains("too large"));
+    }
+
+    #[test]
+    fn token_is_read_from_the_properties() {
+        let properties = json!({ "ss58Format": 0, "tokenDecimals": 10, "tokenSymbol": "DOT" });
+        assert_eq!(token(&properties), ("DOT".to_string(), Some(10)));
+        let properties = json!({ "tokenDecimals": [12, 18], "tokenSymbol": ["ACA", "AUSD"] });
+        assert_eq!(token(&properties), ("ACA".to_string(), Some(12)));
+        assert_eq!(token(&json!({})), ("UNIT".to_string(), None));
+    }
+}
@@ -0,0 +1,521 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Encodes calls of contract messages and decodes values, guided by the contract metadata.
+//!
+//! Only primitive types and 32 byte arrays, e.g. an `AccountId` or a `Hash`, are supported.
+//! Arrays are given as hex, an `AccountId` also as an SS58 address.
+
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use codec::{Decode, Encode};
+use serde_json::Value;
+use sp_core::crypto::{AccountId32, Ss58Codec};
+
+use super::{bindings::spec_name, suggest::did_you_mean};
+
+/// Returns the spec of the message `name` from the metadata.
+pub(crate) fn message<'a>(metadata: &'a Value, name: &str) -> Result<&'a Value> {
+    spec(metadata, "message", "messages", name)
+}
+
+/// Returns the spec of the constructor `name` from the metadata.
+pub(crate) fn constructor<'a>(metadata: &'a Value, name: &str) -> Result<&'a Value> {
+    spec(metadata, "constructor", "constructors", name)
+}
+
+/// Returns the spec `name` of the `messages` or the `constructors` of the metadata, a `kind` of
+/// spec in the errors.
+fn spec<'a>(metadata: &'a Value, kind: &str, specs: &str, name: &str) -> Result<&'a Value> {
+    let all = metadata
+        .pointer(&format!("/spec/{}", specs))
+        .and_then(Value::as_array)
+        .ok_or(anyhow::anyhow!("spec.{} not found in the metadata", specs))?;
+    all.iter()
+        .find(|spec| spec_name(spec) == name)
+        .ok_or_else(|| {
+            let names = all.iter().map(spec_name).collect::<Vec<_>>();
+            match did_you_mean(name, &names) {
+                Some(suggestion) => anyhow::anyhow!(
+                    "No {} `{}` in the metadata, {} The {} are: {}",
+                    kind,
+                    name,
+                    suggestion,
+                    specs,
+                    names.join(", ")
+                ),
+                None => anyhow::anyhow!(
+                    "No {} `{}` in the metadata, the {} are: {}",
+                    kind,
+                    name,
+                    specs,
+                    names.join(", ")
+                ),
+            }
+        })
+}
+
+/// Encodes a call of `message`, or of a constructor, with the arguments given as strings,
+/// prefixed by its selector.
+pub(crate) fn encode_call(metadata: &Value, message: &Value, args: &[String]) -> Result<Vec<u8>> {
+    let name = spec_name(message);
+    let selector = message
+        .get("selector")
+        .and_then(Value::as_str)
+        .ok_or(anyhow::anyhow!("`{}` has no selector", name))?;
+    let mut data = hex::decode(selector.trim_start_matches("0x"))?;
+
+    let specs = message
+        .get("args")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    if specs.len() != args.len() {
+        anyhow::bail!(
+            "`{}` takes {} arguments, {} given",
+            name,
+            specs.len(),
+            args.len()
+        )
+    }
+    let types = types(metadata)?;
+    for (spec, arg) in specs.iter().zip(args) {
+        let arg_name = spec_name(spec);
+        let ty = spec
+            .pointer("/type/type")
+            .and_then(Value::as_u64)
+            .ok_or(anyhow::anyhow!("argument `{}` has no type", arg_name))?;
+        let encoded = encode_value(types, ty, arg)
+            .context(format!("Failed to encode argument `{}`", arg_name))?;
+        data.extend(encoded);
+    }
+    Ok(data)
+}
+
+/// Decodes the value returned by `message`, displayed as hex if its type is not supported.
+pub(crate) fn decode_return(metadata: &Value, message: &Value, value: &[u8]) -> Result<String> {
+    let ty = match message
+        .get("returnType")
+        .filter(|ty| !ty.is_null())
+        .and_then(|ty| ty.get("type"))
+        .and_then(Value::as_u64)
+    {
+        Some(ty) => ty,
+        None => return Ok("()".into()),
+    };
+    let types = types(metadata)?;
+    Ok(decode_primitive(types, ty, value)
+        .or_else(|| decode_byte_array(types, ty, value))
+        .unwrap_or_else(|| format!("0x{}", hex::encode(value))))
+}
+
+fn types(metadata: &Value) -> Result<&[Value]> {
+    metadata
+        .get("types")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .ok_or(anyhow::anyhow!("types not found in the metadata"))
+}
+
+/// Returns the definition of a type in the type registry.
+fn type_def(types: &[Value], ty: u64) -> Option<&Value> {
+    // type ids in the registry are 1-based
+    types.get((ty as usize).checked_sub(1)?)?.get("def")
+}
+
+fn primitive(types: &[Value], ty: u64) -> Option<&str> {
+    type_def(types, ty)?.get("primitive")?.as_str()
+}
+
+/// Returns whether the type is a 32 byte array, or a composite wrapping one like `AccountId`.
+fn is_byte_array_32(types: &[Value], ty: u64) -> bool {
+    let def = match type_def(types, ty) {
+        Some(def) => def,
+        None => return false,
+    };
+    if let Some(array) = def.get("array") {
+        let elem = array.get("type").and_then(Value::as_u64);
+        return array.get("len").and_then(Value::as_u64) == Some(32)
+            && elem.and_then(|elem| primitive(types, elem)) == Some("u8");
+    }
+    match def.pointer("/composite/fields").and_then(Value::as_array) {
+        Some(fields) if fields.len() == 1 => fields[0]
+            .get("type")
+            .and_then(Value::as_u64)
+            .map_or(false, |field| is_byte_array_32(types, field)),
+        _ => false,
+    }
+}
+
+/// Encodes a value of a primitive type or a 32 byte array from its string representation.
+fn encode_value(types: &[Value], ty: u64, arg: &str) -> Result<Vec<u8>> {
+    if is_byte_array_32(types, ty) {
+        let bytes = match AccountId32::from_ss58check(arg) {
+            Ok(account) => AsRef::<[u8]>::as_ref(&account).to_vec(),
+            Err(_) => hex::decode(arg.trim_start_matches("0x"))
+                .context("expected an SS58 address or 32 hex encoded bytes")?,
+        };
+        if bytes.len() != 32 {
+            anyhow::bail!("expected 32 bytes, found {}", bytes.len())
+        }
+        return Ok(bytes);
+    }
+    fn encode<T: FromStr + Encode>(arg: &str) -> Result<Vec<u8>> {
+        arg.parse::<T>()
+            .map(|value| value.encode())
+            .map_err(|_| anyhow::anyhow!("`{}` is not a valid {}", arg, std::any::type_name::<T>()))
+    }
+    match primitive(types, ty) {
+        Some("bool") => encode::<bool>(arg),
+        Some("str") => Ok(arg.encode()),
+        Some("u8") => encode::<u8>(arg),
+        Some("u16") => encode::<u16>(arg),
+        Some("u32") => encode::<u32>(arg),
+        Some("u64") => encode::<u64>(arg),
+        Some("u128") => encode::<u128>(arg),
+        Some("i8") => encode::<i8>(arg),
+        Some("i16") => encode::<i16>(arg),
+        Some("i32") => encode::<i32>(arg),
+        Some("i64") => encode::<i64>(arg),
+        Some("i128") => encode::<i128>(arg),
+        _ => anyhow::bail!("only primitive types and 32 byte arrays can be encoded"),
+    }
+}
+
+fn decode_byte_array(types: &[Value], ty: u64, value: &[u8]) -> Option<String> {
+    if is_byte_array_32(types, ty) && value.len() == 32 {
+        Some(format!("0x{}", hex::encode(value)))
+    } else {
+        None
+    }
+}
+
+/// Decodes the data of an event emitted by a contract, the index of the event in the metadata
+/// followed by its arguments.
+///
+/// Returns the name of the event and its arguments. The arguments from the first one of an
+/// unsupported type on are displayed as hex.
+pub(crate) fn decode_event(
+    metadata: &Value,
+    data: &[u8],
+) -> Result<(String, Vec<(String, String)>)> {
+    let (index, input) = data
+        .split_first()
+        .ok_or(anyhow::anyhow!("The event data is empty"))?;
+    let event = metadata
+        .pointer("/spec/events")
+        .and_then(Value::as_array)
+        .and_then(|events| events.get(*index as usize))
+        .ok_or_else(|| anyhow::anyhow!("No event with the index {} in the metadata", index))?;
+    Ok((
+        spec_name(event),
+        decode_args(types(metadata)?, event, input),
+    ))
+}
+
+/// Decodes the data of a call, the selector of a message or a constructor followed by its
+/// arguments. `specs` are the `messages` or the `constructors` of the metadata.
+///
+/// Returns the name of the message or constructor and its arguments, like [`decode_event`].
+pub(crate) fn decode_call(
+    metadata: &Value,
+    specs: &str,
+    data: &[u8],
+) -> Result<(String, Vec<(String, String)>)> {
+    if data.len() < 4 {
+        anyhow::bail!(
+            "The data must start with a 4 byte selector, found {} bytes",
+            data.len()
+        )
+    }
+    let (selector, input) = data.split_at(4);
+    let selector = format!("0x{}", hex::encode(selector));
+    let spec = metadata
+        .pointer(&format!("/spec/{}", specs))
+        .and_then(Value::as_array)
+        .ok_or(anyhow::anyhow!("spec.{} not found in the metadata", specs))?
+        .iter()
+        .find(|spec| spec.get("selector").and_then(Value::as_str) == Some(selector.as_str()))
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "No {} with the selector {} in the metadata",
+                specs.trim_end_matches('s'),
+                selector
+            )
+        })?;
+    Ok((spec_name(spec), decode_args(types(metadata)?, spec, input)))
+}
+
+/// Decodes the arguments of an event, a message or a constructor from `input`. The arguments
+/// from the first one of an unsupported type on are displayed as hex.
+fn decode_args(types: &[Value], spec: &Value, mut input: &[u8]) -> Vec<(String, String)> {
+    let mut args = Vec::new();
+    for arg in spec
+        .get("args")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+    {
+        let mut rest = input;
+        let ty = arg.pointer("/type/type").and_then(Value::as_u64);
+        match ty.and_then(|ty| decode_value(types, ty, &mut rest)) {
+            Some(value) => {
+                args.push((spec_name(arg), value));
+                input = rest;
+            }
+            None => {
+                args.push((spec_name(arg), format!("0x{}", hex::encode(input))));
+                break;
+            }
+        }
+    }
+    args
+}
+
+/// Decodes a value of a primitive type or a 32 byte array from the start of `input`, advancing
+/// it past the value.
+fn decode_value(types: &[Value], ty: u64, input: &mut &[u8]) -> Option<String> {
+    if is_byte_array_32(types, ty) {
+        let bytes = <[u8; 32]>::decode(input).ok()?;
+        return Some(format!("0x{}", hex::encode(bytes)));
+    }
+    decode_primitive_from(types, ty, input)
+}
+
+/// Decodes a SCALE encoded value of a primitive type from the type registry.
+///
+/// Returns `None` for all other types, or if the value cannot be decoded.
+pub(crate) fn decode_primitive(types: &[Value], ty: u64, mut value: &[u8]) -> Option<String> {
+    decode_primitive_from(types, ty, &mut value)
+}
+
+/// Decodes a value of a primitive type from the start of `input`, advancing it past the value.
+fn decode_primitive_from(types: &[Value], ty: u64, input: &mut &[u8]) -> Option<String> {
+    fn decode<T: Decode + ToString>(input: &mut &[u8]) -> Option<String> {
+        T::decode(input).ok().map(|v| v.to_string())
+    }
+    match primitive(types, ty)? {
+        "bool" => decode::<bool>(input),
+        "str" => decode::<String>(input),
+        "u8" => decode::<u8>(input),
+        "u16" => decode::<u16>(input),
+        "u32" => decode::<u32>(input),
+        "u64" => decode::<u64>(input),
+        "u128" => decode::<u128>(input),
+        "i8" => decode::<i8>(input),
+        "i16" => decode::<i16>(input),
+        "i32" => decode::<i32>(input),
+        "i64" => decode::<i64>(input),
+        "i128" => decode::<i128>(input),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn metadata() -> Value {
+        json!({
+            "spec": {
+                "messages": [
+                    {
+                        "name": ["transfer"],
+                        "selector": "0xfae3a09d",
+                        "args": [
+                            { "name": "to", "type": { "type": 3 } },
+                            { "name": "value", "type": { "type": 1 } }
+                        ],
+                        "returnType": { "type": 4 }
+                    },
+                    {
+                        "name": ["owner"],
+                        "selector": "0x0a0b0c0d",
+                        "args": [],
+                        "returnType": { "type": 3 }
+                    },
+                    { "name": ["flip"], "selector": "0x633aa551", "args": [], "returnType": null }
+                ],
+                "constructors": [
+                    {
+                        "name": ["new"],
+                        "selector": "0x9bae9d5e",
+                        "args": [ { "name": "init_value", "type": { "type": 4 } } ]
+                    }
+                ]
+            },
+            "types": [
+                { "def": { "primitive": "u128" } },
+                { "def": { "array": { "len": 32, "type": 5 } } },
+                { "def": { "composite": { "fields": [ { "type": 2, "typeName": "[u8; 32]" } ] } } },
+                { "def": { "primitive": "bool" } },
+                { "def": { "primitive": "u8" } }
+            ]
+        })
+    }
+
+    #[test]
+    fn calls_are_encoded_with_the_selector_and_arguments() {
+        let metadata = metadata();
+        let transfer = message(&metadata, "transfer").unwrap();
+        let alice = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+
+        let data = encode_call(&metadata, transfer, &[alice.into(), "100".into()]).unwrap();
+
+        let mut expected = vec![0xfa, 0xe3, 0xa0, 0x9d];
+        expected.extend(AsRef::<[u8]>::as_ref(
+            &AccountId32::from_ss58check(alice).unwrap(),
+        ));
+        expected.extend(100u128.encode());
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn constructors_are_encoded_with_the_selector_and_arguments() {
+        let metadata = metadata();
+        let new = constructor(&metadata, "new").unwrap();
+
+        let data = encode_call(&metadata, new, &["true".into()]).unwrap();
+
+        assert_eq!(data, vec![0x9b, 0xae, 0x9d, 0x5e, 1]);
+        assert_eq!(
+            constructor(&metadata, "default").unwrap_err().to_string(),
+            "No constructor `default` in the metadata, the constructors are: new"
+        );
+    }
+
+    #[test]
+    fn invalid_arguments_are_rejected() {
+        let metadata = metadata();
+        let transfer = message(&metadata, "transfer").unwrap();
+
+        assert!(encode_call(&metadata, transfer, &["100".into()]).is_err());
+        let err = encode_call(&metadata, transfer, &["0x01".into(), "100".into()]).unwrap_err();
+        assert_eq!(err.to_string(), "Failed to encode argument `to`");
+        assert!(encode_call(
+            &metadata,
+            transfer,
+            &[format!("0x{}", "00".repeat(32)), "-1".into()]
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn unknown_messages_list_the_messages() {
+        let err = message(&metadata(), "get").unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "No message `get` in the metadata, the messages are: transfer, owner, flip"
+        );
+        let err = message(&metadata(), "flpi").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "No message `flpi` in the metadata, did you mean `flip`? The messages are: transfer, \
+             owner, flip"
+        );
+    }
+
+    #[test]
+    fn return_values_are_decoded() {
+        let metadata = metadata();
+        let decode = |name, value: &[u8]| {
+            decode_return(&metadata, message(&metadata, name).unwrap(), value).unwrap()
+        };
+
+        assert_eq!(decode("transfer", &true.encode()), "true");
+        assert_eq!(
+            decode("owner", &[1u8; 32]),
+            format!("0x{}", "01".repeat(32))
+        );
+        assert_eq!(decode("flip", &[]), "()");
+    }
+
+    #[test]
+    fn events_are_decoded() {
+        let mut metadata = metadata();
+        metadata["spec"]["events"] = json!([
+            { "name": "Flipped", "args": [ { "name": "value", "type": { "type": 4 } } ] },
+            {
+                "name": "Transfer",
+                "args": [
+                    { "name": "to", "type": { "type": 3 } },
+                    { "name": "value", "type": { "type": 1 } },
+                    { "name": "memo", "type": { "type": 6 } }
+                ]
+            }
+        ]);
+        let mut data = vec![1];
+        data.extend(&[2u8; 32]);
+        data.extend(100u128.encode());
+        data.extend(&[0xab, 0xcd]);
+
+        assert_eq!(
+            decode_event(&metadata, &[0, 1]).unwrap(),
+            (
+                "Flipped".to_string(),
+                vec![("value".to_string(), "true".to_string())]
+            )
+        );
+        let (name, args) = decode_event(&metadata, &data).unwrap();
+        assert_eq!(name, "Transfer");
+        assert_eq!(args[0].1, format!("0x{}", "02".repeat(32)));
+        assert_eq!(args[1].1, "100");
+        assert_eq!(args[2], ("memo".to_string(), "0xabcd".to_string()));
+        assert!(decode_event(&metadata, &[2]).is_err());
+    }
+
+    #[test]
+    fn calls_are_decoded_by_their_selector() {
+        let mut data = hex::decode("fae3a09d").unwrap();
+        data.extend(&[2u8; 32]);
+        data.extend(100u128.encode());
+
+        let (name, args) = decode_call(&metadata(), "messages", &data).unwrap();
+        assert_eq!(name, "transfer");
+        assert_eq!(
+            args,
+            vec![
+                ("to".to_string(), format!("0x{}", "02".repeat(32))),
+                ("value".to_string(), "100".to_string())
+            ]
+        );
+        assert_eq!(
+            decode_call(&metadata(), "messages", &[1, 2, 3, 4])
+                .unwrap_err()
+                .to_string(),
+            "No message with the selector 0x01020304 in the metadata"
+        );
+        assert!(decode_call(&metadata(), "messages", &[0xfa]).is_err());
+    }
+
+    #[test]
+    fn only_primitives_are_decoded() {
+        let types = vec![
+            json!({ "def": { "primitive": "u32" } }),
+            json!({ "def": { "composite": { "fields": [] } } }),
+        ];
+
+        assert_eq!(
+            decode_primitive(&types, 1, &42u32.encode()),
+            Some("42".to_string())
+        );
+        assert_eq!(decode_primitive(&types, 2, &42u32.encode()), None);
+        assert_eq!(decode_primitive(&types, 3, &42u32.encode()), None);
+    }
+}
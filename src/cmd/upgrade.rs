@@ -0,0 +1,369 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Simulates upgrading the code of a deployed contract, without submitting anything.
+//!
+//! The upload of the new code, `Contracts::set_code_hash` and a representative call of the
+//! upgraded contract are put into a single `Utility::batch_all` extrinsic, which the node applies
+//! to a copy of its state with the `system_dryRun` RPC. The batch fails as a whole if any of its
+//! calls fails, e.g. because the upgraded contract traps.
+
+use std::{convert::TryFrom, path::PathBuf};
+
+use anyhow::{Context, Result};
+use codec::{Compact, Decode, Encode, Output};
+use colored::Colorize;
+use jsonrpsee::common::{to_value, Params};
+use serde_json::Value;
+use sp_core::{crypto::AccountId32, Bytes, H256};
+use structopt::StructOpt;
+use subxt::{system::System, Call, ClientBuilder, DefaultNodeRuntime, Metadata};
+
+use super::{
+    deploy::load_wasm_code,
+    gas_policy::{load_gas_policy, policy_gas_limit},
+    metadata::read_metadata,
+};
+use crate::{crate_metadata::CrateMetadata, workspace::ManifestPath, ExtrinsicOpts, HexData};
+
+/// The RPC methods the dry run needs, `system_dryRun` is only exposed with
+/// `--rpc-methods=Unsafe`.
+const REQUIRED_RPC_METHODS: &[&str] = &["system_dryRun"];
+
+/// The gas limit of the representative call if neither `--gas` nor a policy entry applies.
+const DEFAULT_GAS_LIMIT: u64 = 500_000_000;
+
+/// Simulate upgrading the code of a deployed contract
+#[derive(Debug, StructOpt)]
+#[structopt(name = "upgrade")]
+pub struct UpgradeCommand {
+    #[structopt(flatten)]
+    extrinsic_opts: ExtrinsicOpts,
+    /// The SS58 address of the contract to upgrade
+    #[structopt(long)]
+    contract: AccountId32,
+    /// Path to the new wasm contract code, defaults to `./target/ink/<name>.wasm`
+    #[structopt(parse(from_os_str))]
+    wasm_path: Option<PathBuf>,
+    /// Hex encoded data of a representative message, called after the upgrade
+    #[structopt(long)]
+    data: HexData,
+    /// Maximum amount of gas to be used by the representative call, overrides the
+    /// `--gas-policy` [default: 500000000]
+    #[structopt(name = "gas", long)]
+    gas_limit: Option<u64>,
+    /// Path to a JSON file mapping message names to gas limits, e.g. `{ "flip": 200000000 }`,
+    /// the entry of the representative message is applied
+    #[structopt(long, parse(from_os_str))]
+    gas_policy: Option<PathBuf>,
+    /// Path to the contract metadata the gas policy is checked against, defaults to the
+    /// `metadata.json` built for the contract in the current directory
+    #[structopt(long, parse(from_os_str), requires = "gas-policy")]
+    metadata: Option<PathBuf>,
+    /// Only simulate the upgrade with the `system_dryRun` RPC, nothing is submitted
+    #[structopt(long)]
+    dry_run: bool,
+}
+
+impl UpgradeCommand {
+    pub fn exec(&self) -> Result<String> {
+        if !self.dry_run {
+            anyhow::bail!("Only simulating an upgrade is supported, pass --dry-run")
+        }
+        let code = match self.wasm_path.as_ref() {
+            Some(path) => load_wasm_code(path)?,
+            None => {
+                let manifest_path = ManifestPath::try_from(None::<PathBuf>)?;
+                load_wasm_code(&CrateMetadata::collect(&manifest_path)?.dest_wasm)?
+            }
+        };
+        let code_hash = H256(sp_core::blake2_256(&code));
+        let gas_limit = self.gas_limit()?;
+
+        async_std::task::block_on(async move {
+            let url = self.extrinsic_opts.url()?;
+            let rpc = self.extrinsic_opts.rpc_client().await?;
+            let methods: Value = rpc.request("rpc_methods", Params::None).await?;
+            let missing = missing_methods(&methods, REQUIRED_RPC_METHODS);
+            if !missing.is_empty() {
+                anyhow::bail!(
+                    "The node at {} does not expose {}, which the upgrade dry run needs. Start \
+                     the node with `--rpc-methods=Unsafe`",
+                    url,
+                    missing.join(", ")
+                )
+            }
+
+            let cli = ClientBuilder::<DefaultNodeRuntime>::new()
+                .set_client(rpc.clone())
+                .build()
+                .await?;
+            let metadata = cli.metadata();
+            let dest = <DefaultNodeRuntime as System>::Address::from(self.contract.clone());
+            let calls = vec![
+                encode_call(metadata, "Contracts", "put_code", code)?,
+                encode_call(
+                    metadata,
+                    "Contracts",
+                    "set_code_hash",
+                    (dest.clone(), code_hash),
+                )?,
+                encode_call(
+                    metadata,
+                    "Contracts",
+                    "call",
+                    (
+                        dest,
+                        Compact(0u128),
+                        Compact(gas_limit),
+                        self.data.0.clone(),
+                    ),
+                )?,
+            ];
+            // fail early if the runtime cannot batch the calls
+            encode_call(metadata, "Utility", "batch_all", Vec::<RawCall>::new())?;
+
+            let signer = self.extrinsic_opts.signer()?;
+            self.extrinsic_opts.print_signer(&signer)?;
+            let extrinsic = cli.create_signed(BatchAllCall { calls }, &signer).await?;
+            let params = Params::Array(vec![to_value(format!(
+                "0x{}",
+                hex::encode(extrinsic.encode())
+            ))?]);
+            let result: Bytes = rpc.request("system_dryRun", params).await?;
+            let result = ApplyExtrinsicResult::decode(&mut &result.0[..])
+                .context("Failed to decode the result of the dry run")?;
+
+            match describe_failure(&result, |index, error| {
+                module_error_name(metadata, index, error)
+            }) {
+                Some(failure) => anyhow::bail!("The upgrade dry run failed: {}", failure),
+                None => Ok(format!(
+                    "The upgrade dry run succeeded: the contract {} runs code {:?} and the \
+                     representative call did not trap. Nothing was submitted.",
+                    self.contract, code_hash
+                )),
+            }
+        })
+    }
+
+    /// Returns the gas limit of the representative call, `--gas` takes precedence over the entry
+    /// of the message in the `--gas-policy`.
+    fn gas_limit(&self) -> Result<u64> {
+        if let Some(gas_limit) = self.gas_limit {
+            return Ok(gas_limit);
+        }
+        let policy_path = match self.gas_policy.as_ref() {
+            Some(policy_path) => policy_path,
+            None => return Ok(DEFAULT_GAS_LIMIT),
+        };
+        let metadata_path = match self.metadata.as_ref() {
+            Some(metadata_path) => metadata_path.clone(),
+            None => {
+                let manifest_path = ManifestPath::try_from(None::<PathBuf>)?;
+                CrateMetadata::collect(&manifest_path)?.dest_metadata
+            }
+        };
+        let metadata = read_metadata(&metadata_path)?;
+        let policy = load_gas_policy(policy_path, &metadata)?;
+        match policy_gas_limit(&policy, &metadata, &self.data.0)? {
+            Some((name, gas_limit)) => {
+                println!(
+                    "{} {} from the gas policy entry `{}`",
+                    "Gas limit:".bold(),
+                    gas_limit,
+                    name
+                );
+                Ok(gas_limit)
+            }
+            None => Ok(DEFAULT_GAS_LIMIT),
+        }
+    }
+}
+
+/// A call of the runtime, already encoded with its module and call index.
+struct RawCall(Vec<u8>);
+
+impl Encode for RawCall {
+    fn encode_to<W: Output>(&self, dest: &mut W) {
+        dest.write(&self.0)
+    }
+}
+
+/// The `Utility::batch_all` call, which reverts all calls if one of them fails.
+#[derive(Encode)]
+struct BatchAllCall {
+    calls: Vec<RawCall>,
+}
+
+impl Call<DefaultNodeRuntime> for BatchAllCall {
+    const MODULE: &'static str = "Utility";
+    const FUNCTION: &'static str = "batch_all";
+}
+
+/// Encodes a call of the runtime, failing with a clear message if the runtime lacks it.
+fn encode_call<P: Encode>(
+    metadata: &Metadata,
+    module: &str,
+    function: &'static str,
+    params: P,
+) -> Result<RawCall> {
+    let encoded = metadata
+        .module_with_calls(module)
+        .and_then(|calls| calls.call(function, params))
+        .map_err(|_| {
+            anyhow::anyhow!(
+                "The runtime of the node has no `{}::{}` call, which the upgrade dry run needs",
+                module,
+                function
+            )
+        })?;
+    Ok(RawCall(encoded.encode()))
+}
+
+/// Returns the `module::error` name of a module error, if the runtime metadata knows it.
+fn module_error_name(metadata: &Metadata, index: u8, error: u8) -> Option<String> {
+    let module = metadata.module_with_errors(index).ok()?;
+    let error = module.error(error).ok()?;
+    Some(format!("{}::{}", module.name(), error))
+}
+
+/// Returns the methods of `required` which are not in the response of the `rpc_methods` RPC.
+fn missing_methods(methods: &Value, required: &[&'static str]) -> Vec<&'static str> {
+    let available = methods
+        .get("methods")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    required
+        .iter()
+        .filter(|method| !available.iter().any(|available| available == *method))
+        .copied()
+        .collect()
+}
+
+/// Mirrors `sp_runtime::ApplyExtrinsicResult`, the result of `system_dryRun`.
+type ApplyExtrinsicResult = Result<Result<(), DispatchError>, TransactionValidityError>;
+
+/// Mirrors `sp_runtime::DispatchError`, whose messages are not encoded.
+#[derive(Debug, Decode)]
+enum DispatchError {
+    Other,
+    CannotLookup,
+    BadOrigin,
+    Module { index: u8, error: u8 },
+}
+
+/// Mirrors `sp_runtime::transaction_validity::TransactionValidityError`, only the kind of the
+/// invalid or unknown transaction is decoded.
+#[derive(Debug, Decode)]
+enum TransactionValidityError {
+    Invalid(u8),
+    Unknown(u8),
+}
+
+/// Describes why the dry run failed, `None` if it succeeded.
+fn describe_failure<F>(result: &ApplyExtrinsicResult, module_error_name: F) -> Option<String>
+where
+    F: Fn(u8, u8) -> Option<String>,
+{
+    let error = match result {
+        Ok(Ok(())) => return None,
+        Ok(Err(error)) => error,
+        Err(TransactionValidityError::Invalid(kind)) => {
+            return Some(format!(
+                "the extrinsic is invalid (kind {}), e.g. the signer cannot pay the fees",
+                kind
+            ))
+        }
+        Err(TransactionValidityError::Unknown(kind)) => {
+            return Some(format!(
+                "the validity of the extrinsic is unknown (kind {})",
+                kind
+            ))
+        }
+    };
+    let description = match error {
+        DispatchError::Other => "other error".to_string(),
+        DispatchError::CannotLookup => "cannot look up the contract account".to_string(),
+        DispatchError::BadOrigin => "bad origin, the signer may not set the code hash".to_string(),
+        DispatchError::Module { index, error } => match module_error_name(*index, *error) {
+            Some(name) if name == "Contracts::ContractTrapped" => {
+                format!("{}, the upgraded contract trapped", name)
+            }
+            Some(name) => name,
+            None => format!("module {} error {}", index, error),
+        },
+    };
+    Some(description)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn decode(bytes: &[u8]) -> ApplyExtrinsicResult {
+        ApplyExtrinsicResult::decode(&mut &bytes[..]).unwrap()
+    }
+
+    fn names(index: u8, error: u8) -> Option<String> {
+        match (index, error) {
+            (18, 11) => Some("Contracts::ContractTrapped".into()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn missing_rpc_methods_are_reported() {
+        let methods = json!({ "version": 1, "methods": ["author_submitExtrinsic", "rpc_methods"] });
+
+        assert_eq!(
+            missing_methods(&methods, REQUIRED_RPC_METHODS),
+            vec!["system_dryRun"]
+        );
+        assert!(
+            missing_methods(&json!({ "methods": ["system_dryRun"] }), &["system_dryRun"])
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn successful_dry_run_has_no_failure() {
+        assert_eq!(describe_failure(&decode(&[0, 0]), names), None);
+    }
+
+    #[test]
+    fn trap_is_reported() {
+        let failure = describe_failure(&decode(&[0, 1, 3, 18, 11]), names);
+
+        assert_eq!(
+            failure.as_deref(),
+            Some("Contracts::ContractTrapped, the upgraded contract trapped")
+        );
+    }
+
+    #[test]
+    fn unknown_module_errors_and_invalid_extrinsics_are_reported() {
+        assert_eq!(
+            describe_failure(&decode(&[0, 1, 3, 5, 2]), names).as_deref(),
+            Some("module 5 error 2")
+        );
+        assert!(describe_failure(&decode(&[1, 0, 2]), names)
+            .unwrap()
+            .starts_with("the extrinsic is invalid"));
+    }
+}
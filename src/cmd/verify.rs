@@ -0,0 +1,147 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Verifies that the code on chain is the code built from the local sources.
+//!
+//! Unlike `diff-deployed`, which compares an existing build, the contract is rebuilt with the
+//! default build settings first, so that the result does not depend on a stale or modified
+//! build output.
+
+use std::{convert::TryFrom, fs, path::PathBuf};
+
+use anyhow::Result;
+use colored::Colorize;
+use sp_core::{crypto::AccountId32, H256};
+use structopt::StructOpt;
+use subxt::{ClientBuilder, DefaultNodeRuntime};
+
+use super::{
+    build::execute_with_crate_metadata,
+    storage::{code_exists, contract_code_hash},
+};
+use crate::{
+    crate_metadata::CrateMetadata, parse_code_hash, workspace::ManifestPath, BuildArtifacts,
+    BuildOptions, BuildTimings, Verbosity, VerbosityFlags,
+};
+
+/// Rebuilds the contract and checks that its code hash matches the code on chain.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "verify")]
+pub struct VerifyCommand {
+    /// Websockets url of a substrate node
+    #[structopt(
+        name = "url",
+        long,
+        parse(try_from_str),
+        default_value = "ws://localhost:9944"
+    )]
+    url: url::Url,
+    /// The address of the deployed contract whose code is verified
+    #[structopt(long, required_unless = "code-hash", conflicts_with = "code-hash")]
+    contract: Option<AccountId32>,
+    /// The hash of the uploaded code which is verified
+    #[structopt(long, parse(try_from_str = parse_code_hash))]
+    code_hash: Option<H256>,
+    /// Path to the Cargo.toml of the contract to build
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    #[structopt(flatten)]
+    verbosity: VerbosityFlags,
+}
+
+impl VerifyCommand {
+    pub fn exec(&self) -> Result<String> {
+        let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+        let verbosity: Option<Verbosity> = TryFrom::<&VerbosityFlags>::try_from(&self.verbosity)?;
+        let crate_metadata = CrateMetadata::collect(&manifest_path)?;
+        let mut timings = BuildTimings::default();
+        execute_with_crate_metadata(
+            &crate_metadata,
+            verbosity,
+            true,
+            BuildArtifacts::CodeOnly,
+            BuildOptions::default(),
+            &mut timings,
+        )?;
+        let code = fs::read(&crate_metadata.dest_wasm)?;
+        let local_hash = H256(sp_core::blake2_256(&code));
+
+        let on_chain_hash = self.fetch_code_hash()?;
+        let subject = match self.contract.as_ref() {
+            Some(contract) => format!("the code of the contract {}", contract),
+            None => "the uploaded code".to_string(),
+        };
+        verify_code_hash(&local_hash, &on_chain_hash, &subject)
+    }
+
+    /// Fetches the hash of the code of the contract, or checks that the code of the given hash
+    /// is uploaded.
+    fn fetch_code_hash(&self) -> Result<H256> {
+        async_std::task::block_on(async move {
+            let rpc = jsonrpsee::ws_client(self.url.as_str()).await?;
+            let cli = ClientBuilder::<DefaultNodeRuntime>::new()
+                .set_client(rpc)
+                .build()
+                .await?;
+            match (self.contract.as_ref(), self.code_hash.as_ref()) {
+                (Some(contract), _) => contract_code_hash(&cli, contract).await,
+                (None, Some(code_hash)) => {
+                    if !code_exists(&cli, code_hash).await? {
+                        anyhow::bail!("No code with the hash {:?} is uploaded", code_hash)
+                    }
+                    Ok(*code_hash)
+                }
+                (None, None) => anyhow::bail!("Pass --contract or --code-hash"),
+            }
+        })
+    }
+}
+
+/// Reports a match of the locally built code with the code on chain, fails on a mismatch.
+fn verify_code_hash(local_hash: &H256, on_chain_hash: &H256, subject: &str) -> Result<String> {
+    if local_hash != on_chain_hash {
+        anyhow::bail!(
+            "MISMATCH, {} has the hash {:?} but the local build {:?}",
+            subject,
+            on_chain_hash,
+            local_hash
+        )
+    }
+    Ok(format!(
+        "{} {} is the code of the local build ({:?})",
+        "Verified:".bright_green().bold(),
+        subject,
+        local_hash
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mismatching_code_hashes_fail() {
+        let local = H256::repeat_byte(1);
+        let on_chain = H256::repeat_byte(2);
+
+        let verified = verify_code_hash(&local, &local, "the uploaded code").unwrap();
+        assert!(verified.contains("the uploaded code is the code of the local build (0x0101"));
+        let err = verify_code_hash(&local, &on_chain, "the uploaded code").unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("MISMATCH, the uploaded code has the hash 0x0202"));
+    }
+}
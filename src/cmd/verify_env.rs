@@ -0,0 +1,135 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Checks that the build environment matches the one attested by an earlier build.
+//!
+//! The attestation is the `source` section of the metadata or the bundle of that build, which
+//! records the compiler in `source.compiler` and the tools in `source.build_info`. A file
+//! containing only the `source` section is accepted as well.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use contract_metadata::{Compiler, SourceCompiler};
+use semver::Version;
+use serde_json::{json, Value};
+
+use super::metadata::{build_info, read_metadata};
+
+/// The components of the environment, as the JSON pointer into the `source` section and the
+/// name they are reported with.
+const COMPONENTS: &[(&str, &str)] = &[
+    ("/compiler", "toolchain"),
+    (
+        "/build_info/binaryen_crate_version",
+        "binaryen crate version",
+    ),
+    ("/build_info/target", "target"),
+];
+
+/// Fails if the build environment differs from the one attested in the file at `path`,
+/// reporting each mismatched component.
+pub(crate) fn verify_env(path: &Path) -> Result<()> {
+    let attestation = read_metadata(path)?;
+    let attested = attestation.get("source").unwrap_or(&attestation);
+    let mismatches = mismatches(attested, &current_env()?)
+        .context(format!("Invalid attestation {}", path.display()))?;
+    if !mismatches.is_empty() {
+        anyhow::bail!(
+            "The build environment does not match the attestation {}, the build would differ:\
+             \n  - {}",
+            path.display(),
+            mismatches.join("\n  - ")
+        )
+    }
+    Ok(())
+}
+
+/// Returns the environment of a build, in the layout of the `source` section of the metadata.
+fn current_env() -> Result<Value> {
+    let rust_version = Version::parse(&rustc_version::version()?.to_string())?;
+    Ok(json!({
+        "compiler": SourceCompiler::new(Compiler::RustC, rust_version).to_string(),
+        "build_info": build_info(),
+    }))
+}
+
+/// Returns the components in which the `current` environment differs from the `attested` one.
+///
+/// Components which older builds did not record are not compared, the toolchain is always
+/// recorded.
+fn mismatches(attested: &Value, current: &Value) -> Result<Vec<String>> {
+    if attested.pointer("/compiler").is_none() {
+        anyhow::bail!("The attestation records no compiler, it is no metadata of a build")
+    }
+    let mut mismatches = Vec::new();
+    for (pointer, component) in COMPONENTS {
+        let attested = match attested.pointer(pointer) {
+            Some(attested) => attested,
+            None => continue,
+        };
+        let current = current.pointer(pointer).unwrap_or(&Value::Null);
+        if attested != current {
+            let display = |value: &Value| value.as_str().map_or(value.to_string(), Into::into);
+            mismatches.push(format!(
+                "{}: attested `{}`, found `{}`",
+                component,
+                display(attested),
+                display(current)
+            ));
+        }
+    }
+    Ok(mismatches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(compiler: &str, binaryen_crate_version: &str) -> Value {
+        json!({
+            "compiler": compiler,
+            "build_info": {
+                "binaryen_crate_version": binaryen_crate_version,
+                "target": "wasm32-unknown-unknown"
+            }
+        })
+    }
+
+    #[test]
+    fn each_mismatched_component_is_reported() {
+        let current = env("rustc 1.49.0-nightly", "0.12.0");
+        let attested = env("rustc 1.48.0-nightly", "0.11.0");
+
+        assert_eq!(
+            mismatches(&attested, &current).unwrap(),
+            vec![
+                "toolchain: attested `rustc 1.48.0-nightly`, found `rustc 1.49.0-nightly`",
+                "binaryen crate version: attested `0.11.0`, found `0.12.0`",
+            ]
+        );
+        assert!(mismatches(&current, &current).unwrap().is_empty());
+    }
+
+    #[test]
+    fn unrecorded_components_are_not_compared() {
+        let current = env("rustc 1.49.0-nightly", "0.12.0");
+        let attested = json!({ "compiler": "rustc 1.49.0-nightly" });
+
+        assert!(mismatches(&attested, &current).unwrap().is_empty());
+        assert!(mismatches(&json!({ "hash": "0x00" }), &current).is_err());
+    }
+}
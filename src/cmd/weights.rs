@@ -0,0 +1,155 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Merges measured message weights into the contract metadata.
+//!
+//! The weights file is a JSON object which maps a message to its weight, e.g.
+//! `{ "flip": 1250000, "0x2f865bd9": 800000 }`. A message is either named like in the bindings,
+//! the segments of its path joined by `_`, or given by its hex encoded selector.
+//!
+//! The merge rules are:
+//!
+//! - every entry must name exactly one message, otherwise the merge fails
+//! - a message with an entry gets a `weight` field, replacing any existing one
+//! - a message without an entry is left unchanged, it has no `weight` field
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+
+use super::bindings::spec_name;
+
+/// The weight of each message, keyed by its name or selector.
+pub(crate) type Weights = BTreeMap<String, u64>;
+
+/// Reads a weights file.
+pub(crate) fn load_weights(path: &Path) -> Result<Weights> {
+    let file = fs::File::open(path).context(format!("Failed to open {}", path.display()))?;
+    serde_json::from_reader(file).context(format!(
+        "Failed to parse {}, it should map messages to weights",
+        path.display()
+    ))
+}
+
+/// Adds the weights to the message specs of the ink! metadata.
+pub(crate) fn merge_weights(ink_meta: &mut Map<String, Value>, weights: &Weights) -> Result<()> {
+    let messages = ink_meta
+        .get_mut("spec")
+        .and_then(|spec| spec.get_mut("messages"))
+        .and_then(Value::as_array_mut)
+        .ok_or(anyhow::anyhow!("spec.messages not found in the metadata"))?;
+
+    let mut unmatched = Vec::new();
+    for (key, weight) in weights {
+        let mut matching = messages.iter_mut().filter(|message| {
+            spec_name(message) == *key
+                || message.get("selector").and_then(Value::as_str) == Some(key.as_str())
+        });
+        match (matching.next(), matching.next()) {
+            (Some(message), None) => {
+                if let Some(message) = message.as_object_mut() {
+                    message.insert("weight".into(), (*weight).into());
+                }
+            }
+            _ => unmatched.push(key.as_str()),
+        }
+    }
+    if !unmatched.is_empty() {
+        anyhow::bail!(
+            "The weights of {} do not map to exactly one message",
+            unmatched.join(", ")
+        )
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn ink_meta() -> Map<String, Value> {
+        json!({
+            "spec": {
+                "messages": [
+                    { "name": ["flip"], "selector": "0x633aa551" },
+                    { "name": ["get"], "selector": "0x2f865bd9", "weight": 1 }
+                ]
+            }
+        })
+        .as_object()
+        .unwrap()
+        .clone()
+    }
+
+    fn message_weights(ink_meta: &Value) -> Vec<Option<u64>> {
+        ink_meta["spec"]["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|message| message.get("weight").and_then(Value::as_u64))
+            .collect()
+    }
+
+    #[test]
+    fn merges_weights_by_name_and_selector() {
+        let mut meta = ink_meta();
+        let weights = vec![
+            ("flip".to_string(), 1250000),
+            ("0x2f865bd9".to_string(), 800000),
+        ]
+        .into_iter()
+        .collect();
+
+        merge_weights(&mut meta, &weights).unwrap();
+
+        let json = serde_json::to_string(&meta).unwrap();
+        let round_tripped: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            message_weights(&round_tripped),
+            vec![Some(1250000), Some(800000)]
+        );
+    }
+
+    #[test]
+    fn messages_without_weight_are_unchanged() {
+        let mut meta = ink_meta();
+        let weights = vec![("flip".to_string(), 10)].into_iter().collect();
+
+        merge_weights(&mut meta, &weights).unwrap();
+
+        assert_eq!(
+            message_weights(&Value::Object(meta)),
+            vec![Some(10), Some(1)]
+        );
+    }
+
+    #[test]
+    fn unknown_messages_are_rejected() {
+        let mut meta = ink_meta();
+        let weights = vec![("flip".to_string(), 10), ("transfer".to_string(), 20)]
+            .into_iter()
+            .collect();
+
+        let err = merge_weights(&mut meta, &weights).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "The weights of transfer do not map to exactly one message"
+        );
+    }
+}
@@ -19,7 +19,10 @@ use anyhow::{Context, Result};
 use cargo_metadata::{Metadata as CargoMetadata, MetadataCommand, Package};
 use semver::Version;
 use serde_json::{Map, Value};
-use std::{fs, path::PathBuf};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 use toml::value;
 use url::Url;
 
@@ -32,17 +35,41 @@ pub struct CrateMetadata {
     pub root_package: Package,
     pub original_wasm: PathBuf,
     pub dest_wasm: PathBuf,
-    pub ink_version: Version,
+    /// The metadata of the contract, `{target_dir}/metadata.json`.
+    ///
+    /// The contracts of a workspace share its target directory, so if it has several their
+    /// metadata is named after the package, `{target_dir}/{package_name}.json`.
+    pub dest_metadata: PathBuf,
+    /// The lock summary, `{target_dir}/lock-summary.json`, named after the package like the
+    /// metadata.
+    pub dest_lock_summary: PathBuf,
+    /// The version of ink!, `None` for a contract in another language.
+    pub ink_version: Option<Version>,
+    pub language: ContractLanguage,
     pub documentation: Option<Url>,
     pub homepage: Option<Url>,
     pub user: Option<Map<String, Value>>,
+    pub release_notes: Option<Url>,
+    pub required_features: Vec<String>,
     pub target_directory: PathBuf,
 }
 
 impl CrateMetadata {
     /// Parses the contract manifest and returns relevant metadata.
+    ///
+    /// The artifacts are located in the target directory reported by `cargo metadata`, which
+    /// honors `CARGO_TARGET_DIR` and the `build.target-dir` of the cargo config.
     pub fn collect(manifest_path: &ManifestPath) -> Result<Self> {
-        let (metadata, root_package) = get_cargo_metadata(manifest_path)?;
+        let metadata = MetadataCommand::new()
+            .manifest_path(manifest_path.as_ref())
+            .exec()
+            .context("Error invoking `cargo metadata`")?;
+        Self::from_cargo_metadata(manifest_path, metadata)
+    }
+
+    /// Returns the relevant metadata of the contract from the output of `cargo metadata`.
+    fn from_cargo_metadata(manifest_path: &ManifestPath, metadata: CargoMetadata) -> Result<Self> {
+        let root_package = get_root_package(&metadata)?;
 
         let mut target_directory = metadata.target_directory.clone();
         target_directory.push("ink");
@@ -62,22 +89,35 @@ impl CrateMetadata {
         dest_wasm.push(package_name.clone());
         dest_wasm.set_extension("wasm");
 
-        let ink_version = metadata
-            .packages
-            .iter()
-            .find_map(|package| {
-                if package.name == "ink_lang" {
-                    Some(
-                        Version::parse(&package.version.to_string())
-                            .expect("Invalid ink_lang version string"),
-                    )
-                } else {
-                    None
-                }
-            })
-            .ok_or(anyhow::anyhow!("No 'ink_lang' dependency found"))?;
+        // {target_dir}/metadata.json, or {target_dir}/{package_name}.json next to other contracts
+        let artifact_name = if crate::cmd::workspace_contracts(&metadata).len() > 1 {
+            package_name.as_str()
+        } else {
+            "metadata"
+        };
+        let dest_metadata = target_directory.join(format!("{}.json", artifact_name));
+        let dest_lock_summary = match artifact_name {
+            "metadata" => target_directory.join("lock-summary.json"),
+            _ => dest_metadata.with_extension("lock-summary.json"),
+        };
 
         let (documentation, homepage, user) = get_cargo_toml_metadata(manifest_path)?;
+        let (release_notes, required_features, language) =
+            get_contract_section_metadata(manifest_path)?;
+
+        let ink_version = metadata.packages.iter().find_map(|package| {
+            if package.name == "ink_lang" {
+                Some(
+                    Version::parse(&package.version.to_string())
+                        .expect("Invalid ink_lang version string"),
+                )
+            } else {
+                None
+            }
+        });
+        if language == ContractLanguage::Ink && ink_version.is_none() {
+            anyhow::bail!("No 'ink_lang' dependency found")
+        }
 
         let crate_metadata = CrateMetadata {
             manifest_path: manifest_path.clone(),
@@ -86,38 +126,49 @@ impl CrateMetadata {
             package_name,
             original_wasm,
             dest_wasm,
+            dest_metadata,
+            dest_lock_summary,
             ink_version,
+            language,
             documentation,
             homepage,
             user,
+            release_notes,
+            required_features,
             target_directory,
         };
         Ok(crate_metadata)
     }
 }
 
-/// Get the result of `cargo metadata`, together with the root package id.
-fn get_cargo_metadata(manifest_path: &ManifestPath) -> Result<(CargoMetadata, Package)> {
-    let mut cmd = MetadataCommand::new();
-    let metadata = cmd
-        .manifest_path(manifest_path.as_ref())
-        .exec()
-        .context("Error invoking `cargo metadata`")?;
+/// The language a contract is written in, set by `[package.metadata.contract] language`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ContractLanguage {
+    /// An ink! contract compiled by rustc, the default.
+    Ink,
+    /// A Solidity contract compiled by solang from the `source` file, `src/lib.sol` by default.
+    Solidity { source: PathBuf },
+}
+
+/// The Solidity source of a contract if `[package.metadata.contract] source` is not set.
+const DEFAULT_SOLIDITY_SOURCE: &str = "src/lib.sol";
+
+/// Returns the root package of the result of `cargo metadata`.
+fn get_root_package(metadata: &CargoMetadata) -> Result<Package> {
     let root_package_id = metadata
         .resolve
         .as_ref()
         .and_then(|resolve| resolve.root.as_ref())
-        .context("Cannot infer the root project id")?
-        .clone();
+        .context("Cannot infer the root project id")?;
     // Find the root package by id in the list of packages. It is logical error if the root
     // package is not found in the list.
     let root_package = metadata
         .packages
         .iter()
-        .find(|package| package.id == root_package_id)
+        .find(|package| &package.id == root_package_id)
         .expect("The package is not found in the `cargo metadata` output")
         .clone();
-    Ok((metadata, root_package))
+    Ok(root_package)
 }
 
 /// Read extra metadata not available via `cargo metadata` directly from `Cargo.toml`
@@ -155,3 +206,121 @@ fn get_cargo_toml_metadata(
 
     Ok((documentation, homepage, user))
 }
+
+/// Read the `[package.metadata.contract]` fields of `Cargo.toml` which are not user defined
+///
+/// Returns the url of the release notes, the runtime features the contract requires and the
+/// language of the contract.
+fn get_contract_section_metadata(
+    manifest_path: &ManifestPath,
+) -> Result<(Option<Url>, Vec<String>, ContractLanguage)> {
+    let toml = fs::read_to_string(manifest_path)?;
+    let toml: value::Table = toml::from_str(&toml)?;
+    let section = toml
+        .get("package")
+        .and_then(|v| v.get("metadata"))
+        .and_then(|v| v.get("contract"));
+
+    let release_notes = section
+        .and_then(|v| v.get("release-notes"))
+        .map(|v| {
+            v.as_str()
+                .ok_or(anyhow::anyhow!(
+                    "metadata.contract.release-notes should be a string"
+                ))
+                .and_then(|url| {
+                    Url::parse(url).context("metadata.contract.release-notes should be a valid URL")
+                })
+        })
+        .transpose()?;
+
+    let required_features = match section.and_then(|v| v.get("required-features")) {
+        Some(required_features) => required_features
+            .as_array()
+            .and_then(|features| {
+                features
+                    .iter()
+                    .map(|feature| feature.as_str().map(ToString::to_string))
+                    .collect::<Option<Vec<_>>>()
+            })
+            .ok_or(anyhow::anyhow!(
+                "metadata.contract.required-features should be an array of strings"
+            ))?,
+        None => Vec::new(),
+    };
+
+    let language = match section.and_then(|v| v.get("language")).map(|v| v.as_str()) {
+        None | Some(Some("ink")) => ContractLanguage::Ink,
+        Some(Some("solidity")) => {
+            let source = section
+                .and_then(|v| v.get("source"))
+                .map(|v| {
+                    v.as_str().ok_or(anyhow::anyhow!(
+                        "metadata.contract.source should be a string"
+                    ))
+                })
+                .transpose()?
+                .unwrap_or(DEFAULT_SOLIDITY_SOURCE);
+            let package_dir = manifest_path.directory().unwrap_or_else(|| Path::new(""));
+            ContractLanguage::Solidity {
+                source: package_dir.join(source),
+            }
+        }
+        Some(_) => anyhow::bail!("metadata.contract.language should be \"ink\" or \"solidity\""),
+    };
+
+    Ok((release_notes, required_features, language))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CrateMetadata;
+    use crate::{util::tests::with_tmp_dir, ManifestPath};
+    use cargo_metadata::MetadataCommand;
+    use std::{fs, process::Command};
+
+    #[test]
+    fn artifacts_are_located_in_the_cargo_target_dir() {
+        with_tmp_dir(|path| {
+            // a Solidity contract has no dependencies, so `cargo metadata` runs offline
+            let package = path.join("flipper");
+            fs::create_dir_all(package.join("src"))?;
+            fs::write(
+                package.join("Cargo.toml"),
+                "[package]\nname = \"flipper\"\nversion = \"0.1.0\"\n\n\
+                 [package.metadata.contract]\nlanguage = \"solidity\"\n",
+            )?;
+            fs::write(package.join("src").join("lib.rs"), "")?;
+            let manifest_path = ManifestPath::new(package.join("Cargo.toml"))?;
+            let shared = path.join("shared-target");
+
+            // the variable is only set for `cargo metadata`, the tests run concurrently
+            let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+            let output = Command::new(cargo)
+                .args(&["metadata", "--format-version", "1"])
+                .arg(manifest_path.cargo_arg())
+                .env("CARGO_TARGET_DIR", &shared)
+                .output()?;
+            assert!(output.status.success());
+            let metadata = MetadataCommand::parse(String::from_utf8(output.stdout)?)?;
+            let crate_metadata = CrateMetadata::from_cargo_metadata(&manifest_path, metadata)?;
+
+            let target = shared.join("ink");
+            assert_eq!(crate_metadata.target_directory, target);
+            assert_eq!(crate_metadata.dest_wasm, target.join("flipper.wasm"));
+            assert_eq!(crate_metadata.dest_metadata, target.join("metadata.json"));
+            assert_eq!(
+                crate_metadata.dest_lock_summary,
+                target.join("lock-summary.json")
+            );
+            assert_eq!(
+                crate_metadata.original_wasm,
+                target
+                    .join("wasm32-unknown-unknown")
+                    .join("release")
+                    .join("flipper.wasm")
+            );
+            Ok(())
+        })
+    }
+}
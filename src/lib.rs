@@ -0,0 +1,1480 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Setup and deployment tool for developing Wasm based smart contracts via ink!
+//!
+//! Besides the `cargo contract` command line tool, contracts can be built programmatically with
+//! [`build`], which returns the paths of the generated artifacts and the sizes of the Wasm before
+//! and after optimizing:
+//!
+//! ```no_run
+//! use cargo_contract::{BuildArtifacts, BuildOptions, ManifestPath};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let manifest_path = ManifestPath::new("flipper/Cargo.toml")?;
+//! let result = cargo_contract::build(
+//!     &manifest_path,
+//!     None,
+//!     true,
+//!     BuildArtifacts::All,
+//!     BuildOptions::default(),
+//! )?;
+//! if let Some(optimization) = result.optimization_result {
+//!     println!("optimized to {} kB", optimization.optimized_size);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+mod cmd;
+mod crate_metadata;
+mod util;
+mod workspace;
+
+pub use self::workspace::ManifestPath;
+
+#[cfg(feature = "extrinsics")]
+use crate::cmd::{
+    AccountCommand, CallCommand, CryptoScheme, DecodeCommand, DeployCommand, DiffDeployedCommand,
+    EventsCommand, ExportStateCommand, ExtrinsicSigner, InstantiateCommand, SubmitCommand,
+    UpgradeCommand, VerifyCommand,
+};
+use crate::cmd::{
+    AnalyzeCommand, BuildCommand, CheckCommand, CheckMetadataCommand, ConvertCommand,
+    DiffStorageCommand, GenerateBindingsCommand, GenerateDockerfileCommand, PublishCommand,
+    TestCommand,
+};
+
+use std::{
+    convert::TryFrom,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+#[cfg(feature = "extrinsics")]
+use subxt::{PairSigner, Signer};
+
+use anyhow::{Error, Result};
+use colored::Colorize;
+use structopt::{clap, StructOpt};
+
+#[derive(Debug, StructOpt)]
+#[structopt(bin_name = "cargo")]
+pub(crate) enum Opts {
+    /// Utilities to develop Wasm smart contracts.
+    #[structopt(name = "contract")]
+    #[structopt(setting = clap::AppSettings::UnifiedHelpMessage)]
+    #[structopt(setting = clap::AppSettings::DeriveDisplayOrder)]
+    #[structopt(setting = clap::AppSettings::DontCollapseArgsInUsage)]
+    Contract(ContractArgs),
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct ContractArgs {
+    #[structopt(subcommand)]
+    cmd: Command,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct HexData(pub Vec<u8>);
+
+#[cfg(feature = "extrinsics")]
+impl std::str::FromStr for HexData {
+    type Err = hex::FromHexError;
+
+    /// Parses hex encoded bytes, optionally prefixed by `0x`.
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        hex::decode(input.trim_start_matches("0x")).map(HexData)
+    }
+}
+
+/// The gas limit of an extrinsic, `auto` estimates it with a dry run.
+#[cfg(feature = "extrinsics")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GasLimit {
+    Auto,
+    Limit(u64),
+}
+
+#[cfg(feature = "extrinsics")]
+impl std::str::FromStr for GasLimit {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        match input {
+            "auto" => Ok(GasLimit::Auto),
+            limit => limit.parse().map(GasLimit::Limit),
+        }
+    }
+}
+
+/// Arguments selecting the substrate node a command connects to
+#[cfg(feature = "extrinsics")]
+#[derive(Debug, StructOpt)]
+pub(crate) struct NodeOpts {
+    /// Websockets url of a substrate node, defaults to `ws://localhost:9944`
+    #[structopt(name = "url", long, parse(try_from_str))]
+    url: Option<url::Url>,
+    /// A known network whose node is used instead of `--url`: `canvas`, `rococo-contracts` or
+    /// `local`
+    #[structopt(long, parse(try_from_str = parse_network))]
+    network: Option<url::Url>,
+}
+
+#[cfg(feature = "extrinsics")]
+impl NodeOpts {
+    /// Returns the url of `--url`, of the node of `--network`, or the local node by default.
+    pub fn url(&self) -> Result<url::Url> {
+        match (self.url.as_ref(), self.network.as_ref()) {
+            (Some(_), Some(_)) => anyhow::bail!(
+                "Pass either --url or --network, not both: --network selects the url of a known \
+                 node"
+            ),
+            (Some(url), None) | (None, Some(url)) => Ok(url.clone()),
+            (None, None) => Ok(url::Url::parse(DEFAULT_URL)?),
+        }
+    }
+}
+
+/// Arguments required for creating and sending an extrinsic to a substrate node
+///
+/// Its `Debug` output redacts the secret key URI and the password.
+#[cfg(feature = "extrinsics")]
+#[derive(StructOpt)]
+pub(crate) struct ExtrinsicOpts {
+    #[structopt(flatten)]
+    node: NodeOpts,
+    /// Secret key URI for the account deploying the contract.
+    #[structopt(
+        name = "suri",
+        long,
+        short,
+        required_unless_one = &["from", "submit-signed", "account", "keystore"]
+    )]
+    suri: Option<String>,
+    /// Name of the keyfile, or SS58 address, of the signing account in the `--keystore-path`,
+    /// instead of a secret key URI
+    #[structopt(long, conflicts_with = "suri", requires = "keystore-path")]
+    account: Option<String>,
+    /// Path to a Substrate keystore directory, e.g. `<base-path>/chains/<chain>/keystore`
+    #[structopt(long, parse(from_os_str))]
+    keystore_path: Option<PathBuf>,
+    /// Path to a single keyfile of a Substrate keystore, the signing key is derived from the
+    /// secret it contains with the `--password`
+    ///
+    /// Keeps the secret key out of the command line and the shell history.
+    #[structopt(
+        long,
+        parse(from_os_str),
+        value_name = "path",
+        conflicts_with_all = &["suri", "account", "keystore-path"]
+    )]
+    keystore: Option<PathBuf>,
+    /// Password for the secret key
+    ///
+    /// Without a value the password is prompted for on the terminal, so it is not recorded in
+    /// the shell history. A keyfile whose password is wrong or missing also prompts for it.
+    #[structopt(name = "password", long, short)]
+    password: Option<Option<String>>,
+    /// The signature scheme of the `--suri` key: `sr25519`, `ed25519` or `ecdsa`
+    ///
+    /// Keys of a keystore are always sr25519 keys.
+    #[structopt(long, value_name = "scheme", conflicts_with_all = &["account", "keystore"])]
+    scheme: Option<CryptoScheme>,
+    /// Refuse to submit the extrinsic if the free balance of the signer does not cover the
+    /// estimated fee plus any transferred balance
+    #[structopt(long)]
+    check_balance: bool,
+    /// Ask for a confirmation before submitting if the node runs a known production chain, e.g.
+    /// Polkadot or Kusama
+    #[structopt(long)]
+    prompt_on_mainnet: bool,
+    /// Confirm submitting to a production chain without asking, required with
+    /// `--prompt-on-mainnet` if stdin is not a terminal
+    #[structopt(long)]
+    yes: bool,
+    /// Fetch the runtime metadata from the node, instead of reusing the one cached for its
+    /// genesis hash and runtime version
+    #[structopt(long)]
+    refresh_metadata: bool,
+    #[structopt(flatten)]
+    verbosity: VerbosityFlags,
+}
+
+#[cfg(feature = "extrinsics")]
+impl std::fmt::Debug for ExtrinsicOpts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let redact = |secret: &Option<String>| secret.as_ref().map(|_| "***");
+        f.debug_struct("ExtrinsicOpts")
+            .field("node", &self.node)
+            .field("suri", &redact(&self.suri))
+            .field("account", &self.account)
+            .field("keystore_path", &self.keystore_path)
+            .field("keystore", &self.keystore)
+            .field("password", &self.password.as_ref().map(|_| "***"))
+            .field("scheme", &self.scheme)
+            .field("check_balance", &self.check_balance)
+            .field("prompt_on_mainnet", &self.prompt_on_mainnet)
+            .field("yes", &self.yes)
+            .field("refresh_metadata", &self.refresh_metadata)
+            .field("verbosity", &self.verbosity)
+            .finish()
+    }
+}
+
+#[cfg(feature = "extrinsics")]
+impl ExtrinsicOpts {
+    /// Returns the signer derived from the secret key URI, or loaded from the `--keystore` file
+    /// or the keystore with `--account`.
+    ///
+    /// The password of the key may be prompted for, a command resolves the signer only once.
+    pub fn signer(&self) -> Result<ExtrinsicSigner> {
+        let password = match self.password.as_ref() {
+            Some(Some(password)) => Some(password.clone()),
+            Some(None) => Some(cmd::prompt_password()?),
+            None => None,
+        };
+        let signer = match self.signer_with(password.as_deref()) {
+            // the keyfile needs a password, ask for it instead of failing
+            Err(err) if password.is_none() && err.is::<cmd::WrongPassword>() => {
+                self.signer_with(Some(&cmd::prompt_password()?))?
+            }
+            signer => signer?,
+        };
+        Ok(signer)
+    }
+
+    /// Prints the SS58 address of the signing account with `--verbose`, so users can confirm
+    /// which account is acting.
+    pub fn print_signer(&self, signer: &ExtrinsicSigner) -> Result<()> {
+        if Verbosity::at_least(self.verbosity()?, Verbosity::Verbose) {
+            println!("{} {}", "Signer account:".bold(), signer.account_id());
+        }
+        Ok(())
+    }
+
+    /// Returns the signer of the `--keystore` file, the `--account` or the `--suri` with the
+    /// `--scheme`.
+    fn signer_with(&self, password: Option<&str>) -> Result<ExtrinsicSigner> {
+        Ok(
+            match (
+                self.keystore.as_ref(),
+                self.account.as_ref(),
+                self.keystore_path.as_ref(),
+            ) {
+                (Some(keyfile), _, _) => {
+                    ExtrinsicSigner::Sr25519(PairSigner::new(cmd::keyfile_pair(keyfile, password)?))
+                }
+                (None, Some(account), Some(keystore_path)) => ExtrinsicSigner::Sr25519(
+                    PairSigner::new(cmd::keystore_pair(keystore_path, account, password)?),
+                ),
+                _ => {
+                    let suri = self.suri.as_ref().ok_or(anyhow::anyhow!(
+                        "A secret key URI is required, pass --suri, --keystore or --account"
+                    ))?;
+                    self.scheme.unwrap_or_default().signer(suri, password)?
+                }
+            },
+        )
+    }
+
+    /// Returns the verbosity set by `--quiet` or `--verbose`.
+    pub fn verbosity(&self) -> Result<Option<Verbosity>> {
+        TryFrom::<&VerbosityFlags>::try_from(&self.verbosity)
+    }
+
+    /// Returns the url of the node, given by `--url` or `--network`.
+    pub fn url(&self) -> Result<url::Url> {
+        self.node.url()
+    }
+
+    /// Connects to the node, the runtime metadata subxt requests is answered from the cache
+    /// unless `--refresh-metadata` is passed.
+    pub async fn rpc_client(&self) -> Result<jsonrpsee::Client> {
+        cmd::cached_client(&self.url()?, self.refresh_metadata).await
+    }
+
+    /// Asks for a confirmation if `--prompt-on-mainnet` is set and the node runs a production
+    /// chain. Must be called before submitting any extrinsic.
+    pub fn confirm_chain(&self) -> Result<()> {
+        if self.prompt_on_mainnet {
+            cmd::confirm_mainnet(&self.url()?, self.yes)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default, StructOpt)]
+pub struct VerbosityFlags {
+    /// Print nothing but errors, the verbosity level 0
+    #[structopt(long)]
+    quiet: bool,
+    /// Print more, can be repeated: `-v` prints the output of cargo, `-vv` also the command
+    /// lines of the invoked tools, `-vvv` also the requests to and the responses of the node
+    #[structopt(long, short, parse(from_occurrences))]
+    verbose: u8,
+    /// The verbosity level from 0 (`--quiet`) to 3 (`-vvv`)
+    #[structopt(long, value_name = "level", conflicts_with_all = &["quiet", "verbose"])]
+    verbosity: Option<u8>,
+}
+
+/// How much a command prints, `None` prints the progress.
+///
+/// The levels are ordered, each one prints everything the lower ones print.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Print nothing but errors, level 0.
+    Quiet,
+    /// Also print the output of cargo, level 1.
+    Verbose,
+    /// Also print the command lines of cargo and the other invoked tools, level 2.
+    Debug,
+    /// Also log the requests to and the responses of the node, level 3.
+    Trace,
+}
+
+impl Verbosity {
+    /// Returns the verbosity of the `level`, from 0 to 3.
+    fn from_level(level: u8) -> Result<Self> {
+        match level {
+            0 => Ok(Verbosity::Quiet),
+            1 => Ok(Verbosity::Verbose),
+            2 => Ok(Verbosity::Debug),
+            3 => Ok(Verbosity::Trace),
+            _ => anyhow::bail!("The verbosity level must be from 0 to 3, not {}", level),
+        }
+    }
+
+    /// Returns `true` if `verbosity` is at least `level`.
+    pub fn at_least(verbosity: Option<Verbosity>, level: Verbosity) -> bool {
+        matches!(verbosity, Some(verbosity) if verbosity >= level)
+    }
+}
+
+impl TryFrom<&VerbosityFlags> for Option<Verbosity> {
+    type Error = Error;
+
+    fn try_from(value: &VerbosityFlags) -> Result<Self, Self::Error> {
+        let verbosity = match (value.quiet, value.verbose, value.verbosity) {
+            (false, 0, None) => None,
+            (true, 0, None) => Some(Verbosity::Quiet),
+            // more than `-vvv` prints everything
+            (false, verbose, None) => Some(Verbosity::from_level(verbose.min(3))?),
+            (false, 0, Some(level)) => Some(Verbosity::from_level(level)?),
+            (true, _, None) => anyhow::bail!("Cannot pass both --quiet and --verbose flags"),
+            _ => anyhow::bail!("Pass either --verbosity or --quiet and --verbose"),
+        };
+        #[cfg(feature = "extrinsics")]
+        if Verbosity::at_least(verbosity, Verbosity::Trace) {
+            util::trace_rpc();
+        }
+        Ok(verbosity)
+    }
+}
+
+#[derive(Clone, Debug, StructOpt)]
+struct UnstableOptions {
+    /// Use the original manifest (Cargo.toml), do not modify for build optimizations
+    #[structopt(long = "unstable-options", short = "Z", number_of_values = 1)]
+    options: Vec<String>,
+}
+
+/// Settings of a build beyond the build artifacts, `Default` builds as `cargo contract build`
+/// does without any flags.
+///
+/// The settings are changed by the methods named after them:
+///
+/// ```no_run
+/// use cargo_contract::{BuildArtifacts, BuildMode, BuildOptions, ManifestPath};
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let manifest_path = ManifestPath::new("flipper/Cargo.toml")?;
+/// let build_options = BuildOptions::default().build_mode(BuildMode::Debug);
+/// let result = cargo_contract::build(
+///     &manifest_path,
+///     None,
+///     true,
+///     BuildArtifacts::All,
+///     build_options,
+/// )?;
+/// assert_eq!(result.build_mode, BuildMode::Debug);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct BuildOptions {
+    /// Build for a shared memory, set by `build --shared-memory`.
+    shared_memory: bool,
+    /// Keep the `name` section and the DWARF debug info in the Wasm, set by
+    /// `build --keep-debug-symbols`.
+    keep_debug_symbols: bool,
+    /// The percentage by which optimizing may grow the wasm, set by
+    /// `build --max-relative-size-increase`.
+    max_relative_size_increase: f64,
+    /// The weights merged into the message specs of the metadata, set by `build --weights`.
+    weights: Option<cmd::Weights>,
+    /// Compress the Wasm embedded in the bundle, set by `build --compress-wasm`.
+    compress_wasm: bool,
+    /// Explain common `cargo build` failures, set by `build --explain-error`.
+    explain_error: bool,
+    /// Reuse the Wasm of the last build if the code is unchanged, set by `build --reuse-wasm`.
+    reuse_wasm: bool,
+    /// The name of the contract in the metadata, set by `build --contract-name`.
+    contract_name: Option<String>,
+    /// The version of the contract in the metadata, set by `build --contract-version`.
+    contract_version: Option<semver::Version>,
+    /// The format of the metadata file, set by `build --metadata-format`.
+    metadata_format: MetadataFormat,
+    /// Runtime features required of the chain in addition to the ones of the manifest, set by
+    /// `build --required-feature`.
+    required_features: Vec<String>,
+    /// The settings of the `wasm-opt` optimizer, set by `build --opt-config` and the flags
+    /// overriding it.
+    optimizer: cmd::OptimizerConfig,
+    /// Compile with the dev profile and skip optimizing, set by `build --debug`.
+    build_mode: BuildMode,
+    /// Keep machine specific paths and the build time out of the artifacts, set by
+    /// `build --reproducible`.
+    reproducible: bool,
+    /// The features of the contract to enable, set by `build --features` and `check --features`.
+    features: Vec<String>,
+    /// The unstable options of `-Z`.
+    unstable_flags: UnstableFlags,
+}
+
+impl BuildOptions {
+    /// Builds for a shared memory, as `build --shared-memory`.
+    pub fn shared_memory(mut self, shared_memory: bool) -> Self {
+        self.shared_memory = shared_memory;
+        self
+    }
+
+    /// Keeps the `name` section and the DWARF debug info in the Wasm, as
+    /// `build --keep-debug-symbols`.
+    pub fn keep_debug_symbols(mut self, keep_debug_symbols: bool) -> Self {
+        self.keep_debug_symbols = keep_debug_symbols;
+        self
+    }
+
+    /// Sets the percentage by which optimizing may grow the Wasm, as
+    /// `build --max-relative-size-increase`.
+    pub fn max_relative_size_increase(mut self, max_relative_size_increase: f64) -> Self {
+        self.max_relative_size_increase = max_relative_size_increase;
+        self
+    }
+
+    /// Compresses the Wasm embedded in the bundle, as `build --compress-wasm`.
+    pub fn compress_wasm(mut self, compress_wasm: bool) -> Self {
+        self.compress_wasm = compress_wasm;
+        self
+    }
+
+    /// Explains common `cargo build` failures, as `build --explain-error`.
+    pub fn explain_error(mut self, explain_error: bool) -> Self {
+        self.explain_error = explain_error;
+        self
+    }
+
+    /// Reuses the Wasm of the last build if the code is unchanged, as `build --reuse-wasm`.
+    pub fn reuse_wasm(mut self, reuse_wasm: bool) -> Self {
+        self.reuse_wasm = reuse_wasm;
+        self
+    }
+
+    /// Sets the name of the contract in the metadata, as `build --contract-name`.
+    pub fn contract_name(mut self, contract_name: Option<String>) -> Self {
+        self.contract_name = contract_name;
+        self
+    }
+
+    /// Sets the version of the contract in the metadata, as `build --contract-version`.
+    pub fn contract_version(mut self, contract_version: Option<semver::Version>) -> Self {
+        self.contract_version = contract_version;
+        self
+    }
+
+    /// Sets the format of the metadata file, as `build --metadata-format`.
+    pub fn metadata_format(mut self, metadata_format: MetadataFormat) -> Self {
+        self.metadata_format = metadata_format;
+        self
+    }
+
+    /// Sets the runtime features required of the chain in addition to the ones of the
+    /// manifest, as `build --required-feature`.
+    pub fn required_features(mut self, required_features: Vec<String>) -> Self {
+        self.required_features = required_features;
+        self
+    }
+
+    /// Sets the profile the contract is compiled with, as `build --debug`.
+    pub fn build_mode(mut self, build_mode: BuildMode) -> Self {
+        self.build_mode = build_mode;
+        self
+    }
+
+    /// Keeps machine specific paths and the build time out of the artifacts, as
+    /// `build --reproducible`.
+    pub fn reproducible(mut self, reproducible: bool) -> Self {
+        self.reproducible = reproducible;
+        self
+    }
+
+    /// Sets the features of the contract to enable, as `build --features`.
+    pub fn features(mut self, features: Vec<String>) -> Self {
+        self.features = features;
+        self
+    }
+
+    /// Sets the unstable options, as `-Z`.
+    pub fn unstable_flags(mut self, unstable_flags: UnstableFlags) -> Self {
+        self.unstable_flags = unstable_flags;
+        self
+    }
+}
+
+/// The unstable options of a build, set by `-Z`.
+#[derive(Clone, Default)]
+pub struct UnstableFlags {
+    original_manifest: bool,
+}
+
+impl UnstableFlags {
+    /// Builds with the original manifest instead of one modified for build optimizations, as
+    /// `-Z original-manifest`.
+    pub fn original_manifest(mut self, original_manifest: bool) -> Self {
+        self.original_manifest = original_manifest;
+        self
+    }
+}
+
+impl TryFrom<&UnstableOptions> for UnstableFlags {
+    type Error = Error;
+
+    fn try_from(value: &UnstableOptions) -> Result<Self, Self::Error> {
+        let valid_flags = ["original-manifest"];
+        let invalid_flags = value
+            .options
+            .iter()
+            .filter(|o| !valid_flags.contains(&o.as_str()))
+            .collect::<Vec<_>>();
+        if !invalid_flags.is_empty() {
+            anyhow::bail!("Unknown unstable-options {:?}", invalid_flags)
+        }
+        Ok(UnstableFlags {
+            original_manifest: value.options.contains(&"original-manifest".to_owned()),
+        })
+    }
+}
+
+/// Describes which artifacts to generate
+#[derive(Copy, Clone, Eq, PartialEq, Debug, StructOpt)]
+#[structopt(name = "build-artifacts")]
+pub enum BuildArtifacts {
+    /// Generate the Wasm, the metadata and a bundled `<name>.contract` file
+    #[structopt(name = "all")]
+    All,
+    /// Only the Wasm is created, generation of metadata and a bundled `<name>.contract` file is skipped
+    #[structopt(name = "code-only")]
+    CodeOnly,
+    CheckOnly,
+}
+
+impl BuildArtifacts {
+    /// Returns the number of steps required to complete a build artifact.
+    /// Used as output on the cli.
+    ///
+    /// A debug build skips the optimization step.
+    pub fn steps(&self, build_mode: BuildMode) -> usize {
+        let steps = match self {
+            BuildArtifacts::All => 5,
+            BuildArtifacts::CodeOnly => 3,
+            BuildArtifacts::CheckOnly => return 2,
+        };
+        match build_mode {
+            BuildMode::Release => steps,
+            BuildMode::Debug => steps - 1,
+        }
+    }
+}
+
+impl std::str::FromStr for BuildArtifacts {
+    type Err = String;
+    fn from_str(artifact: &str) -> Result<Self, Self::Err> {
+        match artifact {
+            "all" => Ok(BuildArtifacts::All),
+            "code-only" => Ok(BuildArtifacts::CodeOnly),
+            _ => Err("Could not parse build artifact".to_string()),
+        }
+    }
+}
+
+/// Describes the layout in which the metadata is emitted
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum MetadataCompat {
+    /// The current layout, with nested `source` and `contract` sections
+    Current,
+    /// The legacy flat layout expected by some older explorers
+    Legacy,
+}
+
+impl std::str::FromStr for MetadataCompat {
+    type Err = String;
+    fn from_str(compat: &str) -> Result<Self, Self::Err> {
+        match compat {
+            "current" => Ok(MetadataCompat::Current),
+            "legacy" => Ok(MetadataCompat::Legacy),
+            _ => Err("Could not parse metadata compat".to_string()),
+        }
+    }
+}
+
+/// Describes the format in which the metadata file is written, the bundle is always JSON
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum MetadataFormat {
+    /// Pretty printed JSON, the canonical form, friendly to diffing
+    Json,
+    /// JSON without whitespace, smaller for uploading
+    CompactJson,
+    /// YAML, for reading and editing by humans
+    Yaml,
+}
+
+impl Default for MetadataFormat {
+    fn default() -> Self {
+        MetadataFormat::Json
+    }
+}
+
+impl std::str::FromStr for MetadataFormat {
+    type Err = String;
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "json" | "pretty" => Ok(MetadataFormat::Json),
+            "compact" => Ok(MetadataFormat::CompactJson),
+            "yaml" => Ok(MetadataFormat::Yaml),
+            _ => Err("Could not parse metadata format".to_string()),
+        }
+    }
+}
+
+/// Describes the cargo profile the contract is compiled with
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum BuildMode {
+    /// The release profile, the Wasm is optimized
+    Release,
+    /// The dev profile, optimizing the Wasm is skipped for a faster build
+    Debug,
+}
+
+impl Default for BuildMode {
+    fn default() -> Self {
+        BuildMode::Release
+    }
+}
+
+impl BuildMode {
+    /// The name of the cargo profile directory.
+    pub fn profile(&self) -> &'static str {
+        match self {
+            BuildMode::Release => "release",
+            BuildMode::Debug => "debug",
+        }
+    }
+}
+
+/// Describes the representation `cargo contract convert` converts to
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ConvertTarget {
+    /// A single `<name>.contract` bundle of the code and the metadata
+    Bundle,
+    /// A separate `<name>.wasm` and `<name>.json`
+    Split,
+}
+
+impl std::str::FromStr for ConvertTarget {
+    type Err = String;
+    fn from_str(target: &str) -> Result<Self, Self::Err> {
+        match target {
+            "bundle" => Ok(ConvertTarget::Bundle),
+            "split" => Ok(ConvertTarget::Split),
+            _ => Err("Could not parse convert target".to_string()),
+        }
+    }
+}
+
+/// Describes the format of the bindings generated from the contract metadata
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum BindingsFormat {
+    /// A Solidity style ABI JSON
+    SolidityAbi,
+}
+
+impl std::str::FromStr for BindingsFormat {
+    type Err = String;
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "solidity-abi" => Ok(BindingsFormat::SolidityAbi),
+            _ => Err("Could not parse bindings format".to_string()),
+        }
+    }
+}
+
+/// Describes what the data decoded by `cargo contract decode` is
+#[cfg(feature = "extrinsics")]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum DecodeType {
+    /// An event emitted by the contract, its index followed by its arguments
+    Event,
+    /// A call of a message, its selector followed by its arguments
+    Message,
+    /// A call of a constructor, its selector followed by its arguments
+    Constructor,
+}
+
+#[cfg(feature = "extrinsics")]
+impl std::str::FromStr for DecodeType {
+    type Err = String;
+    fn from_str(ty: &str) -> Result<Self, Self::Err> {
+        match ty {
+            "event" => Ok(DecodeType::Event),
+            "message" => Ok(DecodeType::Message),
+            "constructor" => Ok(DecodeType::Constructor),
+            _ => Err("Could not parse decode type".to_string()),
+        }
+    }
+}
+
+/// Result of the metadata generation process.
+pub struct BuildResult {
+    /// Path to the resulting metadata file.
+    pub dest_metadata: Option<PathBuf>,
+    /// Path to the resulting Wasm file.
+    pub dest_wasm: Option<PathBuf>,
+    /// Path to the text format of the resulting Wasm file.
+    pub dest_wat: Option<PathBuf>,
+    /// Path to the bundled file.
+    pub dest_bundle: Option<PathBuf>,
+    /// Path to the file describing the MIME type of the bundle.
+    pub dest_mime: Option<PathBuf>,
+    /// Path to the metadata reduced to the names and selectors.
+    pub dest_slim_metadata: Option<PathBuf>,
+    /// Path to the summary of the toolchain, ink! version and code hash.
+    pub dest_lock_summary: Option<PathBuf>,
+    /// Path to the directory where output files are written to.
+    pub target_directory: PathBuf,
+    /// If existent the result of the optimization.
+    pub optimization_result: Option<OptimizationResult>,
+    /// The size of the sections and largest functions of the Wasm, set by `build --size-report`.
+    pub size_report: Option<cmd::SizeReport>,
+    /// Which build artifacts were generated.
+    pub build_artifact: BuildArtifacts,
+    /// The profile the contract was compiled with.
+    pub build_mode: BuildMode,
+    /// How long each step of the build took.
+    pub timings: BuildTimings,
+}
+
+/// Result of the optimization process.
+pub struct OptimizationResult {
+    /// The original Wasm size.
+    pub original_size: f64,
+    /// The Wasm size after optimizations have been applied.
+    pub optimized_size: f64,
+    /// Whether the optimized Wasm was larger than allowed, so the original was kept.
+    pub kept_original: bool,
+    /// The optimization of `wasm-opt`, e.g. `-Oz`, set by `build --optimization-passes`.
+    pub optimization_level: String,
+}
+
+/// Wall-clock durations of the steps of a build.
+#[derive(Debug, Default)]
+pub struct BuildTimings {
+    steps: Vec<(&'static str, Duration)>,
+}
+
+impl BuildTimings {
+    /// Records the duration of a step.
+    pub fn record(&mut self, step: &'static str, duration: Duration) {
+        self.steps.push((step, duration))
+    }
+
+    /// Runs `f` and records its duration under `step`.
+    pub fn time<T>(&mut self, step: &'static str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.record(step, start.elapsed());
+        result
+    }
+
+    fn total(&self) -> Duration {
+        self.steps.iter().map(|(_, duration)| *duration).sum()
+    }
+
+    /// Displays the duration of each step and its share of the total.
+    pub fn display(&self) -> String {
+        let total = self.total().as_secs_f64();
+        let mut out = String::from("Build timings:");
+        for (step, duration) in &self.steps {
+            let secs = duration.as_secs_f64();
+            let share = if total > 0.0 {
+                secs / total * 100.0
+            } else {
+                0.0
+            };
+            out.push_str(&format!("\n  {:<24} {:>8.2}s {:>5.1}%", step, secs, share));
+        }
+        out.push_str(&format!("\n  {:<24} {:>8.2}s", "Total".bold(), total));
+        out
+    }
+
+    /// Returns the timing report as JSON.
+    pub fn to_json(&self) -> serde_json::Value {
+        let steps = self
+            .steps
+            .iter()
+            .map(|(step, duration)| {
+                serde_json::json!({ "step": step, "seconds": duration.as_secs_f64() })
+            })
+            .collect::<Vec<_>>();
+        serde_json::json!({ "steps": steps, "total_seconds": self.total().as_secs_f64() })
+    }
+}
+
+impl BuildResult {
+    pub fn display(&self) -> String {
+        let size_diff = match self.build_mode {
+            BuildMode::Release => {
+                let optimization = self.display_optimization();
+                let kept_original = self
+                    .optimization_result
+                    .as_ref()
+                    .map_or(false, |optimization| optimization.kept_original);
+                format!(
+                    "\nOriginal wasm size: {}, Optimized ({}): {}{}\n\n",
+                    format!("{:.1}K", optimization.0).bold(),
+                    optimization.2,
+                    format!("{:.1}K", optimization.1).bold(),
+                    if kept_original {
+                        " (larger, the original was kept)"
+                    } else {
+                        ""
+                    },
+                )
+            }
+            BuildMode::Debug => format!(
+                "\n{} {}\n\n",
+                "Debug build (unoptimized).".yellow().bold(),
+                "Do not deploy it, build without --debug for deployment.".bold()
+            ),
+        };
+
+        let size_diff = match self.size_report.as_ref() {
+            Some(report) => format!("{}{}\n\n", size_diff, report.display()),
+            None => size_diff,
+        };
+
+        if self.build_artifact == BuildArtifacts::CodeOnly {
+            let out = format!(
+                "{}Your contract's code is ready. You can find it here:\n{}",
+                size_diff,
+                self.dest_wasm
+                    .as_ref()
+                    .expect("wasm path must exist")
+                    .display()
+                    .to_string()
+                    .bold()
+            );
+            return out;
+        };
+
+        let mut out = format!(
+            "{}Your contract artifacts are ready. You can find them in:\n{}\n\n",
+            size_diff,
+            self.target_directory.display().to_string().bold(),
+        );
+        if let Some(dest_bundle) = self.dest_bundle.as_ref() {
+            let bundle = format!(
+                "  - {} (code + metadata)\n",
+                util::base_name(&dest_bundle).bold()
+            );
+            out.push_str(&bundle);
+        }
+        if let Some(dest_mime) = self.dest_mime.as_ref() {
+            let mime = format!(
+                "  - {} (the MIME type of the bundle)\n",
+                util::base_name(&dest_mime).bold()
+            );
+            out.push_str(&mime);
+        }
+        if let Some(dest_wasm) = self.dest_wasm.as_ref() {
+            let wasm = format!(
+                "  - {} (the contract's code)\n",
+                util::base_name(&dest_wasm).bold()
+            );
+            out.push_str(&wasm);
+        }
+        if let Some(dest_wat) = self.dest_wat.as_ref() {
+            let wat = format!(
+                "  - {} (the text format of the contract's code)\n",
+                util::base_name(&dest_wat).bold()
+            );
+            out.push_str(&wat);
+        }
+        if let Some(dest_metadata) = self.dest_metadata.as_ref() {
+            let metadata = format!(
+                "  - {} (the contract's metadata)",
+                util::base_name(&dest_metadata).bold()
+            );
+            out.push_str(&metadata);
+        }
+        if let Some(dest_slim_metadata) = self.dest_slim_metadata.as_ref() {
+            let slim_metadata = format!(
+                "\n  - {} (the names and selectors, without types)",
+                util::base_name(&dest_slim_metadata).bold()
+            );
+            out.push_str(&slim_metadata);
+        }
+        if let Some(dest_lock_summary) = self.dest_lock_summary.as_ref() {
+            let lock_summary = format!(
+                "\n  - {} (the toolchain, ink! version and code hash)",
+                util::base_name(&dest_lock_summary).bold()
+            );
+            out.push_str(&lock_summary);
+        }
+        out
+    }
+
+    /// Returns the paths of the artifacts and the optimization result as JSON.
+    ///
+    /// The paths are absolute and artifacts which were not generated are `null`, so the schema is
+    /// the same for all build artifacts. The timings are `null` unless `with_timings` is set, as by
+    /// `build --timings`.
+    pub fn to_json(&self, with_timings: bool) -> Result<serde_json::Value> {
+        let cwd = std::env::current_dir()?;
+        let path = |path: Option<&PathBuf>| path.map(|path| cwd.join(path).display().to_string());
+        let optimization_result = self.optimization_result.as_ref().map(|optimization| {
+            serde_json::json!({
+                "original_size": optimization.original_size,
+                "optimized_size": optimization.optimized_size,
+                "kept_original": optimization.kept_original,
+                "optimization_level": optimization.optimization_level,
+            })
+        });
+        Ok(serde_json::json!({
+            "dest_metadata": path(self.dest_metadata.as_ref()),
+            "dest_wasm": path(self.dest_wasm.as_ref()),
+            "dest_wat": path(self.dest_wat.as_ref()),
+            "dest_bundle": path(self.dest_bundle.as_ref()),
+            "dest_mime": path(self.dest_mime.as_ref()),
+            "dest_slim_metadata": path(self.dest_slim_metadata.as_ref()),
+            "dest_lock_summary": path(self.dest_lock_summary.as_ref()),
+            "target_directory": path(Some(&self.target_directory)),
+            "optimization_result": optimization_result,
+            "size_report": self.size_report.as_ref().map(|report| report.to_json()),
+            "timings": Some(self.timings.to_json()).filter(|_| with_timings),
+            "build_mode": self.build_mode.profile(),
+        }))
+    }
+
+    /// Returns a tuple of `(original_size, optimized_size, optimization_level)`.
+    ///
+    /// Panics if no optimization result is available.
+    fn display_optimization(&self) -> (f64, f64, &str) {
+        let optimization = self
+            .optimization_result
+            .as_ref()
+            .expect("optimization result must exist");
+        (
+            optimization.original_size,
+            optimization.optimized_size,
+            &optimization.optimization_level,
+        )
+    }
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Setup and create a new smart contract project
+    #[structopt(name = "new")]
+    New {
+        /// The name of the newly created smart contract
+        name: String,
+        /// The optional target directory for the contract project
+        #[structopt(short, long, parse(from_os_str))]
+        target_dir: Option<PathBuf>,
+        /// Add the contract as a module of the existing library crate in this directory,
+        /// instead of creating a new crate. Missing ink! dependencies and features are added to
+        /// its manifest
+        #[structopt(long, parse(from_os_str), conflicts_with = "target-dir")]
+        as_module: Option<PathBuf>,
+        /// The starter contract: `flipper` (the default), `erc20`, `erc721` or `empty`
+        #[structopt(long)]
+        template: Option<cmd::new::Template>,
+        /// Create the contract from the template in this git repository
+        ///
+        /// `{{name}}`, `{{camel_name}}` and `{{authors}}` are replaced in the names and the
+        /// contents of its files.
+        #[structopt(long, value_name = "url", conflicts_with_all = &["template", "as-module"])]
+        git: Option<String>,
+        /// Add the new contract to the `members` of the workspace with this root manifest,
+        /// unless it is a member already. The manifest is rewritten without its comments
+        #[structopt(
+            long,
+            parse(from_os_str),
+            value_name = "Cargo.toml",
+            conflicts_with = "as-module"
+        )]
+        workspace: Option<PathBuf>,
+    },
+    /// Compiles the contract, generates metadata, bundles both together in a `<name>.contract` file
+    #[structopt(name = "build")]
+    Build(Box<BuildCommand>),
+    /// Command has been deprecated, use `cargo contract build` instead
+    #[structopt(name = "generate-metadata")]
+    GenerateMetadata {},
+    /// Check that the code builds as Wasm; does not output any build artifact to the top level `target/` directory
+    #[structopt(name = "check")]
+    Check(CheckCommand),
+    /// Validate a metadata file against the schema of its `metadataVersion`
+    #[structopt(name = "check-metadata")]
+    CheckMetadata(CheckMetadataCommand),
+    /// Test the smart contract off-chain
+    #[structopt(name = "test")]
+    Test(TestCommand),
+    /// Generate bindings for other ecosystems from the contract metadata
+    #[structopt(name = "generate-bindings")]
+    GenerateBindings(GenerateBindingsCommand),
+    /// Generate a Dockerfile which builds the contract with the same toolchain and tools
+    #[structopt(name = "generate-dockerfile")]
+    GenerateDockerfile(GenerateDockerfileCommand),
+    /// Report which host functions each constructor and message of the contract can reach
+    #[structopt(name = "analyze")]
+    Analyze(AnalyzeCommand),
+    /// Convert between a `.contract` bundle and a separate Wasm and metadata
+    #[structopt(name = "convert")]
+    Convert(ConvertCommand),
+    /// Compare the storage layouts of two metadata files, failing on changes which break upgrades
+    #[structopt(name = "diff-storage")]
+    DiffStorage(DiffStorageCommand),
+    /// Upload the `<name>.contract` bundle to a contract registry
+    #[structopt(name = "publish")]
+    Publish(PublishCommand),
+    /// Upload the smart contract code to the chain
+    #[cfg(feature = "extrinsics")]
+    #[structopt(name = "deploy")]
+    Deploy(Box<DeployCommand>),
+    /// Instantiate a deployed smart contract
+    #[cfg(feature = "extrinsics")]
+    #[structopt(name = "instantiate")]
+    Instantiate(Box<InstantiateCommand>),
+    /// Call a message of a deployed contract, optionally many times for load testing
+    #[cfg(feature = "extrinsics")]
+    #[structopt(name = "call")]
+    Call(Box<CallCommand>),
+    /// Sign and submit a call of the contracts pallet which was encoded elsewhere
+    #[cfg(feature = "extrinsics")]
+    #[structopt(name = "submit")]
+    Submit(Box<SubmitCommand>),
+    /// Dump the storage of a deployed contract
+    #[cfg(feature = "extrinsics")]
+    #[structopt(name = "export-state")]
+    ExportState(ExportStateCommand),
+    /// Print the events emitted by a deployed contract as they are finalized, until interrupted
+    #[cfg(feature = "extrinsics")]
+    #[structopt(name = "events")]
+    Events(EventsCommand),
+    /// Decode SCALE encoded events and call data of a contract with its metadata
+    #[cfg(feature = "extrinsics")]
+    #[structopt(name = "decode")]
+    Decode(DecodeCommand),
+    /// Print the balance of a deployed contract, its code hash and storage rent
+    #[cfg(feature = "extrinsics")]
+    #[structopt(name = "account")]
+    Account(AccountCommand),
+    /// Check whether a deployed contract runs the code, and has the abi, of the local build
+    #[cfg(feature = "extrinsics")]
+    #[structopt(name = "diff-deployed")]
+    DiffDeployed(DiffDeployedCommand),
+    /// Simulate upgrading the code of a deployed contract, checking it still handles a message
+    #[cfg(feature = "extrinsics")]
+    #[structopt(name = "upgrade")]
+    Upgrade(Box<UpgradeCommand>),
+    /// Rebuild the contract and check that its code hash matches the code on chain
+    #[cfg(feature = "extrinsics")]
+    #[structopt(name = "verify")]
+    Verify(VerifyCommand),
+}
+
+/// The url of the node if neither `--url` nor `--network` is passed.
+#[cfg(feature = "extrinsics")]
+const DEFAULT_URL: &str = "ws://localhost:9944";
+
+/// The networks of `--network` and the urls of their nodes.
+#[cfg(feature = "extrinsics")]
+const NETWORKS: [(&str, &str); 3] = [
+    ("canvas", "wss://canvas-rpc.parity.io"),
+    ("rococo-contracts", "wss://rococo-contracts-rpc.polkadot.io"),
+    ("local", DEFAULT_URL),
+];
+
+#[cfg(feature = "extrinsics")]
+fn parse_network(name: &str) -> Result<url::Url> {
+    let (_, url) = NETWORKS
+        .iter()
+        .find(|(network, _)| *network == name)
+        .ok_or_else(|| {
+            let networks = NETWORKS.iter().map(|(network, _)| *network);
+            anyhow::anyhow!(
+                "Unknown network `{}`, the available networks are: {}",
+                name,
+                networks.collect::<Vec<_>>().join(", ")
+            )
+        })?;
+    Ok(url::Url::parse(url)?)
+}
+
+/// Builds the contract of `manifest_path`, as `cargo contract build` does.
+///
+/// Returns the paths of the generated artifacts and, if the Wasm was optimized, its sizes before
+/// and after optimizing. The progress is printed unless `verbosity` is [`Verbosity::Quiet`].
+pub fn build(
+    manifest_path: &ManifestPath,
+    verbosity: Option<Verbosity>,
+    optimize_contract: bool,
+    build_artifact: BuildArtifacts,
+    build_options: BuildOptions,
+) -> Result<BuildResult> {
+    cmd::build::execute(
+        manifest_path,
+        verbosity,
+        optimize_contract,
+        build_artifact,
+        MetadataCompat::Current,
+        false,
+        build_options,
+    )
+}
+
+/// Runs the `cargo contract` command line tool, exiting with 1 on an error.
+#[doc(hidden)]
+pub fn run_cli() {
+    util::init_logger();
+
+    let Opts::Contract(args) = Opts::from_args();
+    match exec(args.cmd) {
+        Ok(msg) => println!("\t{}", msg),
+        Err(err) => {
+            eprintln!(
+                "{} {}",
+                "ERROR:".bright_red().bold(),
+                format!("{:?}", err).bright_red()
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+fn exec(cmd: Command) -> Result<String> {
+    match &cmd {
+        Command::New {
+            name,
+            as_module: Some(host_dir),
+            template,
+            ..
+        } => cmd::new::execute_as_module(name, host_dir, template.unwrap_or_default()),
+        Command::New {
+            name,
+            target_dir,
+            template,
+            git,
+            workspace,
+            ..
+        } => {
+            let created = match git {
+                Some(url) => cmd::new::execute_from_git(name, target_dir.as_ref(), url)?,
+                None => cmd::new::execute(name, target_dir.as_ref(), template.unwrap_or_default())?,
+            };
+            match workspace {
+                Some(workspace) => {
+                    let contract_dir = match target_dir {
+                        Some(dir) => dir.join(name),
+                        None => std::env::current_dir()?.join(name),
+                    };
+                    let added = cmd::new::add_workspace_member(workspace, &contract_dir)?;
+                    Ok(format!("{}\n{}", created, added))
+                }
+                None => Ok(created),
+            }
+        }
+        Command::Build(build) => {
+            if build.workspace()? {
+                return build.exec_workspace();
+            }
+            let result = build.exec()?;
+            if build.output_json() {
+                let json = result.to_json(build.timings())?;
+                return Ok(serde_json::to_string_pretty(&json)?);
+            }
+            Ok(result.display())
+        }
+        Command::Check(check) if check.workspace() => check.exec_workspace(),
+        Command::Check(check) => {
+            let res = check.exec()?;
+            assert!(
+                res.dest_wasm.is_none(),
+                "no dest_wasm must be on the generation result"
+            );
+            Ok("\nYour contract's code was built successfully.".to_string())
+        }
+        Command::CheckMetadata(check_metadata) => check_metadata.exec(),
+        Command::GenerateMetadata {} => Err(anyhow::anyhow!(
+            "Command deprecated, use `cargo contract build` instead"
+        )),
+        Command::Test(test) => test.exec(),
+        Command::GenerateBindings(bindings) => bindings.exec(),
+        Command::GenerateDockerfile(dockerfile) => dockerfile.exec(),
+        Command::Analyze(analyze) => analyze.exec(),
+        Command::Convert(convert) => convert.exec(),
+        Command::DiffStorage(diff_storage) => diff_storage.exec(),
+        Command::Publish(publish) => publish.exec(),
+        #[cfg(feature = "extrinsics")]
+        Command::Deploy(deploy) => deploy.exec(),
+        #[cfg(feature = "extrinsics")]
+        Command::Instantiate(instantiate) => instantiate.exec(),
+        #[cfg(feature = "extrinsics")]
+        Command::Call(call) => call.exec(),
+        #[cfg(feature = "extrinsics")]
+        Command::Submit(submit) => submit.exec(),
+        #[cfg(feature = "extrinsics")]
+        Command::ExportState(export_state) => export_state.exec(),
+        #[cfg(feature = "extrinsics")]
+        Command::Events(events) => events.exec(),
+        #[cfg(feature = "extrinsics")]
+        Command::Decode(decode) => decode.exec(),
+        #[cfg(feature = "extrinsics")]
+        Command::Account(account) => account.exec(),
+        #[cfg(feature = "extrinsics")]
+        Command::DiffDeployed(diff_deployed) => diff_deployed.exec(),
+        #[cfg(feature = "extrinsics")]
+        Command::Upgrade(upgrade) => upgrade.exec(),
+        #[cfg(feature = "extrinsics")]
+        Command::Verify(verify) => verify.exec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_result_json_has_a_stable_schema() {
+        let result = BuildResult {
+            dest_metadata: None,
+            dest_wasm: Some(PathBuf::from("target/ink/flipper.wasm")),
+            dest_wat: None,
+            dest_bundle: None,
+            dest_mime: None,
+            dest_slim_metadata: None,
+            dest_lock_summary: None,
+            target_directory: PathBuf::from("target/ink"),
+            optimization_result: Some(OptimizationResult {
+                original_size: 64.0,
+                optimized_size: 32.0,
+                kept_original: false,
+                optimization_level: "-Oz".into(),
+            }),
+            size_report: None,
+            build_artifact: BuildArtifacts::CodeOnly,
+            build_mode: BuildMode::Release,
+            timings: Default::default(),
+        };
+
+        let json = result.to_json(false).unwrap();
+
+        assert!(json["dest_metadata"].is_null());
+        assert!(json["dest_bundle"].is_null());
+        assert!(json["size_report"].is_null());
+        let dest_wasm = PathBuf::from(json["dest_wasm"].as_str().unwrap());
+        assert!(dest_wasm.is_absolute());
+        assert!(dest_wasm.ends_with("target/ink/flipper.wasm"));
+        assert_eq!(json["optimization_result"]["optimized_size"], 32.0);
+        assert_eq!(json["optimization_result"]["optimization_level"], "-Oz");
+        assert_eq!(json["build_mode"], "release");
+        assert!(json["timings"].is_null());
+    }
+
+    #[test]
+    fn build_result_json_has_the_timings_if_requested() {
+        let mut timings = BuildTimings::default();
+        timings.record("Compiling", Duration::from_secs(2));
+        let result = BuildResult {
+            dest_metadata: None,
+            dest_wasm: None,
+            dest_wat: None,
+            dest_bundle: None,
+            dest_mime: None,
+            dest_slim_metadata: None,
+            dest_lock_summary: None,
+            target_directory: PathBuf::from("target/ink"),
+            optimization_result: None,
+            size_report: None,
+            build_artifact: BuildArtifacts::CheckOnly,
+            build_mode: BuildMode::Debug,
+            timings,
+        };
+
+        let json = result.to_json(true).unwrap();
+
+        assert_eq!(json["timings"]["steps"][0]["step"], "Compiling");
+        assert_eq!(json["timings"]["total_seconds"], 2.0);
+    }
+
+    #[test]
+    fn metadata_format_is_pretty_json_unless_compact() {
+        let format = |input: &str| input.parse::<MetadataFormat>();
+        assert_eq!(MetadataFormat::default(), MetadataFormat::Json);
+        assert_eq!(format("pretty"), Ok(MetadataFormat::Json));
+        assert_eq!(format("compact"), Ok(MetadataFormat::CompactJson));
+        assert!(format("minified").is_err());
+    }
+
+    #[test]
+    fn debug_builds_skip_the_optimization_step() {
+        assert_eq!(BuildArtifacts::All.steps(BuildMode::Release), 5);
+        assert_eq!(BuildArtifacts::All.steps(BuildMode::Debug), 4);
+        assert_eq!(BuildArtifacts::CodeOnly.steps(BuildMode::Debug), 2);
+        assert_eq!(BuildArtifacts::CheckOnly.steps(BuildMode::Debug), 2);
+
+        let result = BuildResult {
+            dest_metadata: None,
+            dest_wasm: Some(PathBuf::from("target/ink/flipper.wasm")),
+            dest_wat: None,
+            dest_bundle: None,
+            dest_mime: None,
+            dest_slim_metadata: None,
+            dest_lock_summary: None,
+            target_directory: PathBuf::from("target/ink"),
+            optimization_result: None,
+            size_report: None,
+            build_artifact: BuildArtifacts::CodeOnly,
+            build_mode: BuildMode::Debug,
+            timings: Default::default(),
+        };
+        assert!(result.display().contains("Debug build (unoptimized)"));
+    }
+
+    #[cfg(feature = "extrinsics")]
+    #[test]
+    fn extrinsic_opts_debug_output_redacts_secrets() {
+        let opts = ExtrinsicOpts {
+            node: NodeOpts {
+                url: Some(url::Url::parse("ws://localhost:9944").unwrap()),
+                network: None,
+            },
+            suri: Some(
+                "bottom drive obey lake curtain smoke basket hold race lonely fit walk".into(),
+            ),
+            account: None,
+            keystore_path: None,
+            keystore: None,
+            password: Some(Some("hunter2".into())),
+            scheme: None,
+            check_balance: false,
+            prompt_on_mainnet: false,
+            yes: false,
+            refresh_metadata: false,
+            verbosity: Default::default(),
+        };
+
+        let debug = format!("{:?}", opts);
+
+        assert!(!debug.contains("bottom drive"));
+        assert!(!debug.contains("hunter2"));
+        assert!(debug.contains(r#"suri: Some("***")"#));
+        assert!(debug.contains(r#"password: Some("***")"#));
+    }
+
+    #[cfg(feature = "extrinsics")]
+    #[test]
+    fn network_presets_resolve_to_node_urls() {
+        let opts = |url: Option<&str>, network: Option<&str>| NodeOpts {
+            url: url.map(|url| url::Url::parse(url).unwrap()),
+            network: network.map(|network| parse_network(network).unwrap()),
+        };
+
+        assert_eq!(
+            opts(None, None).url().unwrap().as_str(),
+            "ws://localhost:9944/"
+        );
+        assert_eq!(
+            opts(None, Some("canvas")).url().unwrap().as_str(),
+            "wss://canvas-rpc.parity.io/"
+        );
+        assert_eq!(
+            opts(Some("ws://127.0.0.1:9000"), None)
+                .url()
+                .unwrap()
+                .as_str(),
+            "ws://127.0.0.1:9000/"
+        );
+        assert!(opts(Some("ws://127.0.0.1:9000"), Some("local"))
+            .url()
+            .unwrap_err()
+            .to_string()
+            .starts_with("Pass either --url or --network, not both"));
+        assert_eq!(
+            parse_network("kusama").unwrap_err().to_string(),
+            "Unknown network `kusama`, the available networks are: canvas, rococo-contracts, \
+             local"
+        );
+    }
+
+    #[test]
+    fn verbosity_flags_map_to_levels() {
+        let verbosity = |args: &[&str]| -> Result<Option<Verbosity>> {
+            let flags = VerbosityFlags::from_iter_safe(
+                std::iter::once("verbosity").chain(args.iter().copied()),
+            )?;
+            TryFrom::<&VerbosityFlags>::try_from(&flags)
+        };
+        let level = |args: &[&str]| -> Option<Verbosity> { verbosity(args).unwrap() };
+
+        assert_eq!(level(&[]), None);
+        assert_eq!(level(&["--quiet"]), Some(Verbosity::Quiet));
+        assert_eq!(level(&["-v"]), Some(Verbosity::Verbose));
+        assert_eq!(level(&["--verbose"]), Some(Verbosity::Verbose));
+        assert_eq!(level(&["-vv"]), Some(Verbosity::Debug));
+        assert_eq!(level(&["-vvv"]), Some(Verbosity::Trace));
+        assert_eq!(level(&["-vvvvv"]), Some(Verbosity::Trace));
+        assert_eq!(level(&["--verbosity", "0"]), Some(Verbosity::Quiet));
+        assert_eq!(level(&["--verbosity", "2"]), Some(Verbosity::Debug));
+        assert!(verbosity(&["--quiet", "-v"]).is_err());
+        assert!(verbosity(&["--verbosity", "1", "-v"]).is_err());
+        assert_eq!(
+            verbosity(&["--verbosity", "4"]).unwrap_err().to_string(),
+            "The verbosity level must be from 0 to 3, not 4"
+        );
+
+        assert!(Verbosity::at_least(
+            Some(Verbosity::Trace),
+            Verbosity::Debug
+        ));
+        assert!(!Verbosity::at_least(
+            Some(Verbosity::Quiet),
+            Verbosity::Verbose
+        ));
+        assert!(!Verbosity::at_least(None, Verbosity::Verbose));
+    }
+}
@@ -16,9 +16,15 @@
 
 use crate::Verbosity;
 use anyhow::{Context, Result};
+use colored::Colorize;
 use rustc_version::Channel;
 use std::path::PathBuf;
-use std::{ffi::OsStr, path::Path, process::Command};
+use std::{
+    ffi::OsStr,
+    path::Path,
+    process::{Command, Output},
+    sync::atomic::{AtomicBool, Ordering},
+};
 
 /// Check whether the current rust channel is valid: `nightly` is recommended.
 pub fn assert_channel() -> Result<()> {
@@ -36,7 +42,7 @@ pub fn assert_channel() -> Result<()> {
     }
 }
 
-/// Run cargo with the supplied args
+/// Run cargo with the supplied args and environment variables
 ///
 /// If successful, returns the stdout bytes
 pub(crate) fn invoke_cargo<I, S, P>(
@@ -44,7 +50,84 @@ pub(crate) fn invoke_cargo<I, S, P>(
     args: I,
     working_dir: Option<P>,
     verbosity: Option<Verbosity>,
+    env: &[(&str, &str)],
 ) -> Result<Vec<u8>>
+where
+    I: IntoIterator<Item = S> + std::fmt::Debug,
+    S: AsRef<OsStr>,
+    P: AsRef<Path>,
+{
+    let mut cmd = cargo_command(command, args, working_dir, verbosity, env);
+    let child = cmd
+        // capture the stdout to return from this function as bytes
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .context(format!("Error executing `{:?}`", cmd))?;
+    let output = child.wait_with_output()?;
+
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        anyhow::bail!(
+            "`{:?}` failed with exit code: {:?}",
+            cmd,
+            output.status.code()
+        );
+    }
+}
+
+/// Run cargo with the supplied args and environment variables, capturing both stdout and stderr
+///
+/// Returns the output whether cargo succeeded or not.
+pub(crate) fn invoke_cargo_capturing_stderr<I, S, P>(
+    command: &str,
+    args: I,
+    working_dir: Option<P>,
+    verbosity: Option<Verbosity>,
+    env: &[(&str, &str)],
+) -> Result<Output>
+where
+    I: IntoIterator<Item = S> + std::fmt::Debug,
+    S: AsRef<OsStr>,
+    P: AsRef<Path>,
+{
+    let mut cmd = cargo_command(command, args, working_dir, verbosity, env);
+    cmd.output().context(format!("Error executing `{:?}`", cmd))
+}
+
+/// Run cargo with the supplied args, streaming its output
+///
+/// Fails if cargo exits with an error.
+pub(crate) fn invoke_cargo_streaming<I, S, P>(
+    command: &str,
+    args: I,
+    working_dir: Option<P>,
+    verbosity: Option<Verbosity>,
+) -> Result<()>
+where
+    I: IntoIterator<Item = S> + std::fmt::Debug,
+    S: AsRef<OsStr>,
+    P: AsRef<Path>,
+{
+    let mut cmd = cargo_command(command, args, working_dir, verbosity, &[]);
+    let status = cmd
+        .status()
+        .context(format!("Error executing `{:?}`", cmd))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        anyhow::bail!("`{:?}` failed with exit code: {:?}", cmd, status.code());
+    }
+}
+
+fn cargo_command<I, S, P>(
+    command: &str,
+    args: I,
+    working_dir: Option<P>,
+    verbosity: Option<Verbosity>,
+    env: &[(&str, &str)],
+) -> Command
 where
     I: IntoIterator<Item = S> + std::fmt::Debug,
     S: AsRef<OsStr>,
@@ -58,30 +141,90 @@ where
     }
 
     cmd.arg(command);
-    cmd.args(args);
+    // before the args, which may end with `-- <args of the invoked binary>`
     match verbosity {
         Some(Verbosity::Quiet) => cmd.arg("--quiet"),
         Some(Verbosity::Verbose) => cmd.arg("--verbose"),
+        Some(Verbosity::Debug) | Some(Verbosity::Trace) => cmd.arg("-vv"),
         None => &mut cmd,
     };
+    cmd.args(args);
+    // only for this invocation, contracts of a workspace are built concurrently
+    cmd.envs(env.iter().copied());
 
     log::info!("invoking cargo: {:?}", cmd);
+    print_command(verbosity, &cmd);
+    cmd
+}
 
-    let child = cmd
-        // capture the stdout to return from this function as bytes
-        .stdout(std::process::Stdio::piped())
-        .spawn()
-        .context(format!("Error executing `{:?}`", cmd))?;
-    let output = child.wait_with_output()?;
+/// Prints the command line of `cmd` if the verbosity is at least [`Verbosity::Debug`].
+pub(crate) fn print_command(verbosity: Option<Verbosity>, cmd: &Command) {
+    if Verbosity::at_least(verbosity, Verbosity::Debug) {
+        println!("{} {:?}", "Running".bold(), cmd);
+    }
+}
 
-    if output.status.success() {
-        Ok(output.stdout)
-    } else {
-        anyhow::bail!(
-            "`{:?}` failed with exit code: {:?}",
-            cmd,
-            output.status.code()
-        );
+/// Whether the log records of the RPC client are printed, see [`trace_rpc`].
+static TRACE_RPC: AtomicBool = AtomicBool::new(false);
+
+/// Prints the requests to and the responses of the node, i.e. the log records of the RPC client,
+/// regardless of `RUST_LOG`.
+#[cfg(feature = "extrinsics")]
+pub(crate) fn trace_rpc() {
+    if !TRACE_RPC.swap(true, Ordering::Relaxed) {
+        log::set_max_level(log::LevelFilter::Trace);
+    }
+}
+
+/// The targets of the log records printed by [`trace_rpc`].
+const RPC_TARGETS: &[&str] = &["jsonrpsee", "substrate_subxt"];
+
+/// Logs as configured by `RUST_LOG`, and also the RPC client once [`trace_rpc`] is called.
+struct Logger {
+    env: env_logger::Logger,
+}
+
+impl Logger {
+    fn is_rpc(metadata: &log::Metadata) -> bool {
+        TRACE_RPC.load(Ordering::Relaxed)
+            && RPC_TARGETS
+                .iter()
+                .any(|target| metadata.target().starts_with(target))
+    }
+}
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.env.enabled(metadata) || Self::is_rpc(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.env.matches(record) {
+            self.env.log(record)
+        } else if Self::is_rpc(record.metadata()) {
+            eprintln!("[{} {}] {}", record.level(), record.target(), record.args());
+        }
+    }
+
+    fn flush(&self) {
+        self.env.flush()
+    }
+}
+
+/// Initializes the logger, configured by `RUST_LOG` like `env_logger`.
+pub(crate) fn init_logger() {
+    let env = env_logger::Builder::from_default_env().build();
+    let max_level = env.filter();
+    if log::set_boxed_logger(Box::new(Logger { env })).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+/// Prints the `message` unless the verbosity is quiet, e.g. to keep the output of
+/// `build --output-json` parseable.
+pub(crate) fn maybe_println(verbosity: Option<Verbosity>, message: impl std::fmt::Display) {
+    if verbosity != Some(Verbosity::Quiet) {
+        println!("{}", message);
     }
 }
 
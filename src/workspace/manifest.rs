@@ -137,6 +137,12 @@ impl Manifest {
         &self.path
     }
 
+    /// Returns `true` if this is the virtual manifest of a workspace, i.e. it has a
+    /// `[workspace]` but no `[package]` section.
+    pub fn is_virtual(&self) -> bool {
+        self.toml.contains_key("workspace") && !self.toml.contains_key("package")
+    }
+
     /// Get mutable reference to `[lib] crate-types = []` section
     fn get_crate_types_mut(&mut self) -> Result<&mut value::Array> {
         let lib = self
@@ -19,7 +19,7 @@ use std::{fs, path::Path};
 use toml::value;
 
 /// Generates a cargo workspace package `metadata-gen` which will be invoked via `cargo run` to
-/// generate contract metadata.
+/// generate contract metadata, its binary is named `metadata-gen-{contract_package_name}`.
 ///
 /// # Note
 ///
@@ -41,6 +41,19 @@ pub(super) fn generate_package<P: AsRef<Path>>(
     let main_rs = include_str!("../../templates/tools/generate-metadata/main.rs");
 
     let mut cargo_toml: value::Table = toml::from_str(cargo_toml)?;
+
+    // cargo copies the binary to the shared target directory, the contracts of a workspace built
+    // concurrently must not run the binary of another contract
+    let bin = cargo_toml
+        .get_mut("bin")
+        .and_then(|bins| bins.as_array_mut())
+        .and_then(|bins| bins.first_mut())
+        .and_then(|bin| bin.as_table_mut())
+        .expect("[[bin]] section specified in the template");
+    bin.insert(
+        "name".into(),
+        format!("metadata-gen-{}", contract_package_name).into(),
+    );
     let deps = cargo_toml
         .get_mut("dependencies")
         .expect("[dependencies] section specified in the template")
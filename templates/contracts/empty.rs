@@ -0,0 +1,26 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use ink_lang as ink;
+
+#[ink::contract]
+mod {{name}} {
+
+    /// Defines the storage of your contract.
+    /// Add new fields to the below struct in order
+    /// to add new static storage fields to your contract.
+    #[ink(storage)]
+    pub struct {{camel_name}} {}
+
+    impl {{camel_name}} {
+        /// Constructor that creates the contract.
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            Self {}
+        }
+
+        /// A message that can be called on instantiated contracts.
+        /// A contract needs at least one message.
+        #[ink(message)]
+        pub fn noop(&self) {}
+    }
+}
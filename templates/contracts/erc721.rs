@@ -0,0 +1,210 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use ink_lang as ink;
+
+#[ink::contract]
+mod {{name}} {
+    #[cfg(not(feature = "ink-as-dependency"))]
+    use ink_storage::collections::HashMap as StorageHashMap;
+    use scale::{Decode, Encode};
+
+    /// A token ID.
+    pub type TokenId = u32;
+
+    /// A simple ERC-721 contract.
+    #[ink(storage)]
+    pub struct {{camel_name}} {
+        /// Mapping from token to owner.
+        token_owner: StorageHashMap<TokenId, AccountId>,
+        /// Mapping from token to the account approved to transfer it.
+        token_approvals: StorageHashMap<TokenId, AccountId>,
+        /// Mapping from owner to number of owned token.
+        owned_tokens_count: StorageHashMap<AccountId, u32>,
+    }
+
+    /// The ERC-721 error types.
+    #[derive(Encode, Decode, Debug, PartialEq, Eq, Copy, Clone)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        /// Returned if the caller does not own the token.
+        NotOwner,
+        /// Returned if the caller is neither the owner nor approved for the token.
+        NotApproved,
+        /// Returned if a token with the ID already exists.
+        TokenExists,
+        /// Returned if there is no token with the ID.
+        TokenNotFound,
+        /// Returned if an owner approves itself.
+        NotAllowed,
+    }
+
+    /// Event emitted when a token transfer occurs.
+    #[ink(event)]
+    pub struct Transfer {
+        #[ink(topic)]
+        from: Option<AccountId>,
+        #[ink(topic)]
+        to: Option<AccountId>,
+        #[ink(topic)]
+        id: TokenId,
+    }
+
+    /// Event emitted when a token approval occurs.
+    #[ink(event)]
+    pub struct Approval {
+        #[ink(topic)]
+        from: AccountId,
+        #[ink(topic)]
+        to: AccountId,
+        #[ink(topic)]
+        id: TokenId,
+    }
+
+    impl {{camel_name}} {
+        /// Creates a new ERC-721 contract without any tokens.
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            Self {
+                token_owner: StorageHashMap::new(),
+                token_approvals: StorageHashMap::new(),
+                owned_tokens_count: StorageHashMap::new(),
+            }
+        }
+
+        /// Returns the number of tokens owned by `owner`.
+        #[ink(message)]
+        pub fn balance_of(&self, owner: AccountId) -> u32 {
+            self.owned_tokens_count.get(&owner).copied().unwrap_or(0)
+        }
+
+        /// Returns the owner of the token.
+        #[ink(message)]
+        pub fn owner_of(&self, id: TokenId) -> Option<AccountId> {
+            self.token_owner.get(&id).cloned()
+        }
+
+        /// Returns the account approved to transfer the token, if any.
+        #[ink(message)]
+        pub fn get_approved(&self, id: TokenId) -> Option<AccountId> {
+            self.token_approvals.get(&id).cloned()
+        }
+
+        /// Creates a new token owned by the caller.
+        #[ink(message)]
+        pub fn mint(&mut self, id: TokenId) -> Result<(), Error> {
+            let caller = self.env().caller();
+            if self.token_owner.contains_key(&id) {
+                return Err(Error::TokenExists)
+            }
+            self.token_owner.insert(id, caller);
+            let count = self.balance_of(caller);
+            self.owned_tokens_count.insert(caller, count + 1);
+            self.env().emit_event(Transfer {
+                from: None,
+                to: Some(caller),
+                id,
+            });
+            Ok(())
+        }
+
+        /// Transfers the token from the caller to the `destination`.
+        #[ink(message)]
+        pub fn transfer(&mut self, destination: AccountId, id: TokenId) -> Result<(), Error> {
+            let caller = self.env().caller();
+            self.transfer_token_from(caller, destination, id)
+        }
+
+        /// Transfers the token of `from` to `to`, the caller must own it or be approved.
+        #[ink(message)]
+        pub fn transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            id: TokenId,
+        ) -> Result<(), Error> {
+            self.transfer_token_from(from, to, id)
+        }
+
+        /// Approves the account `to` to transfer the token of the caller.
+        #[ink(message)]
+        pub fn approve(&mut self, to: AccountId, id: TokenId) -> Result<(), Error> {
+            let caller = self.env().caller();
+            if self.owner_of(id) != Some(caller) {
+                return Err(Error::NotOwner)
+            }
+            if to == caller {
+                return Err(Error::NotAllowed)
+            }
+            self.token_approvals.insert(id, to);
+            self.env().emit_event(Approval {
+                from: caller,
+                to,
+                id,
+            });
+            Ok(())
+        }
+
+        /// Transfers the token of `from` to `to`, clearing its approval.
+        fn transfer_token_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            id: TokenId,
+        ) -> Result<(), Error> {
+            let caller = self.env().caller();
+            let owner = self.owner_of(id).ok_or(Error::TokenNotFound)?;
+            if owner != from {
+                return Err(Error::NotOwner)
+            }
+            if caller != owner && self.get_approved(id) != Some(caller) {
+                return Err(Error::NotApproved)
+            }
+            self.token_approvals.take(&id);
+            let from_count = self.balance_of(from);
+            self.owned_tokens_count.insert(from, from_count - 1);
+            let to_count = self.balance_of(to);
+            self.owned_tokens_count.insert(to, to_count + 1);
+            self.token_owner.insert(id, to);
+            self.env().emit_event(Transfer {
+                from: Some(from),
+                to: Some(to),
+                id,
+            });
+            Ok(())
+        }
+    }
+
+    /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
+    /// module and test functions are marked with a `#[test]` attribute.
+    #[cfg(test)]
+    mod tests {
+        /// Imports all the definitions from the outer scope so we can use them here.
+        use super::*;
+        use ink_lang as ink;
+
+        /// A minted token is owned by the caller.
+        #[ink::test]
+        fn mint_works() {
+            let mut {{name}} = {{camel_name}}::new();
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get accounts");
+            assert_eq!({{name}}.mint(1), Ok(()));
+            assert_eq!({{name}}.owner_of(1), Some(accounts.alice));
+            assert_eq!({{name}}.balance_of(accounts.alice), 1);
+            assert_eq!({{name}}.mint(1), Err(Error::TokenExists));
+        }
+
+        /// A token can be transferred by its owner.
+        #[ink::test]
+        fn transfer_works() {
+            let mut {{name}} = {{camel_name}}::new();
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get accounts");
+            assert_eq!({{name}}.mint(1), Ok(()));
+            assert_eq!({{name}}.transfer(accounts.bob, 1), Ok(()));
+            assert_eq!({{name}}.owner_of(1), Some(accounts.bob));
+            assert_eq!({{name}}.balance_of(accounts.alice), 0);
+            assert_eq!({{name}}.transfer(accounts.bob, 1), Err(Error::NotOwner));
+        }
+    }
+}